@@ -0,0 +1,91 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::{json, Value};
+use toon::{decode, encode, Encoder};
+
+/// Uniform rows, the shape TOON's tabular form is built for
+fn tabular(rows: usize) -> Value {
+    let items: Vec<Value> = (0..rows)
+        .map(|i| json!({"id": i, "sku": format!("SKU-{i:05}"), "qty": i % 7, "price": 9.99 + i as f64, "active": i % 2 == 0}))
+        .collect();
+    json!({"items": items})
+}
+
+/// Nested objects with mixed arrays, which fall back to list items
+fn nested(rows: usize) -> Value {
+    let users: Vec<Value> = (0..rows)
+        .map(|i| {
+            json!({
+                "id": i,
+                "name": format!("user {i}"),
+                "tags": ["admin", "ops", i.to_string()],
+                "address": {"city": "Springfield", "zip": format!("{:05}", i * 13 % 99999)},
+                "history": [{"event": "login", "at": i}, "note: see ticket"],
+            })
+        })
+        .collect();
+    json!({"users": users})
+}
+
+/// One long string-heavy array, dominated by quoting decisions
+fn strings(rows: usize) -> Value {
+    let values: Vec<Value> = (0..rows).map(|i| json!(format!("value {i}, with a comma: and colon"))).collect();
+    json!({"values": values})
+}
+
+/// Short lines under deep indentation, so writing the lines outweighs encoding the values
+fn indented(rows: usize) -> Value {
+    let items: Vec<Value> = (0..rows)
+        .map(|i| (0..12).fold(json!({"id": i, "ok": true}), |inner, level| json!({format!("level{level}"): inner, "n": level})))
+        .collect();
+    json!({"items": items})
+}
+
+type Shape = (&'static str, fn(usize) -> Value);
+
+fn bench_encode(c: &mut Criterion) {
+    let shapes: [Shape; 3] = [("tabular", tabular), ("nested", nested), ("strings", strings)];
+
+    for (name, build) in shapes {
+        let mut group = c.benchmark_group(name);
+        for rows in [100, 10_000] {
+            let value = build(rows);
+            group.throughput(Throughput::Bytes(serde_json::to_string(&value).unwrap().len() as u64));
+
+            group.bench_with_input(BenchmarkId::new("toon::encode", rows), &value, |b, v| b.iter(|| encode(black_box(v), None)));
+            group.bench_with_input(BenchmarkId::new("toon::Encoder", rows), &value, |b, v| {
+                let mut encoder = Encoder::new(None);
+                b.iter(|| encoder.encode(black_box(v)).len())
+            });
+            #[cfg(feature = "arena")]
+            group.bench_with_input(BenchmarkId::new("toon::encode_in", rows), &value, |b, v| {
+                let mut bump = bumpalo::Bump::new();
+                b.iter(|| {
+                    let len = toon::encode_in(black_box(v), None, &bump).len();
+                    bump.reset();
+                    len
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("serde_json::to_string", rows), &value, |b, v| {
+                b.iter(|| serde_json::to_string(black_box(v)).unwrap())
+            });
+
+            let toon = encode(&value, None);
+            group.bench_with_input(BenchmarkId::new("toon::decode", rows), &toon, |b, t| b.iter(|| decode(black_box(t), None).unwrap()));
+        }
+        group.finish();
+    }
+}
+
+/// Documents of hundreds of thousands of lines, where building the output string dominates
+fn bench_large_documents(c: &mut Criterion) {
+    let mut group = c.benchmark_group("large");
+    group.sample_size(10);
+    for (name, value) in [("tabular", tabular(200_000)), ("nested", nested(50_000)), ("indented", indented(20_000))] {
+        group.throughput(Throughput::Bytes(encode(&value, None).len() as u64));
+        group.bench_with_input(BenchmarkId::new("toon::encode", name), &value, |b, v| b.iter(|| encode(black_box(v), None)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encode, bench_large_documents);
+criterion_main!(benches);
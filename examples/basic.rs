@@ -53,8 +53,23 @@ fn main() {
     let data = json!({
         "tags": ["reading", "gaming", "coding"]
     });
-    let mut options = EncodeOptions::default();
-    options.delimiter = Delimiter::Tab;
+    let options = EncodeOptions {
+        delimiter: Delimiter::Tab,
+        ..Default::default()
+    };
+    println!("{}\n", encode(&data, Some(options)));
+
+    println!("=== With Semicolon Delimiter ===");
+    let data = json!({
+        "measurements": [
+            { "sensor": "A", "reading": "1,25" },
+            { "sensor": "B", "reading": "0,75" }
+        ]
+    });
+    let options = EncodeOptions {
+        delimiter: Delimiter::Semicolon,
+        ..Default::default()
+    };
     println!("{}\n", encode(&data, Some(options)));
 
     println!("=== With Length Marker ===");
@@ -64,8 +79,10 @@ fn main() {
             { "id": 2, "name": "Second" }
         ]
     });
-    let mut options = EncodeOptions::default();
-    options.length_marker = Some('#');
+    let options = EncodeOptions {
+        length_marker: Some('#'.into()),
+        ..Default::default()
+    };
     println!("{}\n", encode(&data, Some(options)));
 
     println!("=== Mixed Array ===");
@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// Any input must decode to a value or a DecodeError, never panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = toon::decode(input, None);
+        let _ = toon::decode_documents(input, None);
+        let _ = toon::decode_with_front_matter(input, None);
+    }
+});
@@ -0,0 +1,60 @@
+#![no_main]
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use serde_json::{Map, Number, Value};
+use toon::{Delimiter, EncodeOptions};
+
+/// Containers nested deeper than this become leaves, keeping inputs small
+const MAX_DEPTH: usize = 8;
+
+/// A generated JSON value plus the delimiter to encode it with
+#[derive(Debug)]
+struct Input {
+    value: Value,
+    delimiter: Delimiter,
+}
+
+impl<'a> Arbitrary<'a> for Input {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let delimiter = *u.choose(&[Delimiter::Comma, Delimiter::Tab, Delimiter::Pipe, Delimiter::Semicolon])?;
+        Ok(Input { value: arbitrary_value(u, 0)?, delimiter })
+    }
+}
+
+fn arbitrary_value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let kinds = if depth >= MAX_DEPTH { 5 } else { 7 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::Number(Number::from(u.arbitrary::<i64>()?)),
+        3 => Number::from_f64(u.arbitrary()?).map_or(Value::Null, Value::Number),
+        4 => Value::String(u.arbitrary()?),
+        5 => {
+            let mut items = Vec::new();
+            u.arbitrary_loop(None, Some(8), |u| {
+                items.push(arbitrary_value(u, depth + 1)?);
+                Ok(std::ops::ControlFlow::Continue(()))
+            })?;
+            Value::Array(items)
+        }
+        _ => {
+            let mut fields = Map::new();
+            u.arbitrary_loop(None, Some(8), |u| {
+                fields.insert(u.arbitrary()?, arbitrary_value(u, depth + 1)?);
+                Ok(std::ops::ControlFlow::Continue(()))
+            })?;
+            Value::Object(fields)
+        }
+    })
+}
+
+// Whatever lossless mode accepts must decode back to the same value
+fuzz_target!(|input: Input| {
+    let options = EncodeOptions { delimiter: input.delimiter, lossless: true, ..Default::default() };
+    let Ok(encoded) = toon::try_encode(&input.value, Some(options)) else {
+        return;
+    };
+    let decoded = toon::decode(&encoded, None).unwrap_or_else(|err| panic!("{}\n---\n{}", err, encoded));
+    assert_eq!(decoded, input.value, "\n---\n{}", encoded);
+});
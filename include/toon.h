@@ -0,0 +1,56 @@
+/*
+ * Token-Oriented Object Notation (TOON) C interface
+ *
+ * All strings are NUL-terminated UTF-8. Strings returned by the library, including error
+ * messages, belong to the caller and must be released with toon_free.
+ */
+
+#ifndef TOON_H
+#define TOON_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Encode JSON text to TOON with the default options
+//
+// Returns null and, when `error` is not null, stores a message in `*error` if the JSON is
+// invalid or encoding fails.
+//
+// # Safety
+//
+// `json` must be null or point to a NUL-terminated string, and `error` must be null or
+// point to writable storage for one pointer.
+char *toon_encode(const char *json, char **error);
+
+// Decode TOON text to compact JSON text with the default options
+//
+// Returns null and, when `error` is not null, stores a message naming the line in `*error`
+// if the text is malformed.
+//
+// # Safety
+//
+// As for `toon_encode`.
+char *toon_decode(const char *toon, char **error);
+
+// Release a string returned by `toon_encode` or `toon_decode`, including error messages
+//
+// Null is ignored.
+//
+// # Safety
+//
+// `text` must be null or a pointer returned by this library that hasn't been freed yet.
+void toon_free(char *text);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TOON_H */
@@ -0,0 +1,38 @@
+// Run after `npm run build`
+const { encode, encodeJson, decode } = require("./index.js");
+
+// Simple example
+const data = {
+    user: {
+        id: 123,
+        name: "Ada",
+        tags: ["reading", "gaming"],
+        active: true,
+    },
+};
+
+console.log("=== Simple encoding ===");
+console.log(encode(data));
+
+// Array of objects
+const data2 = {
+    items: [
+        { sku: "A1", qty: 2, price: 9.99 },
+        { sku: "B2", qty: 1, price: 14.5 },
+    ],
+};
+
+console.log("\n=== Tabular format ===");
+console.log(encode(data2));
+
+// Options
+console.log("\n=== Pipe delimiter, sorted by price ===");
+console.log(encode(data2, { delimiter: "|", sortRows: ["-price"] }));
+
+// JSON.stringify rules, so dates become ISO 8601 strings
+console.log("\n=== From JSON text ===");
+console.log(encodeJson(JSON.stringify({ joined: new Date("2024-01-15T00:00:00Z") })));
+
+// Round trip
+console.log("\n=== Decoded ===");
+console.log(JSON.stringify(decode(encode(data2))));
@@ -0,0 +1,88 @@
+/* auto-generated by NAPI-RS */
+/* eslint-disable */
+/** Decoding options, named after the Rust `DecodeOptions` fields; anything left out keeps its default */
+export interface DecodeOptions {
+  indent?: number | string
+  strict?: boolean
+  expandPaths?: boolean
+  runLength?: boolean
+}
+
+/** Encoding options, named after the Rust `EncodeOptions` fields; anything left out keeps its default */
+export interface EncodeOptions {
+  /** Spaces per level, or the text of one level such as `"\t"` */
+  indent?: number | string
+  delimiter?: ',' | '\t' | '|' | ';'
+  /** Text written before array lengths, such as `"#"` */
+  lengthMarker?: string
+  lineEnding?: '\n' | '\r\n'
+  trailingNewline?: boolean
+  alignColumns?: boolean
+  maxLineWidth?: number
+  emitLengths?: 'always' | 'never' | 'tables'
+  keyFolding?: 'off' | 'safe' | 'aggressive'
+  tableFlattenDepth?: number
+  tabularMinRows?: number
+  tabularMaxColumns?: number
+  tabularUnionThreshold?: number
+  missingCell?: 'null' | 'empty'
+  /** A strategy, or the explicit column names */
+  columnOrder?: 'alphabetical' | 'first_object' | 'shortest_first' | Array<string>
+  maxRows?: RowLimit
+  maxDepth?: number
+  skipNulls?: boolean
+  skipEmpty?: boolean
+  /** Column names to sort rows by, each prefixed with `-` to sort descending */
+  sortRows?: Array<string>
+  groupBy?: string
+  stringDictionary?: number
+  inlineObjects?: number
+  tableChunkRows?: number
+  columnAliases?: Record<string, string>
+  aliasLegend?: boolean
+  runLength?: boolean
+  typeHints?: boolean
+  /** Hint names by column, such as `{ amount: "decimal" }` */
+  columnTypes?: Record<string, string>
+  summarize?: Summarize
+  liftConstantColumns?: boolean
+  keyOrder?: 'alphabetical' | 'insertion'
+  maxOutputBytes?: number
+  maxNodes?: number
+  failOverDepth?: number
+  lossless?: boolean
+  specVersion?: '1.0' | '1.5' | '2.0'
+  maxStringChars?: number
+  floatDigits?: number
+}
+
+/** Rows kept per table: the first `head` and the last `tail` */
+export interface RowLimit {
+  head: number
+  tail?: number
+}
+
+/** Arrays longer than `threshold` are replaced by `sample` rows and a summary line */
+export interface Summarize {
+  threshold: number
+  sample: RowLimit
+}
+
+/**
+ * Decode TOON text to plain objects and arrays
+ *
+ * Throws with the line number when the text is malformed.
+ */
+export declare function decode(text: string, options?: DecodeOptions | undefined | null): any
+
+/**
+ * Encode a JSON-compatible value to TOON
+ *
+ * Values are read as they are, so `NaN`, functions and `undefined` array items throw and a
+ * `Date` has no fields; `encodeJson(JSON.stringify(data))` applies `JSON.stringify` rules
+ * instead. Throws when a budget is exceeded or a value is lossy under `lossless`.
+ */
+export declare function encode(data: any, options?: EncodeOptions | undefined | null): string
+
+/** Encode JSON text to TOON, parsing it on the native side */
+export declare function encodeJson(json: string, options?: EncodeOptions | undefined | null): string
@@ -0,0 +1,39 @@
+extern crate toon as toon_lib;
+
+mod options;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use options::{DecodeOptions, EncodeOptions};
+
+fn encode_value(data: &serde_json::Value, options: Option<EncodeOptions>) -> Result<String> {
+    let options = options.map(|options| options.to_rust()).transpose()?;
+    toon_lib::try_encode(data, options).map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+}
+
+/// Encode a JSON-compatible value to TOON
+///
+/// Values are read as they are, so `NaN`, functions and `undefined` array items throw and a
+/// `Date` has no fields; `encodeJson(JSON.stringify(data))` applies `JSON.stringify` rules
+/// instead. Throws when a budget is exceeded or a value is lossy under `lossless`.
+#[napi]
+pub fn encode(data: serde_json::Value, options: Option<EncodeOptions>) -> Result<String> {
+    encode_value(&data, options)
+}
+
+/// Encode JSON text to TOON, parsing it on the native side
+#[napi]
+pub fn encode_json(json: String, options: Option<EncodeOptions>) -> Result<String> {
+    let data: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| Error::new(Status::InvalidArg, format!("Invalid JSON: {}", e)))?;
+    encode_value(&data, options)
+}
+
+/// Decode TOON text to plain objects and arrays
+///
+/// Throws with the line number when the text is malformed.
+#[napi]
+pub fn decode(text: String, options: Option<DecodeOptions>) -> Result<serde_json::Value> {
+    let options = options.map(|options| options.to_rust());
+    toon_lib::decode(&text, options).map_err(|e| Error::new(Status::InvalidArg, e.to_string()))
+}
@@ -0,0 +1,234 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::collections::HashMap;
+
+/// Rows kept per table: the first `head` and the last `tail`
+#[napi(object)]
+pub struct RowLimit {
+    pub head: u32,
+    pub tail: Option<u32>,
+}
+
+impl RowLimit {
+    fn to_rust(&self) -> toon_lib::RowLimit {
+        toon_lib::RowLimit::new(self.head as usize, self.tail.unwrap_or(0) as usize)
+    }
+}
+
+/// Arrays longer than `threshold` are replaced by `sample` rows and a summary line
+#[napi(object)]
+pub struct Summarize {
+    pub threshold: u32,
+    pub sample: RowLimit,
+}
+
+/// Encoding options, named after the Rust `EncodeOptions` fields; anything left out keeps its default
+#[napi(object)]
+pub struct EncodeOptions {
+    /// Spaces per level, or the text of one level such as `"\t"`
+    pub indent: Option<Either<u32, String>>,
+    #[napi(ts_type = "',' | '\\t' | '|' | ';'")]
+    pub delimiter: Option<String>,
+    /// Text written before array lengths, such as `"#"`
+    pub length_marker: Option<String>,
+    #[napi(ts_type = "'\\n' | '\\r\\n'")]
+    pub line_ending: Option<String>,
+    pub trailing_newline: Option<bool>,
+    pub align_columns: Option<bool>,
+    pub max_line_width: Option<u32>,
+    #[napi(ts_type = "'always' | 'never' | 'tables'")]
+    pub emit_lengths: Option<String>,
+    #[napi(ts_type = "'off' | 'safe' | 'aggressive'")]
+    pub key_folding: Option<String>,
+    pub table_flatten_depth: Option<u32>,
+    pub tabular_min_rows: Option<u32>,
+    pub tabular_max_columns: Option<u32>,
+    pub tabular_union_threshold: Option<f64>,
+    #[napi(ts_type = "'null' | 'empty'")]
+    pub missing_cell: Option<String>,
+    /// A strategy, or the explicit column names
+    #[napi(ts_type = "'alphabetical' | 'first_object' | 'shortest_first' | Array<string>")]
+    pub column_order: Option<Either<String, Vec<String>>>,
+    pub max_rows: Option<RowLimit>,
+    pub max_depth: Option<u32>,
+    pub skip_nulls: Option<bool>,
+    pub skip_empty: Option<bool>,
+    /// Column names to sort rows by, each prefixed with `-` to sort descending
+    pub sort_rows: Option<Vec<String>>,
+    pub group_by: Option<String>,
+    pub string_dictionary: Option<u32>,
+    pub inline_objects: Option<u32>,
+    pub table_chunk_rows: Option<u32>,
+    pub column_aliases: Option<HashMap<String, String>>,
+    pub alias_legend: Option<bool>,
+    pub run_length: Option<bool>,
+    pub type_hints: Option<bool>,
+    /// Hint names by column, such as `{ amount: "decimal" }`
+    pub column_types: Option<HashMap<String, String>>,
+    pub summarize: Option<Summarize>,
+    pub lift_constant_columns: Option<bool>,
+    #[napi(ts_type = "'alphabetical' | 'insertion'")]
+    pub key_order: Option<String>,
+    pub max_output_bytes: Option<u32>,
+    pub max_nodes: Option<u32>,
+    pub fail_over_depth: Option<u32>,
+    pub lossless: Option<bool>,
+    #[napi(ts_type = "'1.0' | '1.5' | '2.0'")]
+    pub spec_version: Option<String>,
+    pub max_string_chars: Option<u32>,
+    pub float_digits: Option<u32>,
+}
+
+/// Decoding options, named after the Rust `DecodeOptions` fields; anything left out keeps its default
+#[napi(object)]
+pub struct DecodeOptions {
+    pub indent: Option<Either<u32, String>>,
+    pub strict: Option<bool>,
+    pub expand_paths: Option<bool>,
+    pub run_length: Option<bool>,
+}
+
+fn indent_style(indent: &Either<u32, String>) -> toon_lib::IndentStyle {
+    match indent {
+        Either::A(n) => toon_lib::IndentStyle::Spaces(*n as usize),
+        Either::B(text) if text == "\t" => toon_lib::IndentStyle::Tabs,
+        Either::B(text) => toon_lib::IndentStyle::Custom(text.clone()),
+    }
+}
+
+/// Look `name` up in `choices`, or `InvalidArg` listing them; a missing name stays `None`
+fn choose<T: Clone>(option: &str, name: Option<&str>, choices: &[(&str, T)]) -> Result<Option<T>> {
+    let Some(name) = name else {
+        return Ok(None);
+    };
+    match choices.iter().find(|(choice, _)| *choice == name) {
+        Some((_, value)) => Ok(Some(value.clone())),
+        None => {
+            let names: Vec<String> = choices.iter().map(|(choice, _)| format!("{:?}", choice)).collect();
+            Err(Error::new(Status::InvalidArg, format!("{} must be one of {}, not {:?}", option, names.join(", "), name)))
+        }
+    }
+}
+
+fn size(n: Option<u32>) -> Option<usize> {
+    n.map(|n| n as usize)
+}
+
+impl DecodeOptions {
+    pub fn to_rust(&self) -> toon_lib::DecodeOptions {
+        let defaults = toon_lib::DecodeOptions::default();
+        toon_lib::DecodeOptions {
+            indent: self.indent.as_ref().map_or(defaults.indent, indent_style),
+            strict: self.strict.unwrap_or(defaults.strict),
+            expand_paths: self.expand_paths.unwrap_or(defaults.expand_paths),
+            run_length: self.run_length.unwrap_or(defaults.run_length),
+            fail_over_depth: defaults.fail_over_depth,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The Rust options these describe, or `InvalidArg` naming the first invalid value
+    pub fn to_rust(&self) -> Result<toon_lib::EncodeOptions> {
+        let defaults = toon_lib::EncodeOptions::default();
+        let delimiter = choose("delimiter", self.delimiter.as_deref(), &[
+            (",", toon_lib::Delimiter::Comma),
+            ("\t", toon_lib::Delimiter::Tab),
+            ("|", toon_lib::Delimiter::Pipe),
+            (";", toon_lib::Delimiter::Semicolon),
+        ])?.unwrap_or(defaults.delimiter);
+        let line_ending = choose("lineEnding", self.line_ending.as_deref(), &[
+            ("\n", toon_lib::LineEnding::Lf),
+            ("\r\n", toon_lib::LineEnding::CrLf),
+        ])?.unwrap_or(defaults.line_ending);
+        let emit_lengths = choose("emitLengths", self.emit_lengths.as_deref(), &[
+            ("always", toon_lib::EmitLengths::Always),
+            ("never", toon_lib::EmitLengths::Never),
+            ("tables", toon_lib::EmitLengths::OnlyTables),
+        ])?.unwrap_or(defaults.emit_lengths);
+        let key_folding = choose("keyFolding", self.key_folding.as_deref(), &[
+            ("off", toon_lib::KeyFolding::Off),
+            ("safe", toon_lib::KeyFolding::Safe),
+            ("aggressive", toon_lib::KeyFolding::Aggressive),
+        ])?.unwrap_or(defaults.key_folding);
+        let missing_cell = choose("missingCell", self.missing_cell.as_deref(), &[
+            ("null", toon_lib::MissingCell::Null),
+            ("empty", toon_lib::MissingCell::Empty),
+        ])?.unwrap_or(defaults.missing_cell);
+        let column_order = match &self.column_order {
+            Some(Either::B(names)) => toon_lib::ColumnOrder::Explicit(names.clone()),
+            Some(Either::A(name)) => choose("columnOrder", Some(name), &[
+                ("alphabetical", toon_lib::ColumnOrder::Alphabetical),
+                ("first_object", toon_lib::ColumnOrder::FirstObject),
+                ("shortest_first", toon_lib::ColumnOrder::ShortestFirst),
+            ])?.unwrap_or(defaults.column_order),
+            None => defaults.column_order,
+        };
+        let sort_rows = self.sort_rows.as_ref().map(|columns| {
+            columns.iter().fold(toon_lib::SortSpec::new(), |spec, column| match column.strip_prefix('-') {
+                Some(column) => spec.desc(column),
+                None => spec.asc(column.as_str()),
+            })
+        });
+        let mut column_types = HashMap::new();
+        for (column, name) in self.column_types.iter().flatten() {
+            let column_type = toon_lib::ColumnType::from_name(name).ok_or_else(|| {
+                Error::new(Status::InvalidArg, format!("unknown column type {:?} for {:?}", name, column))
+            })?;
+            column_types.insert(column.clone(), column_type);
+        }
+        let key_order = choose("keyOrder", self.key_order.as_deref(), &[
+            ("alphabetical", toon_lib::KeyOrder::Alphabetical),
+            ("insertion", toon_lib::KeyOrder::Insertion),
+        ])?.unwrap_or(defaults.key_order);
+        let spec_version = choose("specVersion", self.spec_version.as_deref(), &[
+            ("1.0", toon_lib::SpecVersion::V1_0),
+            ("1.5", toon_lib::SpecVersion::V1_5),
+            ("2.0", toon_lib::SpecVersion::V2_0),
+        ])?;
+
+        Ok(toon_lib::EncodeOptions {
+            indent: self.indent.as_ref().map_or(defaults.indent, indent_style),
+            delimiter,
+            length_marker: self.length_marker.as_deref().map(toon_lib::LengthMarker::prefix),
+            line_ending,
+            trailing_newline: self.trailing_newline.unwrap_or(defaults.trailing_newline),
+            align_columns: self.align_columns.unwrap_or(defaults.align_columns),
+            max_line_width: size(self.max_line_width),
+            emit_lengths,
+            key_folding,
+            table_flatten_depth: size(self.table_flatten_depth).unwrap_or(defaults.table_flatten_depth),
+            tabular_min_rows: size(self.tabular_min_rows).unwrap_or(defaults.tabular_min_rows),
+            tabular_max_columns: size(self.tabular_max_columns),
+            tabular_union_threshold: self.tabular_union_threshold,
+            missing_cell,
+            column_order,
+            max_rows: self.max_rows.as_ref().map(RowLimit::to_rust),
+            max_depth: size(self.max_depth),
+            skip_nulls: self.skip_nulls.unwrap_or(defaults.skip_nulls),
+            skip_empty: self.skip_empty.unwrap_or(defaults.skip_empty),
+            sort_rows,
+            group_by: self.group_by.clone(),
+            string_dictionary: size(self.string_dictionary),
+            inline_objects: size(self.inline_objects),
+            table_chunk_rows: size(self.table_chunk_rows),
+            column_aliases: self.column_aliases.clone().unwrap_or_default(),
+            alias_legend: self.alias_legend.unwrap_or(defaults.alias_legend),
+            run_length: self.run_length.unwrap_or(defaults.run_length),
+            type_hints: self.type_hints.unwrap_or(defaults.type_hints),
+            column_types,
+            summarize: self.summarize.as_ref().map(|summarize| {
+                toon_lib::Summarize::new(summarize.threshold as usize, summarize.sample.to_rust())
+            }),
+            lift_constant_columns: self.lift_constant_columns.unwrap_or(defaults.lift_constant_columns),
+            key_order,
+            max_output_bytes: size(self.max_output_bytes),
+            max_nodes: size(self.max_nodes),
+            fail_over_depth: size(self.fail_over_depth).or(defaults.fail_over_depth),
+            lossless: self.lossless.unwrap_or(defaults.lossless),
+            spec_version,
+            max_string_chars: size(self.max_string_chars),
+            float_digits: size(self.float_digits),
+        })
+    }
+}
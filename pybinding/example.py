@@ -1,4 +1,3 @@
-import json
 import toon
 
 # Simple example
@@ -11,8 +10,7 @@ data = {
     }
 }
 
-json_str = json.dumps(data)
-result = toon.encode(json_str)
+result = toon.encode(data)
 print("=== Simple encoding ===")
 print(result)
 
@@ -24,7 +22,15 @@ data2 = {
     ]
 }
 
-json_str2 = json.dumps(data2)
-result2 = toon.encode(json_str2)
+result2 = toon.encode(data2)
 print("\n=== Tabular format ===")
 print(result2)
+
+# Options as keyword arguments or a reusable object
+options = toon.EncodeOptions(delimiter="|", sort_rows=["-price"])
+print("\n=== Pipe-delimited, priciest first ===")
+print(toon.encode(data2, options=options))
+
+# Back to Python objects
+print("\n=== Round trip ===")
+print(toon.decode(result2) == data2)
@@ -1,25 +1,338 @@
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyByteArray, PyBytes, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple};
 extern crate toon as toon_lib;
 
+mod options;
+mod pandas;
+mod stream;
+
+use options::PyEncodeOptions;
+
+/// Containers nested deeper than this are taken to be a reference cycle
+const MAX_DEPTH: usize = 512;
+
+/// Convert a Python object to JSON
+///
+/// Integers outside the 64-bit range become their decimal text and NaN or infinite floats
+/// become null. Objects that aren't JSON types are converted through `__json__()` when they
+/// define it, then as mappings (via `items()`), then `isoformat()` for dates and times, and
+/// finally as any other iterable other than `bytes`. A pandas `DataFrame` becomes an array
+/// of row objects, read a column at a time.
+fn to_json(obj: &Bound<'_, PyAny>, depth: usize) -> PyResult<serde_json::Value> {
+    use serde_json::Value;
+
+    if depth > MAX_DEPTH {
+        return Err(PyValueError::new_err("Nesting too deep (circular reference?)"));
+    }
+    if obj.is_none() {
+        return Ok(Value::Null);
+    }
+    // bool is a subclass of int, so it must be checked first
+    if let Ok(b) = obj.downcast::<PyBool>() {
+        return Ok(Value::Bool(b.is_true()));
+    }
+    if obj.is_instance_of::<PyInt>() {
+        if let Ok(i) = obj.extract::<i64>() {
+            return Ok(Value::from(i));
+        }
+        if let Ok(u) = obj.extract::<u64>() {
+            return Ok(Value::from(u));
+        }
+        return Ok(Value::String(obj.str()?.to_string()));
+    }
+    if let Ok(f) = obj.downcast::<PyFloat>() {
+        return Ok(serde_json::Number::from_f64(f.value()).map_or(Value::Null, Value::Number));
+    }
+    if let Ok(s) = obj.downcast::<PyString>() {
+        return Ok(Value::String(s.to_str()?.to_string()));
+    }
+    if let Ok(dict) = obj.downcast::<PyDict>() {
+        return entries_to_json(dict.iter().map(Ok), depth);
+    }
+    if let Ok(list) = obj.downcast::<PyList>() {
+        return list.iter().map(|item| to_json(&item, depth + 1)).collect::<PyResult<_>>().map(Value::Array);
+    }
+    if let Ok(tuple) = obj.downcast::<PyTuple>() {
+        return tuple.iter().map(|item| to_json(&item, depth + 1)).collect::<PyResult<_>>().map(Value::Array);
+    }
+
+    if pandas::is_data_frame(obj)? {
+        return pandas::frame_to_json(obj, &|item| to_json(item, depth + 1));
+    }
+    if obj.hasattr("__json__")? {
+        return to_json(&obj.call_method0("__json__")?, depth + 1);
+    }
+    if obj.hasattr("items")? {
+        let items = obj.call_method0("items")?;
+        return entries_to_json(items.try_iter()?.map(|entry| entry?.extract::<(Bound<'_, PyAny>, Bound<'_, PyAny>)>()), depth);
+    }
+    if obj.hasattr("isoformat")? {
+        return Ok(Value::String(obj.call_method0("isoformat")?.extract()?));
+    }
+    let binary = obj.is_instance_of::<PyBytes>() || obj.is_instance_of::<PyByteArray>();
+    if let (false, Ok(iter)) = (binary, obj.try_iter()) {
+        return iter.map(|item| to_json(&item?, depth + 1)).collect::<PyResult<_>>().map(Value::Array);
+    }
+    let type_name = obj.get_type().name()?;
+    Err(PyTypeError::new_err(format!("Object of type {} is not JSON serializable", type_name)))
+}
+
+/// Convert mapping entries to a JSON object; keys that aren't strings become their JSON text
+fn entries_to_json<'py>(
+    entries: impl Iterator<Item = PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)>>,
+    depth: usize,
+) -> PyResult<serde_json::Value> {
+    let mut map = serde_json::Map::new();
+    for entry in entries {
+        let (key, value) = entry?;
+        let key = match to_json(&key, depth + 1)? {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        };
+        map.insert(key, to_json(&value, depth + 1)?);
+    }
+    Ok(serde_json::Value::Object(map))
+}
+
+/// Read `data` as the value to encode
+///
+/// A `str` is parsed as JSON text, as in earlier releases; pass `json.dumps(s)` to encode a
+/// string itself. Any other object is converted directly.
+fn input_value(data: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    match data.downcast::<PyString>() {
+        Ok(text) => serde_json::from_str(text.to_str()?).map_err(|e| PyValueError::new_err(format!("Invalid JSON: {}", e))),
+        Err(_) => to_json(data, 0),
+    }
+}
+
+/// Encode a Python object to TOON
+///
+/// Options come from `options` with any keyword arguments applied on top, so
+/// `encode(data, delimiter="\t")` needs no options object. An exceeded budget or a lossy value
+/// under `lossless` raises `ValueError`.
 #[pyfunction]
-#[pyo3(signature = (data, indent=None))]
-fn encode(data: String, indent: Option<usize>) -> PyResult<String> {
-    // Parse JSON string
-    let json_value: serde_json::Value = serde_json::from_str(&data)
-        .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Invalid JSON: {}", e)))?;
-    
-    // Create options
-    let options = indent.map(|i| toon_lib::EncodeOptions {
-        indent: i,
-        delimiter: toon_lib::Delimiter::Comma,
-        length_marker: None,
+#[pyo3(signature = (data, indent=None, options=None, **kwargs))]
+fn encode(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    indent: Option<options::Indent>,
+    options: Option<&Bound<'_, PyEncodeOptions>>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let json_value = input_value(data)?;
+    let mut options = options::resolve(py, options, kwargs)?;
+    if let Some(indent) = indent {
+        options.indent = options::indent_style(&indent);
+    }
+    toon_lib::try_encode(&json_value, Some(options)).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Encode a Python object to TOON and write it to the file object `fp`
+///
+/// The whole document is encoded first and then handed to `fp` in 64 KiB chunks, as `str`
+/// to text files and UTF-8 `bytes` to binary ones, so it is held once on the Rust side but
+/// never as one Python string. Use `dump_rows` to write a large table without building it.
+/// Options are taken as in `encode`.
+#[pyfunction]
+#[pyo3(signature = (data, fp, indent=None, options=None, **kwargs))]
+fn dump(
+    py: Python<'_>,
+    data: &Bound<'_, PyAny>,
+    fp: &Bound<'_, PyAny>,
+    indent: Option<options::Indent>,
+    options: Option<&Bound<'_, PyEncodeOptions>>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<()> {
+    use std::io::Write;
+
+    let json_value = input_value(data)?;
+    let mut options = options::resolve(py, options, kwargs)?;
+    if let Some(indent) = indent {
+        options.indent = options::indent_style(&indent);
+    }
+    let mut writer = stream::buffered(stream::PyWriter::new(fp.clone())?);
+    toon_lib::encode_to_writer(&json_value, &mut writer, Some(options)).map_err(stream::raise)?;
+    writer.flush().map_err(stream::raise)
+}
+
+/// Write rows from any iterable to `fp` as one table named `key`, without collecting them
+///
+/// Rows are converted and written `table_chunk_rows` at a time (1000 by default), each chunk
+/// under its own `key[start..end]{...}:` header that `decode` merges back into one array, so
+/// a generator of millions of rows never has to fit in memory. Returns the number of rows.
+#[pyfunction]
+#[pyo3(signature = (rows, fp, key="rows", options=None, **kwargs))]
+fn dump_rows(
+    py: Python<'_>,
+    rows: &Bound<'_, PyAny>,
+    fp: &Bound<'_, PyAny>,
+    key: &str,
+    options: Option<&Bound<'_, PyEncodeOptions>>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<usize> {
+    use std::io::Write;
+
+    let options = options::resolve(py, options, kwargs)?;
+    let rows = rows.try_iter()?.map(|row| {
+        row.and_then(|row| to_json(&row, 0)).map_err(std::io::Error::other)
     });
-    
-    Ok(toon_lib::encode(&json_value, options))
+    let mut writer = stream::buffered(stream::PyWriter::new(fp.clone())?);
+    let count = toon_lib::encode_stream(key, rows, &mut writer, Some(options)).map_err(stream::raise)?;
+    writer.flush().map_err(stream::raise)?;
+    Ok(count)
+}
+
+/// Encode a pandas `DataFrame` as one table, reading its columns in bulk
+///
+/// Integer, float and boolean columns are copied straight out of their NumPy buffers instead
+/// of going through `df.to_dict("records")`; other columns are converted value by value, with
+/// datetimes as ISO 8601 text. Missing values become null. Rows are written straight from
+/// the columns through `encode_columns`, so no object is built per row unless an option such
+/// as `sort_rows` or `max_rows` works on rows. With `key` the table is written as
+/// `key[N]{...}:`, and with `index` the index is written as leading columns.
+#[pyfunction]
+#[pyo3(signature = (df, key=None, index=false, options=None, **kwargs))]
+fn encode_dataframe(
+    py: Python<'_>,
+    df: &Bound<'_, PyAny>,
+    key: Option<String>,
+    index: bool,
+    options: Option<&Bound<'_, PyEncodeOptions>>,
+    kwargs: Option<&Bound<'_, PyDict>>,
+) -> PyResult<String> {
+    let (names, columns) = pandas::frame_columns(df, index, &|item| to_json(item, 1))?;
+    let options = options::resolve(py, options, kwargs)?;
+    toon_lib::encode_columns(key.as_deref(), &names, columns, Some(options)).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Read TOON from the file object `fp` and decode it to native Python objects
+///
+/// Text and binary files are both accepted. The file is read to the end in 64 KiB chunks and
+/// the collected text is then decoded as a whole, so it is held once on the Rust side but
+/// never as one Python string; decoding doesn't start until the file is read. Options are
+/// taken as in `decode`.
+#[pyfunction]
+#[pyo3(signature = (fp, strict=true, indent=None, expand_paths=false, run_length=false, fail_over_depth=toon_lib::DecodeOptions::default().fail_over_depth))]
+fn load<'py>(
+    py: Python<'py>,
+    fp: &Bound<'py, PyAny>,
+    strict: bool,
+    indent: Option<options::Indent>,
+    expand_paths: bool,
+    run_length: bool,
+    fail_over_depth: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let text = stream::read_all(fp)?;
+    decode(py, &text, strict, indent, expand_paths, run_length, fail_over_depth)
+}
+
+/// An encoder that keeps one set of options and reuses its output buffer across calls
+#[pyclass(name = "ToonEncoder", module = "toon")]
+struct ToonEncoder {
+    encoder: toon_lib::Encoder,
+    options: PyEncodeOptions,
+}
+
+#[pymethods]
+impl ToonEncoder {
+    #[new]
+    #[pyo3(signature = (options=None, **kwargs))]
+    fn new(py: Python<'_>, options: Option<&Bound<'_, PyEncodeOptions>>, kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        let base = match options {
+            Some(options) => options.borrow().clone(),
+            None => PyEncodeOptions::default(),
+        };
+        let options = match kwargs {
+            Some(kwargs) => base.replace_with(py, kwargs)?,
+            None => base,
+        };
+        let encoder = toon_lib::Encoder::new(Some(options.to_rust()?));
+        Ok(Self { encoder, options })
+    }
+
+    /// A copy of the options used for every call
+    #[getter]
+    fn options(&self) -> PyEncodeOptions {
+        self.options.clone()
+    }
+
+    /// Encode `data` as the free `encode` function does
+    fn encode(&mut self, data: &Bound<'_, PyAny>) -> PyResult<String> {
+        let json_value = input_value(data)?;
+        self.encoder.try_encode(&json_value).map(str::to_string).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+}
+
+/// Convert a decoded JSON value to native Python objects
+fn to_python<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    use serde_json::Value;
+
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(b) => PyBool::new(py, *b).to_owned().into_any(),
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_pyobject(py)?.into_any(),
+            (None, Some(u)) => u.into_pyobject(py)?.into_any(),
+            _ => n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any(),
+        },
+        Value::String(s) => PyString::new(py, s).into_any(),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(to_python(py, item)?)?;
+            }
+            list.into_any()
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, to_python(py, item)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+/// Decode TOON text to native Python objects
+///
+/// With `strict`, malformed indentation and array lengths that don't match their header
+/// raise `ValueError`, as any other malformed input does. `indent` is the indentation the
+/// text was written with, spaces per level or the text of one level, and `expand_paths`
+/// turns dotted keys written with `key_folding` back into nested dicts. `run_length` expands
+/// the `value xN` runs that encoding with `run_length=True` writes. Lines nested more than
+/// `fail_over_depth` levels deep raise `ValueError`; pass `None` for no limit.
+#[pyfunction]
+#[pyo3(signature = (text, strict=true, indent=None, expand_paths=false, run_length=false, fail_over_depth=toon_lib::DecodeOptions::default().fail_over_depth))]
+fn decode<'py>(
+    py: Python<'py>,
+    text: &str,
+    strict: bool,
+    indent: Option<options::Indent>,
+    expand_paths: bool,
+    run_length: bool,
+    fail_over_depth: Option<usize>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let options = toon_lib::DecodeOptions {
+        indent: indent.as_ref().map(options::indent_style).unwrap_or_default(),
+        strict,
+        expand_paths,
+        run_length,
+        fail_over_depth,
+    };
+    let value = toon_lib::decode(text, Some(options)).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    to_python(py, &value)
 }
 
 #[pymodule]
 fn toon(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add_function(wrap_pyfunction!(dump, m)?)?;
+    m.add_function(wrap_pyfunction!(dump_rows, m)?)?;
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+    m.add_function(wrap_pyfunction!(encode_dataframe, m)?)?;
+    m.add_class::<PyEncodeOptions>()?;
+    m.add_class::<ToonEncoder>()?;
     Ok(())
 }
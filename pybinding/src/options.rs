@@ -0,0 +1,350 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use toon_lib::{
+    ColumnOrder, ColumnType, Delimiter, EmitLengths, IndentStyle, KeyFolding, KeyOrder, LengthMarker, LineEnding,
+    MissingCell, RowLimit, SortSpec, SpecVersion, Summarize,
+};
+
+/// An indent given as a number of spaces or as the text of one level
+#[derive(Clone, FromPyObject, IntoPyObject)]
+pub enum Indent {
+    Spaces(usize),
+    Text(String),
+}
+
+/// Rows kept per table: the first `n`, or the first `head` and the last `tail`
+#[derive(Clone, FromPyObject, IntoPyObject)]
+pub enum Rows {
+    Head(usize),
+    HeadTail(usize, usize),
+}
+
+impl Rows {
+    fn limit(&self) -> RowLimit {
+        match *self {
+            Rows::Head(head) => RowLimit::new(head, 0),
+            Rows::HeadTail(head, tail) => RowLimit::new(head, tail),
+        }
+    }
+}
+
+/// Table column order: a strategy name or the explicit column names
+#[derive(Clone, FromPyObject, IntoPyObject)]
+pub enum Columns {
+    Strategy(String),
+    Explicit(Vec<String>),
+}
+
+/// Encoding options, mirroring the Rust `EncodeOptions` with strings for its enums
+///
+/// Values are checked when the options are created and again when they are used, so an
+/// attribute set to an unknown name raises `ValueError` at the next `encode`.
+#[pyclass(name = "EncodeOptions", module = "toon")]
+#[derive(Clone)]
+pub struct PyEncodeOptions {
+    /// Spaces per level, or the text of one level such as `"\t"`
+    #[pyo3(get, set)]
+    pub indent: Indent,
+    /// `","`, `"\t"`, `"|"` or `";"`
+    #[pyo3(get, set)]
+    pub delimiter: String,
+    /// Text written before array lengths, such as `"#"`
+    #[pyo3(get, set)]
+    pub length_marker: Option<String>,
+    /// `"\n"` or `"\r\n"`
+    #[pyo3(get, set)]
+    pub line_ending: String,
+    #[pyo3(get, set)]
+    pub trailing_newline: bool,
+    #[pyo3(get, set)]
+    pub align_columns: bool,
+    #[pyo3(get, set)]
+    pub max_line_width: Option<usize>,
+    /// `"always"`, `"never"` or `"tables"`
+    #[pyo3(get, set)]
+    pub emit_lengths: String,
+    /// `"off"`, `"safe"` or `"aggressive"`
+    #[pyo3(get, set)]
+    pub key_folding: String,
+    #[pyo3(get, set)]
+    pub table_flatten_depth: usize,
+    #[pyo3(get, set)]
+    pub tabular_min_rows: usize,
+    #[pyo3(get, set)]
+    pub tabular_max_columns: Option<usize>,
+    #[pyo3(get, set)]
+    pub tabular_union_threshold: Option<f64>,
+    /// `"null"` or `"empty"`
+    #[pyo3(get, set)]
+    pub missing_cell: String,
+    /// `"alphabetical"`, `"first_object"`, `"shortest_first"` or a list of column names
+    #[pyo3(get, set)]
+    pub column_order: Columns,
+    /// `n` or `(head, tail)`
+    #[pyo3(get, set)]
+    pub max_rows: Option<Rows>,
+    #[pyo3(get, set)]
+    pub max_depth: Option<usize>,
+    #[pyo3(get, set)]
+    pub skip_nulls: bool,
+    #[pyo3(get, set)]
+    pub skip_empty: bool,
+    /// Column names to sort rows by, each prefixed with `-` to sort descending
+    #[pyo3(get, set)]
+    pub sort_rows: Option<Vec<String>>,
+    #[pyo3(get, set)]
+    pub group_by: Option<String>,
+    #[pyo3(get, set)]
+    pub string_dictionary: Option<usize>,
+    #[pyo3(get, set)]
+    pub inline_objects: Option<usize>,
+    #[pyo3(get, set)]
+    pub table_chunk_rows: Option<usize>,
+    #[pyo3(get, set)]
+    pub column_aliases: HashMap<String, String>,
+    #[pyo3(get, set)]
+    pub alias_legend: bool,
+    #[pyo3(get, set)]
+    pub run_length: bool,
+    #[pyo3(get, set)]
+    pub type_hints: bool,
+    /// Hint names by column, such as `{"amount": "decimal"}`
+    #[pyo3(get, set)]
+    pub column_types: HashMap<String, String>,
+    /// `(threshold, sample)` where `sample` is `n` or `(head, tail)`
+    #[pyo3(get, set)]
+    pub summarize: Option<(usize, Rows)>,
+    #[pyo3(get, set)]
+    pub lift_constant_columns: bool,
+    /// `"alphabetical"` or `"insertion"`
+    #[pyo3(get, set)]
+    pub key_order: String,
+    #[pyo3(get, set)]
+    pub max_output_bytes: Option<usize>,
+    #[pyo3(get, set)]
+    pub max_nodes: Option<usize>,
+    #[pyo3(get, set)]
+    pub fail_over_depth: Option<usize>,
+    #[pyo3(get, set)]
+    pub lossless: bool,
+    /// `"1.0"`, `"1.5"` or `"2.0"`
+    #[pyo3(get, set)]
+    pub spec_version: Option<String>,
+    #[pyo3(get, set)]
+    pub max_string_chars: Option<usize>,
+    #[pyo3(get, set)]
+    pub float_digits: Option<usize>,
+}
+
+impl Default for PyEncodeOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            delimiter: ",".to_string(),
+            length_marker: None,
+            line_ending: "\n".to_string(),
+            trailing_newline: false,
+            align_columns: false,
+            max_line_width: None,
+            emit_lengths: "always".to_string(),
+            key_folding: "off".to_string(),
+            table_flatten_depth: 0,
+            tabular_min_rows: 1,
+            tabular_max_columns: None,
+            tabular_union_threshold: None,
+            missing_cell: "null".to_string(),
+            column_order: Columns::Strategy("alphabetical".to_string()),
+            max_rows: None,
+            max_depth: None,
+            skip_nulls: false,
+            skip_empty: false,
+            sort_rows: None,
+            group_by: None,
+            string_dictionary: None,
+            inline_objects: None,
+            table_chunk_rows: None,
+            column_aliases: HashMap::new(),
+            alias_legend: false,
+            run_length: false,
+            type_hints: false,
+            column_types: HashMap::new(),
+            summarize: None,
+            lift_constant_columns: false,
+            key_order: "alphabetical".to_string(),
+            max_output_bytes: None,
+            max_nodes: None,
+            fail_over_depth: toon_lib::EncodeOptions::default().fail_over_depth,
+            lossless: false,
+            spec_version: None,
+            max_string_chars: None,
+            float_digits: None,
+        }
+    }
+}
+
+pub fn indent_style(indent: &Indent) -> IndentStyle {
+    match indent {
+        Indent::Spaces(n) => IndentStyle::Spaces(*n),
+        Indent::Text(text) if text == "\t" => IndentStyle::Tabs,
+        Indent::Text(text) => IndentStyle::Custom(text.clone()),
+    }
+}
+
+/// Look `name` up in `choices`, raising `ValueError` that lists them otherwise
+fn choose<T: Clone>(option: &str, name: &str, choices: &[(&str, T)]) -> PyResult<T> {
+    match choices.iter().find(|(choice, _)| *choice == name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => {
+            let names: Vec<String> = choices.iter().map(|(choice, _)| format!("{:?}", choice)).collect();
+            Err(PyValueError::new_err(format!("{} must be one of {}, not {:?}", option, names.join(", "), name)))
+        }
+    }
+}
+
+#[pymethods]
+impl PyEncodeOptions {
+    /// Create options from keyword arguments; anything not given keeps its default
+    #[new]
+    #[pyo3(signature = (**kwargs))]
+    fn new(py: Python<'_>, kwargs: Option<&Bound<'_, pyo3::types::PyDict>>) -> PyResult<Self> {
+        match kwargs {
+            Some(kwargs) => Self::default().replace(py, Some(kwargs)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// A copy with the given attributes replaced
+    #[pyo3(signature = (**kwargs))]
+    fn replace(&self, py: Python<'_>, kwargs: Option<&Bound<'_, pyo3::types::PyDict>>) -> PyResult<Self> {
+        let options = match kwargs {
+            Some(kwargs) => self.replace_with(py, kwargs)?,
+            None => self.clone(),
+        };
+        options.to_rust()?;
+        Ok(options)
+    }
+}
+
+/// Set each keyword argument as an attribute, rejecting names that aren't options
+fn set_all(options: &Bound<'_, PyEncodeOptions>, kwargs: &Bound<'_, pyo3::types::PyDict>) -> PyResult<()> {
+    for (name, value) in kwargs {
+        let name: String = name.extract()?;
+        if !options.hasattr(name.as_str())? {
+            return Err(pyo3::exceptions::PyTypeError::new_err(format!("unexpected option {:?}", name)));
+        }
+        options.setattr(name.as_str(), value)?;
+    }
+    Ok(())
+}
+
+impl PyEncodeOptions {
+    /// A copy with each keyword argument set as an attribute
+    pub fn replace_with(&self, py: Python<'_>, kwargs: &Bound<'_, pyo3::types::PyDict>) -> PyResult<Self> {
+        let options = Bound::new(py, self.clone())?;
+        set_all(&options, kwargs)?;
+        let options = options.borrow().clone();
+        Ok(options)
+    }
+
+    /// The Rust options these describe, or `ValueError` naming the first invalid value
+    pub fn to_rust(&self) -> PyResult<toon_lib::EncodeOptions> {
+        let indent = indent_style(&self.indent);
+        let delimiter = choose("delimiter", &self.delimiter, &[
+            (",", Delimiter::Comma), ("\t", Delimiter::Tab), ("|", Delimiter::Pipe), (";", Delimiter::Semicolon),
+        ])?;
+        let line_ending = choose("line_ending", &self.line_ending, &[("\n", LineEnding::Lf), ("\r\n", LineEnding::CrLf)])?;
+        let emit_lengths = choose("emit_lengths", &self.emit_lengths, &[
+            ("always", EmitLengths::Always), ("never", EmitLengths::Never), ("tables", EmitLengths::OnlyTables),
+        ])?;
+        let key_folding = choose("key_folding", &self.key_folding, &[
+            ("off", KeyFolding::Off), ("safe", KeyFolding::Safe), ("aggressive", KeyFolding::Aggressive),
+        ])?;
+        let missing_cell = choose("missing_cell", &self.missing_cell, &[("null", MissingCell::Null), ("empty", MissingCell::Empty)])?;
+        let column_order = match &self.column_order {
+            Columns::Explicit(names) => ColumnOrder::Explicit(names.clone()),
+            Columns::Strategy(name) => choose("column_order", name, &[
+                ("alphabetical", ColumnOrder::Alphabetical),
+                ("first_object", ColumnOrder::FirstObject),
+                ("shortest_first", ColumnOrder::ShortestFirst),
+            ])?,
+        };
+        let sort_rows = self.sort_rows.as_ref().map(|columns| {
+            columns.iter().fold(SortSpec::new(), |spec, column| match column.strip_prefix('-') {
+                Some(column) => spec.desc(column),
+                None => spec.asc(column.as_str()),
+            })
+        });
+        let mut column_types = HashMap::new();
+        for (column, name) in &self.column_types {
+            let column_type = ColumnType::from_name(name)
+                .ok_or_else(|| PyValueError::new_err(format!("unknown column type {:?} for {:?}", name, column)))?;
+            column_types.insert(column.clone(), column_type);
+        }
+        let key_order = choose("key_order", &self.key_order, &[("alphabetical", KeyOrder::Alphabetical), ("insertion", KeyOrder::Insertion)])?;
+        let spec_version = match &self.spec_version {
+            Some(version) => Some(choose("spec_version", version, &[
+                ("1.0", SpecVersion::V1_0), ("1.5", SpecVersion::V1_5), ("2.0", SpecVersion::V2_0),
+            ])?),
+            None => None,
+        };
+
+        Ok(toon_lib::EncodeOptions {
+            indent,
+            delimiter,
+            length_marker: self.length_marker.as_deref().map(LengthMarker::prefix),
+            line_ending,
+            trailing_newline: self.trailing_newline,
+            align_columns: self.align_columns,
+            max_line_width: self.max_line_width,
+            emit_lengths,
+            key_folding,
+            table_flatten_depth: self.table_flatten_depth,
+            tabular_min_rows: self.tabular_min_rows,
+            tabular_max_columns: self.tabular_max_columns,
+            tabular_union_threshold: self.tabular_union_threshold,
+            missing_cell,
+            column_order,
+            max_rows: self.max_rows.as_ref().map(Rows::limit),
+            max_depth: self.max_depth,
+            skip_nulls: self.skip_nulls,
+            skip_empty: self.skip_empty,
+            sort_rows,
+            group_by: self.group_by.clone(),
+            string_dictionary: self.string_dictionary,
+            inline_objects: self.inline_objects,
+            table_chunk_rows: self.table_chunk_rows,
+            column_aliases: self.column_aliases.clone(),
+            alias_legend: self.alias_legend,
+            run_length: self.run_length,
+            type_hints: self.type_hints,
+            column_types,
+            summarize: self.summarize.as_ref().map(|(threshold, sample)| Summarize::new(*threshold, sample.limit())),
+            lift_constant_columns: self.lift_constant_columns,
+            key_order,
+            max_output_bytes: self.max_output_bytes,
+            max_nodes: self.max_nodes,
+            fail_over_depth: self.fail_over_depth,
+            lossless: self.lossless,
+            spec_version,
+            max_string_chars: self.max_string_chars,
+            float_digits: self.float_digits,
+        })
+    }
+}
+
+/// Options for one call: `options` (or the defaults) with `kwargs` applied on top
+pub fn resolve(
+    py: Python<'_>,
+    options: Option<&Bound<'_, PyEncodeOptions>>,
+    kwargs: Option<&Bound<'_, pyo3::types::PyDict>>,
+) -> PyResult<toon_lib::EncodeOptions> {
+    let base = match options {
+        Some(options) => options.borrow().clone(),
+        None => PyEncodeOptions::default(),
+    };
+    match kwargs {
+        Some(kwargs) => base.replace_with(py, kwargs)?.to_rust(),
+        None => base.to_rust(),
+    }
+}
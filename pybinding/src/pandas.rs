@@ -0,0 +1,98 @@
+use pyo3::buffer::{Element, PyBuffer};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::{Map, Number, Value};
+
+/// Whether `obj` is a `pandas.DataFrame`, checked by name so pandas need not be installed
+pub fn is_data_frame(obj: &Bound<'_, PyAny>) -> PyResult<bool> {
+    let class = obj.get_type();
+    if class.name()? != "DataFrame" {
+        return Ok(false);
+    }
+    let module: String = class.getattr("__module__")?.extract()?;
+    Ok(module.starts_with("pandas"))
+}
+
+/// Copy a NumPy array's values out through the buffer protocol
+fn buffer_values<T: Element + Copy>(py: Python<'_>, array: &Bound<'_, PyAny>) -> PyResult<Vec<T>> {
+    PyBuffer::<T>::get(array)?.to_vec(py)
+}
+
+/// One column's values as JSON, read in bulk by dtype
+///
+/// Integer, float and boolean columns are copied through NumPy buffers; other columns
+/// (strings, datetimes, categoricals, objects) are converted item by item with `convert`.
+/// Missing values (`NaN`, `None`, `NA`, `NaT`) become null.
+fn column_values(
+    py: Python<'_>,
+    series: &Bound<'_, PyAny>,
+    convert: &dyn Fn(&Bound<'_, PyAny>) -> PyResult<Value>,
+) -> PyResult<Vec<Value>> {
+    // Booleans are read as `uint8`, which the buffer protocol can hand over directly
+    let missing: Vec<u8> = buffer_values(py, &series.call_method0("isna")?.call_method1("astype", ("uint8",))?.call_method0("to_numpy")?)?;
+    let kind: String = series.getattr("dtype")?.getattr("kind")?.extract()?;
+    let to_numpy = |dtype: &str, na_value: Bound<'_, PyAny>| -> PyResult<Bound<'_, PyAny>> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("dtype", dtype)?;
+        kwargs.set_item("na_value", na_value)?;
+        series.call_method("to_numpy", (), Some(&kwargs))
+    };
+
+    let values: Vec<Value> = match kind.as_str() {
+        "i" => buffer_values::<i64>(py, &to_numpy("int64", 0i64.into_pyobject(py)?.into_any())?)?.into_iter().map(Value::from).collect(),
+        "u" => buffer_values::<u64>(py, &to_numpy("uint64", 0u64.into_pyobject(py)?.into_any())?)?.into_iter().map(Value::from).collect(),
+        "f" => buffer_values::<f64>(py, &to_numpy("float64", f64::NAN.into_pyobject(py)?.into_any())?)?
+            .into_iter()
+            .map(|f| Number::from_f64(f).map_or(Value::Null, Value::Number))
+            .collect(),
+        "b" => buffer_values::<u8>(py, &to_numpy("uint8", 0u8.into_pyobject(py)?.into_any())?)?.into_iter().map(|b| Value::Bool(b != 0)).collect(),
+        _ => {
+            let items = series.call_method1("astype", ("object",))?.call_method0("tolist")?;
+            let mut values = Vec::with_capacity(missing.len());
+            for (item, missing) in items.try_iter()?.zip(&missing) {
+                values.push(if *missing != 0 { Value::Null } else { convert(&item?)? });
+            }
+            return Ok(values);
+        }
+    };
+    Ok(values.into_iter().zip(missing).map(|(value, missing)| if missing != 0 { Value::Null } else { value }).collect())
+}
+
+/// A data frame's column names and the values of each column, read a column at a time
+///
+/// With `index`, the index is read as leading columns, as `reset_index()` would.
+pub fn frame_columns(
+    frame: &Bound<'_, PyAny>,
+    index: bool,
+    convert: &dyn Fn(&Bound<'_, PyAny>) -> PyResult<Value>,
+) -> PyResult<(Vec<String>, Vec<Vec<Value>>)> {
+    let py = frame.py();
+    let frame = if index { frame.call_method0("reset_index")? } else { frame.clone() };
+    let names: Vec<String> = frame.getattr("columns")?.try_iter()?.map(|name| name?.str().map(|name| name.to_string())).collect::<PyResult<_>>()?;
+    let iloc = frame.getattr("iloc")?;
+
+    let mut columns = Vec::with_capacity(names.len());
+    for position in 0..names.len() {
+        let series = iloc.get_item((pyo3::types::PySlice::full(py), position))?;
+        columns.push(column_values(py, &series, convert)?);
+    }
+    Ok((names, columns))
+}
+
+/// Convert a data frame to an array of row objects keyed by column name, for frames nested
+/// inside other data
+pub fn frame_to_json(frame: &Bound<'_, PyAny>, convert: &dyn Fn(&Bound<'_, PyAny>) -> PyResult<Value>) -> PyResult<Value> {
+    let height = frame.len()?;
+    let (names, columns) = frame_columns(frame, false, convert)?;
+    let mut columns: Vec<_> = columns.into_iter().map(Vec::into_iter).collect();
+
+    let mut rows = Vec::with_capacity(height);
+    for _ in 0..height {
+        let mut row = Map::new();
+        for (name, column) in names.iter().zip(columns.iter_mut()) {
+            row.insert(name.clone(), column.next().unwrap_or(Value::Null));
+        }
+        rows.push(Value::Object(row));
+    }
+    Ok(Value::Array(rows))
+}
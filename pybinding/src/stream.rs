@@ -0,0 +1,91 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+use std::io::{self, Write};
+
+/// Bytes handed to a file object per `write` call
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Writes to a Python file object, as `str` for text files and `bytes` otherwise
+pub struct PyWriter<'py> {
+    file: Bound<'py, PyAny>,
+    text: bool,
+    /// Bytes of a character split across two `write` calls, kept until the rest arrives
+    pending: Vec<u8>,
+}
+
+impl<'py> PyWriter<'py> {
+    pub fn new(file: Bound<'py, PyAny>) -> PyResult<Self> {
+        // Text files have an `encoding`; binary files and `BytesIO` don't
+        let text = file.hasattr("encoding")?;
+        Ok(Self { file, text, pending: Vec::new() })
+    }
+}
+
+impl Write for PyWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let py = self.file.py();
+        let chunk = if self.text {
+            self.pending.extend_from_slice(buf);
+            let valid = match std::str::from_utf8(&self.pending) {
+                Ok(text) => text.len(),
+                Err(err) => err.valid_up_to(),
+            };
+            if valid == 0 {
+                return Ok(buf.len());
+            }
+            let text = std::str::from_utf8(&self.pending[..valid]).expect("checked above");
+            let chunk = PyString::new(py, text).into_any();
+            self.pending.drain(..valid);
+            chunk
+        } else {
+            PyBytes::new(py, buf).into_any()
+        };
+        self.file.call_method1("write", (chunk,)).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Turn an I/O error back into the Python exception it wraps, or `OSError`
+pub fn raise(err: io::Error) -> PyErr {
+    if err.get_ref().is_some_and(|inner| inner.is::<PyErr>()) {
+        return *err.into_inner().expect("checked above").downcast::<PyErr>().expect("checked above");
+    }
+    match err.kind() {
+        io::ErrorKind::InvalidData => PyValueError::new_err(err.to_string()),
+        _ => PyErr::from(err),
+    }
+}
+
+/// A buffered writer sized for handing large chunks to Python
+pub fn buffered(writer: PyWriter<'_>) -> io::BufWriter<PyWriter<'_>> {
+    io::BufWriter::with_capacity(CHUNK_BYTES, writer)
+}
+
+/// Read a file object to the end, `CHUNK_BYTES` at a time, as UTF-8 text
+///
+/// Both text and binary files are accepted; the whole text is held only on the Rust side.
+pub fn read_all(file: &Bound<'_, PyAny>) -> PyResult<String> {
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = file.call_method1("read", (CHUNK_BYTES,))?;
+        if let Ok(text) = chunk.downcast::<PyString>() {
+            let text = text.to_str()?;
+            if text.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(text.as_bytes());
+        } else {
+            let data: &[u8] = chunk.extract()?;
+            if data.is_empty() {
+                break;
+            }
+            bytes.extend_from_slice(data);
+        }
+    }
+    String::from_utf8(bytes).map_err(|e| PyValueError::new_err(format!("Invalid UTF-8: {}", e)))
+}
@@ -0,0 +1,43 @@
+import io
+import unittest
+
+import toon
+
+
+class DecodeTest(unittest.TestCase):
+    def test_round_trip(self):
+        data = {"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Bob"}], "active": True}
+        self.assertEqual(toon.decode(toon.encode(data)), data)
+
+    def test_indent(self):
+        data = {"a": {"b": {"c": 1}}}
+        for indent in [4, "\t"]:
+            self.assertEqual(toon.decode(toon.encode(data, indent=indent), indent=indent), data)
+
+    def test_expand_paths(self):
+        data = {"a": {"b": {"c": 1}}, "rows": [{"x": 1}, {"x": 2}]}
+        folded = toon.encode(data, key_folding="safe")
+        self.assertEqual(toon.decode(folded), {"a.b.c": 1, "rows": data["rows"]})
+        self.assertEqual(toon.decode(folded, expand_paths=True), data)
+
+    def test_run_length(self):
+        data = {"flags": [True, True, True, True, False], "rows": [{"x": 0}, {"x": 0}, {"x": 0}, {"x": 1}]}
+        runs = toon.encode(data, run_length=True)
+        self.assertIn("x3", runs)
+        self.assertEqual(toon.decode(runs, run_length=True), data)
+        self.assertEqual(toon.load(io.StringIO(runs), run_length=True), data)
+
+    def test_fail_over_depth(self):
+        text = "a:\n  b:\n    c: 1"
+        with self.assertRaises(ValueError):
+            toon.decode(text, fail_over_depth=1)
+        self.assertEqual(toon.decode(text, fail_over_depth=None), {"a": {"b": {"c": 1}}})
+
+    def test_load(self):
+        folded = toon.encode({"a": {"b": 1}}, key_folding="safe", indent=4)
+        self.assertEqual(toon.load(io.StringIO(folded), indent=4, expand_paths=True), {"a": {"b": 1}})
+        self.assertEqual(toon.load(io.BytesIO(folded.encode())), {"a.b": 1})
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,38 @@
+import unittest
+
+import toon
+
+try:
+    import pandas
+except ImportError:
+    pandas = None
+
+
+@unittest.skipUnless(pandas, "pandas is not installed")
+class DataFrameTest(unittest.TestCase):
+    def frame(self):
+        return pandas.DataFrame({"sku": ["A1", "B2", None], "qty": [2, 1, 3], "price": [9.99, float("nan"), 1.5]})
+
+    def records(self, df):
+        return [{k: (None if v != v else v) for k, v in row.items()} for row in df.to_dict("records")]
+
+    def test_matches_records(self):
+        df = self.frame()
+        records = self.records(df)
+        layouts = [{}, {"key_order": "insertion", "column_order": "first_object"}, {"delimiter": "\t"}, {"sort_rows": ["-qty"], "max_rows": 1}]
+        for options in layouts:
+            with self.subTest(options=options):
+                self.assertEqual(toon.encode_dataframe(df, key="items", **options), toon.encode({"items": records}, **options))
+
+    def test_columns(self):
+        self.assertEqual(toon.encode_dataframe(self.frame(), key="items"), "items[3]{price,qty,sku}:\n  9.99,2,A1\n  null,1,B2\n  1.5,3,null")
+
+    def test_empty(self):
+        self.assertEqual(toon.encode_dataframe(self.frame().iloc[:0]), toon.encode([]))
+
+    def test_nested(self):
+        self.assertEqual(toon.decode(toon.encode({"orders": self.frame()}))["orders"][0], {"sku": "A1", "qty": 2, "price": 9.99})
+
+
+if __name__ == "__main__":
+    unittest.main()
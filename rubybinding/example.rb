@@ -0,0 +1,37 @@
+# frozen_string_literal: true
+
+require "time"
+require_relative "lib/toon"
+
+# Simple example
+data = {
+  user: {
+    id: 123,
+    name: "Ada",
+    tags: %w[reading gaming],
+    active: true,
+    joined: Time.utc(2024, 1, 15)
+  }
+}
+
+puts "=== Simple encoding ==="
+puts Toon.encode(data)
+
+# Array of objects
+data2 = {
+  items: [
+    { sku: "A1", qty: 2, price: 9.99 },
+    { sku: "B2", qty: 1, price: 14.5 }
+  ]
+}
+
+puts "\n=== Tabular format ==="
+puts Toon.encode(data2)
+
+# Options as a hash or keyword arguments
+puts "\n=== Pipe delimiter, sorted by price ==="
+puts Toon.encode(data2, { delimiter: "|" }, sort_rows: ["-price"])
+
+# Round trip
+puts "\n=== Decoded ==="
+p Toon.decode(Toon.encode(data2), symbolize_names: true)
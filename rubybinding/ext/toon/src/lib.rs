@@ -0,0 +1,181 @@
+extern crate toon as toon_lib;
+
+mod options;
+
+use magnus::prelude::*;
+use magnus::r_hash::ForEach;
+use magnus::scan_args::scan_args;
+use magnus::value::{Lazy, Qfalse, Qtrue};
+use magnus::{function, Error, ExceptionClass, Float, Integer, IntoValue, RArray, RHash, RModule, RString, Ruby, Symbol, Value};
+use options::{RbDecodeOptions, RbEncodeOptions};
+use serde_json::{Map, Number};
+
+/// Nesting depth past which conversion stops, so cyclic structures raise instead of overflowing
+const MAX_DEPTH: usize = 512;
+
+static ENCODE_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| toon_error(ruby, "EncodeError"));
+static DECODE_ERROR: Lazy<ExceptionClass> = Lazy::new(|ruby| toon_error(ruby, "DecodeError"));
+
+fn toon_error(ruby: &Ruby, name: &str) -> ExceptionClass {
+    let module: RModule = ruby.class_object().const_get("Toon").expect("Toon is defined by init");
+    module.const_get(name).expect("errors are defined by init")
+}
+
+/// Convert a Ruby object to JSON
+///
+/// Hashes, arrays, strings, symbols, numbers, booleans and nil are converted directly. Other
+/// objects go through `as_json` when they define it (as Rails models do), then `iso8601` for
+/// dates and times, then `to_h`; anything else raises `TypeError`. `NaN` and infinities
+/// become null, and integers that don't fit in 64 bits become strings.
+fn to_json(ruby: &Ruby, obj: Value, depth: usize) -> Result<serde_json::Value, Error> {
+    if depth > MAX_DEPTH {
+        return Err(Error::new(ruby.exception_arg_error(), format!("object is nested more than {} levels deep", MAX_DEPTH)));
+    }
+    if obj.is_nil() {
+        return Ok(serde_json::Value::Null);
+    }
+    if Qtrue::from_value(obj).is_some() {
+        return Ok(serde_json::Value::Bool(true));
+    }
+    if Qfalse::from_value(obj).is_some() {
+        return Ok(serde_json::Value::Bool(false));
+    }
+    if let Some(i) = Integer::from_value(obj) {
+        if let Ok(i) = i.to_i64() {
+            return Ok(serde_json::Value::from(i));
+        }
+        if let Ok(u) = i.to_u64() {
+            return Ok(serde_json::Value::from(u));
+        }
+        return Ok(serde_json::Value::String(obj.to_r_string()?.to_string()?));
+    }
+    if let Some(f) = Float::from_value(obj) {
+        return Ok(Number::from_f64(f.to_f64()).map_or(serde_json::Value::Null, serde_json::Value::Number));
+    }
+    if let Some(s) = RString::from_value(obj) {
+        return Ok(serde_json::Value::String(s.to_string()?));
+    }
+    if let Some(symbol) = Symbol::from_value(obj) {
+        return Ok(serde_json::Value::String(symbol.name()?.into_owned()));
+    }
+    if let Some(array) = RArray::from_value(obj) {
+        let items = array.to_vec::<Value>()?;
+        return items.into_iter().map(|item| to_json(ruby, item, depth + 1)).collect::<Result<_, _>>().map(serde_json::Value::Array);
+    }
+    if let Some(hash) = RHash::from_value(obj) {
+        return hash_to_json(ruby, hash, depth);
+    }
+    if obj.respond_to("as_json", false)? {
+        return to_json(ruby, obj.funcall("as_json", ())?, depth + 1);
+    }
+    if obj.respond_to("iso8601", false)? {
+        return Ok(serde_json::Value::String(obj.funcall("iso8601", ())?));
+    }
+    if obj.respond_to("to_h", false)? {
+        return to_json(ruby, obj.funcall("to_h", ())?, depth + 1);
+    }
+    let class = obj.class().inspect();
+    Err(Error::new(ruby.exception_type_error(), format!("{} can't be encoded as TOON", class)))
+}
+
+/// Convert a hash to a JSON object, turning keys that aren't strings into their `to_s`
+fn hash_to_json(ruby: &Ruby, hash: RHash, depth: usize) -> Result<serde_json::Value, Error> {
+    let mut map = Map::new();
+    hash.foreach(|key: Value, value: Value| {
+        let key = match Symbol::from_value(key) {
+            Some(symbol) => symbol.name()?.into_owned(),
+            None => key.to_r_string()?.to_string()?,
+        };
+        map.insert(key, to_json(ruby, value, depth + 1)?);
+        Ok(ForEach::Continue)
+    })?;
+    Ok(serde_json::Value::Object(map))
+}
+
+/// Read an options hash, or the defaults when there isn't one
+fn read_options<T: Default + serde::de::DeserializeOwned>(ruby: &Ruby, options: Option<RHash>) -> Result<T, Error> {
+    match options {
+        Some(options) => serde_json::from_value(hash_to_json(ruby, options, 0)?)
+            .map_err(|e| Error::new(ruby.exception_arg_error(), format!("invalid options: {}", e))),
+        None => Ok(T::default()),
+    }
+}
+
+/// The options hash given positionally, with any keyword arguments merged over it
+fn merged_options(options: Option<RHash>, keywords: RHash) -> Result<Option<RHash>, Error> {
+    match options {
+        Some(options) if !keywords.is_empty() => Ok(Some(options.funcall("merge", (keywords,))?)),
+        Some(options) => Ok(Some(options)),
+        None if keywords.is_empty() => Ok(None),
+        None => Ok(Some(keywords)),
+    }
+}
+
+/// `Toon.encode(data, options = {}, **kwargs)`
+///
+/// Options are named after the Rust `EncodeOptions` fields, with strings or symbols for
+/// enums; keyword arguments apply on top of the hash. Unknown or invalid options raise
+/// `ArgumentError`, and an exceeded budget or a lossy value under `lossless` raises
+/// `Toon::EncodeError`.
+fn encode(ruby: &Ruby, args: &[Value]) -> Result<String, Error> {
+    let args = scan_args::<(Value,), (Option<RHash>,), (), (), RHash, ()>(args)?;
+    let (data,) = args.required;
+    let (options,) = args.optional;
+    let json_value = to_json(ruby, data, 0)?;
+    let options: RbEncodeOptions = read_options(ruby, merged_options(options, args.keywords)?)?;
+    let options = options.to_rust().map_err(|message| Error::new(ruby.exception_arg_error(), message))?;
+    toon_lib::try_encode(&json_value, Some(options)).map_err(|e| Error::new(ruby.get_inner(&ENCODE_ERROR), e.to_string()))
+}
+
+/// Convert a decoded JSON value to Ruby hashes, arrays and scalars
+fn to_ruby(ruby: &Ruby, value: &serde_json::Value, symbolize_names: bool) -> Result<Value, Error> {
+    Ok(match value {
+        serde_json::Value::Null => ruby.qnil().as_value(),
+        serde_json::Value::Bool(b) => b.into_value_with(ruby),
+        serde_json::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => ruby.integer_from_i64(i).as_value(),
+            (None, Some(u)) => ruby.integer_from_u64(u).as_value(),
+            _ => ruby.float_from_f64(n.as_f64().unwrap_or(f64::NAN)).as_value(),
+        },
+        serde_json::Value::String(s) => ruby.str_new(s).as_value(),
+        serde_json::Value::Array(items) => {
+            let array = ruby.ary_new_capa(items.len());
+            for item in items {
+                array.push(to_ruby(ruby, item, symbolize_names)?)?;
+            }
+            array.as_value()
+        }
+        serde_json::Value::Object(map) => {
+            let hash = ruby.hash_new_capa(map.len());
+            for (key, item) in map {
+                let key = if symbolize_names { ruby.to_symbol(key).as_value() } else { ruby.str_new(key).as_value() };
+                hash.aset(key, to_ruby(ruby, item, symbolize_names)?)?;
+            }
+            hash.as_value()
+        }
+    })
+}
+
+/// `Toon.decode(text, options = {}, **kwargs)`
+///
+/// Options are `indent`, `strict`, `expand_paths`, `run_length` and `symbolize_names`.
+/// Malformed input raises `Toon::DecodeError`.
+fn decode(ruby: &Ruby, args: &[Value]) -> Result<Value, Error> {
+    let args = scan_args::<(String,), (Option<RHash>,), (), (), RHash, ()>(args)?;
+    let (text,) = args.required;
+    let (options,) = args.optional;
+    let options: RbDecodeOptions = read_options(ruby, merged_options(options, args.keywords)?)?;
+    let value = toon_lib::decode(&text, Some(options.to_rust())).map_err(|e| Error::new(ruby.get_inner(&DECODE_ERROR), e.to_string()))?;
+    to_ruby(ruby, &value, options.symbolize_names)
+}
+
+#[magnus::init]
+fn init(ruby: &Ruby) -> Result<(), Error> {
+    let module = ruby.define_module("Toon")?;
+    let error = module.define_error("Error", ruby.exception_standard_error())?;
+    module.define_error("EncodeError", error)?;
+    module.define_error("DecodeError", error)?;
+    module.define_singleton_method("encode", function!(encode, -1))?;
+    module.define_singleton_method("decode", function!(decode, -1))?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use toon_lib::{
+    ColumnOrder, ColumnType, Delimiter, EmitLengths, IndentStyle, KeyFolding, KeyOrder, LengthMarker, LineEnding,
+    MissingCell, RowLimit, SortSpec, SpecVersion, Summarize,
+};
+
+/// An indent given as a number of spaces or as the text of one level
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Indent {
+    Spaces(usize),
+    Text(String),
+}
+
+/// Rows kept per table: the first `n`, or the first `head` and the last `tail`
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Rows {
+    Head(usize),
+    HeadTail(usize, usize),
+}
+
+impl Rows {
+    fn limit(&self) -> RowLimit {
+        match *self {
+            Rows::Head(head) => RowLimit::new(head, 0),
+            Rows::HeadTail(head, tail) => RowLimit::new(head, tail),
+        }
+    }
+}
+
+/// Table column order: a strategy name or the explicit column names
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Columns {
+    Strategy(String),
+    Explicit(Vec<String>),
+}
+
+/// Encoding options from a Ruby hash, mirroring the Rust `EncodeOptions` with strings or
+/// symbols for its enums
+///
+/// Every key may be left out; unknown keys are rejected so a misspelt option isn't ignored.
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RbEncodeOptions {
+    /// Spaces per level, or the text of one level such as `"\t"`
+    pub indent: Indent,
+    /// `","`, `"\t"`, `"|"` or `";"`
+    pub delimiter: String,
+    /// Text written before array lengths, such as `"#"`
+    pub length_marker: Option<String>,
+    /// `"\n"` or `"\r\n"`
+    pub line_ending: String,
+    pub trailing_newline: bool,
+    pub align_columns: bool,
+    pub max_line_width: Option<usize>,
+    /// `"always"`, `"never"` or `"tables"`
+    pub emit_lengths: String,
+    /// `"off"`, `"safe"` or `"aggressive"`
+    pub key_folding: String,
+    pub table_flatten_depth: usize,
+    pub tabular_min_rows: usize,
+    pub tabular_max_columns: Option<usize>,
+    pub tabular_union_threshold: Option<f64>,
+    /// `"null"` or `"empty"`
+    pub missing_cell: String,
+    /// `"alphabetical"`, `"first_object"`, `"shortest_first"` or an array of column names
+    pub column_order: Columns,
+    /// `n` or `[head, tail]`
+    pub max_rows: Option<Rows>,
+    pub max_depth: Option<usize>,
+    pub skip_nulls: bool,
+    pub skip_empty: bool,
+    /// Column names to sort rows by, each prefixed with `-` to sort descending
+    pub sort_rows: Option<Vec<String>>,
+    pub group_by: Option<String>,
+    pub string_dictionary: Option<usize>,
+    pub inline_objects: Option<usize>,
+    pub table_chunk_rows: Option<usize>,
+    pub column_aliases: HashMap<String, String>,
+    pub alias_legend: bool,
+    pub run_length: bool,
+    pub type_hints: bool,
+    /// Hint names by column, such as `{ amount: "decimal" }`
+    pub column_types: HashMap<String, String>,
+    /// `[threshold, sample]` where `sample` is `n` or `[head, tail]`
+    pub summarize: Option<(usize, Rows)>,
+    pub lift_constant_columns: bool,
+    /// `"alphabetical"` or `"insertion"`
+    pub key_order: String,
+    pub max_output_bytes: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub fail_over_depth: Option<usize>,
+    pub lossless: bool,
+    /// `"1.0"`, `"1.5"` or `"2.0"`
+    pub spec_version: Option<String>,
+    pub max_string_chars: Option<usize>,
+    pub float_digits: Option<usize>,
+}
+
+impl Default for RbEncodeOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            delimiter: ",".to_string(),
+            length_marker: None,
+            line_ending: "\n".to_string(),
+            trailing_newline: false,
+            align_columns: false,
+            max_line_width: None,
+            emit_lengths: "always".to_string(),
+            key_folding: "off".to_string(),
+            table_flatten_depth: 0,
+            tabular_min_rows: 1,
+            tabular_max_columns: None,
+            tabular_union_threshold: None,
+            missing_cell: "null".to_string(),
+            column_order: Columns::Strategy("alphabetical".to_string()),
+            max_rows: None,
+            max_depth: None,
+            skip_nulls: false,
+            skip_empty: false,
+            sort_rows: None,
+            group_by: None,
+            string_dictionary: None,
+            inline_objects: None,
+            table_chunk_rows: None,
+            column_aliases: HashMap::new(),
+            alias_legend: false,
+            run_length: false,
+            type_hints: false,
+            column_types: HashMap::new(),
+            summarize: None,
+            lift_constant_columns: false,
+            key_order: "alphabetical".to_string(),
+            max_output_bytes: None,
+            max_nodes: None,
+            fail_over_depth: toon_lib::EncodeOptions::default().fail_over_depth,
+            lossless: false,
+            spec_version: None,
+            max_string_chars: None,
+            float_digits: None,
+        }
+    }
+}
+
+/// Decoding options from a Ruby hash, mirroring the Rust `DecodeOptions`
+#[derive(Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RbDecodeOptions {
+    pub indent: Indent,
+    pub strict: bool,
+    pub expand_paths: bool,
+    pub run_length: bool,
+    /// Return hash keys as symbols, as `JSON.parse(text, symbolize_names: true)` does
+    pub symbolize_names: bool,
+}
+
+impl Default for RbDecodeOptions {
+    fn default() -> Self {
+        Self {
+            indent: Indent::Spaces(2),
+            strict: true,
+            expand_paths: false,
+            run_length: false,
+            symbolize_names: false,
+        }
+    }
+}
+
+impl RbDecodeOptions {
+    pub fn to_rust(&self) -> toon_lib::DecodeOptions {
+        toon_lib::DecodeOptions {
+            indent: indent_style(&self.indent),
+            strict: self.strict,
+            expand_paths: self.expand_paths,
+            run_length: self.run_length,
+            fail_over_depth: toon_lib::DecodeOptions::default().fail_over_depth,
+        }
+    }
+}
+
+fn indent_style(indent: &Indent) -> IndentStyle {
+    match indent {
+        Indent::Spaces(n) => IndentStyle::Spaces(*n),
+        Indent::Text(text) if text == "\t" => IndentStyle::Tabs,
+        Indent::Text(text) => IndentStyle::Custom(text.clone()),
+    }
+}
+
+/// Look `name` up in `choices`, or describe the choices in the error
+fn choose<T: Clone>(option: &str, name: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    match choices.iter().find(|(choice, _)| *choice == name) {
+        Some((_, value)) => Ok(value.clone()),
+        None => {
+            let names: Vec<String> = choices.iter().map(|(choice, _)| format!("{:?}", choice)).collect();
+            Err(format!("{} must be one of {}, not {:?}", option, names.join(", "), name))
+        }
+    }
+}
+
+impl RbEncodeOptions {
+    /// The Rust options these describe, or a message naming the first invalid value
+    pub fn to_rust(&self) -> Result<toon_lib::EncodeOptions, String> {
+        let indent = indent_style(&self.indent);
+        let delimiter = choose("delimiter", &self.delimiter, &[
+            (",", Delimiter::Comma), ("\t", Delimiter::Tab), ("|", Delimiter::Pipe), (";", Delimiter::Semicolon),
+        ])?;
+        let line_ending = choose("line_ending", &self.line_ending, &[("\n", LineEnding::Lf), ("\r\n", LineEnding::CrLf)])?;
+        let emit_lengths = choose("emit_lengths", &self.emit_lengths, &[
+            ("always", EmitLengths::Always), ("never", EmitLengths::Never), ("tables", EmitLengths::OnlyTables),
+        ])?;
+        let key_folding = choose("key_folding", &self.key_folding, &[
+            ("off", KeyFolding::Off), ("safe", KeyFolding::Safe), ("aggressive", KeyFolding::Aggressive),
+        ])?;
+        let missing_cell = choose("missing_cell", &self.missing_cell, &[("null", MissingCell::Null), ("empty", MissingCell::Empty)])?;
+        let column_order = match &self.column_order {
+            Columns::Explicit(names) => ColumnOrder::Explicit(names.clone()),
+            Columns::Strategy(name) => choose("column_order", name, &[
+                ("alphabetical", ColumnOrder::Alphabetical),
+                ("first_object", ColumnOrder::FirstObject),
+                ("shortest_first", ColumnOrder::ShortestFirst),
+            ])?,
+        };
+        let sort_rows = self.sort_rows.as_ref().map(|columns| {
+            columns.iter().fold(SortSpec::new(), |spec, column| match column.strip_prefix('-') {
+                Some(column) => spec.desc(column),
+                None => spec.asc(column.as_str()),
+            })
+        });
+        let mut column_types = HashMap::new();
+        for (column, name) in &self.column_types {
+            let column_type = ColumnType::from_name(name).ok_or_else(|| format!("unknown column type {:?} for {:?}", name, column))?;
+            column_types.insert(column.clone(), column_type);
+        }
+        let key_order = choose("key_order", &self.key_order, &[("alphabetical", KeyOrder::Alphabetical), ("insertion", KeyOrder::Insertion)])?;
+        let spec_version = match &self.spec_version {
+            Some(version) => Some(choose("spec_version", version, &[
+                ("1.0", SpecVersion::V1_0), ("1.5", SpecVersion::V1_5), ("2.0", SpecVersion::V2_0),
+            ])?),
+            None => None,
+        };
+
+        Ok(toon_lib::EncodeOptions {
+            indent,
+            delimiter,
+            length_marker: self.length_marker.as_deref().map(LengthMarker::prefix),
+            line_ending,
+            trailing_newline: self.trailing_newline,
+            align_columns: self.align_columns,
+            max_line_width: self.max_line_width,
+            emit_lengths,
+            key_folding,
+            table_flatten_depth: self.table_flatten_depth,
+            tabular_min_rows: self.tabular_min_rows,
+            tabular_max_columns: self.tabular_max_columns,
+            tabular_union_threshold: self.tabular_union_threshold,
+            missing_cell,
+            column_order,
+            max_rows: self.max_rows.as_ref().map(Rows::limit),
+            max_depth: self.max_depth,
+            skip_nulls: self.skip_nulls,
+            skip_empty: self.skip_empty,
+            sort_rows,
+            group_by: self.group_by.clone(),
+            string_dictionary: self.string_dictionary,
+            inline_objects: self.inline_objects,
+            table_chunk_rows: self.table_chunk_rows,
+            column_aliases: self.column_aliases.clone(),
+            alias_legend: self.alias_legend,
+            run_length: self.run_length,
+            type_hints: self.type_hints,
+            column_types,
+            summarize: self.summarize.as_ref().map(|(threshold, sample)| Summarize::new(*threshold, sample.limit())),
+            lift_constant_columns: self.lift_constant_columns,
+            key_order,
+            max_output_bytes: self.max_output_bytes,
+            max_nodes: self.max_nodes,
+            fail_over_depth: self.fail_over_depth,
+            lossless: self.lossless,
+            spec_version,
+            max_string_chars: self.max_string_chars,
+            float_digits: self.float_digits,
+        })
+    }
+}
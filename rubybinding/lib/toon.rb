@@ -0,0 +1,4 @@
+# frozen_string_literal: true
+
+require_relative "toon/version"
+require_relative "toon/toon"
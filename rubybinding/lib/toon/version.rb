@@ -0,0 +1,5 @@
+# frozen_string_literal: true
+
+module Toon
+  VERSION = "0.1.2"
+end
@@ -0,0 +1,103 @@
+use crate::columnar::{float_cell, write_columnar_table};
+use crate::primitives::encode_run_primitive;
+use crate::types::{EncodeOptions, JsonPrimitive};
+use crate::writer::LineWriter;
+use arrow_array::cast::AsArray;
+use arrow_array::types::*;
+use arrow_array::{Array, RecordBatch};
+use arrow_cast::display::{ArrayFormatter, FormatOptions};
+use arrow_schema::{ArrowError, DataType};
+use std::borrow::Cow;
+
+/// Encoded text of one column's cells, given a row index whose value is not null
+type CellWriter<'a> = Box<dyn Fn(usize) -> String + 'a>;
+
+/// Write a record batch as one root table, reading each column directly
+///
+/// Integers keep every digit, NaN and infinite floats become null, decimals are written as
+/// numbers and any other type (dates, timestamps, binary, lists, structs) as its Arrow
+/// display text.
+pub fn encode_batch(batch: &RecordBatch, options: &EncodeOptions) -> Result<String, ArrowError> {
+    let schema = batch.schema();
+    let names: Vec<String> = schema.fields().iter().map(|field| field.name().clone()).collect();
+
+    let format_options = FormatOptions::default();
+    let mut cells = Vec::with_capacity(names.len());
+    for column in batch.columns() {
+        cells.push((column.logical_nulls(), cell_writer(column.as_ref(), &format_options, options)?));
+    }
+
+    let mut writer = LineWriter::with_buffer(String::new(), &options.indent, options.line_ending);
+    write_columnar_table(&mut writer, 0, None, &names, batch.num_rows(), |column, row| {
+        let (nulls, cell) = &cells[column];
+        match nulls {
+            Some(nulls) if nulls.is_null(row) => "null".to_string(),
+            _ => cell(row),
+        }
+    }, options);
+
+    let mut output = writer.into_string();
+    if options.trailing_newline {
+        output.push_str(options.line_ending.as_str());
+    }
+    Ok(output)
+}
+
+fn cell_writer<'a>(array: &'a dyn Array, format_options: &FormatOptions<'a>, options: &'a EncodeOptions) -> Result<CellWriter<'a>, ArrowError> {
+    macro_rules! integers {
+        ($($variant:ident => $type:ty),*) => {
+            match array.data_type() {
+                $(DataType::$variant => {
+                    let values = array.as_primitive::<$type>();
+                    return Ok(Box::new(move |row| values.value(row).to_string()));
+                })*
+                _ => {}
+            }
+        };
+    }
+    integers!(
+        Int8 => Int8Type, Int16 => Int16Type, Int32 => Int32Type, Int64 => Int64Type,
+        UInt8 => UInt8Type, UInt16 => UInt16Type, UInt32 => UInt32Type, UInt64 => UInt64Type
+    );
+
+    let string = move |text: &str| encode_run_primitive(&JsonPrimitive::String(Cow::Borrowed(text)), options);
+    Ok(match array.data_type() {
+        DataType::Boolean => {
+            let values = array.as_boolean();
+            Box::new(move |row| values.value(row).to_string())
+        }
+        // Going through the shortest text of narrow floats keeps 0.1f32 from printing as 0.10000000149
+        DataType::Float16 => {
+            let values = array.as_primitive::<Float16Type>();
+            Box::new(move |row| float_cell(values.value(row).to_string().parse().unwrap_or(f64::NAN)))
+        }
+        DataType::Float32 => {
+            let values = array.as_primitive::<Float32Type>();
+            Box::new(move |row| float_cell(values.value(row).to_string().parse().unwrap_or(f64::NAN)))
+        }
+        DataType::Float64 => {
+            let values = array.as_primitive::<Float64Type>();
+            Box::new(move |row| float_cell(values.value(row)))
+        }
+        DataType::Utf8 => {
+            let values = array.as_string::<i32>();
+            Box::new(move |row| string(values.value(row)))
+        }
+        DataType::LargeUtf8 => {
+            let values = array.as_string::<i64>();
+            Box::new(move |row| string(values.value(row)))
+        }
+        DataType::Utf8View => {
+            let values = array.as_string_view();
+            Box::new(move |row| string(values.value(row)))
+        }
+        DataType::Decimal32(..) | DataType::Decimal64(..) | DataType::Decimal128(..) | DataType::Decimal256(..) => {
+            let formatter = ArrayFormatter::try_new(array, format_options)?;
+            Box::new(move |row| formatter.value(row).to_string())
+        }
+        _ => {
+            let formatter = ArrayFormatter::try_new(array, format_options)?;
+            Box::new(move |row| string(&formatter.value(row).to_string()))
+        }
+    })
+}
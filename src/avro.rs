@@ -0,0 +1,229 @@
+use crate::binary::base64;
+use crate::stack::nested;
+use crate::types::ColumnType;
+use apache_avro::schema::{NamesRef, ResolvedSchema, Schema};
+use apache_avro::types::Value as Avro;
+use apache_avro::reader::datum::GenericDatumReader;
+use apache_avro::{AvroResult, Reader};
+use serde_json::{Map, Number, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Read};
+
+/// Records read from Avro input, with the hint for each top-level field the schema types
+pub struct AvroRecords {
+    pub rows: Vec<Value>,
+    pub column_types: HashMap<String, ColumnType>,
+}
+
+/// Read every record of an object container file, using the schema in its header
+pub fn read_container<R: Read>(input: R) -> AvroResult<AvroRecords> {
+    let reader = Reader::new(input)?;
+    let schema = reader.writer_schema().clone();
+    let values = reader.collect::<AvroResult<Vec<_>>>()?;
+    convert(&schema, &values)
+}
+
+/// Read datums written back to back with `schema` until the input ends
+pub fn read_datums<R: BufRead>(schema: &Schema, mut input: R) -> AvroResult<AvroRecords> {
+    let datum_reader = GenericDatumReader::builder(schema).build()?;
+    let mut values = Vec::new();
+    while !input.fill_buf().map_err(apache_avro::error::Details::ReadBytes)?.is_empty() {
+        values.push(datum_reader.read_value(&mut input)?);
+    }
+    convert(schema, &values)
+}
+
+fn convert(schema: &Schema, values: &[Avro]) -> AvroResult<AvroRecords> {
+    let resolved = ResolvedSchema::try_from(schema)?;
+    let converter = Converter { names: resolved.get_names() };
+    let rows = values.iter().map(|value| converter.to_json(value, schema)).collect();
+
+    let mut column_types = HashMap::new();
+    if let Schema::Record(record) = converter.resolve(schema) {
+        for field in &record.fields {
+            if let Some(column_type) = converter.column_type(&field.schema) {
+                column_types.insert(field.name.clone(), column_type);
+            }
+        }
+    }
+    Ok(AvroRecords { rows, column_types })
+}
+
+/// Converts values using their schema, which carries what the value alone lacks such as a decimal's scale
+pub struct Converter<'s> {
+    names: &'s NamesRef<'s>,
+}
+
+impl<'s> Converter<'s> {
+    fn resolve<'a>(&'a self, mut schema: &'a Schema) -> &'a Schema {
+        while let Schema::Ref { name } = schema {
+            match self.names.get(name) {
+                Some(named) => schema = named,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    /// Convert an Avro value to JSON
+    ///
+    /// Bytes and fixed become base64, enums their symbol, decimals numbers, dates, times and
+    /// timestamps ISO 8601 text (local timestamps without the `Z`), durations `PnMnDTnS` and
+    /// NaN or infinite floats null.
+    pub fn to_json(&self, value: &Avro, schema: &Schema) -> Value {
+        let schema = self.resolve(schema);
+        match value {
+            Avro::Null => Value::Null,
+            Avro::Boolean(b) => Value::Bool(*b),
+            Avro::Int(i) => Value::from(*i),
+            Avro::Long(i) => Value::from(*i),
+            // The shortest text of an f32 keeps 0.1 from widening to 0.10000000149
+            Avro::Float(f) => float(f.to_string().parse().unwrap_or(f64::NAN)),
+            Avro::Double(f) => float(*f),
+            Avro::Bytes(bytes) | Avro::Fixed(_, bytes) => Value::String(base64(bytes)),
+            Avro::String(s) | Avro::Enum(_, s) => Value::String(s.clone()),
+            Avro::Union(index, inner) => {
+                let variant = match schema {
+                    Schema::Union(union) => union.variants().get(*index as usize).unwrap_or(&Schema::Null),
+                    other => other,
+                };
+                self.to_json(inner, variant)
+            }
+            Avro::Array(items) => {
+                let item_schema = match schema {
+                    Schema::Array(array) => &*array.items,
+                    _ => &Schema::Null,
+                };
+                Value::Array(items.iter().map(|item| nested(|| self.to_json(item, item_schema))).collect())
+            }
+            Avro::Map(entries) => {
+                let value_schema = match schema {
+                    Schema::Map(map) => &*map.types,
+                    _ => &Schema::Null,
+                };
+                Value::Object(entries.iter().map(|(k, v)| (k.clone(), nested(|| self.to_json(v, value_schema)))).collect())
+            }
+            Avro::Record(fields) => {
+                let field_schemas = match schema {
+                    Schema::Record(record) => record.fields.iter().map(|field| &field.schema).collect(),
+                    _ => Vec::new(),
+                };
+                let object: Map<String, Value> = fields.iter().enumerate().map(|(index, (name, value))| {
+                    let field_schema = field_schemas.get(index).copied().unwrap_or(&Schema::Null);
+                    (name.clone(), nested(|| self.to_json(value, field_schema)))
+                }).collect();
+                Value::Object(object)
+            }
+            Avro::Date(days) => Value::String(date_text(*days as i64)),
+            Avro::Decimal(decimal) => {
+                let scale = match schema {
+                    Schema::Decimal(decimal) => decimal.scale,
+                    _ => 0,
+                };
+                decimal_number(&decimal_text(&num_bigint::BigInt::from(decimal.clone()).to_string(), scale))
+            }
+            Avro::BigDecimal(decimal) => decimal_number(&decimal.to_plain_string()),
+            Avro::TimeMillis(millis) => Value::String(time_text(*millis as i64, 3)),
+            Avro::TimeMicros(micros) => Value::String(time_text(*micros, 6)),
+            Avro::TimestampMillis(t) => Value::String(timestamp_text(*t, 3, true)),
+            Avro::TimestampMicros(t) => Value::String(timestamp_text(*t, 6, true)),
+            Avro::TimestampNanos(t) => Value::String(timestamp_text(*t, 9, true)),
+            Avro::LocalTimestampMillis(t) => Value::String(timestamp_text(*t, 3, false)),
+            Avro::LocalTimestampMicros(t) => Value::String(timestamp_text(*t, 6, false)),
+            Avro::LocalTimestampNanos(t) => Value::String(timestamp_text(*t, 9, false)),
+            Avro::Duration(duration) => {
+                let (months, days, millis) = (u32::from(duration.months()), u32::from(duration.days()), u32::from(duration.millis()));
+                let seconds = match millis % 1000 {
+                    0 => (millis / 1000).to_string(),
+                    _ => decimal_text(&millis.to_string(), 3).trim_end_matches('0').to_string(),
+                };
+                Value::String(format!("P{}M{}DT{}S", months, days, seconds))
+            }
+            Avro::Uuid(uuid) => Value::String(uuid.to_string()),
+        }
+    }
+
+    /// Hint for a field of this schema; nullable unions take their one other branch
+    fn column_type(&self, schema: &Schema) -> Option<ColumnType> {
+        Some(match self.resolve(schema) {
+            Schema::Union(union) => {
+                let mut branches = union.variants().iter().filter(|variant| !matches!(variant, Schema::Null));
+                return match (branches.next(), branches.next()) {
+                    (Some(branch), None) => self.column_type(branch),
+                    _ => None,
+                };
+            }
+            Schema::Int | Schema::Long => ColumnType::Int,
+            Schema::Float | Schema::Double => ColumnType::Float,
+            Schema::Boolean => ColumnType::Bool,
+            Schema::String | Schema::Enum(_) | Schema::Bytes | Schema::Fixed(_) => ColumnType::Str,
+            Schema::Decimal(_) | Schema::BigDecimal => ColumnType::Decimal,
+            Schema::Date => ColumnType::Date,
+            Schema::TimeMillis | Schema::TimeMicros => ColumnType::Time,
+            Schema::TimestampMillis | Schema::TimestampMicros | Schema::TimestampNanos => ColumnType::Timestamp,
+            Schema::LocalTimestampMillis | Schema::LocalTimestampMicros | Schema::LocalTimestampNanos => ColumnType::Timestamp,
+            Schema::Duration(_) => ColumnType::Duration,
+            Schema::Uuid(_) => ColumnType::Uuid,
+            _ => return None,
+        })
+    }
+}
+
+fn float(f: f64) -> Value {
+    Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+/// A decimal as a JSON number, falling back to its text if it has no `f64` form
+fn decimal_number(text: &str) -> Value {
+    text.parse::<Number>().map_or_else(|_| Value::String(text.to_string()), Value::Number)
+}
+
+/// Place the point `scale` digits from the right of a signed integer's text
+fn decimal_text(unscaled: &str, scale: usize) -> String {
+    if scale == 0 {
+        return unscaled.to_string();
+    }
+    let (sign, digits) = match unscaled.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unscaled),
+    };
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, whole, fraction)
+}
+
+/// `YYYY-MM-DD` for a count of days since 1970-01-01, in the proleptic Gregorian calendar
+fn date_text(days: i64) -> String {
+    // Howard Hinnant's civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `HH:MM:SS` for a time of day in units of 10^-`digits` seconds, with the fraction when it isn't zero
+fn time_text(units: i64, digits: u32) -> String {
+    let per_second = 10i64.pow(digits);
+    let (seconds, fraction) = (units.div_euclid(per_second), units.rem_euclid(per_second));
+    let mut text = format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if fraction != 0 {
+        text.push_str(&format!(".{:0width$}", fraction, width = digits as usize));
+    }
+    text
+}
+
+/// RFC 3339 text for a count of 10^-`digits` seconds since the epoch, ending in `Z` for UTC
+fn timestamp_text(units: i64, digits: u32, utc: bool) -> String {
+    let per_day = 86_400 * 10i64.pow(digits);
+    let mut text = format!("{}T{}", date_text(units.div_euclid(per_day)), time_text(units.rem_euclid(per_day), digits));
+    if utc {
+        text.push('Z');
+    }
+    text
+}
@@ -0,0 +1,105 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// One file to convert and where its output goes
+pub struct Job {
+    pub input: PathBuf,
+    pub target: PathBuf,
+}
+
+/// Whether `input` names more than one file: a directory or a glob pattern
+pub fn is_pattern(input: &Path) -> bool {
+    input.is_dir() || input.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// The leading components of a glob pattern that hold no wildcards
+fn pattern_base(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|component| !component.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// Every file under `dir` with the extension `extension`, in sorted order
+fn walk(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect();
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            walk(&path, extension, files)?;
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expand files, directories and glob patterns into jobs writing `<name>.<target_extension>`
+///
+/// Directories are searched for `.<extension>` files. With `out`, each output keeps the input's
+/// path below the directory or the pattern's fixed prefix, so the tree is mirrored there;
+/// without it, outputs go beside their inputs. Two inputs that would write the same output are
+/// an error.
+pub fn expand(inputs: &[PathBuf], extension: &str, target_extension: &str, out: Option<&Path>) -> Result<Vec<Job>, String> {
+    let mut jobs: Vec<Job> = Vec::new();
+    for input in inputs {
+        let (base, files) = if input.is_dir() {
+            let mut files = Vec::new();
+            walk(input, extension, &mut files)?;
+            (input.clone(), files)
+        } else if is_pattern(input) {
+            let pattern = input.to_string_lossy();
+            let paths = glob::glob(&pattern).map_err(|err| format!("{}: {}", pattern, err))?;
+            let files: Vec<PathBuf> = paths.filter_map(Result::ok).filter(|path| path.is_file()).collect();
+            if files.is_empty() {
+                return Err(format!("{}: no files match", pattern));
+            }
+            (pattern_base(input), files)
+        } else {
+            (input.parent().map(Path::to_path_buf).unwrap_or_default(), vec![input.clone()])
+        };
+
+        for file in files {
+            if jobs.iter().any(|job| job.input == file) {
+                continue;
+            }
+            let target = match out {
+                Some(out) => {
+                    let relative = file.strip_prefix(&base).unwrap_or(&file);
+                    // Keep `..` and roots in the relative part from escaping the output directory
+                    let relative: PathBuf = relative.components().filter(|c| matches!(c, Component::Normal(_))).collect();
+                    out.join(relative)
+                }
+                None => file.clone(),
+            }
+            .with_extension(target_extension);
+            if let Some(job) = jobs.iter().find(|job| job.target == target) {
+                return Err(format!("{} and {} would both write {}", job.input.display(), file.display(), target.display()));
+            }
+            jobs.push(Job { input: file, target });
+        }
+    }
+    Ok(jobs)
+}
+
+/// Run `f` on every job across the available cores, returning the results in job order
+pub fn run<T: Send>(jobs: &[Job], f: impl Fn(&Job) -> T + Sync) -> Vec<T> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(jobs.len());
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..jobs.len()).map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(job) = jobs.get(i) else { break };
+                let result = f(job);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+    results.into_inner().unwrap().into_iter().map(|result| result.expect("every job runs")).collect()
+}
@@ -0,0 +1,38 @@
+use serde_json::json;
+use toon::{LintOptions, Rule};
+
+/// A lint diagnostic found in one file
+pub struct Diagnostic {
+    pub file: String,
+    pub found: toon::Diagnostic,
+}
+
+impl Diagnostic {
+    /// `file:line:column: severity[rule]: message`, the way compilers report problems
+    pub fn human(&self) -> String {
+        let found = &self.found;
+        format!("{}:{}:{}: {}[{}]: {}", self.file, found.line, found.column, found.severity, found.rule, found.message)
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        json!({
+            "file": self.file,
+            "line": self.found.line,
+            "column": self.found.column,
+            "severity": self.found.severity.to_string(),
+            "rule": self.found.rule.id(),
+            "message": self.found.message,
+        })
+    }
+}
+
+/// A rule ID given to `--allow`, `--warn` or `--deny`
+pub fn rule(id: &str) -> Result<Rule, String> {
+    let ids: Vec<&str> = Rule::ALL.iter().map(Rule::id).collect();
+    Rule::from_id(id).ok_or_else(|| format!("unknown rule `{}`; expected one of {}", id, ids.join(", ")))
+}
+
+/// Lint every `---`-separated document of `input`
+pub fn check(file: &str, input: &str, options: &LintOptions) -> Vec<Diagnostic> {
+    toon::lint(input, options.clone()).into_iter().map(|found| Diagnostic { file: file.to_string(), found }).collect()
+}
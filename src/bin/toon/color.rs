@@ -0,0 +1,121 @@
+use std::io::IsTerminal;
+
+const KEY: &str = "\x1b[1;34m";
+const HEADER: &str = "\x1b[36m";
+const NUMBER: &str = "\x1b[33m";
+const STRING: &str = "\x1b[32m";
+const LITERAL: &str = "\x1b[35m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether to highlight output on standard output, as `--color` resolves it
+///
+/// `auto` highlights only a terminal, and not when `NO_COLOR` is set or `TERM` is `dumb`.
+pub fn enabled(always: Option<bool>) -> bool {
+    always.unwrap_or_else(|| {
+        std::io::stdout().is_terminal()
+            && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            && std::env::var_os("TERM").is_none_or(|term| term != "dumb")
+    })
+}
+
+/// Byte offset of the first `target` in `text` outside double quotes
+fn unquoted(text: &str, target: impl Fn(char) -> bool) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if !quoted && target(c) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn paint(out: &mut String, color: &str, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    out.push_str(color);
+    out.push_str(text);
+    out.push_str(RESET);
+}
+
+/// Color a single value by what it decodes to, leaving surrounding spaces plain
+fn token(out: &mut String, token: &str) {
+    let trimmed = token.trim();
+    let start = token.len() - token.trim_start().len();
+    out.push_str(&token[..start]);
+    let number = trimmed.parse::<f64>().is_ok() && trimmed.chars().all(|c| c.is_ascii_digit() || "+-.eE".contains(c));
+    let color = match trimmed {
+        "true" | "false" | "null" => LITERAL,
+        _ if number => NUMBER,
+        _ => STRING,
+    };
+    paint(out, color, trimmed);
+    out.push_str(&token[start + trimmed.len()..]);
+}
+
+/// Color each `delimiter`-separated value of `values`
+fn values(out: &mut String, values: &str, delimiter: char) {
+    let mut rest = values;
+    while let Some(i) = unquoted(rest, |c| c == delimiter) {
+        token(out, &rest[..i]);
+        out.push(delimiter);
+        rest = &rest[i + delimiter.len_utf8()..];
+    }
+    token(out, rest);
+}
+
+/// The delimiter a header such as `[3|]` declares, defaulting to a comma
+fn header_delimiter(header: &str) -> char {
+    let length = header.find(']').map_or("", |end| &header[..end]);
+    length.chars().last().filter(|c| "\t|;".contains(*c)).unwrap_or(',')
+}
+
+/// `text` as TOON with ANSI colors for keys, array headers, numbers, strings and literals
+///
+/// Rows take the delimiter of the last table header above them.
+pub fn highlight(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut rows = ',';
+    for line in text.split_inclusive('\n') {
+        let body = line.trim_end_matches(['\r', '\n']);
+        let ending = &line[body.len()..];
+        let content = body.trim_start_matches([' ', '\t']);
+        out.push_str(&body[..body.len() - content.len()]);
+        let content = match content.strip_prefix("- ") {
+            Some(item) => {
+                out.push_str("- ");
+                item
+            }
+            None => content,
+        };
+
+        let colon = unquoted(content, |c| c == ':').filter(|&i| content[i + 1..].is_empty() || content[i + 1..].starts_with(' '));
+        match colon {
+            _ if content == "---" => out.push_str(content),
+            Some(i) => {
+                let (key, value) = (&content[..i], &content[i + 1..]);
+                let header = unquoted(key, |c| c == '[' || c == '{').unwrap_or(key.len());
+                paint(&mut out, KEY, &key[..header]);
+                paint(&mut out, HEADER, &key[header..]);
+                out.push(':');
+                if header == key.len() {
+                    token(&mut out, value);
+                } else {
+                    let delimiter = header_delimiter(&key[header..]);
+                    if key[header..].contains('{') {
+                        rows = delimiter;
+                    }
+                    values(&mut out, value, delimiter);
+                }
+            }
+            None => values(&mut out, content, rows),
+        }
+        out.push_str(ending);
+    }
+    out
+}
@@ -0,0 +1,112 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toon::{EncodeOptions, IndentStyle, LengthMarker, RowLimit};
+
+use crate::DelimiterArg;
+
+/// File names searched for in the working directory and each of its parents, in order
+pub const FILE_NAMES: [&str; 2] = [".toonrc", "toon.toml"];
+
+/// Defaults read from a `.toonrc` or `toon.toml`, which command-line flags override
+#[derive(Default)]
+pub struct Config {
+    /// The preset with the file's settings applied
+    pub encode: EncodeOptions,
+    /// Spaces per nesting level, for output and for reading TOON input
+    pub indent: Option<usize>,
+}
+
+/// The nearest config file in `dir` or its parents
+pub fn discover(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().flat_map(|dir| FILE_NAMES.map(|name| dir.join(name))).find(|path| path.is_file())
+}
+
+fn integer(value: &Value, key: &str) -> Result<usize, String> {
+    value
+        .as_u64()
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| format!("`{}` must be a non-negative integer", key))
+}
+
+fn boolean(value: &Value, key: &str) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("`{}` must be true or false", key))
+}
+
+fn string<'a>(value: &'a Value, key: &str) -> Result<&'a str, String> {
+    value.as_str().ok_or_else(|| format!("`{}` must be a string", key))
+}
+
+/// Options for `preset`: `default`, `canonical`, or a model name for `EncodeOptions::for_model`
+fn preset(name: &str) -> EncodeOptions {
+    match name {
+        "default" => EncodeOptions::default(),
+        "canonical" => EncodeOptions::canonical(),
+        model => EncodeOptions::for_model(model),
+    }
+}
+
+impl Config {
+    /// Read the file at `path`, or find one from the working directory when `path` is `None`
+    ///
+    /// Without a file anywhere, every setting keeps its built-in default.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match std::env::current_dir().ok().as_deref().and_then(discover) {
+                Some(path) => path,
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        let table = toon::from_toml_str(&text).map_err(|err| format!("{}: {}", path.display(), err.to_string().trim_end()))?;
+        let Value::Object(table) = table else { unreachable!("TOML documents are tables") };
+        Self::from_table(&table).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    /// Only the layout settings of `encode`, for commands that must keep every value
+    pub fn layout(&self) -> EncodeOptions {
+        EncodeOptions {
+            indent: self.encode.indent.clone(),
+            delimiter: self.encode.delimiter,
+            length_marker: self.encode.length_marker.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// `flag` when given, then the file's `indent`, then 2
+    pub fn indent(&self, flag: Option<usize>) -> usize {
+        flag.or(self.indent).unwrap_or(2)
+    }
+
+    fn from_table(table: &Map<String, Value>) -> Result<Self, String> {
+        let mut encode = match table.get("preset") {
+            Some(name) => preset(string(name, "preset")?),
+            None => EncodeOptions::default(),
+        };
+        let mut indent = None;
+        for (key, value) in table {
+            match key.as_str() {
+                "preset" => {}
+                "indent" => indent = Some(integer(value, key)?),
+                "delimiter" => {
+                    let name = string(value, key)?;
+                    let delimiter = DelimiterArg::from_str(name, true).map_err(|_| format!("unknown delimiter `{}`", name))?;
+                    encode.delimiter = delimiter.into();
+                }
+                "length_marker" => encode.length_marker = Some(LengthMarker::prefix(string(value, key)?)),
+                "skip_nulls" => encode.skip_nulls = boolean(value, key)?,
+                "skip_empty" => encode.skip_empty = boolean(value, key)?,
+                "max_depth" => encode.max_depth = Some(integer(value, key)?),
+                "max_rows" => encode.max_rows = Some(RowLimit::new(integer(value, key)?, 0)),
+                "max_string_chars" => encode.max_string_chars = Some(integer(value, key)?),
+                key => return Err(format!("unknown setting `{}`", key)),
+            }
+        }
+        if let Some(indent) = indent {
+            encode.indent = IndentStyle::Spaces(indent);
+        }
+        Ok(Self { encode, indent })
+    }
+}
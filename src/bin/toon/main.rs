@@ -0,0 +1,759 @@
+mod batch;
+mod check;
+mod color;
+mod config;
+mod query;
+mod serve;
+mod stats;
+mod watch;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use batch::Job;
+use config::Config;
+use stats::{Counter, Stats};
+use toon::{CsvOptions, DecodeOptions, Delimiter, EncodeOptions, FormatOptions, IndentStyle, JsonOptions, LengthMarker, LintOptions, Rule, Severity};
+
+/// Convert between JSON and TOON
+#[derive(Parser)]
+#[command(name = "toon", version, about)]
+struct Cli {
+    /// Settings file to use instead of the nearest `.toonrc` or `toon.toml`
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Highlight TOON written to a terminal
+    #[arg(long, global = true, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encode JSON to TOON
+    Encode(EncodeArgs),
+    /// Decode TOON to JSON, or its tables to CSV or Markdown
+    Decode(DecodeArgs),
+    /// Convert between JSON, JSON Lines, CSV, YAML, TOML, TOON and Markdown
+    Convert(ConvertArgs),
+    /// Print the value at a path of keys and indexes, such as `orders[0].total`
+    Get(QueryArgs),
+    /// Select values with a path expression, such as `orders[*].lines[?qty>1]{sku,qty}`
+    Query(QueryArgs),
+    /// Compare two TOON or JSON documents by path; exits with 1 when they differ
+    Diff(DiffArgs),
+    /// Rewrite TOON files with consistent indentation, delimiters and quoting
+    Fmt(FmtArgs),
+    /// Lint TOON files; exits with 1 when any has errors, not just warnings
+    Check(CheckArgs),
+    /// Count tokens of a JSON document and its TOON encoding for each model
+    #[cfg(feature = "tokens")]
+    Tokens(TokensArgs),
+    /// Serve `POST /encode` (JSON to TOON) and `POST /decode` (TOON to JSON) over HTTP
+    Serve(ServeArgs),
+}
+
+#[derive(Args)]
+struct Io {
+    /// File to read; standard input when left out or `-`
+    input: Option<PathBuf>,
+    /// File to write; standard output when left out
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// How TOON output is laid out; flags left out keep the config file's settings
+#[derive(Args)]
+struct Style {
+    /// Delimiter between array values and table cells [default: comma]
+    #[arg(short, long, value_enum)]
+    delimiter: Option<DelimiterArg>,
+    /// Spaces per nesting level [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
+    /// Text written before array lengths, such as `#`
+    #[arg(long, value_name = "PREFIX")]
+    length_marker: Option<String>,
+}
+
+impl Style {
+    /// `base` with the flags that were given applied over it
+    fn options(&self, base: EncodeOptions) -> EncodeOptions {
+        let mut options = EncodeOptions { trailing_newline: true, ..base };
+        if let Some(delimiter) = self.delimiter {
+            options.delimiter = delimiter.into();
+        }
+        if let Some(indent) = self.indent {
+            options.indent = IndentStyle::Spaces(indent);
+        }
+        if let Some(marker) = &self.length_marker {
+            options.length_marker = Some(LengthMarker::prefix(marker));
+        }
+        options
+    }
+}
+
+#[derive(Args)]
+struct EncodeArgs {
+    /// Files, directories or quoted glob patterns such as `"data/**/*.json"` to read; standard
+    /// input when left out or `-`
+    inputs: Vec<PathBuf>,
+    /// File to write, or for several inputs the directory that mirrors their tree with `.toon`
+    /// files; standard output when left out, or beside each input for several
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    #[command(flatten)]
+    style: Style,
+    /// Keep running and re-encode inputs when they change
+    #[arg(short, long, conflicts_with = "stats")]
+    watch: bool,
+    /// Keep reading a growing JSON Lines file, writing new lines to standard output as rows of
+    /// one table
+    #[arg(short, long, conflicts_with_all = ["watch", "stats", "output"])]
+    follow: bool,
+    /// Name of the `--follow` table
+    #[arg(long, default_value = "rows", requires = "follow")]
+    key: String,
+    /// Print input and output sizes to standard error
+    #[arg(long)]
+    stats: bool,
+    /// Also count tokens for this model in `--stats`
+    #[cfg(feature = "tokens")]
+    #[arg(long, value_enum, requires = "stats")]
+    model: Option<ModelArg>,
+}
+
+#[derive(Args)]
+struct DecodeArgs {
+    #[command(flatten)]
+    io: Io,
+    /// Spaces per nesting level [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
+    /// Accept malformed indentation and array lengths that don't match their header
+    #[arg(long)]
+    lenient: bool,
+    /// Output format; with `csv` and an output path, each table is written to its own file there
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    to: Format,
+    /// Write JSON on one line
+    #[arg(long)]
+    compact: bool,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    #[command(flatten)]
+    io: Io,
+    /// Input format; taken from the input's extension when left out
+    #[arg(long, value_enum)]
+    from: Option<Source>,
+    /// Output format; with `csv` and an output path, each table is written to its own file there
+    #[arg(long, value_enum, default_value_t = Target::Toon)]
+    to: Target,
+    #[command(flatten)]
+    style: Style,
+    /// Write JSON on one line
+    #[arg(long)]
+    compact: bool,
+}
+
+#[derive(Args)]
+struct QueryArgs {
+    /// Keys and indexes such as `a.b[0]`; `query` also takes `*`, slices like `[1:3]`, filters
+    /// like `[?status=open]` and field lists like `{id,name}`
+    expression: String,
+    #[command(flatten)]
+    io: Io,
+    /// Spaces per nesting level of the input [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
+    /// Print the result as JSON instead of TOON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct FmtArgs {
+    /// Files to format in place; standard input to standard output when left out
+    files: Vec<PathBuf>,
+    #[command(flatten)]
+    style: Style,
+    /// List the files that aren't formatted instead of rewriting them; exits with 1 when there are any
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// Files to check; standard input when left out
+    files: Vec<PathBuf>,
+    /// Spaces per nesting level [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
+    /// Report diagnostics as `file:line:column: severity[rule]: message` lines or as a JSON array
+    #[arg(long, value_enum, default_value_t = Report::Human)]
+    format: Report,
+    /// Turn off a rule, such as `unquoted-value`; repeat for several
+    #[arg(long, value_name = "RULE", value_parser = check::rule)]
+    allow: Vec<Rule>,
+    /// Report a rule as a warning, which doesn't fail the check
+    #[arg(long, value_name = "RULE", value_parser = check::rule)]
+    warn: Vec<Rule>,
+    /// Report a rule as an error
+    #[arg(long, value_name = "RULE", value_parser = check::rule)]
+    deny: Vec<Rule>,
+}
+
+#[cfg(feature = "tokens")]
+#[derive(Args)]
+struct TokensArgs {
+    /// JSON file to measure; standard input when left out or `-`
+    input: Option<PathBuf>,
+    /// Model to count for, such as `gpt-4o`; repeat to compare several [default: gpt-4o]
+    #[arg(short, long = "model", value_name = "NAME")]
+    models: Vec<String>,
+    #[command(flatten)]
+    style: Style,
+}
+
+#[derive(Args)]
+struct ServeArgs {
+    /// Address to listen on; `0.0.0.0` accepts connections from other machines
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    /// Port to listen on; 0 picks a free one
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// The old document; files ending in `.json` are read as JSON
+    old: PathBuf,
+    /// The new document
+    new: PathBuf,
+    /// Spaces per nesting level of TOON inputs [default: 2]
+    #[arg(short, long)]
+    indent: Option<usize>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DelimiterArg {
+    #[value(alias = ",")]
+    Comma,
+    #[value(alias = "\t")]
+    Tab,
+    #[value(alias = "|")]
+    Pipe,
+    #[value(alias = ";")]
+    Semicolon,
+}
+
+impl From<DelimiterArg> for Delimiter {
+    fn from(arg: DelimiterArg) -> Self {
+        match arg {
+            DelimiterArg::Comma => Delimiter::Comma,
+            DelimiterArg::Tab => Delimiter::Tab,
+            DelimiterArg::Pipe => Delimiter::Pipe,
+            DelimiterArg::Semicolon => Delimiter::Semicolon,
+        }
+    }
+}
+
+#[cfg(feature = "tokens")]
+#[derive(Clone, Copy, ValueEnum)]
+enum ModelArg {
+    #[value(name = "gpt-4o")]
+    Gpt4o,
+    #[value(name = "gpt-4")]
+    Gpt4,
+    #[value(name = "gpt-3.5-turbo")]
+    Gpt35Turbo,
+}
+
+#[cfg(feature = "tokens")]
+impl From<ModelArg> for toon::Model {
+    fn from(arg: ModelArg) -> Self {
+        match arg {
+            ModelArg::Gpt4o => toon::Model::Gpt4o,
+            ModelArg::Gpt4 => toon::Model::Gpt4,
+            ModelArg::Gpt35Turbo => toon::Model::Gpt35Turbo,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+    #[value(alias = "markdown")]
+    Md,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Source {
+    Json,
+    #[value(alias = "ndjson")]
+    Jsonl,
+    Csv,
+    #[value(alias = "yml")]
+    Yaml,
+    Toml,
+    Toon,
+}
+
+impl Source {
+    /// The format a file extension such as `.yml` names
+    fn from_path(path: &Path) -> Option<Self> {
+        Self::from_str(path.extension()?.to_str()?, true).ok()
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Target {
+    Toon,
+    Json,
+    Csv,
+    #[value(alias = "markdown")]
+    Md,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Report {
+    Human,
+    Json,
+}
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Read all of `path`, or standard input for `None` and `-`
+fn read_input(path: Option<&Path>) -> Result<String> {
+    match path {
+        Some(path) if path != Path::new("-") => {
+            fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err).into())
+        }
+        _ => {
+            let mut text = String::new();
+            io::stdin().read_to_string(&mut text)?;
+            Ok(text)
+        }
+    }
+}
+
+/// Write `text` to `path`, or standard output for `None`
+fn write_output(path: Option<&Path>, text: &str) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, text).map_err(|err| format!("{}: {}", path.display(), err).into()),
+        None => Ok(io::stdout().lock().write_all(text.as_bytes())?),
+    }
+}
+
+/// Write TOON to `path`, highlighted when it goes to standard output and `color` is set
+fn write_toon(path: Option<&Path>, text: &str, color: bool) -> Result<()> {
+    match path {
+        None if color => write_output(None, &color::highlight(text)),
+        _ => write_output(path, text),
+    }
+}
+
+/// How `path` is named in messages
+fn display_name(path: Option<&Path>) -> String {
+    match path {
+        Some(path) if path != Path::new("-") => path.display().to_string(),
+        _ => "<stdin>".to_string(),
+    }
+}
+
+/// The token counter `--model` asks for, if any
+#[cfg(feature = "tokens")]
+fn token_counter(args: &EncodeArgs) -> Option<Counter> {
+    let model = toon::Model::from(args.model?);
+    Some(Box::new(move |text: &str| toon::count_tokens(text, model)))
+}
+
+#[cfg(not(feature = "tokens"))]
+fn token_counter(_: &EncodeArgs) -> Option<Counter> {
+    None
+}
+
+fn encode_text(input: &str, options: &EncodeOptions) -> Result<String> {
+    let value: serde_json::Value = serde_json::from_str(input).map_err(|err| format!("invalid JSON: {}", err))?;
+    Ok(toon::try_encode(&value, Some(options.clone()))?)
+}
+
+/// Encode the JSON file behind `job` to its target, returning the input and output text
+fn encode_file(job: &Job, options: &EncodeOptions) -> std::result::Result<(String, String), String> {
+    let text = read_input(Some(&job.input)).map_err(|err| err.to_string())?;
+    let output = encode_text(&text, options).map_err(|err| format!("{}: {}", job.input.display(), err))?;
+    if let Some(dir) = job.target.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    }
+    write_output(Some(&job.target), &output).map_err(|err| err.to_string())?;
+    Ok((text, output))
+}
+
+fn encode(args: EncodeArgs, config: &Config, color: bool) -> Result<()> {
+    let options = args.style.options(config.encode.clone());
+    if args.follow {
+        return match args.inputs.as_slice() {
+            [input] if input != Path::new("-") && !batch::is_pattern(input) => follow(input, &args.key, &options),
+            _ => Err("--follow needs one JSON Lines file".into()),
+        };
+    }
+    let mut stats = Stats::new(token_counter(&args));
+    let output = args.output.as_deref();
+    let single = match args.inputs.as_slice() {
+        [] => true,
+        [input] => !args.watch && !batch::is_pattern(input),
+        _ => false,
+    };
+    if args.inputs.iter().any(|input| input == Path::new("-")) && !single {
+        return Err("standard input can't be combined with other inputs or --watch".into());
+    }
+
+    if single {
+        let input = args.inputs.first().map(PathBuf::as_path);
+        let text = read_input(input)?;
+        let encoded = encode_text(&text, &options)?;
+        write_toon(output, &encoded, color)?;
+        stats.record(&display_name(input), &text, &encoded);
+        if args.stats {
+            stats.report(io::stderr().lock())?;
+        }
+        return Ok(());
+    }
+
+    if args.inputs.is_empty() {
+        return Err("--watch needs input files".into());
+    }
+    let jobs = batch::expand(&args.inputs, "json", "toon", output)?;
+    let mut failed = 0;
+    for (job, result) in jobs.iter().zip(batch::run(&jobs, |job| encode_file(job, &options))) {
+        match result {
+            Ok((text, encoded)) => stats.record(&display_name(Some(&job.input)), &text, &encoded),
+            Err(err) => {
+                eprintln!("toon: {}", err);
+                failed += 1;
+            }
+        }
+    }
+    if args.stats {
+        stats.report(io::stderr().lock())?;
+    }
+
+    if args.watch {
+        let inputs: Vec<PathBuf> = jobs.iter().map(|job| job.input.clone()).collect();
+        eprintln!("watching {} files", inputs.len());
+        watch::watch(&inputs, watch::INTERVAL, |i| match encode_file(&jobs[i], &options) {
+            Ok(_) => eprintln!("{} -> {}", jobs[i].input.display(), jobs[i].target.display()),
+            Err(err) => eprintln!("toon: {}", err),
+        });
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{} of {} files failed", failed, jobs.len()).into()),
+    }
+}
+
+/// Print the bytes and tokens of the input as minified JSON and as TOON, a line per model
+///
+/// Each model's TOON starts from `EncodeOptions::for_model`, under the layout flags. Models
+/// without a published tokenizer are estimated with `cl100k_base`.
+#[cfg(feature = "tokens")]
+fn tokens(args: TokensArgs) -> Result<()> {
+    let input = read_input(args.input.as_deref())?;
+    let value: serde_json::Value = serde_json::from_str(&input).map_err(|err| format!("{}: invalid JSON: {}", display_name(args.input.as_deref()), err))?;
+    let json = serde_json::to_string(&value)?;
+    let models = match args.models.is_empty() {
+        true => vec!["gpt-4o".to_string()],
+        false => args.models,
+    };
+    for name in &models {
+        let (model, label) = match toon::Model::from_name(name) {
+            Some(model) => (model, name.clone()),
+            None => (toon::Model::Gpt4, format!("{} (estimated with cl100k_base)", name)),
+        };
+        let options = EncodeOptions { trailing_newline: false, ..args.style.options(EncodeOptions::for_model(name)) };
+        let encoded = toon::try_encode(&value, Some(options))?;
+        let mut stats = Stats::new(Some(Box::new(move |text: &str| toon::count_tokens(text, model))));
+        stats.record(&label, &json, &encoded);
+        stats.report(io::stdout().lock())?;
+    }
+    Ok(())
+}
+
+/// Write the lines of a growing JSON Lines file to standard output as chunks of one `key` table
+fn follow(path: &Path, key: &str, options: &EncodeOptions) -> Result<()> {
+    let options = EncodeOptions { trailing_newline: false, ..options.clone() };
+    let mut table = toon::TableStream::new(key, io::stdout().lock(), &options);
+    let mut number = 0;
+    watch::follow(path, watch::INTERVAL, |lines| {
+        for line in lines.lines() {
+            number += 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row = serde_json::from_str(line).map_err(|err| format!("{}: line {}: invalid JSON: {}", path.display(), number, err))?;
+            table.push(row).map_err(|err| err.to_string())?;
+        }
+        table.flush().map_err(|err| format!("{}: line {}: {}", path.display(), number, err))
+    })?;
+    Ok(())
+}
+
+fn decode(args: DecodeArgs, config: &Config) -> Result<()> {
+    let input = read_input(args.io.input.as_deref())?;
+    let options = DecodeOptions {
+        indent: IndentStyle::Spaces(config.indent(args.indent)),
+        strict: !args.lenient,
+        ..Default::default()
+    };
+    let output = args.io.output.as_deref();
+    match args.to {
+        Format::Json => {
+            let json = JsonOptions { pretty: !args.compact, trailing_newline: true, ..Default::default() };
+            write_output(output, &toon::to_json_string(&input, Some(options), json)?)
+        }
+        Format::Md => {
+            let mut markdown = toon::decode_markdown(&input, Some(options))?;
+            markdown.push('\n');
+            write_output(output, &markdown)
+        }
+        Format::Csv => write_csv(output, &toon::decode_csv_tables(&input, Some(options), CsvOptions::default())?),
+    }
+}
+
+/// Parse `input` written in `from`, reading TOON with `indent` spaces per level
+fn parse(input: &str, from: Source, indent: usize) -> Result<serde_json::Value> {
+    Ok(match from {
+        Source::Json => serde_json::from_str(input).map_err(|err| format!("invalid JSON: {}", err))?,
+        Source::Jsonl => {
+            let mut rows = Vec::new();
+            for (number, line) in input.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                rows.push(serde_json::from_str(line).map_err(|err| format!("line {}: invalid JSON: {}", number + 1, err))?);
+            }
+            serde_json::Value::Array(rows)
+        }
+        Source::Csv => toon::from_csv(input.as_bytes(), CsvOptions::default()).map_err(|err| format!("invalid CSV: {}", err))?,
+        Source::Yaml => toon::from_yaml_str(input).map_err(|err| format!("invalid YAML: {}", err))?,
+        Source::Toml => toon::from_toml_str(input).map_err(|err| format!("invalid TOML: {}", err.to_string().trim_end()))?,
+        Source::Toon => toon::decode(input, Some(DecodeOptions { indent: IndentStyle::Spaces(indent), ..Default::default() }))?,
+    })
+}
+
+fn convert(args: ConvertArgs, config: &Config, color: bool) -> Result<()> {
+    let path = args.io.input.as_deref();
+    let from = match args.from.or_else(|| path.and_then(Source::from_path)) {
+        Some(from) => from,
+        None => return Err(format!("{}: can't tell the input format; pass --from", display_name(path)).into()),
+    };
+    let input = read_input(path)?;
+    let value = parse(&input, from, config.indent(args.style.indent)).map_err(|err| format!("{}: {}", display_name(path), err))?;
+    let output = args.io.output.as_deref();
+    match args.to {
+        Target::Toon => write_toon(output, &toon::try_encode(&value, Some(args.style.options(config.encode.clone())))?, color),
+        Target::Json => {
+            let json = match args.compact {
+                true => serde_json::to_string(&value)?,
+                false => serde_json::to_string_pretty(&value)?,
+            };
+            write_output(output, &(json + "\n"))
+        }
+        Target::Csv => write_csv(output, &toon::to_csv_tables(&value, CsvOptions::default())),
+        Target::Md => write_output(output, &(toon::to_markdown(&value) + "\n")),
+    }
+}
+
+/// Read a document as JSON when `path` ends in `.json`, and as TOON otherwise
+fn read_document(path: Option<&Path>, indent: usize) -> Result<serde_json::Value> {
+    let input = read_input(path)?;
+    let name = display_name(path);
+    match path.and_then(Path::extension) {
+        Some(ext) if ext == "json" => Ok(serde_json::from_str(&input).map_err(|err| format!("{}: invalid JSON: {}", name, err))?),
+        _ => {
+            let options = DecodeOptions { indent: IndentStyle::Spaces(indent), ..Default::default() };
+            Ok(toon::decode(&input, Some(options)).map_err(|err| format!("{}: {}", name, err))?)
+        }
+    }
+}
+
+/// Print the differences between two documents, returning whether there were any
+fn diff(args: DiffArgs, config: &Config) -> Result<bool> {
+    let indent = config.indent(args.indent);
+    let old = read_document(Some(&args.old), indent)?;
+    let new = read_document(Some(&args.new), indent)?;
+    let changes = toon::render_diff(&toon::diff(&old, &new));
+    write_output(None, &changes)?;
+    Ok(!changes.is_empty())
+}
+
+fn format(args: FmtArgs, config: &Config, color: bool) -> Result<bool> {
+    let layout = args.style.options(config.layout());
+    let options = FormatOptions {
+        indent: layout.indent,
+        delimiter: layout.delimiter,
+        length_marker: layout.length_marker,
+        ..Default::default()
+    };
+    if args.files.is_empty() {
+        let input = read_input(None)?;
+        let formatted = toon::format(&input, options.clone()).map_err(|err| err.to_string())?;
+        match args.check {
+            true => return Ok(formatted != input),
+            false => write_toon(None, &formatted, color)?,
+        }
+        return Ok(false);
+    }
+
+    let mut unformatted = false;
+    for file in &args.files {
+        let input = read_input(Some(file))?;
+        let formatted = toon::format(&input, options.clone()).map_err(|err| format!("{}: {}", file.display(), err))?;
+        if formatted == input {
+            continue;
+        }
+        unformatted = true;
+        match args.check {
+            true => println!("{}", file.display()),
+            false => write_output(Some(file), &formatted)?,
+        }
+    }
+    Ok(args.check && unformatted)
+}
+
+/// Print the diagnostics for every file, returning whether there were any
+fn check(args: CheckArgs, config: &Config) -> Result<bool> {
+    let mut options = LintOptions { indent: IndentStyle::Spaces(config.indent(args.indent)), ..Default::default() };
+    let levels = [(&args.allow, None), (&args.warn, Some(Severity::Warning)), (&args.deny, Some(Severity::Error))];
+    for (rules, severity) in levels {
+        options.rules.extend(rules.iter().map(|rule| (*rule, severity)));
+    }
+    let files: Vec<Option<&Path>> = match args.files.is_empty() {
+        true => vec![None],
+        false => args.files.iter().map(|file| Some(file.as_path())).collect(),
+    };
+    let mut diagnostics = Vec::new();
+    for file in files {
+        let input = read_input(file)?;
+        diagnostics.extend(check::check(&display_name(file), &input, &options));
+    }
+    let report = match args.format {
+        Report::Human => diagnostics.iter().map(|diagnostic| diagnostic.human() + "\n").collect(),
+        Report::Json => {
+            let json: Vec<serde_json::Value> = diagnostics.iter().map(check::Diagnostic::json).collect();
+            serde_json::to_string_pretty(&json)? + "\n"
+        }
+    };
+    write_output(None, &report)?;
+    Ok(diagnostics.iter().any(|diagnostic| diagnostic.found.severity == Severity::Error))
+}
+
+/// Run `get` or, with `plain` unset, `query`
+fn select(args: QueryArgs, plain: bool, config: &Config, color: bool) -> Result<()> {
+    let segments = query::parse(&args.expression).map_err(|err| format!("invalid expression: {}", err))?;
+    if plain && !segments.iter().all(query::Segment::is_plain) {
+        return Err("get takes only keys and indexes; use query for wildcards, slices, filters and field lists".into());
+    }
+    let document = read_document(args.io.input.as_deref(), config.indent(args.indent))?;
+
+    let mut matches = query::evaluate(&segments, &document);
+    let result = match matches.len() {
+        0 => return Err(format!("nothing matches `{}`", args.expression).into()),
+        1 => matches.remove(0),
+        _ => serde_json::Value::Array(matches),
+    };
+    let output = args.io.output.as_deref();
+    match args.json {
+        true => write_output(output, &(serde_json::to_string_pretty(&result)? + "\n")),
+        false => {
+            let text = toon::try_encode(&result, Some(EncodeOptions { trailing_newline: true, ..config.layout() }))?;
+            write_toon(output, &text, color)
+        }
+    }
+}
+
+/// Write the tables to standard output one after another, or into the directory `output`
+fn write_csv(output: Option<&Path>, tables: &[toon::CsvTable]) -> Result<()> {
+    match output {
+        Some(dir) => write_tables(dir, tables),
+        None => {
+            let csv: Vec<&str> = tables.iter().map(|table| table.csv.as_str()).collect();
+            write_output(None, &csv.join("\r\n"))
+        }
+    }
+}
+
+/// Write each table to `<path>.csv` in `dir`, naming a root table `table.csv`
+fn write_tables(dir: &Path, tables: &[toon::CsvTable]) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    for table in tables {
+        let name = match table.path.as_str() {
+            "" => "table".to_string(),
+            path => path.replace(['/', '\\'], "_"),
+        };
+        write_output(Some(&dir.join(format!("{}.csv", name))), &table.csv)?;
+    }
+    Ok(())
+}
+
+/// Exit with 1 for `Ok(true)`, or `error` after printing the error
+fn status(result: Result<bool>, error: u8) -> ExitCode {
+    match result {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("toon: {}", err);
+            ExitCode::from(error)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config = match Config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("toon: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let color = color::enabled(match cli.color {
+        ColorChoice::Auto => None,
+        ColorChoice::Always => Some(true),
+        ColorChoice::Never => Some(false),
+    });
+    let result = match cli.command {
+        Command::Encode(args) => encode(args, &config, color),
+        Command::Decode(args) => decode(args, &config),
+        Command::Convert(args) => convert(args, &config, color),
+        Command::Get(args) => select(args, true, &config, color),
+        Command::Query(args) => select(args, false, &config, color),
+        // Like diff(1): 1 when the documents differ and 2 when they can't be compared
+        Command::Diff(args) => return status(diff(args, &config), 2),
+        Command::Fmt(args) => return status(format(args, &config, color), 1),
+        // 1 for invalid TOON, leaving 2 for files that can't be read
+        Command::Check(args) => return status(check(args, &config), 2),
+        #[cfg(feature = "tokens")]
+        Command::Tokens(args) => tokens(args),
+        Command::Serve(args) => serve::serve(&format!("{}:{}", args.host, args.port), &config).map_err(Into::into),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("toon: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
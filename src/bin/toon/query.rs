@@ -0,0 +1,337 @@
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/// One step of a path expression
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    /// `.name` or `["name"]`; on an array, from every item as separate matches
+    Key(String),
+    /// `.*` or `[*]`: every item or field value as a separate match
+    Wildcard,
+    /// `[2]`, or `[-1]` counting from the end
+    Index(i64),
+    /// `[1:3]`: a sub-array, with either bound optional
+    Slice(Option<i64>, Option<i64>),
+    /// `[?qty>1]`: the items that pass, as a sub-array
+    Filter(Filter),
+    /// `{sku,qty}`: only these fields; on an array, from every item, keeping the table
+    Pick(Vec<String>),
+}
+
+impl Segment {
+    /// Whether the segment picks out at most one existing value, as `get` allows
+    pub fn is_plain(&self) -> bool {
+        matches!(self, Segment::Key(_) | Segment::Index(_))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Filter {
+    path: Vec<String>,
+    test: Option<(Op, Value)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("{} at column {}", message, self.pos + 1))
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(found) if found == c => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => self.error(&format!("expected `{}`", c)),
+        }
+    }
+
+    /// Characters up to the next one in `stops` or whitespace
+    fn word(&mut self, stops: &str) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| !c.is_whitespace() && !stops.contains(c)) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// A `"..."` or `'...'` string, with `\` escaping the quote and itself
+    fn quoted(&mut self) -> Result<String, String> {
+        let quote = self.chars[self.pos];
+        self.pos += 1;
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                '\\' if matches!(self.peek(), Some(next) if next == quote || next == '\\') => {
+                    text.push(self.chars[self.pos]);
+                    self.pos += 1;
+                }
+                c if c == quote => return Ok(text),
+                c => text.push(c),
+            }
+        }
+        self.error("unterminated string")
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('"' | '\'') => self.quoted(),
+            _ => match self.word(".[]{},=!<>") {
+                key if key.is_empty() => self.error("expected a key"),
+                key => Ok(key),
+            },
+        }
+    }
+
+    /// A bare key after `.` or at the start, where `*` is a wildcard
+    fn key_segment(&mut self) -> Result<Segment, String> {
+        match self.key()? {
+            key if key == "*" => Ok(Segment::Wildcard),
+            key => Ok(Segment::Key(key)),
+        }
+    }
+
+    fn integer(&mut self) -> Result<Option<i64>, String> {
+        self.skip_whitespace();
+        let text = self.word(":]");
+        match text.as_str() {
+            "" => Ok(None),
+            _ => text.parse().map(Some).or_else(|_| self.error(&format!("`{}` is not an index", text))),
+        }
+    }
+
+    fn bracket(&mut self) -> Result<Segment, String> {
+        self.skip_whitespace();
+        let segment = match self.peek() {
+            Some('*') => {
+                self.pos += 1;
+                Segment::Wildcard
+            }
+            Some('"' | '\'') => Segment::Key(self.quoted()?),
+            Some('?') => {
+                self.pos += 1;
+                Segment::Filter(self.filter()?)
+            }
+            _ => {
+                let start = self.integer()?;
+                self.skip_whitespace();
+                if self.peek() == Some(':') {
+                    self.pos += 1;
+                    Segment::Slice(start, self.integer()?)
+                } else {
+                    match start {
+                        Some(index) => Segment::Index(index),
+                        None => return self.error("expected an index, slice, `*` or `?`"),
+                    }
+                }
+            }
+        };
+        self.expect(']')?;
+        Ok(segment)
+    }
+
+    fn filter(&mut self) -> Result<Filter, String> {
+        let mut path = vec![self.key()?];
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            path.push(self.key()?);
+        }
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            return Ok(Filter { path, test: None });
+        }
+        let start = self.pos;
+        while self.peek().is_some_and(|c| "=!<>".contains(c)) {
+            self.pos += 1;
+        }
+        let op: String = self.chars[start..self.pos].iter().collect();
+        let op = match op.as_str() {
+            "=" | "==" => Op::Eq,
+            "!=" => Op::Ne,
+            "<" => Op::Lt,
+            "<=" => Op::Le,
+            ">" => Op::Gt,
+            ">=" => Op::Ge,
+            op => return self.error(&format!("unknown comparison `{}`", op)),
+        };
+        self.skip_whitespace();
+        let literal = match self.peek() {
+            Some('"' | '\'') => Value::String(self.quoted()?),
+            _ => {
+                let start = self.pos;
+                while self.peek().is_some_and(|c| c != ']') {
+                    self.pos += 1;
+                }
+                let text: String = self.chars[start..self.pos].iter().collect();
+                let text = text.trim();
+                // Bare words that aren't JSON literals compare as strings
+                serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()))
+            }
+        };
+        Ok(Filter { path, test: Some((op, literal)) })
+    }
+
+    fn fields(&mut self) -> Result<Vec<String>, String> {
+        let mut fields = vec![self.key()?];
+        self.skip_whitespace();
+        while self.peek() == Some(',') {
+            self.pos += 1;
+            fields.push(self.key()?);
+            self.skip_whitespace();
+        }
+        Ok(fields)
+    }
+}
+
+/// Parse a path expression such as `orders[0].lines[?qty>1]{sku,qty}`
+///
+/// A leading key needs no `.`, and `.` alone selects the whole document.
+pub fn parse(expression: &str) -> Result<Vec<Segment>, String> {
+    let mut parser = Parser { chars: expression.chars().collect(), pos: 0 };
+    let mut segments = Vec::new();
+    parser.skip_whitespace();
+    if parser.peek().is_some_and(|c| !".[{".contains(c)) {
+        segments.push(parser.key_segment()?);
+    }
+    loop {
+        parser.skip_whitespace();
+        match parser.peek() {
+            None => return Ok(segments),
+            Some('.') => {
+                parser.pos += 1;
+                parser.skip_whitespace();
+                match parser.peek() {
+                    None if segments.is_empty() => return Ok(segments),
+                    _ => segments.push(parser.key_segment()?),
+                }
+            }
+            Some('[') => {
+                parser.pos += 1;
+                segments.push(parser.bracket()?);
+            }
+            Some('{') => {
+                parser.pos += 1;
+                let fields = parser.fields()?;
+                parser.expect('}')?;
+                segments.push(Segment::Pick(fields));
+            }
+            Some(c) => return parser.error(&format!("unexpected `{}`", c)),
+        }
+    }
+}
+
+/// Count a negative index back from the end of an array of `len` items
+fn resolve(index: i64, len: usize) -> i64 {
+    if index < 0 {
+        index + len as i64
+    } else {
+        index
+    }
+}
+
+impl Filter {
+    fn matches(&self, item: &Value) -> bool {
+        let found = self.path.iter().try_fold(item, |value, key| value.get(key));
+        match (&self.test, found) {
+            (None, Some(value)) => !matches!(value, Value::Null | Value::Bool(false)),
+            (None, None) => false,
+            (Some((Op::Ne, literal)), found) => found.is_none_or(|value| !equal(value, literal)),
+            (Some(_), None) => false,
+            (Some((Op::Eq, literal)), Some(value)) => equal(value, literal),
+            (Some((op, literal)), Some(value)) => match compare(value, literal) {
+                Some(ordering) => match op {
+                    Op::Lt => ordering.is_lt(),
+                    Op::Le => ordering.is_le(),
+                    Op::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                },
+                None => false,
+            },
+        }
+    }
+}
+
+/// Equality that treats `1` and `1.0` as the same number
+fn equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+/// Order numbers with numbers and strings with strings; anything else doesn't compare
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+fn apply(segment: &Segment, value: &Value) -> Vec<Value> {
+    match (segment, value) {
+        (Segment::Key(key), Value::Object(map)) => map.get(key).cloned().into_iter().collect(),
+        (Segment::Key(_), Value::Array(items)) => items.iter().flat_map(|item| apply(segment, item)).collect(),
+        (Segment::Pick(_), Value::Array(items)) => {
+            vec![Value::Array(items.iter().flat_map(|item| apply(segment, item)).collect())]
+        }
+        (Segment::Wildcard, Value::Object(map)) => map.values().cloned().collect(),
+        (Segment::Wildcard, Value::Array(items)) => items.clone(),
+        (Segment::Index(index), Value::Array(items)) => {
+            let index = resolve(*index, items.len());
+            usize::try_from(index).ok().and_then(|index| items.get(index)).cloned().into_iter().collect()
+        }
+        (Segment::Slice(start, end), Value::Array(items)) => {
+            let bound = |bound: Option<i64>, default: usize| {
+                bound.map_or(default, |bound| resolve(bound, items.len()).clamp(0, items.len() as i64) as usize)
+            };
+            let (start, end) = (bound(*start, 0), bound(*end, items.len()));
+            vec![Value::Array(items.get(start..end.max(start)).unwrap_or_default().to_vec())]
+        }
+        (Segment::Filter(filter), Value::Array(items)) => {
+            vec![Value::Array(items.iter().filter(|item| filter.matches(item)).cloned().collect())]
+        }
+        (Segment::Filter(filter), value) if filter.matches(value) => vec![value.clone()],
+        (Segment::Pick(fields), Value::Object(map)) => {
+            let picked: Map<String, Value> =
+                fields.iter().filter_map(|field| map.get(field).map(|value| (field.clone(), value.clone()))).collect();
+            vec![Value::Object(picked)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Every value `segments` select from `root`
+pub fn evaluate(segments: &[Segment], root: &Value) -> Vec<Value> {
+    segments.iter().fold(vec![root.clone()], |current, segment| {
+        current.iter().flat_map(|value| apply(segment, value)).collect()
+    })
+}
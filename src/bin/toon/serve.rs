@@ -0,0 +1,158 @@
+use clap::ValueEnum;
+use std::io::Read;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+use toon::{DecodeOptions, IndentStyle, JsonOptions, LengthMarker};
+
+use crate::config::Config;
+use crate::DelimiterArg;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY: u64 = 16 * 1024 * 1024;
+
+/// Deepest nesting `/decode` accepts, matching the recursion limit serde_json puts on `/encode`
+///
+/// Decoded values are dropped and serialized recursively, so a limit keeps a worker's stack safe.
+const MAX_DEPTH: usize = 128;
+
+/// Converts a request body, given the query parameters
+type Endpoint = fn(&str, &[(String, String)], &Config) -> Result<String, String>;
+
+/// Decode `%XX` escapes and `+` in a query string component
+fn unescape(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The `name=value` pairs after `?` in `url`
+fn query(url: &str) -> Vec<(String, String)> {
+    let Some((_, query)) = url.split_once('?') else { return Vec::new() };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((name, value)) => (unescape(name), unescape(value)),
+            None => (unescape(pair), String::new()),
+        })
+        .collect()
+}
+
+fn integer(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("`{}` must be a non-negative integer", name))
+}
+
+fn boolean(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "" | "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("`{}` must be true or false", name)),
+    }
+}
+
+/// `/encode`: a JSON body as TOON, laid out by `delimiter`, `indent` and `length_marker`
+fn encode(body: &str, params: &[(String, String)], config: &Config) -> Result<String, String> {
+    let mut options = config.encode.clone();
+    for (name, value) in params {
+        match name.as_str() {
+            "delimiter" => {
+                let delimiter = DelimiterArg::from_str(value, true).map_err(|_| format!("unknown delimiter `{}`", value))?;
+                options.delimiter = delimiter.into();
+            }
+            "indent" => options.indent = IndentStyle::Spaces(integer(name, value)?),
+            "length_marker" => options.length_marker = Some(LengthMarker::prefix(value)),
+            name => return Err(format!("unknown parameter `{}`", name)),
+        }
+    }
+    let value: serde_json::Value = serde_json::from_str(body).map_err(|err| format!("invalid JSON: {}", err))?;
+    toon::try_encode(&value, Some(options)).map_err(|err| err.to_string())
+}
+
+/// `/decode`: a TOON body as JSON, read with `indent` and `lenient` and written `compact` or pretty
+fn decode(body: &str, params: &[(String, String)], config: &Config) -> Result<String, String> {
+    let mut options = DecodeOptions {
+        indent: IndentStyle::Spaces(config.indent(None)),
+        fail_over_depth: Some(MAX_DEPTH),
+        ..Default::default()
+    };
+    let mut json = JsonOptions::default();
+    for (name, value) in params {
+        match name.as_str() {
+            "indent" => options.indent = IndentStyle::Spaces(integer(name, value)?),
+            "lenient" => options.strict = !boolean(name, value)?,
+            "compact" => json.pretty = !boolean(name, value)?,
+            name => return Err(format!("unknown parameter `{}`", name)),
+        }
+    }
+    toon::to_json_string(body, Some(options), json).map_err(|err| err.to_string())
+}
+
+fn respond(mut request: Request, config: &Config) {
+    let url = request.url().to_string();
+    let path = url.split('?').next().unwrap_or_default();
+    let (endpoint, content_type): (Endpoint, &str) = match path {
+        "/encode" => (encode, "text/plain"),
+        "/decode" => (decode, "application/json"),
+        _ => return reply(request, 404, "text/plain", format!("no endpoint at {}; use /encode or /decode", path)),
+    };
+    if *request.method() != Method::Post {
+        return reply(request, 405, "text/plain", format!("{} takes POST", path));
+    }
+
+    let mut body = String::new();
+    // One byte past the limit tells an oversized body from one that fits exactly
+    if let Err(err) = request.as_reader().take(MAX_BODY + 1).read_to_string(&mut body) {
+        return reply(request, 400, "text/plain", format!("unreadable body: {}", err));
+    }
+    if body.len() as u64 > MAX_BODY {
+        return reply(request, 413, "text/plain", format!("bodies are limited to {} bytes", MAX_BODY));
+    }
+    match endpoint(&body, &query(&url), config) {
+        Ok(output) => reply(request, 200, content_type, output),
+        Err(err) => reply(request, 400, "text/plain", err),
+    }
+}
+
+fn reply(request: Request, status: u16, content_type: &str, body: String) {
+    let header = Header::from_bytes("Content-Type", format!("{}; charset=utf-8", content_type)).expect("valid header");
+    // The client may already be gone; there is no one left to tell
+    let _ = request.respond(Response::from_string(body).with_status_code(status).with_header(header));
+}
+
+/// Answer `/encode` and `/decode` requests on `address` until the process is stopped
+///
+/// Requests are handled on one thread per available core.
+pub fn serve(address: &str, config: &Config) -> Result<(), String> {
+    let server = Server::http(address).map_err(|err| format!("{}: {}", address, err))?;
+    let local = server.server_addr().to_ip().map_or_else(|| address.to_string(), |addr| addr.to_string());
+    eprintln!("listening on http://{}", local);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Ok(request) = server.recv() {
+                    respond(request, config);
+                }
+            });
+        }
+    });
+    Ok(())
+}
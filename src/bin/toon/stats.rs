@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+
+/// Counts tokens in a piece of text
+pub type Counter = Box<dyn Fn(&str) -> usize>;
+
+/// Input and output sizes of one conversion
+#[derive(Clone, Copy, Default)]
+struct Sizes {
+    input_bytes: usize,
+    output_bytes: usize,
+    input_tokens: usize,
+    output_tokens: usize,
+}
+
+impl Sizes {
+    fn add(&mut self, other: Sizes) {
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+    }
+}
+
+/// Sizes recorded per file, with tokens counted when there's a counter
+pub struct Stats {
+    counter: Option<Counter>,
+    files: Vec<(String, Sizes)>,
+}
+
+impl Stats {
+    pub fn new(counter: Option<Counter>) -> Self {
+        Self { counter, files: Vec::new() }
+    }
+
+    pub fn record(&mut self, name: &str, input: &str, output: &str) {
+        let count = |text| self.counter.as_ref().map_or(0, |counter| counter(text));
+        let sizes = Sizes {
+            input_bytes: input.len(),
+            output_bytes: output.len(),
+            input_tokens: count(input),
+            output_tokens: count(output),
+        };
+        self.files.push((name.to_string(), sizes));
+    }
+
+    /// Write a line per file, then a total when there's more than one
+    pub fn report(&self, mut out: impl Write) -> io::Result<()> {
+        let mut total = Sizes::default();
+        for (name, sizes) in &self.files {
+            self.write_line(&mut out, name, sizes)?;
+            total.add(*sizes);
+        }
+        if self.files.len() > 1 {
+            self.write_line(&mut out, &format!("total ({} files)", self.files.len()), &total)?;
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, out: &mut impl Write, name: &str, sizes: &Sizes) -> io::Result<()> {
+        write!(out, "{}: {} -> {} bytes ({})", name, sizes.input_bytes, sizes.output_bytes, saving(sizes.input_bytes, sizes.output_bytes))?;
+        if self.counter.is_some() {
+            write!(out, ", {} -> {} tokens ({})", sizes.input_tokens, sizes.output_tokens, saving(sizes.input_tokens, sizes.output_tokens))?;
+        }
+        writeln!(out)
+    }
+}
+
+/// The change from `before` to `after` as a percentage, such as `-42.5%`
+fn saving(before: usize, after: usize) -> String {
+    if before == 0 {
+        return "n/a".to_string();
+    }
+    format!("{:+.1}%", (after as f64 - before as f64) / before as f64 * 100.0)
+}
@@ -0,0 +1,62 @@
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often watched files are checked
+pub const INTERVAL: Duration = Duration::from_millis(250);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Call `changed` with the index of each of `paths` whose modification time moves, checking
+/// every `interval`
+///
+/// Polling rather than file-system events keeps working when an editor saves by replacing the
+/// file. A file that disappears is picked up again once it's recreated.
+pub fn watch(paths: &[PathBuf], interval: Duration, mut changed: impl FnMut(usize)) -> ! {
+    let mut seen: Vec<Option<SystemTime>> = paths.iter().map(|path| modified(path)).collect();
+    loop {
+        thread::sleep(interval);
+        for (i, (path, last)) in paths.iter().zip(&mut seen).enumerate() {
+            let now = modified(path);
+            if now != *last {
+                *last = now;
+                if now.is_some() {
+                    changed(i);
+                }
+            }
+        }
+    }
+}
+
+/// Call `lines` with the complete lines of `path`, then with each batch of lines appended to it,
+/// checking every `interval`
+///
+/// A file that shrinks, as when a log is truncated for rotation, is read again from its start.
+/// Returns only with an error, from reading the file or from `lines`.
+pub fn follow(path: &Path, interval: Duration, mut lines: impl FnMut(&str) -> Result<(), String>) -> Result<(), String> {
+    let error = |err: std::io::Error| format!("{}: {}", path.display(), err);
+    let mut offset = 0;
+    // Bytes of a last line that hasn't been ended yet
+    let mut pending = Vec::new();
+    loop {
+        let len = fs::metadata(path).map_err(error)?.len();
+        if len < offset {
+            offset = 0;
+            pending.clear();
+        }
+        if len > offset {
+            let mut file = File::open(path).map_err(error)?;
+            file.seek(SeekFrom::Start(offset)).map_err(error)?;
+            offset += file.take(len - offset).read_to_end(&mut pending).map_err(error)? as u64;
+            if let Some(end) = pending.iter().rposition(|&byte| byte == b'\n') {
+                let complete: Vec<u8> = pending.drain(..=end).collect();
+                lines(&String::from_utf8_lossy(&complete))?;
+            }
+        }
+        thread::sleep(interval);
+    }
+}
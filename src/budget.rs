@@ -0,0 +1,149 @@
+use crate::prelude::*;
+use crate::error::EncodeError;
+use crate::stack::nested;
+use crate::types::{Delimiter, EncodeOptions, KeyFolding, RowLimit};
+use serde_json::Value;
+
+/// Anything that can count the tokens in a piece of text
+///
+/// Closures such as `|text: &str| text.len() / 4` work as rough counters; with the `tokens`
+/// feature `Model` counts with the model's own tokenizer.
+pub trait TokenCounter {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+impl<F: Fn(&str) -> usize> TokenCounter for F {
+    fn count_tokens(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+/// One reduction `encode_within_budget` applied to make the output fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reduction {
+    /// Null-valued fields were left out, as with `skip_nulls`
+    DropNulls,
+    /// Strings were cut to this many characters, as with `max_string_chars`
+    TruncateStrings(usize),
+    /// Tables kept only these rows, as with `max_rows`
+    SampleRows(RowLimit),
+    /// Numbers were rounded to this many decimal places, as with `float_digits`
+    RoundFloats(usize),
+}
+
+impl Reduction {
+    /// Tighten `options` by this reduction, keeping any stricter limit already set
+    fn apply(self, options: &mut EncodeOptions) {
+        match self {
+            Reduction::DropNulls => options.skip_nulls = true,
+            Reduction::TruncateStrings(max) => options.max_string_chars = Some(options.max_string_chars.map_or(max, |m| m.min(max))),
+            Reduction::SampleRows(limit) => {
+                options.max_rows = Some(match options.max_rows {
+                    Some(current) => RowLimit::new(current.head.min(limit.head), current.tail.min(limit.tail)),
+                    None => limit,
+                })
+            }
+            Reduction::RoundFloats(digits) => options.float_digits = Some(options.float_digits.map_or(digits, |d| d.min(digits))),
+        }
+    }
+
+    fn same_kind(self, other: Reduction) -> bool {
+        core::mem::discriminant(&self) == core::mem::discriminant(&other)
+    }
+}
+
+/// Reductions tried in order, each on top of the ones before it
+const STEPS: [Reduction; 9] = [
+    Reduction::DropNulls,
+    Reduction::TruncateStrings(256),
+    Reduction::TruncateStrings(64),
+    Reduction::SampleRows(RowLimit { head: 40, tail: 10 }),
+    Reduction::SampleRows(RowLimit { head: 8, tail: 2 }),
+    Reduction::RoundFloats(2),
+    Reduction::TruncateStrings(16),
+    Reduction::SampleRows(RowLimit { head: 2, tail: 1 }),
+    Reduction::RoundFloats(0),
+];
+
+/// Output of `encode_within_budget`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budgeted {
+    pub output: String,
+    /// Tokens in `output` by the counter passed in
+    pub tokens: usize,
+    /// Reductions in effect, in the order they were first applied; empty when the value fit as is
+    pub reductions: Vec<Reduction>,
+}
+
+/// Run `encode` with more and more reductions until the output has at most `max_tokens` tokens
+pub fn fit_budget<C: TokenCounter + ?Sized>(
+    encode: impl Fn(&EncodeOptions) -> Result<String, EncodeError>,
+    options: EncodeOptions,
+    max_tokens: usize,
+    counter: &C,
+) -> Result<Budgeted, EncodeError> {
+    let mut options = options;
+    let mut reductions: Vec<Reduction> = Vec::new();
+    let mut steps = STEPS.iter();
+    loop {
+        let output = encode(&options)?;
+        let tokens = counter.count_tokens(&output);
+        if tokens <= max_tokens {
+            return Ok(Budgeted { output, tokens, reductions });
+        }
+        let Some(&step) = steps.next() else {
+            return Err(EncodeError::OverTokenBudget { limit: max_tokens, tokens });
+        };
+        step.apply(&mut options);
+        match reductions.iter_mut().find(|r| r.same_kind(step)) {
+            Some(existing) => *existing = step,
+            None => reductions.push(step),
+        }
+    }
+}
+
+/// Elements of each array kept in the sample `optimize_layout` measures
+const SAMPLE_ITEMS: usize = 50;
+
+/// Delimiters and key folding modes tried by `optimize_layout`
+const LAYOUTS: [(Delimiter, KeyFolding); 6] = [
+    (Delimiter::Comma, KeyFolding::Off),
+    (Delimiter::Tab, KeyFolding::Off),
+    (Delimiter::Pipe, KeyFolding::Off),
+    (Delimiter::Comma, KeyFolding::Safe),
+    (Delimiter::Tab, KeyFolding::Safe),
+    (Delimiter::Pipe, KeyFolding::Safe),
+];
+
+/// `options` with the delimiter and key folding that make `encode` of a sample cheapest
+///
+/// The options as given are tried first and win ties; layouts that fail to encode are skipped.
+pub fn optimize_layout<C: TokenCounter + ?Sized>(
+    value: &Value,
+    encode: impl Fn(&Value, &EncodeOptions) -> Result<String, EncodeError>,
+    options: EncodeOptions,
+    counter: &C,
+) -> Result<EncodeOptions, EncodeError> {
+    let sample = sample_value(value);
+    let mut best_tokens = counter.count_tokens(&encode(&sample, &options)?);
+    let mut best = options.clone();
+    for (delimiter, key_folding) in LAYOUTS {
+        let candidate = EncodeOptions { delimiter, key_folding, ..options.clone() };
+        let Ok(output) = encode(&sample, &candidate) else { continue };
+        let tokens = counter.count_tokens(&output);
+        if tokens < best_tokens {
+            best_tokens = tokens;
+            best = candidate;
+        }
+    }
+    Ok(best)
+}
+
+/// Copy of `value` with every array cut to its first `SAMPLE_ITEMS` elements
+fn sample_value(value: &Value) -> Value {
+    match value {
+        Value::Array(arr) => Value::Array(arr.iter().take(SAMPLE_ITEMS).map(|v| nested(|| sample_value(v))).collect()),
+        Value::Object(obj) => Value::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| sample_value(v)))).collect()),
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,78 @@
+use crate::binary::base64;
+use crate::stack::nested;
+use ciborium::de::Error;
+use ciborium::Value as Cbor;
+use serde_json::{Map, Value};
+
+/// Tags whose content is used as is: date/time text, epoch time, URI and the self-describe marker
+const TRANSPARENT_TAGS: [u64; 4] = [0, 1, 32, 55799];
+
+/// Convert a CBOR value to JSON
+///
+/// Byte strings become base64 strings. Tags 0 (date/time), 1 (epoch time), 32 (URI) and 55799
+/// (self-describe) are dropped in favour of their content, bignums that fit an `i128` become
+/// integers and any other tag `n` becomes `{"!tag:n": content}`. Integers outside the 64-bit
+/// range become their decimal text, map keys that aren't text become their JSON text and NaN
+/// or infinite floats become null.
+pub fn cbor_to_json(value: &Cbor) -> Value {
+    match value {
+        Cbor::Null => Value::Null,
+        Cbor::Bool(b) => Value::Bool(*b),
+        Cbor::Integer(n) => integer(i128::from(*n)),
+        Cbor::Float(f) => serde_json::Number::from_f64(*f).map_or(Value::Null, Value::Number),
+        Cbor::Text(s) => Value::String(s.clone()),
+        Cbor::Bytes(bytes) => Value::String(base64(bytes)),
+        Cbor::Array(items) => Value::Array(items.iter().map(|v| nested(|| cbor_to_json(v))).collect()),
+        Cbor::Map(entries) => Value::Object(entries.iter().map(|(k, v)| (key_text(k), nested(|| cbor_to_json(v)))).collect()),
+        Cbor::Tag(tag, content) if TRANSPARENT_TAGS.contains(tag) => nested(|| cbor_to_json(content)),
+        Cbor::Tag(tag, content) => match bignum(*tag, content) {
+            Some(n) => integer(n),
+            None => {
+                let mut object = Map::new();
+                object.insert(format!("!tag:{}", tag), nested(|| cbor_to_json(content)));
+                Value::Object(object)
+            }
+        },
+        // `Value` is non-exhaustive; nothing the decoder produces reaches this arm
+        _ => Value::Null,
+    }
+}
+
+fn integer(n: i128) -> Value {
+    if let Ok(i) = i64::try_from(n) {
+        Value::from(i)
+    } else if let Ok(u) = u64::try_from(n) {
+        Value::from(u)
+    } else {
+        Value::String(n.to_string())
+    }
+}
+
+/// Value of a positive (2) or negative (3) bignum whose big-endian bytes fit an `i128`
+fn bignum(tag: u64, content: &Cbor) -> Option<i128> {
+    let (2 | 3, Cbor::Bytes(bytes)) = (tag, content) else {
+        return None;
+    };
+    let digits = &bytes[bytes.iter().take_while(|&&b| b == 0).count()..];
+    if digits.len() > 16 {
+        return None;
+    }
+    let magnitude = i128::try_from(digits.iter().fold(0u128, |n, &b| n << 8 | b as u128)).ok()?;
+    Some(if tag == 2 { magnitude } else { -1 - magnitude })
+}
+
+fn key_text(key: &Cbor) -> String {
+    match cbor_to_json(key) {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Decode exactly one CBOR data item from `bytes` and convert it to JSON
+pub fn parse_cbor(mut bytes: &[u8]) -> Result<Value, Error<std::io::Error>> {
+    let value: Cbor = ciborium::from_reader(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(Error::semantic(None, format!("{} trailing bytes after the CBOR item", bytes.len())));
+    }
+    Ok(cbor_to_json(&value))
+}
@@ -0,0 +1,108 @@
+use crate::prelude::*;
+use crate::encoders::{flat_table_header, write_row_lines};
+#[cfg(any(feature = "arrow", feature = "polars"))]
+use crate::primitives::format_number;
+use crate::primitives::{encode_field_key, encode_run_primitive, format_header};
+use crate::normalize::normalize_value;
+use crate::types::{Depth, EncodeOptions, JsonValue};
+use crate::writer::LineWriter;
+use serde_json::{Map, Value};
+
+/// Write a `rows`-row table of the columns named `names` without building row objects
+///
+/// `cell(column, row)` gives the encoded cell for an index into `names`. Columns are ordered
+/// and named as `flat_table_header` does for a table of objects.
+pub fn write_columnar_table(
+    writer: &mut LineWriter,
+    depth: Depth,
+    key: Option<&str>,
+    names: &[String],
+    rows: usize,
+    mut cell: impl FnMut(usize, usize) -> String,
+    options: &EncodeOptions,
+) {
+    let header = flat_table_header(names, options);
+    let fields: Vec<String> = header.iter().map(|(_, name)| name.clone()).collect();
+    let key = key.map(|key| encode_field_key(key, options));
+    writer.push(depth, format_header(rows, key.as_deref(), Some(&fields), options));
+
+    let lines = (0..rows).map(|row| {
+        let cells: Vec<String> = header.iter().map(|(column, _)| cell(*column, row)).collect();
+        cells.join(options.delimiter.as_str())
+    }).collect();
+    write_row_lines(lines, writer, depth + 1, options);
+}
+
+/// Whether `columns` can be written by `write_columnar_table` exactly as their row objects
+/// would be encoded
+///
+/// Every column must hold `rows` primitives under a name of its own, and no option may
+/// reshape the table's rows or cells, which only the JSON encoder knows how to do.
+pub fn writes_directly(names: &[String], columns: &[Vec<Value>], rows: usize, options: &EncodeOptions) -> bool {
+    let mut unique: Vec<&String> = names.iter().collect();
+    unique.sort();
+    unique.dedup();
+    let shape = !names.is_empty()
+        && unique.len() == names.len()
+        && rows > 0
+        && rows >= options.tabular_min_rows
+        && options.tabular_max_columns.is_none_or(|max| names.len() <= max)
+        && columns.iter().all(|column| column.len() == rows && column.iter().all(|v| !v.is_array() && !v.is_object()));
+
+    let reshaped = options.align_columns
+        || options.max_line_width.is_some()
+        || options.max_rows.is_some()
+        || options.max_depth.is_some()
+        || options.skip_nulls
+        || options.skip_empty
+        || options.sort_rows.is_some()
+        || options.group_by.is_some()
+        || options.string_dictionary.is_some()
+        || options.inline_objects.is_some()
+        || options.table_chunk_rows.is_some()
+        || !options.column_aliases.is_empty()
+        || options.alias_legend
+        || options.type_hints
+        || !options.column_types.is_empty()
+        || options.summarize.is_some()
+        || options.lift_constant_columns
+        || options.max_output_bytes.is_some()
+        || options.max_nodes.is_some()
+        || options.fail_over_depth.is_some_and(|levels| levels < 3)
+        || options.lossless
+        || options.max_string_chars.is_some()
+        || options.float_digits.is_some();
+    shape && !reshaped
+}
+
+/// Encoded cell for a primitive JSON value, as a table of objects writes it
+pub fn json_cell(value: &Value, options: &EncodeOptions) -> String {
+    match &normalize_value(value, options.key_order) {
+        JsonValue::Primitive(p) => encode_run_primitive(p, options),
+        _ => unreachable!("writes_directly admits only primitive cells"),
+    }
+}
+
+/// The row objects `columns` stand for, under `key` if given; short columns leave nulls
+pub fn rows_value(key: Option<&str>, names: &[String], columns: Vec<Vec<Value>>) -> Value {
+    let rows = columns.iter().map(Vec::len).max().unwrap_or(0);
+    let mut columns: Vec<_> = columns.into_iter().map(Vec::into_iter).collect();
+    let rows = (0..rows).map(|_| {
+        let row: Map<String, Value> = names.iter().zip(columns.iter_mut()).map(|(name, column)| (name.clone(), column.next().unwrap_or(Value::Null))).collect();
+        Value::Object(row)
+    }).collect();
+    match key {
+        Some(key) => Value::Object(core::iter::once((key.to_string(), Value::Array(rows))).collect()),
+        None => Value::Array(rows),
+    }
+}
+
+/// Encoded float cell, with NaN and infinities written as null
+#[cfg(any(feature = "arrow", feature = "polars"))]
+pub fn float_cell(value: f64) -> String {
+    if value.is_finite() {
+        format_number(value)
+    } else {
+        "null".to_string()
+    }
+}
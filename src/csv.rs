@@ -0,0 +1,217 @@
+use crate::prelude::*;
+use crate::types::{CsvOptions, CsvTable};
+#[cfg(feature = "std")]
+use serde_json::Map;
+use serde_json::{Number, Value};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+/// Read CSV records into an array of objects, one per record
+#[cfg(feature = "std")]
+pub fn read_csv<R: Read>(mut reader: R, options: &CsvOptions) -> io::Result<Value> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let mut records = parse_records(&text, options.delimiter)?.into_iter();
+
+    let headers: Vec<String> = match options.has_headers {
+        true => match records.next() {
+            Some((_, header)) => header.into_iter().map(|field| field.text).collect(),
+            None => return Ok(Value::Array(Vec::new())),
+        },
+        false => Vec::new(),
+    };
+
+    let mut rows = Vec::new();
+    for (line, record) in records {
+        if !headers.is_empty() && record.len() != headers.len() {
+            let message = format!("line {}: expected {} fields, found {}", line, headers.len(), record.len());
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        let row: Map<String, Value> = record
+            .into_iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let name = headers.get(i).cloned().unwrap_or_else(|| format!("col{}", i + 1));
+                (name, field_value(field, options))
+            })
+            .collect();
+        rows.push(Value::Object(row));
+    }
+    Ok(Value::Array(rows))
+}
+
+#[cfg(feature = "std")]
+fn field_value(field: Field, options: &CsvOptions) -> Value {
+    if field.text.is_empty() && options.empty_as_null && !field.quoted {
+        return Value::Null;
+    }
+    // Quoted fields are always strings, so `"007"` survives as text
+    if options.infer_types && !field.quoted {
+        match field.text.as_str() {
+            "true" => return Value::Bool(true),
+            "false" => return Value::Bool(false),
+            text => {
+                if let Ok(number) = text.parse::<Number>() {
+                    return Value::Number(number);
+                }
+            }
+        }
+    }
+    Value::String(field.text)
+}
+
+/// One field as read, remembering whether it was quoted
+#[cfg(feature = "std")]
+struct Field {
+    text: String,
+    quoted: bool,
+}
+
+/// Split `text` into records of fields, each with the 1-based line it starts on
+///
+/// Follows RFC 4180: fields may be quoted, `""` is a literal quote inside quotes, and quoted fields
+/// may span lines. Blank lines are skipped.
+#[cfg(feature = "std")]
+fn parse_records(text: &str, delimiter: char) -> io::Result<Vec<(usize, Vec<Field>)>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = Field { text: String::new(), quoted: false };
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.text.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.text.push(c);
+                }
+                _ => field.text.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.text.is_empty() && !field.quoted => {
+                in_quotes = true;
+                field.quoted = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                end_record(&mut records, &mut record, &mut field, record_line);
+                line += 1;
+                record_line = line;
+            }
+            c if c == delimiter => record.push(core::mem::replace(&mut field, Field { text: String::new(), quoted: false })),
+            _ => field.text.push(c),
+        }
+    }
+    if in_quotes {
+        let message = format!("line {}: unterminated quoted field", record_line);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+    }
+    end_record(&mut records, &mut record, &mut field, record_line);
+    Ok(records)
+}
+
+#[cfg(feature = "std")]
+fn end_record(records: &mut Vec<(usize, Vec<Field>)>, record: &mut Vec<Field>, field: &mut Field, line: usize) {
+    let blank = record.is_empty() && field.text.is_empty() && !field.quoted;
+    record.push(core::mem::replace(field, Field { text: String::new(), quoted: false }));
+    let record = core::mem::take(record);
+    if !blank {
+        records.push((line, record));
+    }
+}
+
+/// Find every table in `value` and write each as CSV, in document order
+///
+/// A table is a non-empty array of objects holding only primitives. Columns follow the order
+/// keys are first seen; a row without a column gets an empty field.
+pub fn write_tables(value: &Value, options: &CsvOptions) -> Vec<CsvTable> {
+    let mut tables = Vec::new();
+    collect_tables(value, String::new(), options, &mut tables);
+    tables
+}
+
+fn collect_tables(value: &Value, path: String, options: &CsvOptions, tables: &mut Vec<CsvTable>) {
+    match value {
+        Value::Array(items) if is_table(items) => tables.push(CsvTable { csv: write_table(items, options), path }),
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                collect_tables(item, format!("{}[{}]", path, index), options, tables);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                let path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                collect_tables(item, path, options, tables);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `items` form a table: a non-empty array of objects holding only primitives
+pub fn is_table(items: &[Value]) -> bool {
+    !items.is_empty()
+        && items.iter().all(|item| match item {
+            Value::Object(map) => map.values().all(|v| !v.is_array() && !v.is_object()),
+            _ => false,
+        })
+}
+
+/// Write rows as RFC 4180 records ending in CRLF, quoting what `read_csv` would read differently
+fn write_table(rows: &[Value], options: &CsvOptions) -> String {
+    let columns = table_columns(rows);
+    let delimiter = options.delimiter.to_string();
+    let mut out = String::new();
+    if options.has_headers {
+        let header: Vec<String> = columns.iter().map(|name| quote_field(name, true, options)).collect();
+        out.push_str(&header.join(&delimiter));
+        out.push_str("\r\n");
+    }
+    for row in rows.iter().filter_map(Value::as_object) {
+        let fields: Vec<String> = columns.iter().map(|name| match row.get(*name) {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::String(s)) => quote_field(s, false, options),
+            Some(other) => other.to_string(),
+        }).collect();
+        out.push_str(&fields.join(&delimiter));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// Column names of a table, in the order keys are first seen
+pub fn table_columns(rows: &[Value]) -> Vec<&String> {
+    let mut columns: Vec<&String> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for key in row.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+    columns
+}
+
+/// Quote a text field that contains the delimiter, a quote or a line break, or that would be
+/// read back as a number, boolean or null; header names only need the first three
+fn quote_field(text: &str, header: bool, options: &CsvOptions) -> String {
+    let special = text.contains(['"', '\r', '\n']) || text.contains(options.delimiter);
+    let typed = !header
+        && ((text.is_empty() && options.empty_as_null)
+            || (options.infer_types && (text == "true" || text == "false" || text.parse::<Number>().is_ok())));
+    if special || typed {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,917 @@
+use crate::prelude::*;
+use crate::error::DecodeError;
+use crate::primitives::{split_run_suffix, DOCUMENT_SEPARATOR};
+use crate::dictionary::is_code;
+use crate::stack::nested;
+use crate::types::{ColumnType, DecodeOptions, Delimiter, Depth, IndentStyle};
+use serde_json::{Map, Number, Value};
+
+/// A non-blank input line with its indentation resolved to a depth
+struct Line<'a> {
+    number: usize,
+    depth: Depth,
+    content: &'a str,
+}
+
+/// Array header parsed from `key[N<delimiter>]{fields}:`
+pub struct Header {
+    pub length: Option<usize>,
+    /// Index of the first row when the header is a `[start..end]` chunk of a split table
+    pub chunk_start: Option<usize>,
+    pub delimiter: Delimiter,
+    pub fields: Option<Vec<Key>>,
+    /// Type hints written as `field:type`, parallel to `fields`
+    pub field_types: Vec<Option<ColumnType>>,
+}
+
+/// An object key, remembering whether it was quoted in the source
+#[derive(Clone)]
+pub struct Key {
+    pub name: String,
+    pub quoted: bool,
+}
+
+/// A structural line: either `key: value` or an array header
+pub enum Entry<'a> {
+    Field { key: Key, rest: &'a str },
+    Array { key: Option<Key>, header: Header, rest: &'a str },
+}
+
+/// Decode a TOON document to a serde_json::Value
+pub fn decode_value(input: &str, options: &DecodeOptions) -> Result<Value, DecodeError> {
+    decode_document(input, options).map(|(_, value)| value)
+}
+
+/// Each document of a stream separated by `---` lines, with the 0-based line it starts on
+pub fn split_documents(input: &str) -> Vec<(usize, &str)> {
+    let mut documents = Vec::new();
+    let (mut start, mut first_line) = (0, 0);
+
+    let mut offset = 0;
+    for (index, line) in input.split_inclusive('\n').enumerate() {
+        if line.trim_end_matches(['\r', '\n']) == DOCUMENT_SEPARATOR {
+            documents.push((first_line, &input[start..offset]));
+            start = offset + line.len();
+            first_line = index + 1;
+        }
+        offset += line.len();
+    }
+    documents.push((first_line, &input[start..]));
+    documents
+}
+
+/// Decode a stream of documents separated by `---` lines
+pub fn decode_documents(input: &str, options: &DecodeOptions) -> Result<Vec<Value>, DecodeError> {
+    split_documents(input)
+        .into_iter()
+        .map(|(first_line, segment)| decode_segment(segment, first_line, options))
+        .collect()
+}
+
+/// Decode one document of a stream, reporting errors against the whole stream's line numbers
+pub fn decode_segment(segment: &str, first_line: usize, options: &DecodeOptions) -> Result<Value, DecodeError> {
+    decode_value(segment, options).map_err(|err| DecodeError::new(err.line + first_line, err.message))
+}
+
+/// Decode a TOON document into its `@meta` front matter and its value
+pub fn decode_document(input: &str, options: &DecodeOptions) -> Result<(Map<String, Value>, Value), DecodeError> {
+    let lines = scan_lines(input, options)?;
+    let mut parser = Parser {
+        lines,
+        pos: 0,
+        options,
+        meta: Map::new(),
+        dictionary: Vec::new(),
+        aliases: HashMap::new(),
+    };
+    let value = parser.parse_root()?;
+    Ok((parser.meta, value))
+}
+
+/// Split input into non-blank lines and resolve their depth
+fn scan_lines<'a>(input: &'a str, options: &DecodeOptions) -> Result<Vec<Line<'a>>, DecodeError> {
+    let unit = options.indent.unit();
+    let mut lines = Vec::new();
+
+    for (index, raw) in input.split('\n').enumerate() {
+        let number = index + 1;
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if raw.trim().is_empty() {
+            continue;
+        }
+
+        let mut depth = 0;
+        let mut content = raw;
+        if !unit.is_empty() {
+            while let Some(rest) = content.strip_prefix(unit.as_str()) {
+                depth += 1;
+                content = rest;
+            }
+        }
+
+        if let Some(limit) = options.fail_over_depth.filter(|limit| depth > *limit) {
+            return Err(DecodeError::new(number, format!("nesting deeper than {} levels", limit)));
+        }
+
+        let trimmed = content.trim_start();
+        if options.strict && trimmed.len() != content.len() {
+            let expected = match &options.indent {
+                IndentStyle::Spaces(n) => format!("{} spaces", n),
+                IndentStyle::Tabs => "tabs".to_string(),
+                IndentStyle::Custom(s) => format!("{:?}", s),
+            };
+            return Err(DecodeError::new(
+                number,
+                format!("indentation must be a multiple of {}", expected),
+            ));
+        }
+
+        lines.push(Line {
+            number,
+            depth,
+            // Trailing tabs may be a tab delimiter marking a continuation line
+            content: trimmed.trim_end_matches(' '),
+        });
+    }
+
+    Ok(lines)
+}
+
+struct Parser<'a, 'o> {
+    lines: Vec<Line<'a>>,
+    pos: usize,
+    options: &'o DecodeOptions,
+    /// Front matter declared by a leading `@meta:` block
+    meta: Map<String, Value>,
+    /// Strings declared by a leading `@dict` line, referenced as `$N`
+    dictionary: Vec<String>,
+    /// Table header aliases declared by a leading `@aliases` legend, mapped to their columns
+    aliases: HashMap<String, String>,
+}
+
+impl<'a> Parser<'a, '_> {
+    fn peek(&self) -> Option<&Line<'a>> {
+        self.lines.get(self.pos)
+    }
+
+    fn next_depth(&self) -> Option<Depth> {
+        self.peek().map(|line| line.depth)
+    }
+
+    fn parse_root(&mut self) -> Result<Value, DecodeError> {
+        self.parse_directives()?;
+
+        let (number, depth, content) = match self.peek() {
+            Some(line) => (line.number, line.depth, line.content),
+            None => return Ok(Value::Object(Map::new())),
+        };
+
+        if depth != 0 {
+            return Err(DecodeError::new(number, "unexpected indentation"));
+        }
+
+        let value = match parse_entry(content, number)? {
+            Some(Entry::Array { key: None, header, rest }) => {
+                self.pos += 1;
+                let mut value = self.parse_array(&header, rest, number, 1)?;
+                if header.chunk_start.is_some() {
+                    self.parse_root_chunks(&mut value)?;
+                }
+                value
+            }
+            Some(_) => Value::Object(self.parse_object(0)?),
+            None if self.pos + 1 == self.lines.len() => {
+                self.pos += 1;
+                self.primitive(content, number)?
+            }
+            None => return Err(DecodeError::new(number, "expected a key-value pair")),
+        };
+
+        match self.peek() {
+            Some(line) => Err(DecodeError::new(line.number, "unexpected content after the root value")),
+            None => Ok(value),
+        }
+    }
+
+    /// Consume leading `@` directive lines
+    fn parse_directives(&mut self) -> Result<(), DecodeError> {
+        while let Some(line) = self.peek() {
+            let Some(directive) = line.content.strip_prefix('@') else { break };
+            let (number, depth) = (line.number, line.depth);
+            if depth != 0 {
+                return Err(DecodeError::new(number, "unexpected indentation"));
+            }
+            self.pos += 1;
+
+            match parse_entry(directive, number)? {
+                Some(Entry::Field { key, rest }) if key.name == "meta" && rest.is_empty() => {
+                    self.meta = self.parse_object(depth + 1)?;
+                }
+                Some(Entry::Array { key: Some(key), header, rest }) if key.name == "dict" && header.fields.is_none() => {
+                    let entries = self
+                        .parse_inline_tokens(rest, header.delimiter, depth + 1)
+                        .into_iter()
+                        .map(|token| match parse_primitive(token, number)? {
+                            Value::String(s) => Ok(s),
+                            _ => Err(DecodeError::new(number, "dictionary entries must be strings")),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    if let Some(length) = header.length {
+                        if self.options.strict && entries.len() != length {
+                            return Err(DecodeError::new(
+                                number,
+                                format!("dictionary declares {} entries but contains {}", length, entries.len()),
+                            ));
+                        }
+                    }
+                    self.dictionary = entries;
+                }
+                Some(Entry::Array { key: Some(key), header, rest }) if key.name == "aliases" && rest.is_empty() => {
+                    let (rows, _) = match &header.fields {
+                        Some(fields) => self.parse_rows(fields, &header.field_types, header.delimiter, depth + 1)?,
+                        None => (Vec::new(), 0),
+                    };
+                    for row in rows {
+                        match (row.get("alias"), row.get("column")) {
+                            (Some(Value::String(alias)), Some(Value::String(column))) => {
+                                self.aliases.insert(alias.clone(), column.clone());
+                            }
+                            _ => return Err(DecodeError::new(number, "alias legend rows need string `alias` and `column` fields")),
+                        }
+                    }
+                }
+                _ => return Err(DecodeError::new(number, format!("unknown directive `@{}`", directive))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the value after `key: `, which is a primitive or an inline `{k: v, ...}` object
+    ///
+    /// Braces that don't hold `key: value` pairs, such as the `{…}` placeholder `max_depth`
+    /// writes, are read as a primitive.
+    fn field_value(&self, rest: &str, number: usize) -> Result<Value, DecodeError> {
+        let Some(inner) = rest.trim_end().strip_prefix('{').and_then(|inner| inner.strip_suffix('}')) else {
+            return self.primitive(rest, number);
+        };
+        let inner = inner.trim();
+
+        let mut map = Map::new();
+        if inner.is_empty() {
+            return Ok(Value::Object(map));
+        }
+        let pairs: Option<Vec<_>> = split_values(inner, Delimiter::Comma)
+            .into_iter()
+            .map(|pair| find_unquoted(pair, ':').map(|colon| (pair, colon)))
+            .collect();
+        let Some(pairs) = pairs else {
+            return self.primitive(rest, number);
+        };
+        for (pair, colon) in pairs {
+            let key = parse_key(pair[..colon].trim(), number)?;
+            let value = self.primitive(&pair[colon + 1..], number)?;
+            self.insert_field(&mut map, key, value, number)?;
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// The column a header field stands for, following the alias legend
+    fn resolve_alias(&self, field: &Key) -> Key {
+        match self.aliases.get(&field.name) {
+            // Columns are named by dotted paths, so expand them like unquoted keys
+            Some(column) => Key { name: column.clone(), quoted: false },
+            None => field.clone(),
+        }
+    }
+
+    /// Split a `value xN` run into the value and its count when runs are enabled
+    fn split_run<'t>(&self, token: &'t str) -> (&'t str, usize) {
+        match split_run_suffix(token.trim_end()) {
+            Some(run) if self.options.run_length => run,
+            _ => (token, 1),
+        }
+    }
+
+    /// Convert a cell to its column's hinted type; nulls fit every type
+    fn coerce(&self, value: Value, cell: &str, field_type: ColumnType, number: usize) -> Result<Value, DecodeError> {
+        let coerced = match (field_type, value) {
+            (_, Value::Null) => Some(Value::Null),
+            (t, Value::String(s)) if t.is_text() => Some(Value::String(s)),
+            // Unquoted numbers and booleans in a string column are the string itself
+            (t, Value::Number(_) | Value::Bool(_)) if t.is_text() => Some(Value::String(cell.trim().to_string())),
+            (ColumnType::Int, Value::Number(n)) if n.is_i64() || n.is_u64() => Some(Value::Number(n)),
+            (ColumnType::Float, Value::Number(n)) => n.as_f64().and_then(Number::from_f64).map(Value::Number),
+            (ColumnType::Decimal, Value::Number(n)) => Some(Value::Number(n)),
+            (ColumnType::Bool, Value::Bool(b)) => Some(Value::Bool(b)),
+            (_, value) if !self.options.strict => Some(value),
+            _ => None,
+        };
+        coerced.ok_or_else(|| {
+            DecodeError::new(number, format!("value `{}` does not match column type `{}`", cell.trim(), field_type.name()))
+        })
+    }
+
+    /// Parse a primitive token, expanding dictionary codes
+    fn primitive(&self, token: &str, number: usize) -> Result<Value, DecodeError> {
+        let token = token.trim();
+        if !self.dictionary.is_empty() && is_code(token) {
+            return token[1..]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| self.dictionary.get(index))
+                .map(|s| Value::String(s.clone()))
+                .ok_or_else(|| DecodeError::new(number, format!("unknown dictionary code `{}`", token)));
+        }
+        parse_primitive(token, number)
+    }
+
+    /// Append the remaining `[start..end]` chunks of a split root table
+    fn parse_root_chunks(&mut self, value: &mut Value) -> Result<(), DecodeError> {
+        while let Some(line) = self.peek() {
+            let (number, content) = (line.number, line.content);
+            let Some(Entry::Array { key: None, header, rest }) = parse_entry(content, number)? else { break };
+            let Some(start) = header.chunk_start else { break };
+            self.pos += 1;
+            let chunk = self.parse_array(&header, rest, number, 1)?;
+            append_chunk(Some(value), chunk, start, number, self.options.strict)?;
+        }
+        Ok(())
+    }
+
+    fn parse_object(&mut self, depth: Depth) -> Result<Map<String, Value>, DecodeError> {
+        let mut map = Map::new();
+        self.parse_fields(depth, &mut map)?;
+        Ok(map)
+    }
+
+    /// Parse consecutive `key: value` lines at `depth` into `map`
+    fn parse_fields(&mut self, depth: Depth, map: &mut Map<String, Value>) -> Result<(), DecodeError> {
+        while let Some(line) = self.peek() {
+            if line.depth < depth {
+                break;
+            }
+
+            let (number, content) = (line.number, line.content);
+            if line.depth > depth {
+                return Err(DecodeError::new(number, "unexpected indentation"));
+            }
+
+            self.pos += 1;
+            let (key, value) = match parse_entry(content, number)? {
+                Some(Entry::Field { key, rest }) => {
+                    let value = if rest.is_empty() {
+                        self.parse_nested_object(depth + 1)?
+                    } else {
+                        self.field_value(rest, number)?
+                    };
+                    (key, value)
+                }
+                Some(Entry::Array { key: Some(key), header, rest }) => {
+                    let value = nested(|| self.parse_array(&header, rest, number, depth + 1))?;
+                    if let Some(start) = header.chunk_start.filter(|start| *start > 0) {
+                        append_chunk(self.field_mut(map, &key), value, start, number, self.options.strict)?;
+                        continue;
+                    }
+                    (key, value)
+                }
+                _ => return Err(DecodeError::new(number, "expected a key-value pair")),
+            };
+            self.insert_field(map, key, value, number)?;
+        }
+
+        Ok(())
+    }
+
+    /// Insert a field, expanding unquoted dotted keys into nested objects when enabled
+    fn insert_field(&self, map: &mut Map<String, Value>, key: Key, value: Value, number: usize) -> Result<(), DecodeError> {
+        if !self.options.expand_paths || key.quoted || !key.name.contains('.') {
+            map.insert(key.name, value);
+            return Ok(());
+        }
+
+        let mut segments: Vec<&str> = key.name.split('.').collect();
+        let last = segments.pop().unwrap_or_default();
+        let mut target = map;
+
+        for segment in segments {
+            let entry = target
+                .entry(segment.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            target = match entry {
+                Value::Object(obj) => obj,
+                _ => {
+                    return Err(DecodeError::new(
+                        number,
+                        format!("path `{}` conflicts with an existing value", key.name),
+                    ))
+                }
+            };
+        }
+
+        match (target.get_mut(last), value) {
+            (Some(Value::Object(existing)), Value::Object(fields)) => existing.extend(fields),
+            (_, value) => {
+                target.insert(last.to_string(), value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The value under `key`, following its dotted path where `insert_field` would expand it
+    fn field_mut<'m>(&self, map: &'m mut Map<String, Value>, key: &Key) -> Option<&'m mut Value> {
+        if !self.options.expand_paths || key.quoted || !key.name.contains('.') {
+            return map.get_mut(&key.name);
+        }
+        let mut segments = key.name.split('.');
+        let mut value = map.get_mut(segments.next()?)?;
+        for segment in segments {
+            value = value.as_object_mut()?.get_mut(segment)?;
+        }
+        Some(value)
+    }
+
+    /// Parse the object under a `key:` line, or an empty object if nothing is nested
+    fn parse_nested_object(&mut self, depth: Depth) -> Result<Value, DecodeError> {
+        match self.next_depth() {
+            Some(next) if next >= depth => Ok(Value::Object(nested(|| self.parse_object(depth))?)),
+            _ => Ok(Value::Object(Map::new())),
+        }
+    }
+
+    /// Parse the body of an array whose header has already been consumed
+    fn parse_array(
+        &mut self,
+        header: &Header,
+        rest: &'a str,
+        number: usize,
+        depth: Depth,
+    ) -> Result<Value, DecodeError> {
+        let mut omitted = 0;
+        let items = if !rest.is_empty() {
+            if header.fields.is_some() {
+                return Err(DecodeError::new(number, "tabular header cannot be followed by inline values"));
+            }
+            let mut items = Vec::new();
+            for token in self.parse_inline_tokens(rest, header.delimiter, depth) {
+                let (token, count) = self.split_run(token);
+                items.extend(core::iter::repeat_n(self.primitive(token, number)?, count));
+            }
+            items
+        } else if let Some(fields) = &header.fields {
+            let fields: Vec<Key> = fields.iter().map(|field| self.resolve_alias(field)).collect();
+            let constants = self.parse_table_constants(header.delimiter, depth)?;
+            let (mut rows, skipped) = self.parse_rows(&fields, &header.field_types, header.delimiter, depth)?;
+            for row in &mut rows {
+                if let Value::Object(row) = row {
+                    for (key, value, line) in &constants {
+                        self.insert_field(row, key.clone(), value.clone(), *line)?;
+                    }
+                }
+            }
+            omitted = skipped;
+            rows
+        } else if header.length == Some(0) {
+            Vec::new()
+        } else {
+            self.parse_list_items(depth)?
+        };
+
+        if let Some(length) = header.length {
+            if self.options.strict && items.len() + omitted != length {
+                return Err(DecodeError::new(
+                    number,
+                    format!("array declares {} items but contains {}", length, items.len() + omitted),
+                ));
+            }
+        }
+
+        Ok(Value::Array(items))
+    }
+
+    /// Split inline values, following continuation lines after a trailing delimiter
+    fn parse_inline_tokens(&mut self, rest: &'a str, delimiter: Delimiter, depth: Depth) -> Vec<&'a str> {
+        let mut tokens = split_values(rest, delimiter);
+
+        while tokens.len() > 1 && tokens.last() == Some(&"") {
+            let content = match self.peek() {
+                Some(line) if line.depth == depth && !is_list_item(line.content) && is_row(line.content, delimiter) => {
+                    line.content
+                }
+                _ => break,
+            };
+            self.pos += 1;
+            tokens.pop();
+            tokens.extend(split_values(content, delimiter));
+        }
+
+        tokens
+    }
+
+    /// Parse `name: value` lines above a table's rows, which hold a column shared by every row
+    fn parse_table_constants(&mut self, delimiter: Delimiter, depth: Depth) -> Result<Vec<(Key, Value, usize)>, DecodeError> {
+        let mut constants = Vec::new();
+
+        while let Some(line) = self.peek() {
+            if line.depth != depth || is_row(line.content, delimiter) {
+                break;
+            }
+            let (number, content) = (line.number, line.content);
+            let Some(Entry::Field { key, rest }) = parse_entry(content, number)? else { break };
+            if rest.is_empty() {
+                break;
+            }
+
+            self.pos += 1;
+            let key = self.resolve_alias(&key);
+            constants.push((key, self.primitive(rest, number)?, number));
+        }
+
+        Ok(constants)
+    }
+
+    /// Parse tabular rows, also returning how many rows omitted-row markers stand in for
+    fn parse_rows(
+        &mut self,
+        fields: &[Key],
+        types: &[Option<ColumnType>],
+        delimiter: Delimiter,
+        depth: Depth,
+    ) -> Result<(Vec<Value>, usize), DecodeError> {
+        let mut rows = Vec::new();
+        let mut omitted = 0;
+
+        while let Some(line) = self.peek() {
+            if line.depth != depth || !is_row(line.content, delimiter) {
+                break;
+            }
+
+            let (number, content) = (line.number, line.content);
+            self.pos += 1;
+
+            if let Some(count) = parse_omitted_rows(content) {
+                omitted += count;
+                continue;
+            }
+
+            let (content, count) = self.split_run(content);
+            let cells = split_values(content, delimiter);
+            if cells.len() != fields.len() {
+                return Err(DecodeError::new(
+                    number,
+                    format!("row has {} values but the header declares {} fields", cells.len(), fields.len()),
+                ));
+            }
+
+            let mut row = Map::new();
+            // Empty strings are always quoted, so an empty cell means the row lacks the field
+            for (i, (field, cell)) in fields.iter().zip(cells).enumerate().filter(|(_, (_, cell))| !cell.is_empty()) {
+                let mut value = self.primitive(cell, number)?;
+                if let Some(field_type) = types.get(i).copied().flatten() {
+                    value = self.coerce(value, cell, field_type, number)?;
+                }
+                self.insert_field(&mut row, field.clone(), value, number)?;
+            }
+            rows.extend(core::iter::repeat_n(Value::Object(row), count));
+        }
+
+        Ok((rows, omitted))
+    }
+
+    fn parse_list_items(&mut self, depth: Depth) -> Result<Vec<Value>, DecodeError> {
+        let mut items = Vec::new();
+
+        while let Some(line) = self.peek() {
+            if line.depth != depth || !is_list_item(line.content) {
+                break;
+            }
+
+            let (number, content) = (line.number, line.content);
+            self.pos += 1;
+            items.push(nested(|| self.parse_list_item(content[1..].trim_start(), number, depth))?);
+        }
+
+        Ok(items)
+    }
+
+    /// Parse the text after a `- ` marker
+    fn parse_list_item(&mut self, body: &'a str, number: usize, depth: Depth) -> Result<Value, DecodeError> {
+        if body.is_empty() {
+            return Ok(Value::Object(Map::new()));
+        }
+
+        let (key, value) = match parse_entry(body, number)? {
+            None => return self.primitive(body, number),
+            Some(Entry::Array { key: None, header, rest }) => {
+                return self.parse_array(&header, rest, number, depth + 1);
+            }
+            Some(Entry::Field { key, rest }) => {
+                let value = if rest.is_empty() {
+                    // Fields of an object opening a list item are nested two levels deeper
+                    self.parse_nested_object(depth + 2)?
+                } else {
+                    self.field_value(rest, number)?
+                };
+                (key, value)
+            }
+            Some(Entry::Array { key: Some(key), header, rest }) => {
+                let child_depth = match self.next_depth() {
+                    Some(next) if next == depth + 2 => depth + 2,
+                    _ => depth + 1,
+                };
+                (key, self.parse_array(&header, rest, number, child_depth)?)
+            }
+        };
+
+        let mut map = Map::new();
+        self.insert_field(&mut map, key, value, number)?;
+        self.parse_fields(depth + 1, &mut map)?;
+        Ok(Value::Object(map))
+    }
+}
+
+pub fn is_list_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// Extend the array decoded from earlier chunks with the rows of the chunk starting at `start`
+fn append_chunk(target: Option<&mut Value>, chunk: Value, start: usize, number: usize, strict: bool) -> Result<(), DecodeError> {
+    let (Some(Value::Array(rows)), Value::Array(chunk)) = (target, chunk) else {
+        return Err(DecodeError::new(number, "table chunk does not follow an earlier chunk"));
+    };
+    if strict && rows.len() != start {
+        return Err(DecodeError::new(number, format!("table chunk starts at row {} but {} rows precede it", start, rows.len())));
+    }
+    rows.extend(chunk);
+    Ok(())
+}
+
+/// Row count of a `…(+K rows)` marker written in place of rows left out of a table
+pub fn parse_omitted_rows(content: &str) -> Option<usize> {
+    let inner = content.strip_prefix("…(+")?.strip_suffix(')')?;
+    let count = inner.strip_suffix(" rows").or_else(|| inner.strip_suffix(" row"))?;
+    count.parse().ok()
+}
+
+/// A line is a tabular row unless a colon appears before the first delimiter
+pub fn is_row(content: &str, delimiter: Delimiter) -> bool {
+    match find_unquoted(content, ':') {
+        None => true,
+        Some(colon) => find_unquoted(content, delimiter.as_str().chars().next().unwrap_or(','))
+            .is_some_and(|pos| pos < colon),
+    }
+}
+
+/// Parse a line as `key: value` or an array header, if it has that shape
+pub fn parse_entry(content: &str, number: usize) -> Result<Option<Entry<'_>>, DecodeError> {
+    let (key, after_key) = if content.starts_with('"') {
+        let (name, used) = parse_quoted(content, number)?;
+        (Some(Key { name, quoted: true }), &content[used..])
+    } else if content.starts_with('[') {
+        (None, content)
+    } else {
+        let end = content.find([':', '[']).unwrap_or(content.len());
+        let name = content[..end].trim_end().to_string();
+        (Some(Key { name, quoted: false }), &content[end..])
+    };
+
+    if let Some(rest) = after_key.strip_prefix(':') {
+        return match key {
+            Some(key) => Ok(Some(Entry::Field { key, rest: rest.trim() })),
+            None => Ok(None),
+        };
+    }
+
+    if after_key.starts_with('[') {
+        let (header, rest) = parse_header(after_key, number)?;
+        return Ok(Some(Entry::Array { key, header, rest }));
+    }
+
+    Ok(None)
+}
+
+/// Parse `[N<delimiter>]{fields}:` and return the header plus anything after the colon
+fn parse_header(text: &str, number: usize) -> Result<(Header, &str), DecodeError> {
+    let close = text
+        .find(']')
+        .ok_or_else(|| DecodeError::new(number, "unterminated array header"))?;
+    let mut inner = &text[1..close];
+
+    let mut delimiter = Delimiter::Comma;
+    if let Some(marker) = inner.chars().last().and_then(Delimiter::from_marker) {
+        delimiter = marker;
+        inner = &inner[..inner.len() - marker.as_str().len()];
+    }
+
+    if let Some((start, end)) = inner.split_once("..") {
+        let bounds = start.parse::<usize>().ok().zip(end.parse::<usize>().ok()).filter(|(s, e)| s <= e);
+        let (start, end) = bounds.ok_or_else(|| DecodeError::new(number, format!("invalid chunk range `{}`", inner)))?;
+        let (mut header, rest) = parse_header_fields(text, close, delimiter, Some(end - start), number)?;
+        header.chunk_start = Some(start);
+        return Ok((header, rest));
+    }
+
+    // Text around the digits is a length marker such as `#` or `len=`; the count itself is optional
+    let digits = inner.trim_start_matches(|c: char| !c.is_ascii_digit());
+    let digits = &digits[..digits.bytes().take_while(u8::is_ascii_digit).count()];
+    let length = if digits.is_empty() {
+        None
+    } else {
+        let length = digits
+            .parse::<usize>()
+            .map_err(|_| DecodeError::new(number, format!("invalid array length `{}`", inner)))?;
+        Some(length)
+    };
+
+    parse_header_fields(text, close, delimiter, length, number)
+}
+
+/// Parse the optional `{fields}` and the colon following the bracket at `close`
+fn parse_header_fields(
+    text: &str,
+    close: usize,
+    delimiter: Delimiter,
+    length: Option<usize>,
+    number: usize,
+) -> Result<(Header, &str), DecodeError> {
+    let mut rest = &text[close + 1..];
+    let mut fields = None;
+    let mut field_types = Vec::new();
+
+    if rest.starts_with('{') {
+        let end = find_unquoted(rest, '}')
+            .ok_or_else(|| DecodeError::new(number, "unterminated field list"))?;
+        let mut names = Vec::new();
+        for field in split_values(&rest[1..end], delimiter) {
+            match find_unquoted(field, ':') {
+                Some(colon) => {
+                    names.push(parse_key(&field[..colon], number)?);
+                    let name = field[colon + 1..].trim();
+                    let field_type = ColumnType::from_name(name)
+                        .ok_or_else(|| DecodeError::new(number, format!("unknown field type `{}`", name)))?;
+                    field_types.push(Some(field_type));
+                }
+                None => {
+                    names.push(parse_key(field, number)?);
+                    field_types.push(None);
+                }
+            }
+        }
+        fields = Some(names);
+        rest = &rest[end + 1..];
+    }
+
+    let rest = rest
+        .strip_prefix(':')
+        .ok_or_else(|| DecodeError::new(number, "expected `:` after array header"))?;
+
+    Ok((Header { length, chunk_start: None, delimiter, fields, field_types }, rest.trim_matches(' ')))
+}
+
+fn parse_key(token: &str, number: usize) -> Result<Key, DecodeError> {
+    let token = token.trim();
+    if token.starts_with('"') {
+        let (name, used) = parse_quoted(token, number)?;
+        if used != token.len() {
+            return Err(DecodeError::new(number, "unexpected characters after quoted key"));
+        }
+        Ok(Key { name, quoted: true })
+    } else {
+        Ok(Key { name: token.to_string(), quoted: false })
+    }
+}
+
+/// Parse a single primitive token
+fn parse_primitive(token: &str, number: usize) -> Result<Value, DecodeError> {
+    let token = token.trim();
+
+    if token.starts_with('"') {
+        let (value, used) = parse_quoted(token, number)?;
+        if used != token.len() {
+            return Err(DecodeError::new(number, "unexpected characters after closing quote"));
+        }
+        return Ok(Value::String(value));
+    }
+
+    match token {
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        "null" => return Ok(Value::Null),
+        _ => {}
+    }
+
+    if let Some(number) = parse_number(token) {
+        return Ok(Value::Number(number));
+    }
+
+    Ok(Value::String(token.to_string()))
+}
+
+/// Parse a JSON-style number, rejecting forms like `05` that decode as strings
+pub fn parse_number(token: &str) -> Option<Number> {
+    let unsigned = token.strip_prefix('-').unwrap_or(token);
+    let int_len = unsigned.bytes().take_while(u8::is_ascii_digit).count();
+    if int_len == 0 || (int_len > 1 && unsigned.starts_with('0')) {
+        return None;
+    }
+
+    let mut rest = &unsigned[int_len..];
+    let is_integer = rest.is_empty();
+
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        rest = &fraction[len..];
+    }
+
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let len = exponent.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        rest = &exponent[len..];
+    }
+
+    if !rest.is_empty() {
+        return None;
+    }
+
+    if is_integer {
+        if let Ok(n) = token.parse::<i64>() {
+            return Some(Number::from(n));
+        }
+        if let Ok(n) = token.parse::<u64>() {
+            return Some(Number::from(n));
+        }
+    }
+
+    token.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+/// Parse a quoted string at the start of `text`, returning it with the bytes consumed
+fn parse_quoted(text: &str, number: usize) -> Result<(String, usize), DecodeError> {
+    let mut result = String::new();
+    let mut chars = text.char_indices().skip(1);
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return Ok((result, index + 1)),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => result.push('\n'),
+                Some((_, 'r')) => result.push('\r'),
+                Some((_, 't')) => result.push('\t'),
+                Some((_, '"')) => result.push('"'),
+                Some((_, '\\')) => result.push('\\'),
+                Some((_, other)) => {
+                    return Err(DecodeError::new(number, format!("invalid escape sequence `\\{}`", other)))
+                }
+                None => break,
+            },
+            _ => result.push(c),
+        }
+    }
+
+    Err(DecodeError::new(number, "unterminated string"))
+}
+
+/// Find the first occurrence of `target` outside quoted strings
+fn find_unquoted(text: &str, target: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        if in_quotes {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_quotes = false;
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == target {
+            return Some(index);
+        }
+    }
+
+    None
+}
+
+/// Split delimited values, ignoring delimiters inside quoted strings
+pub fn split_values(text: &str, delimiter: Delimiter) -> Vec<&str> {
+    let target = delimiter.as_str().chars().next().unwrap_or(',');
+    let mut values = Vec::new();
+    let mut rest = text;
+
+    while let Some(pos) = find_unquoted(rest, target) {
+        values.push(rest[..pos].trim());
+        rest = &rest[pos + target.len_utf8()..];
+    }
+    values.push(rest.trim());
+
+    values
+}
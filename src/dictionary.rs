@@ -0,0 +1,60 @@
+use crate::prelude::*;
+use crate::stack::nested;
+use crate::types::{JsonPrimitive, JsonValue};
+
+/// Strings occurring at least `min_occurrences` times that are longer than their code,
+/// most frequent first
+pub fn build_dictionary(value: &JsonValue, min_occurrences: usize) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    count_strings(value, &mut counts);
+
+    let mut entries: Vec<(&str, usize)> = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= min_occurrences.max(2))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut dictionary = Vec::new();
+    for (text, _) in entries {
+        if text.chars().count() > code(dictionary.len()).len() {
+            dictionary.push(text.to_string());
+        }
+    }
+    dictionary
+}
+
+fn count_strings<'v>(value: &'v JsonValue, counts: &mut HashMap<&'v str, usize>) {
+    match value {
+        JsonValue::Primitive(JsonPrimitive::String(s)) => *counts.entry(s).or_insert(0) += 1,
+        JsonValue::Primitive(_) => {}
+        JsonValue::Array(arr) => arr.iter().for_each(|v| nested(|| count_strings(v, counts))),
+        JsonValue::Object(obj) => obj.iter().for_each(|(_, v)| nested(|| count_strings(v, counts))),
+    }
+}
+
+/// Replace dictionary strings with their `$N` codes
+///
+/// Literal strings that look like a code are written quoted so the decoder leaves them alone.
+pub fn apply_dictionary<'a>(value: &JsonValue<'a>, dictionary: &[String]) -> JsonValue<'a> {
+    match value {
+        JsonValue::Primitive(JsonPrimitive::String(s)) => match dictionary.iter().position(|d| d == s) {
+            Some(index) => JsonValue::Primitive(JsonPrimitive::Raw(code(index))),
+            None if is_code(s) => JsonValue::Primitive(JsonPrimitive::Raw(format!("\"{}\"", s))),
+            None => value.clone(),
+        },
+        JsonValue::Primitive(_) => value.clone(),
+        JsonValue::Array(arr) => JsonValue::Array(arr.iter().map(|v| nested(|| apply_dictionary(v, dictionary))).collect()),
+        JsonValue::Object(obj) => {
+            JsonValue::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| apply_dictionary(v, dictionary)))).collect())
+        }
+    }
+}
+
+fn code(index: usize) -> String {
+    format!("${}", index)
+}
+
+/// Check if an unquoted token has the shape of a dictionary code
+pub fn is_code(token: &str) -> bool {
+    token.strip_prefix('$').is_some_and(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+}
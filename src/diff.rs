@@ -0,0 +1,134 @@
+use crate::prelude::*;
+use crate::stack::nested;
+use serde_json::Value;
+
+/// One difference found by [`diff`](crate::diff), at a JSON Pointer path such as `/users/0/name`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A key or index only the new value has
+    Added { path: String, value: Value },
+    /// A key or index only the old value has
+    Removed { path: String, value: Value },
+    /// A value that differs, of the same or another type
+    Changed { path: String, old: Value, new: Value },
+}
+
+impl Change {
+    /// Where the change is, `""` for the root
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// `key` as a JSON Pointer step, escaping `~` and `/`
+fn key_step(key: &str) -> String {
+    format!("/{}", key.replace('~', "~0").replace('/', "~1"))
+}
+
+/// Equality that treats `1` and `1.0` as the same number
+fn same(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        (Value::Array(x), Value::Array(y)) => x.len() == y.len() && x.iter().zip(y).all(|(a, b)| nested(|| same(a, b))),
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len() && x.iter().all(|(key, a)| y.get(key).is_some_and(|b| nested(|| same(a, b))))
+        }
+        _ => a == b,
+    }
+}
+
+/// Append a change per difference between `a` and `b`, comparing objects by key and arrays by index
+///
+/// Keys are visited sorted, so the order doesn't depend on `preserve_order`.
+fn walk(path: &mut String, a: &Value, b: &Value, changes: &mut Vec<Change>) {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            let mut keys: Vec<&String> = x.keys().chain(y.keys().filter(|key| !x.contains_key(*key))).collect();
+            keys.sort();
+            for key in keys {
+                let len = path.len();
+                path.push_str(&key_step(key));
+                match (x.get(key), y.get(key)) {
+                    (Some(a), Some(b)) => nested(|| walk(path, a, b, changes)),
+                    (Some(a), None) => changes.push(Change::Removed { path: path.clone(), value: a.clone() }),
+                    (None, Some(b)) => changes.push(Change::Added { path: path.clone(), value: b.clone() }),
+                    (None, None) => {}
+                }
+                path.truncate(len);
+            }
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            for i in 0..x.len().max(y.len()) {
+                let len = path.len();
+                path.push_str(&format!("/{}", i));
+                match (x.get(i), y.get(i)) {
+                    (Some(a), Some(b)) => nested(|| walk(path, a, b, changes)),
+                    (Some(a), None) => changes.push(Change::Removed { path: path.clone(), value: a.clone() }),
+                    (None, Some(b)) => changes.push(Change::Added { path: path.clone(), value: b.clone() }),
+                    (None, None) => {}
+                }
+                path.truncate(len);
+            }
+        }
+        _ if same(a, b) => {}
+        _ => changes.push(Change::Changed { path: path.clone(), old: a.clone(), new: b.clone() }),
+    }
+}
+
+/// Every difference between `a` and `b`
+pub fn changes(a: &Value, b: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    walk(&mut String::new(), a, b, &mut changes);
+    changes
+}
+
+/// `path`, or `/` for the root
+fn display(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
+
+fn is_container(value: &Value) -> bool {
+    value.is_array() || value.is_object()
+}
+
+/// `value` after `marker path:`: a primitive on the same line, anything else as an indented
+/// TOON block below it
+fn entry(out: &mut String, marker: char, path: &str, value: &Value) {
+    let path = display(path);
+    let text = crate::encode(value, None);
+    match is_container(value) {
+        true => {
+            out.push_str(&format!("{} {}:\n", marker, path));
+            for line in text.lines() {
+                out.push_str(&format!("    {}\n", line));
+            }
+        }
+        false => out.push_str(&format!("{} {}: {}\n", marker, path, text)),
+    }
+}
+
+/// One entry per change: `+ path: value`, `- path: value` or `~ path: old -> new`
+///
+/// Values are written as TOON, containers as a block indented under their entry; a changed
+/// container becomes a block of `from` and `to`. The root path is written `/`.
+pub fn render(changes: &[Change]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        match change {
+            Change::Added { path, value } => entry(&mut out, '+', path, value),
+            Change::Removed { path, value } => entry(&mut out, '-', path, value),
+            Change::Changed { path, old, new } if !is_container(old) && !is_container(new) => {
+                out.push_str(&format!("~ {}: {} -> {}\n", display(path), crate::encode(old, None), crate::encode(new, None)));
+            }
+            Change::Changed { path, old, new } => {
+                let mut both = serde_json::Map::new();
+                both.insert("from".to_string(), old.clone());
+                both.insert("to".to_string(), new.clone());
+                entry(&mut out, '~', path, &Value::Object(both));
+            }
+        }
+    }
+    out
+}
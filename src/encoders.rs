@@ -1,67 +1,266 @@
-use crate::normalize::{is_array_of_arrays, is_array_of_objects, is_array_of_primitives, is_primitive};
-use crate::primitives::{encode_key, encode_primitive, format_header, join_encoded_values};
-use crate::types::{Depth, EncodeOptions, JsonPrimitive, JsonValue};
-use crate::writer::LineWriter;
-use std::collections::HashMap;
+use crate::prelude::*;
+use crate::dictionary::{apply_dictionary, build_dictionary};
+use crate::error::EncodeError;
+use crate::summary::summarize_value;
+use crate::normalize::{get_field, is_array_of_arrays, is_array_of_objects, is_array_of_primitives, shorten_primitives, truncate_value};
+use crate::primitives::{encode_field_key, encode_primitive, encode_root_primitive, encode_run_primitive, format_chunk_header, format_header, format_number, is_foldable_segment};
+use crate::types::{ColumnOrder, Delimiter, Depth, EncodeOptions, EncodeReport, JsonObject, JsonPrimitive, JsonValue, KeyFolding, MissingCell, SortDirection, SortSpec};
+use crate::stack::nested;
+use crate::validate::find_invalid_option;
+use crate::writer::{LineWriter, Scratch};
+use alloc::borrow::Cow;
+use core::cmp::Ordering;
 
 const LIST_ITEM_PREFIX: &str = "- ";
 
 /// Encode a JsonValue to TOON format
-pub fn encode_value(value: &JsonValue, options: &EncodeOptions) -> String {
-    if is_primitive(value) {
-        if let JsonValue::Primitive(p) = value {
-            return encode_primitive(p, &options.delimiter);
+pub fn encode_value(value: &JsonValue, options: &EncodeOptions) -> Result<String, EncodeError> {
+    encode_document(None, value, options)
+}
+
+/// Encode a JsonValue, preceded by an `@meta` front-matter block when `meta` is given
+pub fn encode_document(meta: Option<&JsonObject>, value: &JsonValue, options: &EncodeOptions) -> Result<String, EncodeError> {
+    encode_document_into(meta, value, options, String::new(), &mut Scratch::default())
+}
+
+/// Encode a document into `buffer`, reusing its allocation and those of `scratch`
+pub fn encode_document_into(
+    meta: Option<&JsonObject>,
+    value: &JsonValue,
+    options: &EncodeOptions,
+    buffer: String,
+    scratch: &mut Scratch,
+) -> Result<String, EncodeError> {
+    encode_document_with_report(meta, value, options, buffer, scratch).map(|(output, _)| output)
+}
+
+/// Encode a document, also returning what the writer observed; `json_bytes` is left at zero
+///
+/// `scratch` is cleared before use and left holding the writer's allocations afterwards.
+pub fn encode_document_with_report(
+    meta: Option<&JsonObject>,
+    value: &JsonValue,
+    options: &EncodeOptions,
+    buffer: String,
+    scratch: &mut Scratch,
+) -> Result<(String, EncodeReport), EncodeError> {
+    if let Some(err) = find_invalid_option(options) {
+        return Err(err);
+    }
+
+    // The root itself is never replaced, so its children get the full budget
+    let truncated;
+    let value = match (options.max_depth, value) {
+        (Some(levels), JsonValue::Array(arr)) => {
+            truncated = JsonValue::Array(arr.iter().map(|v| truncate_value(v, levels)).collect());
+            &truncated
+        }
+        (Some(levels), JsonValue::Object(obj)) => {
+            truncated = JsonValue::Object(obj.iter().map(|(k, v)| (k.clone(), truncate_value(v, levels))).collect());
+            &truncated
+        }
+        _ => value,
+    };
+
+    let shortened;
+    let value = if options.max_string_chars.is_some() || options.float_digits.is_some() {
+        shortened = shorten_primitives(value, options.max_string_chars, options.float_digits);
+        &shortened
+    } else {
+        value
+    };
+
+    let summarized;
+    let value = match &options.summarize {
+        Some(summarize) => {
+            summarized = summarize_value(value, summarize);
+            &summarized
         }
+        None => value,
+    };
+
+    let collision = (!options.column_aliases.is_empty()).then(|| find_alias_collision(value, options)).flatten();
+    if let Some(err) = collision {
+        return Err(err);
     }
 
-    let mut writer = LineWriter::new(options.indent);
+    let mut writer = LineWriter::with_buffer(buffer, &options.indent, options.line_ending)
+        .with_limit(options.max_output_bytes)
+        .with_scratch(core::mem::take(scratch));
+    // A little slack keeps a slight underestimate from forcing a full copy at the very end
+    let estimate = estimate_output_len(value, 0, true, writer.indent_width(1));
+    writer.reserve((estimate + estimate / 8).min(options.max_output_bytes.unwrap_or(usize::MAX)));
+
+    if let Some(meta) = meta {
+        writer.push(0, "@meta:".to_string());
+        encode_object(meta, &mut writer, 1, options);
+    }
+
+    let dictionary = match (options.string_dictionary, value) {
+        (_, JsonValue::Primitive(_)) | (None, _) => Vec::new(),
+        (Some(min_occurrences), _) => build_dictionary(value, min_occurrences),
+    };
+    if options.alias_legend && !options.column_aliases.is_empty() {
+        write_alias_legend(&mut writer, options);
+    }
+
+    let coded;
+    let value = if dictionary.is_empty() {
+        value
+    } else {
+        let entries: Vec<JsonValue> = dictionary.iter().map(|s| JsonValue::Primitive(JsonPrimitive::String(Cow::Borrowed(s.as_str())))).collect();
+        write_inline_values(format_header(entries.len(), Some("@dict"), None, options), &entries, &mut writer, 0, options);
+        coded = apply_dictionary(value, &dictionary);
+        &coded
+    };
 
     match value {
+        JsonValue::Primitive(p) => writer.push(0, encode_root_primitive(p, &options.delimiter)),
         JsonValue::Array(arr) => encode_array(None, arr, &mut writer, 0, options),
         JsonValue::Object(obj) => encode_object(obj, &mut writer, 0, options),
-        _ => {}
+    }
+    let too_large = EncodeError::OutputTooLarge { limit: options.max_output_bytes.unwrap_or(0) };
+    let exceeded = writer.exceeded();
+    let (mut output, mut report, used) = writer.into_parts();
+    *scratch = used;
+    if exceeded {
+        return Err(too_large);
+    }
+
+    if options.trailing_newline && !output.is_empty() {
+        output.push_str(options.line_ending.as_str());
+        if options.max_output_bytes.is_some_and(|limit| output.len() > limit) {
+            return Err(too_large);
+        }
     }
 
-    writer.to_string()
+    report.bytes = output.len();
+    Ok((output, report))
 }
 
-/// Encode an object
-pub fn encode_object(obj: &HashMap<String, JsonValue>, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
-    // We need to preserve insertion order, but HashMap doesn't guarantee it
-    // For now, we'll sort keys alphabetically (JS version uses object key order)
-    let mut keys: Vec<_> = obj.keys().collect();
-    keys.sort();
+/// Write the `@aliases` table mapping each header alias to its column
+fn write_alias_legend(writer: &mut LineWriter, options: &EncodeOptions) {
+    let mut aliases: Vec<(&String, &String)> = options.column_aliases.iter().map(|(column, alias)| (alias, column)).collect();
+    aliases.sort();
 
-    for key in keys {
-        if let Some(value) = obj.get(key.as_str()) {
-            encode_key_value_pair(key, value, writer, depth, options);
-        }
+    let fields = ["alias".to_string(), "column".to_string()];
+    writer.push(0, format_header(aliases.len(), Some("@aliases"), Some(&fields), options));
+    for (alias, column) in aliases {
+        let cells = [alias, column].map(|s| encode_primitive(&JsonPrimitive::String(Cow::Borrowed(s.as_str())), &options.delimiter));
+        writer.push(1, cells.join(options.delimiter.as_str()));
+    }
+}
+
+/// Fields of a non-tabular object that are written, honouring `skip_nulls` and `skip_empty`
+fn written_fields<'o, 'a>(obj: &'o JsonObject<'a>, options: &EncodeOptions) -> Vec<&'o (Cow<'a, str>, JsonValue<'a>)> {
+    obj.iter().filter(|(_, value)| match value {
+        JsonValue::Primitive(JsonPrimitive::Null) => !options.skip_nulls,
+        JsonValue::Array(arr) => !(options.skip_empty && arr.is_empty()),
+        JsonValue::Object(nested) => !(options.skip_empty && nested.is_empty()),
+        JsonValue::Primitive(_) => true,
+    }).collect()
+}
+
+/// Encode an object
+pub fn encode_object(obj: &JsonObject, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
+    let fields = written_fields(obj, options);
+    writer.record_dropped_fields(obj.len() - fields.len());
+    for (key, value) in fields {
+        encode_key_value_pair(key, value, writer, depth, options);
     }
 }
 
 /// Encode a key-value pair
 fn encode_key_value_pair(key: &str, value: &JsonValue, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
-    let encoded_key = encode_key(key);
+    if let JsonValue::Object(nested_obj) = value {
+        if let Some(inline) = encode_inline_object(nested_obj, writer, options) {
+            let line = format!("{}: {}", writer.encoded_key(key, options), inline);
+            writer.push(depth, line);
+            return;
+        }
+        if options.key_folding != KeyFolding::Off && is_foldable_segment(key) {
+            nested(|| encode_folded_object(key.to_string(), nested_obj, writer, depth, options));
+            return;
+        }
+    }
+
+    let encoded_key = writer.encoded_key(key, options);
+    write_key_value_pair(&encoded_key, value, writer, depth, options);
+}
 
+/// Write an already-encoded key and its value
+fn write_key_value_pair(encoded_key: &str, value: &JsonValue, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
     match value {
         JsonValue::Primitive(p) => {
             writer.push(depth, format!("{}: {}", encoded_key, encode_primitive(p, &options.delimiter)));
         }
         JsonValue::Array(arr) => {
-            encode_array(Some(key), arr, writer, depth, options);
+            nested(|| encode_array(Some(encoded_key), arr, writer, depth, options));
         }
         JsonValue::Object(nested_obj) => {
-            if nested_obj.is_empty() {
-                writer.push(depth, format!("{}:", encoded_key));
-            } else {
-                writer.push(depth, format!("{}:", encoded_key));
-                encode_object(nested_obj, writer, depth + 1, options);
+            write_nested_object(encoded_key, nested_obj, writer, depth, options);
+        }
+    }
+}
+
+/// Encode a small object of primitives as `{k: v, ...}` when `inline_objects` allows it
+fn encode_inline_object(obj: &JsonObject, writer: &mut LineWriter, options: &EncodeOptions) -> Option<String> {
+    let max_fields = options.inline_objects?;
+    let written = written_fields(obj, options);
+    if written.is_empty() || written.len() > max_fields {
+        return None;
+    }
+
+    let mut fields = Vec::with_capacity(written.len());
+    for (key, value) in written {
+        let JsonValue::Primitive(p) = value else { return None };
+        // Entries are comma-separated whatever the document delimiter is
+        fields.push(format!("{}: {}", encode_field_key(key, options), encode_primitive(p, &Delimiter::Comma)));
+    }
+    writer.record_dropped_fields(obj.len() - fields.len());
+    Some(format!("{{{}}}", fields.join(", ")))
+}
+
+fn write_nested_object(encoded_key: &str, obj: &JsonObject, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
+    writer.push(depth, format!("{}:", encoded_key));
+    if !obj.is_empty() {
+        nested(|| encode_object(obj, writer, depth + 1, options));
+    }
+}
+
+/// Fold nested objects under `path` into dotted keys
+///
+/// Safe folding follows single-key chains; aggressive folding flattens every object whose
+/// keys can all be joined into a path. Anything else is written as a normal nested object.
+fn encode_folded_object(path: String, obj: &JsonObject, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
+    let fields = written_fields(obj, options);
+    let foldable = !fields.is_empty() && fields.iter().all(|(k, _)| is_foldable_segment(k));
+
+    match options.key_folding {
+        KeyFolding::Safe if foldable && fields.len() == 1 => {
+            writer.record_dropped_fields(obj.len() - 1);
+            let (key, value) = fields[0];
+            let child = format!("{}.{}", path, key);
+            match value {
+                JsonValue::Object(nested_obj) => nested(|| encode_folded_object(child, nested_obj, writer, depth, options)),
+                value => write_key_value_pair(&child, value, writer, depth, options),
+            }
+        }
+        KeyFolding::Aggressive if foldable => {
+            writer.record_dropped_fields(obj.len() - fields.len());
+            for (key, value) in fields {
+                let child = format!("{}.{}", path, key);
+                match value {
+                    JsonValue::Object(nested_obj) => nested(|| encode_folded_object(child, nested_obj, writer, depth, options)),
+                    value => write_key_value_pair(&child, value, writer, depth, options),
+                }
             }
         }
+        _ => write_nested_object(&path, obj, writer, depth, options),
     }
 }
 
-/// Encode an array
+/// Encode an array under an optional, already-encoded key
 pub fn encode_array(
     key: Option<&str>,
     arr: &[JsonValue],
@@ -70,7 +269,7 @@ pub fn encode_array(
     options: &EncodeOptions,
 ) {
     if arr.is_empty() {
-        let header = format_header(0, key, None, &options.delimiter, options.length_marker);
+        let header = format_header(0, key, None, options);
         writer.push(depth, header);
         return;
     }
@@ -99,8 +298,13 @@ pub fn encode_array(
 
     // Array of objects
     if is_array_of_objects(arr) {
-        if let Some(header) = detect_tabular_header(arr) {
-            encode_array_of_objects_as_tabular(key, arr, &header, writer, depth, options);
+        if let Some(groups) = options.group_by.as_deref().and_then(|column| group_rows(arr, column)) {
+            encode_grouped_rows(key, &groups, writer, depth, options);
+            return;
+        }
+        if let Some(columns) = detect_tabular_header(arr, options) {
+            let (constants, columns) = lift_constant_columns(columns, arr, options);
+            encode_array_of_objects_as_tabular(key, arr, &constants, &columns, writer, depth, options);
         } else {
             encode_mixed_array_as_list_items(key, arr, writer, depth, options);
         }
@@ -111,6 +315,55 @@ pub fn encode_array(
     encode_mixed_array_as_list_items(key, arr, writer, depth, options);
 }
 
+/// Split rows by the primitive value of `column`, removing it from each row
+///
+/// Groups keep the order in which their values first appear. Returns `None` when some row
+/// lacks the column or holds a container there.
+fn group_rows<'a>(arr: &[JsonValue<'a>], column: &str) -> Option<Vec<(String, Vec<JsonValue<'a>>)>> {
+    let mut groups: Vec<(String, Vec<JsonValue>)> = Vec::new();
+
+    for value in arr {
+        let JsonValue::Object(obj) = value else { return None };
+        let group = match get_field(obj, column)? {
+            JsonValue::Primitive(JsonPrimitive::String(s)) => s.to_string(),
+            JsonValue::Primitive(JsonPrimitive::Number(n)) => format_number(*n),
+            JsonValue::Primitive(JsonPrimitive::Boolean(b)) => b.to_string(),
+            JsonValue::Primitive(JsonPrimitive::Null) => "null".to_string(),
+            _ => return None,
+        };
+
+        let row: JsonObject = obj.iter().filter(|(key, _)| key != column).cloned().collect();
+        match groups.iter_mut().find(|(name, _)| *name == group) {
+            Some((_, rows)) => rows.push(JsonValue::Object(row)),
+            None => groups.push((group, vec![JsonValue::Object(row)])),
+        }
+    }
+
+    Some(groups)
+}
+
+/// Write grouped rows as an object with one array per group value
+fn encode_grouped_rows(
+    key: Option<&str>,
+    groups: &[(String, Vec<JsonValue>)],
+    writer: &mut LineWriter,
+    depth: Depth,
+    options: &EncodeOptions,
+) {
+    // A root array has no key to hang the groups under, so they become root fields
+    let depth = match key {
+        Some(key) => {
+            writer.push(depth, format!("{}:", key));
+            depth + 1
+        }
+        None => depth,
+    };
+
+    for (group, rows) in groups {
+        nested(|| encode_array(Some(&encode_field_key(group, options)), rows, writer, depth, options));
+    }
+}
+
 /// Encode primitive array inline
 fn encode_inline_primitive_array(
     key: Option<&str>,
@@ -119,22 +372,91 @@ fn encode_inline_primitive_array(
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    let primitives: Vec<&JsonPrimitive> = arr.iter().filter_map(|v| {
+    let header = format_header(arr.len(), key, None, options);
+    write_inline_values(header, arr, writer, depth, options);
+}
+
+/// Write `prefix` followed by inline primitive values, wrapping lines past `max_line_width`
+///
+/// A wrapped line ends with the delimiter and continues one level deeper.
+fn write_inline_values(
+    prefix: String,
+    arr: &[JsonValue],
+    writer: &mut LineWriter,
+    depth: Depth,
+    options: &EncodeOptions,
+) {
+    let values: Vec<String> = arr.iter().filter_map(|v| {
         if let JsonValue::Primitive(p) = v {
-            Some(p)
+            Some(encode_run_primitive(p, options))
         } else {
             None
         }
     }).collect();
+    let values = if options.run_length { collapse_value_runs(values, options.delimiter.as_str()) } else { values };
 
-    let header = format_header(arr.len(), key, None, &options.delimiter, options.length_marker);
-    let joined = join_encoded_values(&primitives, &options.delimiter);
-    
-    if arr.is_empty() {
-        writer.push(depth, header);
-    } else {
-        writer.push(depth, format!("{} {}", header, joined));
+    if values.is_empty() {
+        writer.push(depth, prefix);
+        return;
     }
+
+    let delimiter = options.delimiter.as_str();
+    let max_width = match options.max_line_width {
+        Some(width) => width,
+        None => {
+            writer.push(depth, format!("{} {}", prefix, values.join(delimiter)));
+            return;
+        }
+    };
+
+    let mut line = format!("{} ", prefix);
+    let mut line_depth = depth;
+    let mut line_has_values = false;
+
+    for value in values {
+        if line_has_values {
+            // Leave room for the trailing delimiter in case this line wraps too
+            let width = writer.indent_width(line_depth)
+                + line.chars().count()
+                + value.chars().count()
+                + 2 * delimiter.len();
+            line.push_str(delimiter);
+            if width > max_width {
+                writer.push(line_depth, core::mem::take(&mut line));
+                line_depth = depth + 1;
+            }
+        }
+        line.push_str(&value);
+        line_has_values = true;
+    }
+
+    writer.push(line_depth, line);
+}
+
+/// Collapse runs of equal values into `value xN` wherever that is shorter than repeating them
+fn collapse_value_runs(values: Vec<String>, delimiter: &str) -> Vec<String> {
+    let mut collapsed = Vec::with_capacity(values.len());
+    for (value, count) in runs(values) {
+        let run = format!("{} x{}", value, count);
+        if run.len() < count * (value.len() + delimiter.len()) - delimiter.len() {
+            collapsed.push(run);
+        } else {
+            collapsed.extend(core::iter::repeat_n(value, count));
+        }
+    }
+    collapsed
+}
+
+/// Group consecutive equal items with their counts
+fn runs(items: Vec<String>) -> Vec<(String, usize)> {
+    let mut runs: Vec<(String, usize)> = Vec::new();
+    for item in items {
+        match runs.last_mut() {
+            Some((last, count)) if *last == item => *count += 1,
+            _ => runs.push((item, 1)),
+        }
+    }
+    runs
 }
 
 /// Encode array of arrays as list items
@@ -145,80 +467,246 @@ fn encode_array_of_arrays_as_list_items(
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    let header = format_header(arr.len(), key, None, &options.delimiter, options.length_marker);
+    let header = format_header(arr.len(), key, None, options);
     writer.push(depth, header);
 
-    for item in arr {
-        if let JsonValue::Array(inner) = item {
-            if is_array_of_primitives(inner) {
-                let primitives: Vec<&JsonPrimitive> = inner.iter().filter_map(|v| {
-                    if let JsonValue::Primitive(p) = v {
-                        Some(p)
-                    } else {
-                        None
-                    }
-                }).collect();
-
-                let inline_header = format_header(inner.len(), None, None, &options.delimiter, options.length_marker);
-                let joined = join_encoded_values(&primitives, &options.delimiter);
-                
-                if inner.is_empty() {
-                    writer.push(depth + 1, format!("{}{}", LIST_ITEM_PREFIX, inline_header));
-                } else {
-                    writer.push(depth + 1, format!("{}{} {}", LIST_ITEM_PREFIX, inline_header, joined));
+    nested(|| write_list_items(arr, writer, depth + 1, options));
+}
+
+/// A tabular column: the key path that reaches its value in every row
+pub struct Column {
+    path: Vec<String>,
+}
+
+impl Column {
+    /// Header text for the column; flattened paths are written as dotted segments
+    fn header_name(&self, options: &EncodeOptions) -> String {
+        if let Some(alias) = options.column_aliases.get(&self.path.join(".")) {
+            encode_field_key(alias, options)
+        } else if self.path.len() == 1 {
+            encode_field_key(&self.path[0], options)
+        } else {
+            self.path.join(".")
+        }
+    }
+
+    fn lookup<'v, 'a>(&self, obj: &'v JsonObject<'a>) -> Option<&'v JsonPrimitive<'a>> {
+        let (last, parents) = self.path.split_last()?;
+        let mut current = obj;
+        for segment in parents {
+            match get_field(current, segment) {
+                Some(JsonValue::Object(nested)) => current = nested,
+                _ => return None,
+            }
+        }
+        match get_field(current, last) {
+            Some(JsonValue::Primitive(p)) => Some(p),
+            _ => None,
+        }
+    }
+}
+
+/// Header field names, annotated as `name:type` when `type_hints` is set
+///
+/// Types listed in `column_types` are written as given; the rest are inferred from the cells.
+fn header_names(columns: &[Column], rows: &[JsonValue], options: &EncodeOptions) -> Vec<String> {
+    columns.iter().map(|column| {
+        let name = column.header_name(options);
+        let hint = || match options.column_types.get(&column.path.join(".")) {
+            Some(column_type) => Some(column_type.name()),
+            None => infer_column_type(column, rows),
+        };
+        match options.type_hints.then(hint).flatten() {
+            Some(hint) => format!("{}:{}", name, hint),
+            None => name,
+        }
+    }).collect()
+}
+
+/// The one type shared by a column's non-null cells, with integers widening to floats
+fn infer_column_type(column: &Column, rows: &[JsonValue]) -> Option<&'static str> {
+    let mut inferred = None;
+    for row in rows {
+        let JsonValue::Object(obj) = row else { continue };
+        let cell_type = match column.lookup(obj) {
+            None | Some(JsonPrimitive::Null) => continue,
+            Some(JsonPrimitive::Number(n)) if n.fract() == 0.0 => "int",
+            Some(JsonPrimitive::Number(_)) => "float",
+            Some(JsonPrimitive::Boolean(_)) => "bool",
+            Some(JsonPrimitive::String(_)) => "str",
+            Some(JsonPrimitive::Raw(_)) => return None,
+        };
+        inferred = match (inferred, cell_type) {
+            (None, t) => Some(t),
+            (Some(a), b) if a == b => Some(a),
+            (Some("int"), "float") | (Some("float"), "int") => Some("float"),
+            _ => return None,
+        };
+    }
+    inferred
+}
+
+/// An alias that would write the same header name as another column of the table of `rows`
+pub fn colliding_alias(rows: &[JsonValue], options: &EncodeOptions) -> Option<String> {
+    let columns = detect_tabular_header(rows, options)?;
+    let names: Vec<String> = columns.iter().map(|column| column.header_name(options)).collect();
+    columns.iter().enumerate().find_map(|(i, column)| {
+        let alias = options.column_aliases.get(&column.path.join("."))?;
+        names.iter().enumerate().any(|(j, name)| j != i && *name == names[i]).then(|| alias.clone())
+    })
+}
+
+/// The first table, in document order, whose aliases collide with its other columns
+///
+/// Paths are written as `users[3].orders`, like `fail_over_depth` paths.
+fn find_alias_collision(value: &JsonValue, options: &EncodeOptions) -> Option<EncodeError> {
+    let mut stack = vec![(value, String::new())];
+    while let Some((value, path)) = stack.pop() {
+        match value {
+            JsonValue::Array(arr) => {
+                if let Some(alias) = is_array_of_objects(arr).then(|| colliding_alias(arr, options)).flatten() {
+                    return Some(EncodeError::AliasCollision { path, alias });
                 }
+                stack.extend(arr.iter().enumerate().rev().map(|(i, v)| (v, format!("{}[{}]", path, i))));
             }
+            JsonValue::Object(obj) => {
+                stack.extend(obj.iter().rev().map(|(k, v)| {
+                    let child = if path.is_empty() { k.to_string() } else { format!("{}.{}", path, k) };
+                    (v, child)
+                }));
+            }
+            JsonValue::Primitive(_) => {}
         }
     }
+    None
 }
 
 /// Detect if array of objects can use tabular format
-fn detect_tabular_header(arr: &[JsonValue]) -> Option<Vec<String>> {
-    if arr.is_empty() {
+fn detect_tabular_header(arr: &[JsonValue], options: &EncodeOptions) -> Option<Vec<Column>> {
+    if arr.len() < options.tabular_min_rows {
         return None;
     }
 
-    let first_obj = match &arr[0] {
-        JsonValue::Object(obj) => obj,
-        _ => return None,
-    };
+    let mut rows = Vec::with_capacity(arr.len());
+    for value in arr {
+        let obj = match value {
+            JsonValue::Object(obj) => obj,
+            _ => return None,
+        };
 
-    let mut first_keys: Vec<String> = first_obj.keys().cloned().collect();
-    first_keys.sort();
+        let mut paths = Vec::new();
+        if !collect_column_paths(obj, options.table_flatten_depth, &mut Vec::new(), &mut paths) {
+            return None;
+        }
+        rows.push(paths);
+    }
 
-    if first_keys.is_empty() {
-        return None;
+    let first_row = rows.first()?.clone();
+    for row in &mut rows {
+        row.sort();
     }
 
-    // Check if it's a tabular array
-    if is_tabular_array(arr, &first_keys) {
-        Some(first_keys)
+    // Rows with identical leaves always share a header; otherwise try their union
+    let header = if rows.windows(2).all(|pair| pair[0] == pair[1]) {
+        rows.into_iter().next()?
     } else {
-        None
+        union_header(&rows, options.tabular_union_threshold?)?
+    };
+
+    let max_columns = options.tabular_max_columns.unwrap_or(usize::MAX);
+    if header.is_empty() || header.len() > max_columns {
+        return None;
+    }
+
+    let columns = header.into_iter().map(|path| Column { path }).collect();
+    Some(order_columns(columns, &first_row, options))
+}
+
+/// Column indices and header names for a flat table whose columns are `names`, in input order
+///
+/// `key_order`, `column_order` and `column_aliases` apply as they do to a table of objects.
+pub fn flat_table_header(names: &[String], options: &EncodeOptions) -> Vec<(usize, String)> {
+    let mut first_row: Vec<Vec<String>> = names.iter().map(|name| vec![name.clone()]).collect();
+    if options.key_order == crate::types::KeyOrder::Alphabetical {
+        first_row.sort();
     }
+    let mut columns: Vec<Column> = first_row.iter().map(|path| Column { path: path.clone() }).collect();
+    columns.sort_by(|a, b| a.path.cmp(&b.path));
+
+    order_columns(columns, &first_row, options)
+        .into_iter()
+        .map(|column| {
+            let index = names.iter().position(|name| *name == column.path[0]).unwrap_or_default();
+            (index, column.header_name(options))
+        })
+        .collect()
 }
 
-/// Check if array can use tabular format
-fn is_tabular_array(arr: &[JsonValue], header: &[String]) -> bool {
-    for value in arr {
-        if let JsonValue::Object(obj) = value {
-            let mut keys: Vec<String> = obj.keys().cloned().collect();
-            keys.sort();
+/// Arrange alphabetically sorted columns according to `column_order`
+fn order_columns(mut columns: Vec<Column>, first_row: &[Vec<String>], options: &EncodeOptions) -> Vec<Column> {
+    match &options.column_order {
+        ColumnOrder::Alphabetical => {}
+        ColumnOrder::FirstObject => {
+            // Columns the first row lacks (union headers) keep their sorted place at the end
+            let position = |c: &Column| first_row.iter().position(|p| *p == c.path).unwrap_or(usize::MAX);
+            columns.sort_by_key(position);
+        }
+        ColumnOrder::Explicit(names) => {
+            let position = |c: &Column| names.iter().position(|n| *n == c.path.join(".")).unwrap_or(usize::MAX);
+            columns.sort_by_key(position);
+        }
+        ColumnOrder::ShortestFirst => {
+            columns.sort_by_key(|c| c.header_name(options).chars().count());
+        }
+    }
+    columns
+}
 
-            // All objects must have the same keys
-            if keys.len() != header.len() {
-                return false;
-            }
+/// Union of all row paths, if the paths every row shares make up at least `threshold` of it
+fn union_header(rows: &[Vec<Vec<String>>], threshold: f64) -> Option<Vec<Vec<String>>> {
+    let mut union: Vec<Vec<String>> = rows.iter().flatten().cloned().collect();
+    union.sort();
+    union.dedup();
+
+    // A path that is also a prefix of another could not be rebuilt by the decoder
+    if union.windows(2).any(|pair| pair[1].starts_with(&pair[0])) {
+        return None;
+    }
 
-            // Check all header keys exist and values are primitives
-            for key in header {
-                match obj.get(key) {
-                    Some(JsonValue::Primitive(_)) => {}
-                    _ => return false,
+    let shared = union.iter().filter(|path| rows.iter().all(|row| row.contains(path))).count();
+    if (shared as f64) < threshold * union.len() as f64 {
+        return None;
+    }
+
+    Some(union)
+}
+
+/// Collect the key paths of a row's primitive values, flattening up to `levels` nested objects
+///
+/// Returns false if the row holds a value that cannot become a column.
+fn collect_column_paths(
+    obj: &JsonObject,
+    levels: usize,
+    prefix: &mut Vec<String>,
+    paths: &mut Vec<Vec<String>>,
+) -> bool {
+    for (key, value) in obj {
+        match value {
+            JsonValue::Primitive(_) => {
+                let mut path = prefix.clone();
+                path.push(key.to_string());
+                paths.push(path);
+            }
+            JsonValue::Object(nested) if levels > 0 && !nested.is_empty() && is_foldable_segment(key) => {
+                prefix.push(key.to_string());
+                let flattened = nested.iter().all(|(k, _)| is_foldable_segment(k))
+                    && collect_column_paths(nested, levels - 1, prefix, paths);
+                prefix.pop();
+                if !flattened {
+                    return false;
                 }
             }
-        } else {
-            return false;
+            _ => return false,
         }
     }
     true
@@ -228,37 +716,282 @@ fn is_tabular_array(arr: &[JsonValue], header: &[String]) -> bool {
 fn encode_array_of_objects_as_tabular(
     key: Option<&str>,
     arr: &[JsonValue],
-    header: &[String],
+    constants: &[(Column, JsonPrimitive)],
+    columns: &[Column],
     writer: &mut LineWriter,
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    let header_str = format_header(arr.len(), key, Some(header), &options.delimiter, options.length_marker);
-    writer.push(depth, header_str);
+    let names = header_names(columns, arr, options);
+    writer.record_table(key, arr.len());
+
+    // Chunks carry their row range, which a head/tail sample would misstate
+    match options.table_chunk_rows {
+        Some(size) if size > 0 && arr.len() > size && options.max_rows.is_none() => {
+            let sorted = sorted_rows(arr, columns, options);
+            for (index, chunk) in sorted.chunks(size).enumerate() {
+                let start = index * size;
+                writer.push(depth, format_chunk_header(start..start + chunk.len(), key, &names, options));
+                // Every chunk repeats the constants so it can be read on its own
+                write_constant_columns(constants, writer, depth + 1, options);
+                write_row_block(chunk, columns, writer, depth + 1, options);
+            }
+        }
+        _ => {
+            writer.push(depth, format_header(arr.len(), key, Some(&names), options));
+            write_constant_columns(constants, writer, depth + 1, options);
+            write_tabular_rows(arr, columns, writer, depth + 1, options);
+        }
+    }
+}
+
+/// Encode one chunk of a streamed `key[start..end]{...}:` table into `buffer`
+///
+/// The first chunk fixes `columns`; later chunks must fit them. Returns `None` when the rows
+/// cannot share the header.
+#[cfg(feature = "std")]
+pub fn encode_table_chunk(
+    key: &str,
+    start: usize,
+    rows: &[JsonValue],
+    columns: &mut Option<Vec<Column>>,
+    buffer: String,
+    options: &EncodeOptions,
+) -> Option<String> {
+    let columns: &[Column] = match columns {
+        Some(columns) => {
+            let mut header: Vec<&Vec<String>> = columns.iter().map(|c| &c.path).collect();
+            header.sort();
+            if !rows.iter().all(|row| row_fits(row, &header, options)) {
+                return None;
+            }
+            columns
+        }
+        None => columns.insert(detect_tabular_header(rows, options)?),
+    };
+
+    let mut writer = LineWriter::with_buffer(buffer, &options.indent, options.line_ending);
+    let key = encode_field_key(key, options);
+    let names = header_names(columns, rows, options);
+    writer.push(0, format_chunk_header(start..start + rows.len(), Some(&key), &names, options));
+    write_row_block(rows, columns, &mut writer, 1, options);
+    Some(writer.into_string())
+}
+
+/// Whether a row's leaf paths match a sorted header, allowing gaps only for union headers
+#[cfg(feature = "std")]
+fn row_fits(row: &JsonValue, header: &[&Vec<String>], options: &EncodeOptions) -> bool {
+    let JsonValue::Object(obj) = row else { return false };
+    let mut paths = Vec::new();
+    if !collect_column_paths(obj, options.table_flatten_depth, &mut Vec::new(), &mut paths) {
+        return false;
+    }
+    paths.sort();
+    if options.tabular_union_threshold.is_some() {
+        paths.iter().all(|path| header.binary_search(&path).is_ok())
+    } else {
+        paths.iter().eq(header.iter().copied())
+    }
+}
+
+/// Split off columns holding the same value in every row when `lift_constant_columns` is set
+///
+/// At least two rows and one varying column are needed, and every row must keep a cell of its
+/// own, so a table never loses all its cells and no row is left as a blank line.
+fn lift_constant_columns<'a>(columns: Vec<Column>, arr: &[JsonValue<'a>], options: &EncodeOptions) -> (Vec<(Column, JsonPrimitive<'a>)>, Vec<Column>) {
+    if !options.lift_constant_columns || arr.len() < 2 {
+        return (Vec::new(), columns);
+    }
+
+    let constant_value = |column: &Column| {
+        let mut cells = arr.iter().map(|row| match row {
+            JsonValue::Object(obj) => column.lookup(obj),
+            _ => None,
+        });
+        let first = cells.next().flatten()?;
+        cells.all(|cell| cell == Some(first)).then(|| first.clone())
+    };
+
+    let values: Vec<Option<JsonPrimitive>> = columns.iter().map(constant_value).collect();
+    let row_left_empty = arr.iter().any(|row| match row {
+        JsonValue::Object(obj) => columns.iter().zip(&values).all(|(column, value)| value.is_some() || column.lookup(obj).is_none()),
+        _ => false,
+    });
+    if values.iter().all(Option::is_some) || row_left_empty {
+        return (Vec::new(), columns);
+    }
+
+    let mut constants = Vec::new();
+    let mut varying = Vec::new();
+    for (column, value) in columns.into_iter().zip(values) {
+        match value {
+            Some(value) => constants.push((column, value)),
+            None => varying.push(column),
+        }
+    }
+    (constants, varying)
+}
 
-    write_tabular_rows(arr, header, writer, depth + 1, options);
+/// Write lifted constant columns as `name: value` lines above the rows
+fn write_constant_columns(constants: &[(Column, JsonPrimitive)], writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
+    for (column, value) in constants {
+        writer.push(depth, format!("{}: {}", column.header_name(options), encode_primitive(value, &options.delimiter)));
+    }
 }
 
 /// Write tabular rows
 fn write_tabular_rows(
     arr: &[JsonValue],
-    header: &[String],
+    columns: &[Column],
     writer: &mut LineWriter,
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    for value in arr {
+    write_row_block(&sorted_rows(arr, columns, options), columns, writer, depth, options);
+}
+
+/// Rows in the order `sort_rows` asks for
+fn sorted_rows<'v, 'a>(arr: &'v [JsonValue<'a>], columns: &[Column], options: &EncodeOptions) -> Cow<'v, [JsonValue<'a>]> {
+    match &options.sort_rows {
+        Some(spec) => Cow::Owned(sort_rows(arr, columns, spec)),
+        None => Cow::Borrowed(arr),
+    }
+}
+
+/// Write already-ordered rows, applying the `max_rows` sample
+fn write_row_block(
+    arr: &[JsonValue],
+    columns: &[Column],
+    writer: &mut LineWriter,
+    depth: Depth,
+    options: &EncodeOptions,
+) {
+    let (head, omitted, tail) = split_rows(arr, options);
+
+    let mut rows = writer.take_rows();
+    rows.extend(head.iter().chain(tail).filter_map(|value| {
         if let JsonValue::Object(obj) = value {
-            let values: Vec<&JsonPrimitive> = header.iter().filter_map(|key| {
-                if let Some(JsonValue::Primitive(p)) = obj.get(key) {
-                    Some(p)
-                } else {
-                    None
+            Some(encode_row(obj, columns, options))
+        } else {
+            None
+        }
+    }));
+    if options.align_columns {
+        pad_columns(&mut rows);
+    }
+
+    let mut lines: Vec<String> = rows.iter().map(|row| row.join(options.delimiter.as_str())).collect();
+    writer.restore_rows(rows);
+    let tail_lines = lines.split_off(head.len().min(lines.len()));
+
+    write_row_lines(lines, writer, depth, options);
+    if omitted > 0 {
+        writer.push(depth, omitted_rows_marker(omitted));
+    }
+    write_row_lines(tail_lines, writer, depth, options);
+}
+
+/// Push row lines, collapsing repeated rows when `run_length` is set
+pub fn write_row_lines(lines: Vec<String>, writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
+    if !options.run_length {
+        lines.into_iter().for_each(|line| writer.push(depth, line));
+        return;
+    }
+
+    for (line, count) in runs(lines) {
+        match count {
+            1 => writer.push(depth, line),
+            _ => writer.push(depth, format!("{} x{}", line, count)),
+        }
+    }
+}
+
+/// Sort rows by the spec's columns; keys naming no column of the table are ignored
+fn sort_rows<'a>(arr: &[JsonValue<'a>], columns: &[Column], spec: &SortSpec) -> Vec<JsonValue<'a>> {
+    let keys: Vec<(&Column, SortDirection)> = spec.keys.iter().filter_map(|key| {
+        columns.iter().find(|c| c.path.join(".") == key.column).map(|c| (c, key.direction))
+    }).collect();
+
+    let cell = |value: &JsonValue<'a>, column: &Column| match value {
+        JsonValue::Object(obj) => column.lookup(obj).cloned(),
+        _ => None,
+    };
+
+    let mut rows = arr.to_vec();
+    // Stable, so rows that compare equal keep their input order
+    rows.sort_by(|a, b| {
+        keys.iter().fold(Ordering::Equal, |ordering, (column, direction)| {
+            ordering.then_with(|| {
+                let cmp = compare_cells(cell(a, column).as_ref(), cell(b, column).as_ref());
+                match direction {
+                    SortDirection::Ascending => cmp,
+                    SortDirection::Descending => cmp.reverse(),
                 }
-            }).collect();
+            })
+        })
+    });
+    rows
+}
 
-            let joined = join_encoded_values(&values, &options.delimiter);
-            writer.push(depth, joined);
+/// Order cells as missing and null, then booleans, numbers and strings
+fn compare_cells(a: Option<&JsonPrimitive>, b: Option<&JsonPrimitive>) -> Ordering {
+    let rank = |cell: Option<&JsonPrimitive>| match cell {
+        None | Some(JsonPrimitive::Null) => 0,
+        Some(JsonPrimitive::Boolean(_)) => 1,
+        Some(JsonPrimitive::Number(_)) => 2,
+        Some(JsonPrimitive::String(_)) => 3,
+        Some(JsonPrimitive::Raw(_)) => 4,
+    };
+
+    match (a, b) {
+        (Some(JsonPrimitive::Boolean(x)), Some(JsonPrimitive::Boolean(y))) => x.cmp(y),
+        (Some(JsonPrimitive::Number(x)), Some(JsonPrimitive::Number(y))) => x.total_cmp(y),
+        (Some(JsonPrimitive::String(x)), Some(JsonPrimitive::String(y))) => x.cmp(y),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// Split rows into the kept head, the number of rows left out, and the kept tail
+fn split_rows<'v, 'a>(arr: &'v [JsonValue<'a>], options: &EncodeOptions) -> (&'v [JsonValue<'a>], usize, &'v [JsonValue<'a>]) {
+    match options.max_rows {
+        Some(limit) if limit.head + limit.tail < arr.len() => {
+            let omitted = arr.len() - limit.head - limit.tail;
+            (&arr[..limit.head], omitted, &arr[limit.head + omitted..])
+        }
+        _ => (arr, 0, &[]),
+    }
+}
+
+/// Marker row standing in for rows left out by `max_rows`
+fn omitted_rows_marker(omitted: usize) -> String {
+    format!("…(+{} {})", omitted, if omitted == 1 { "row" } else { "rows" })
+}
+
+/// Encode the cells of one row, marking columns the row doesn't have
+fn encode_row(obj: &JsonObject, columns: &[Column], options: &EncodeOptions) -> Vec<String> {
+    columns.iter().map(|column| match column.lookup(obj) {
+        Some(p) => encode_run_primitive(p, options),
+        None => match options.missing_cell {
+            MissingCell::Null => "null".to_string(),
+            MissingCell::Empty => String::new(),
+        },
+    }).collect()
+}
+
+/// Pad every cell but the last of each row to its column's widest cell
+fn pad_columns(rows: &mut [Vec<String>]) {
+    let mut widths = Vec::new();
+    for row in rows.iter() {
+        widths.resize(widths.len().max(row.len()), 0);
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let last = row.len().saturating_sub(1);
+        for (cell, width) in row.iter_mut().zip(&widths).take(last) {
+            *cell = format!("{:<width$}", cell, width = width);
         }
     }
 }
@@ -271,36 +1004,31 @@ fn encode_mixed_array_as_list_items(
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    let header = format_header(arr.len(), key, None, &options.delimiter, options.length_marker);
+    let header = format_header(arr.len(), key, None, options);
     writer.push(depth, header);
 
+    nested(|| write_list_items(arr, writer, depth + 1, options));
+}
+
+/// Write each array element as a `- ` list item at `depth`
+fn write_list_items(arr: &[JsonValue], writer: &mut LineWriter, depth: Depth, options: &EncodeOptions) {
     for item in arr {
         match item {
             JsonValue::Primitive(p) => {
-                writer.push(depth + 1, format!("{}{}", LIST_ITEM_PREFIX, encode_primitive(p, &options.delimiter)));
+                writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, encode_primitive(p, &options.delimiter)));
             }
             JsonValue::Array(inner) => {
                 if is_array_of_primitives(inner) {
-                    let primitives: Vec<&JsonPrimitive> = inner.iter().filter_map(|v| {
-                        if let JsonValue::Primitive(p) = v {
-                            Some(p)
-                        } else {
-                            None
-                        }
-                    }).collect();
-
-                    let inline_header = format_header(inner.len(), None, None, &options.delimiter, options.length_marker);
-                    let joined = join_encoded_values(&primitives, &options.delimiter);
-                    
-                    if inner.is_empty() {
-                        writer.push(depth + 1, format!("{}{}", LIST_ITEM_PREFIX, inline_header));
-                    } else {
-                        writer.push(depth + 1, format!("{}{} {}", LIST_ITEM_PREFIX, inline_header, joined));
-                    }
+                    let inline_header = format_header(inner.len(), None, None, options);
+                    write_inline_values(format!("{}{}", LIST_ITEM_PREFIX, inline_header), inner, writer, depth, options);
+                } else {
+                    let inline_header = format_header(inner.len(), None, None, options);
+                    writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, inline_header));
+                    nested(|| write_list_items(inner, writer, depth + 1, options));
                 }
             }
             JsonValue::Object(obj) => {
-                encode_object_as_list_item(obj, writer, depth + 1, options);
+                encode_object_as_list_item(obj, writer, depth, options);
             }
         }
     }
@@ -308,23 +1036,22 @@ fn encode_mixed_array_as_list_items(
 
 /// Encode object as list item
 fn encode_object_as_list_item(
-    obj: &HashMap<String, JsonValue>,
+    obj: &JsonObject,
     writer: &mut LineWriter,
     depth: Depth,
     options: &EncodeOptions,
 ) {
-    let mut keys: Vec<_> = obj.keys().collect();
-    keys.sort();
+    let fields = written_fields(obj, options);
+    writer.record_dropped_fields(obj.len() - fields.len());
 
-    if keys.is_empty() {
+    if fields.is_empty() {
         writer.push(depth, "-".to_string());
         return;
     }
 
     // First key-value on the same line as "- "
-    let first_key = keys[0];
-    let encoded_key = encode_key(first_key);
-    let first_value = &obj[first_key.as_str()];
+    let (first_key, first_value) = fields[0];
+    let encoded_key = writer.encoded_key(first_key, options);
 
     match first_value {
         JsonValue::Primitive(p) => {
@@ -332,52 +1059,66 @@ fn encode_object_as_list_item(
         }
         JsonValue::Array(arr) => {
             if is_array_of_primitives(arr) {
-                let primitives: Vec<&JsonPrimitive> = arr.iter().filter_map(|v| {
-                    if let JsonValue::Primitive(p) = v {
-                        Some(p)
-                    } else {
-                        None
-                    }
-                }).collect();
-
-                let inline_header = format_header(arr.len(), Some(first_key), None, &options.delimiter, options.length_marker);
-                let joined = join_encoded_values(&primitives, &options.delimiter);
-                
-                if arr.is_empty() {
-                    writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, inline_header));
-                } else {
-                    writer.push(depth, format!("{}{} {}", LIST_ITEM_PREFIX, inline_header, joined));
-                }
+                let inline_header = format_header(arr.len(), Some(&encoded_key), None, options);
+                write_inline_values(format!("{}{}", LIST_ITEM_PREFIX, inline_header), arr, writer, depth, options);
             } else if is_array_of_objects(arr) {
-                if let Some(header) = detect_tabular_header(arr) {
-                    let header_str = format_header(arr.len(), Some(first_key), Some(&header), &options.delimiter, options.length_marker);
+                if let Some(columns) = detect_tabular_header(arr, options) {
+                    let (constants, columns) = lift_constant_columns(columns, arr, options);
+                    let header_str = format_header(arr.len(), Some(&encoded_key), Some(&header_names(&columns, arr, options)), options);
+                    writer.record_table(Some(&encoded_key), arr.len());
                     writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, header_str));
-                    write_tabular_rows(arr, &header, writer, depth + 1, options);
+                    write_constant_columns(&constants, writer, depth + 1, options);
+                    write_tabular_rows(arr, &columns, writer, depth + 1, options);
                 } else {
-                    writer.push(depth, format!("{}{}[{}]:", LIST_ITEM_PREFIX, encoded_key, arr.len()));
-                    for inner_item in arr {
-                        if let JsonValue::Object(inner_obj) = inner_item {
-                            encode_object_as_list_item(inner_obj, writer, depth + 1, options);
-                        }
-                    }
+                    let header_str = format_header(arr.len(), Some(&encoded_key), None, options);
+                    writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, header_str));
+                    nested(|| write_list_items(arr, writer, depth + 1, options));
                 }
             } else {
-                writer.push(depth, format!("{}{}[{}]:", LIST_ITEM_PREFIX, encoded_key, arr.len()));
-                encode_array(None, arr, writer, depth + 1, options);
+                let header_str = format_header(arr.len(), Some(&encoded_key), None, options);
+                writer.push(depth, format!("{}{}", LIST_ITEM_PREFIX, header_str));
+                nested(|| write_list_items(arr, writer, depth + 1, options));
             }
         }
         JsonValue::Object(nested_obj) => {
-            if nested_obj.is_empty() {
+            if let Some(inline) = encode_inline_object(nested_obj, writer, options) {
+                writer.push(depth, format!("{}{}: {}", LIST_ITEM_PREFIX, encoded_key, inline));
+            } else if nested_obj.is_empty() {
                 writer.push(depth, format!("{}{}:", LIST_ITEM_PREFIX, encoded_key));
             } else {
                 writer.push(depth, format!("{}{}:", LIST_ITEM_PREFIX, encoded_key));
-                encode_object(nested_obj, writer, depth + 2, options);
+                nested(|| encode_object(nested_obj, writer, depth + 2, options));
             }
         }
     }
 
     // Remaining keys on indented lines
-    for key in keys.iter().skip(1) {
-        encode_key_value_pair(key, &obj[key.as_str()], writer, depth + 1, options);
+    for (key, value) in fields.into_iter().skip(1) {
+        encode_key_value_pair(key, value, writer, depth + 1, options);
+    }
+}
+
+/// Rough size of the encoded output, used to reserve the buffer once up front
+///
+/// Only the first object of an array contributes its keys, since uniform rows share one header.
+fn estimate_output_len(value: &JsonValue, depth: Depth, count_keys: bool, indent_width: usize) -> usize {
+    match value {
+        JsonValue::Primitive(JsonPrimitive::String(s)) => s.len() + 1,
+        JsonValue::Primitive(JsonPrimitive::Raw(s)) => s.len() + 1,
+        JsonValue::Primitive(JsonPrimitive::Number(_)) => 8,
+        JsonValue::Primitive(_) => 5,
+        JsonValue::Array(arr) => {
+            let header = 8;
+            let items: usize = arr.iter().enumerate().map(|(i, item)| nested(|| estimate_output_len(item, depth + 1, i == 0, indent_width))).sum();
+            header + items + arr.len() * (depth + 1) * indent_width
+        }
+        JsonValue::Object(obj) => obj
+            .iter()
+            .map(|(key, v)| {
+                let key_len = if count_keys { key.len() + 3 + depth * indent_width } else { 0 };
+                key_len + nested(|| estimate_output_len(v, depth + 1, true, indent_width))
+            })
+            .sum(),
     }
 }
+
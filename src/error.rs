@@ -0,0 +1,151 @@
+use crate::prelude::*;
+use crate::types::SpecVersion;
+use core::fmt;
+
+/// Error returned when a TOON document cannot be decoded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// 1-based line number where the error was detected
+    pub line: usize,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl DecodeError {
+    pub fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+/// Error returned when encoding exceeds one of the configured budgets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The output grew past `max_output_bytes`
+    OutputTooLarge { limit: usize },
+    /// The input holds more values than `max_nodes`
+    TooManyNodes { limit: usize },
+    /// A container at `path` is nested deeper than `fail_over_depth`
+    DepthExceeded { path: String },
+    /// `lossless` is set and the value at `path` (empty for the options themselves) would change
+    Lossy { path: String, reason: String },
+    /// Even the most reduced output of `encode_within_budget` has more than `limit` tokens
+    OverTokenBudget { limit: usize, tokens: usize },
+    /// `option` writes syntax that `spec_version` doesn't define
+    Unsupported { option: String, version: SpecVersion },
+    /// `option` holds a value whose output could not be decoded back
+    InvalidOption { option: String, reason: String },
+    /// A column alias in the table at `path` (empty for the root) names another of its columns
+    AliasCollision { path: String, alias: String },
+    /// The schema passed to `encode_validated` could not be compiled
+    InvalidSchema { reason: String },
+    /// The value does not match the schema passed to `encode_validated`
+    SchemaViolations { violations: Vec<SchemaViolation> },
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::OutputTooLarge { limit } => write!(f, "output exceeds {} bytes", limit),
+            EncodeError::TooManyNodes { limit } => write!(f, "input has more than {} values", limit),
+            EncodeError::DepthExceeded { path } => write!(f, "{} is nested too deeply", path),
+            EncodeError::Lossy { path, reason } if path.is_empty() => write!(f, "not lossless: {}", reason),
+            EncodeError::Lossy { path, reason } => write!(f, "not lossless at {}: {}", path, reason),
+            EncodeError::OverTokenBudget { limit, tokens } => write!(f, "output needs {} tokens, over the budget of {}", tokens, limit),
+            EncodeError::Unsupported { option, version } => write!(f, "`{}` is not part of TOON spec {}", option, version),
+            EncodeError::InvalidOption { option, reason } => write!(f, "invalid `{}`: {}", option, reason),
+            EncodeError::AliasCollision { path, alias } if path.is_empty() => {
+                write!(f, "column alias `{}` collides with another column", alias)
+            }
+            EncodeError::AliasCollision { path, alias } => write!(f, "column alias `{}` collides with another column at {}", alias, path),
+            EncodeError::InvalidSchema { reason } => write!(f, "invalid schema: {}", reason),
+            EncodeError::SchemaViolations { violations } => {
+                write!(f, "value does not match schema")?;
+                for violation in violations {
+                    write!(f, "; {}", violation)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Error returned by `format` for a document it cannot rewrite
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    /// The document is malformed; the line counts from the start of the whole input
+    Decode(DecodeError),
+    /// The decoded document cannot be written back, as when it nests too deeply
+    Encode(EncodeError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Decode(err) => err.fmt(f),
+            FormatError::Encode(err) => err.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for FormatError {}
+
+/// Error returned when a change from `diff` doesn't fit the value it is applied to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    /// JSON Pointer of the change, empty for the root
+    pub path: String,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl PatchError {
+    pub fn new(path: &str, message: impl Into<String>) -> Self {
+        Self { path: path.to_string(), message: message.into() }
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+/// One place where a value fails its JSON Schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value, empty for the root
+    pub instance_path: String,
+    /// JSON Pointer to the schema keyword that failed
+    pub schema_path: String,
+    /// Human-readable description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.instance_path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.instance_path, self.message)
+        }
+    }
+}
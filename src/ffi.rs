@@ -0,0 +1,101 @@
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+/// Store `message` in `error` when the caller asked for it
+unsafe fn set_error(error: *mut *mut c_char, message: String) {
+    if !error.is_null() {
+        *error = into_raw(message);
+    }
+}
+
+/// Hand a string to the caller, dropping any interior NUL so it survives the conversion
+fn into_raw(text: String) -> *mut c_char {
+    let text = CString::new(text).unwrap_or_else(|err| {
+        let mut bytes = err.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).expect("NUL bytes removed")
+    });
+    text.into_raw()
+}
+
+/// Run `convert` on the text behind `input`, returning its output or null with `error` set
+unsafe fn call(
+    input: *const c_char,
+    error: *mut *mut c_char,
+    convert: impl FnOnce(&str) -> Result<String, String>,
+) -> *mut c_char {
+    if !error.is_null() {
+        *error = ptr::null_mut();
+    }
+    if input.is_null() {
+        set_error(error, "input is null".to_string());
+        return ptr::null_mut();
+    }
+    let input = match CStr::from_ptr(input).to_str() {
+        Ok(input) => input,
+        Err(err) => {
+            set_error(error, format!("input is not valid UTF-8: {}", err));
+            return ptr::null_mut();
+        }
+    };
+    // A panic must not unwind into the caller's frames
+    match catch_unwind(AssertUnwindSafe(|| convert(input))) {
+        Ok(Ok(output)) => into_raw(output),
+        Ok(Err(message)) => {
+            set_error(error, message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_error(error, "internal error".to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Encode JSON text to TOON with the default options
+///
+/// Returns null and, when `error` is not null, stores a message in `*error` if the JSON is
+/// invalid or encoding fails.
+///
+/// # Safety
+///
+/// `json` must be null or point to a NUL-terminated string, and `error` must be null or
+/// point to writable storage for one pointer.
+#[no_mangle]
+pub unsafe extern "C" fn toon_encode(json: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    call(json, error, |json| {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| format!("invalid JSON: {}", e))?;
+        crate::try_encode(&value, None).map_err(|e| e.to_string())
+    })
+}
+
+/// Decode TOON text to compact JSON text with the default options
+///
+/// Returns null and, when `error` is not null, stores a message naming the line in `*error`
+/// if the text is malformed.
+///
+/// # Safety
+///
+/// As for `toon_encode`.
+#[no_mangle]
+pub unsafe extern "C" fn toon_decode(toon: *const c_char, error: *mut *mut c_char) -> *mut c_char {
+    call(toon, error, |toon| {
+        let value = crate::decode(toon, None).map_err(|e| e.to_string())?;
+        Ok(value.to_string())
+    })
+}
+
+/// Release a string returned by `toon_encode` or `toon_decode`, including error messages
+///
+/// Null is ignored.
+///
+/// # Safety
+///
+/// `text` must be null or a pointer returned by this library that hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn toon_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
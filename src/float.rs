@@ -0,0 +1,20 @@
+/// The `f64` methods that live in `std`, computed with `libm` when it's missing
+pub trait Float {
+    fn fract(self) -> f64;
+    fn round(self) -> f64;
+    fn powi(self, n: i32) -> f64;
+}
+
+impl Float for f64 {
+    fn fract(self) -> f64 {
+        self - libm::trunc(self)
+    }
+
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+}
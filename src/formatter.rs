@@ -0,0 +1,62 @@
+use crate::prelude::*;
+use crate::decoder::{decode_document, split_documents};
+use crate::encoders::encode_document;
+use crate::error::{DecodeError, FormatError};
+use crate::normalize::{normalize_object, normalize_value};
+use crate::types::{ColumnOrder, DecodeOptions, EncodeOptions, FormatOptions, KeyOrder};
+
+/// `text` with each line indented two spaces per level, taking levels from how far lines are
+/// indented relative to the lines above rather than from a fixed width
+///
+/// A line indented past the one above is one level deeper; a shallower line returns to the
+/// innermost enclosing level it reaches. Tabs and spaces each count as one column.
+fn reindent(text: &str) -> String {
+    let mut widths = vec![0];
+    let mut output = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_start_matches([' ', '\t']);
+        if content.trim().is_empty() {
+            output.push_str(line);
+            continue;
+        }
+        let width = line.len() - content.len();
+        while widths.len() > 1 && width < *widths.last().unwrap() {
+            widths.pop();
+        }
+        if width > *widths.last().unwrap() {
+            widths.push(width);
+        }
+        output.push_str(&"  ".repeat(widths.len() - 1));
+        output.push_str(content);
+    }
+    output
+}
+
+/// Re-emit every `---`-separated document of `input` with `options`, keeping key order, column
+/// order and front matter
+pub fn format_documents(input: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let input = reindent(input);
+    let encode = EncodeOptions {
+        indent: options.indent.clone(),
+        delimiter: options.delimiter,
+        length_marker: options.length_marker.clone(),
+        line_ending: options.line_ending,
+        key_order: KeyOrder::Insertion,
+        column_order: ColumnOrder::FirstObject,
+        ..Default::default()
+    };
+    let mut formatted = Vec::new();
+    for (first_line, text) in split_documents(&input) {
+        let (front_matter, value) = decode_document(text, &DecodeOptions::default())
+            .map_err(|err| FormatError::Decode(DecodeError::new(err.line + first_line, err.message)))?;
+        let meta = normalize_object(&front_matter, KeyOrder::Insertion);
+        let meta = (!front_matter.is_empty()).then_some(&meta);
+        formatted.push(encode_document(meta, &normalize_value(&value, KeyOrder::Insertion), &encode).map_err(FormatError::Encode)?);
+    }
+    let line_ending = options.line_ending.as_str();
+    let mut output = formatted.join(&format!("{}---{}", line_ending, line_ending));
+    if options.trailing_newline {
+        output.push_str(line_ending);
+    }
+    Ok(output)
+}
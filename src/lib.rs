@@ -23,18 +23,113 @@
 //! //   9.99,2,A1
 //! //   14.5,1,B2
 //! ```
+//!
+//! # `no_std`
+//!
+//! With `default-features = false` the crate builds on `core` and `alloc` alone. Encoding,
+//! decoding, budgets and reports stay available; functions that read or write through
+//! `std::io`, and every format conversion feature, need the `std` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
 
+/// The `alloc` items that the `std` prelude would otherwise provide
+mod prelude {
+    pub use alloc::format;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec;
+    pub use alloc::vec::Vec;
+    #[cfg(feature = "std")]
+    pub use std::collections::{HashMap, HashSet};
+    // Without `std` there is no default hasher, so ordered collections stand in
+    #[cfg(not(feature = "std"))]
+    pub use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
+    #[cfg(not(feature = "std"))]
+    pub use crate::float::Float;
+}
+
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "avro")]
+mod avro;
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "rusqlite", feature = "sqlx", feature = "avro", feature = "prost-reflect"))]
+mod binary;
+mod budget;
+#[cfg(feature = "cbor")]
+mod cbor;
+mod columnar;
+mod csv;
+mod decoder;
+mod diff;
+mod dictionary;
 mod encoders;
+mod error;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(not(feature = "std"))]
+mod float;
+mod formatter;
+mod lint;
+mod lossless;
+mod markdown;
+#[cfg(feature = "msgpack")]
+mod msgpack;
 mod normalize;
+mod patch;
+#[cfg(feature = "polars")]
+mod polars;
 mod primitives;
+mod prompt;
+#[cfg(feature = "prost-reflect")]
+mod protobuf;
+#[cfg(feature = "schema")]
+mod schema;
+mod spec;
+#[cfg(any(feature = "rusqlite", feature = "sqlx"))]
+mod sql;
+mod stack;
+#[cfg(feature = "std")]
+mod stream;
+mod summary;
+#[cfg(feature = "tokens")]
+mod tokens;
+#[cfg(feature = "toml")]
+mod toml;
 mod types;
+mod validate;
 mod writer;
+#[cfg(feature = "yaml")]
+mod yaml;
 
-pub use types::{Delimiter, EncodeOptions};
+pub use budget::{Budgeted, Reduction, TokenCounter};
+pub use diff::Change;
+pub use error::{DecodeError, EncodeError, FormatError, PatchError, SchemaViolation};
+pub use types::{ColumnOrder, ColumnType, Comparison, CsvOptions, CsvTable, DecodeOptions, Delimiter, Document, EmitLengths, EncodeOptions, EncodeReport, FormatOptions, FormatSize, IndentStyle, JsonOptions, KeyFolding, KeyOrder, LengthMarker, LineEnding, MissingCell, RowLimit, SortDirection, SortKey, SortSpec, SpecVersion, Summarize, TableReport};
+pub use lint::{Diagnostic, LintOptions, Rule, Severity};
+#[cfg(feature = "tokens")]
+pub use tokens::{count_tokens, Model};
+pub use prompt::{example_from_schema, few_shot_examples, format_instructions, Example};
+#[cfg(feature = "std")]
+pub use stream::TableStream;
 pub use serde_json;
 
-use normalize::normalize_value;
-use encoders::encode_value;
+use prelude::*;
+
+use budget::{fit_budget, optimize_layout};
+#[cfg(feature = "std")]
+use csv::read_csv;
+use csv::write_tables;
+use decoder::{decode_document, decode_documents as decode_stream, decode_value};
+use lossless::find_loss;
+use normalize::{exceeds_node_count, find_over_depth, normalize_object, normalize_value};
+#[cfg(feature = "arena")]
+use normalize::normalize_value_in;
+use encoders::{encode_document, encode_document_into, encode_document_with_report, encode_value};
+use sha2::{Digest, Sha256};
+use spec::find_unsupported;
+#[cfg(feature = "std")]
+use stream::{encode_table_stream, read_ndjson};
 
 /// Encode a serde_json::Value to TOON format
 ///
@@ -47,6 +142,13 @@ use encoders::encode_value;
 ///
 /// A String containing the TOON-formatted output
 ///
+/// # Panics
+///
+/// Panics if a `max_output_bytes`, `max_nodes` or `fail_over_depth` limit is exceeded, if
+/// `lossless` finds a value that would not decode back unchanged, if another option writes
+/// syntax that `spec_version` doesn't define, or if `column_aliases` would repeat a header
+/// name. Use `try_encode` to handle those cases.
+///
 /// # Example
 ///
 /// ```
@@ -58,35 +160,2738 @@ use encoders::encode_value;
 /// assert_eq!(result, "active: true\nname: Ada");
 /// ```
 pub fn encode(value: &serde_json::Value, options: Option<EncodeOptions>) -> String {
+    try_encode(value, options).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Encode a serde_json::Value to TOON format, returning an error where `encode` would panic
+///
+/// `max_nodes` is checked before any work is done and `max_output_bytes` while the output is
+/// written, so an oversized input never builds a huge string.
+///
+/// # Example
+///
+/// ```
+/// use toon::{try_encode, EncodeError, EncodeOptions};
+/// use serde_json::json;
+///
+/// let options = EncodeOptions { max_output_bytes: Some(8), ..Default::default() };
+/// let err = try_encode(&json!({"name": "Ada Lovelace"}), Some(options)).unwrap_err();
+/// assert_eq!(err, EncodeError::OutputTooLarge { limit: 8 });
+/// ```
+pub fn try_encode(value: &serde_json::Value, options: Option<EncodeOptions>) -> Result<String, EncodeError> {
     let opts = options.unwrap_or_default();
-    let normalized = normalize_value(value);
+    check_input(value, &opts)?;
+    let normalized = normalize_value(value, opts.key_order);
     encode_value(&normalized, &opts)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+/// Encode a serde_json::Value to TOON, building the normalized tree in `bump`
+///
+/// The tree's containers are carved out of the arena instead of allocated one by one, and
+/// nothing is freed node by node afterwards: the arena drops them all when it is reset or
+/// dropped. Reusing one `Bump` with `reset` between calls keeps its memory for the next
+/// encode. Requires the `arena` feature.
+///
+/// # Panics
+///
+/// Panics where `encode` does; use `try_encode_in` to handle those cases.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "arena")] {
+/// use bumpalo::Bump;
+/// use serde_json::json;
+/// use toon::encode_in;
+///
+/// let mut bump = Bump::new();
+/// for id in 0..3 {
+///     assert_eq!(encode_in(&json!({"id": id}), None, &bump), format!("id: {}", id));
+///     bump.reset();
+/// }
+/// # }
+/// ```
+#[cfg(feature = "arena")]
+pub fn encode_in(value: &serde_json::Value, options: Option<EncodeOptions>, bump: &bumpalo::Bump) -> String {
+    try_encode_in(value, options, bump).unwrap_or_else(|err| panic!("{}", err))
+}
 
-    #[test]
-    fn test_simple_object() {
-        let data = json!({"id": 123, "name": "Ada"});
-        let result = encode(&data, None);
-        assert!(result.contains("id: 123"));
-        assert!(result.contains("name: Ada"));
+/// Encode a serde_json::Value to TOON in `bump` like `encode_in`, failing as `try_encode` does
+#[cfg(feature = "arena")]
+pub fn try_encode_in(value: &serde_json::Value, options: Option<EncodeOptions>, bump: &bumpalo::Bump) -> Result<String, EncodeError> {
+    let opts = options.unwrap_or_default();
+    check_input(value, &opts)?;
+    let normalized = normalize_value_in(value, opts.key_order, bump);
+    encode_value(&normalized, &opts)
+}
+
+/// Encode a serde_json::Value to TOON as UTF-8 bytes
+///
+/// The bytes are the encoder's own buffer, handed over without copying or revalidating.
+/// Panics where `encode` does.
+///
+/// # Example
+///
+/// ```
+/// use toon::encode_to_vec;
+/// use serde_json::json;
+///
+/// assert_eq!(encode_to_vec(&json!({"id": 1}), None), b"id: 1");
+/// ```
+pub fn encode_to_vec(value: &serde_json::Value, options: Option<EncodeOptions>) -> Vec<u8> {
+    encode(value, options).into_bytes()
+}
+
+/// Encode a serde_json::Value to TOON and write it to `writer`
+///
+/// The whole document is encoded into memory first and then written with one `write_all`,
+/// so nothing reaches `writer` when encoding fails, but the output is held in full; use
+/// `encode_stream` or `TableStream` to write a large table as it is produced. Exceeded
+/// budgets are reported as `io::ErrorKind::InvalidData` wrapping the `EncodeError`.
+#[cfg(feature = "std")]
+pub fn encode_to_writer<W: std::io::Write>(value: &serde_json::Value, mut writer: W, options: Option<EncodeOptions>) -> std::io::Result<()> {
+    let output = try_encode(value, options).map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    writer.write_all(output.as_bytes())
+}
+
+/// Checks made on the input before it is normalized: `max_nodes`, `fail_over_depth` and `lossless`
+fn check_input(value: &serde_json::Value, options: &EncodeOptions) -> Result<(), EncodeError> {
+    if let Some(limit) = options.max_nodes.filter(|&limit| exceeds_node_count(value, limit)) {
+        return Err(EncodeError::TooManyNodes { limit });
+    }
+    if let Some(path) = options.fail_over_depth.and_then(|levels| find_over_depth(value, levels)) {
+        return Err(EncodeError::DepthExceeded { path });
+    }
+    if let Some(err) = find_unsupported(options) {
+        return Err(err);
+    }
+    match options.lossless.then(|| find_loss(value, options)).flatten() {
+        Some(err) => Err(err),
+        None => Ok(()),
     }
+}
 
-    #[test]
-    fn test_primitive_array() {
-        let data = json!({"tags": ["reading", "gaming"]});
-        let result = encode(&data, None);
-        assert_eq!(result, "tags[2]: reading,gaming");
+/// Encode several values as one stream, separated by `---` lines
+///
+/// # Panics
+///
+/// Panics if any value exceeds a `max_output_bytes`, `max_nodes` or `fail_over_depth` limit, if
+/// `lossless` finds a value that would not decode back unchanged, if another option writes
+/// syntax that `spec_version` doesn't define, or if `column_aliases` would repeat a header
+/// name. Use `try_encode` on each value to handle those cases.
+///
+/// # Example
+///
+/// ```
+/// use toon::encode_documents;
+/// use serde_json::json;
+///
+/// let records = [json!({"id": 1}), json!({"id": 2})];
+/// assert_eq!(encode_documents(&records, None), "id: 1\n---\nid: 2");
+/// ```
+pub fn encode_documents<'a, I>(values: I, options: Option<EncodeOptions>) -> String
+where
+    I: IntoIterator<Item = &'a serde_json::Value>,
+{
+    let opts = options.unwrap_or_default();
+    let line_ending = opts.line_ending.as_str();
+    // Each document is written bare; the stream as a whole gets the trailing newline
+    let document_opts = EncodeOptions { trailing_newline: false, ..opts.clone() };
+
+    let documents: Vec<String> = values.into_iter().map(|value| {
+        check_input(value, &opts).and_then(|_| encode_value(&normalize_value(value, opts.key_order), &document_opts))
+            .unwrap_or_else(|err| panic!("{}", err))
+    }).collect();
+    let mut output = documents.join(&format!("{}---{}", line_ending, line_ending));
+    if opts.trailing_newline && !output.is_empty() {
+        output.push_str(line_ending);
     }
+    output
+}
 
-    #[test]
-    fn test_empty_object() {
-        let data = json!({});
-        let result = encode(&data, None);
-        assert_eq!(result, "");
+/// Encode a serde_json::Value preceded by a front-matter block
+///
+/// The `front_matter` fields (source, timestamps, row counts, units, ...) are written as an
+/// `@meta:` block above the body and come back separately from `decode_with_front_matter`.
+///
+/// # Panics
+///
+/// Panics if a `max_output_bytes`, `max_nodes` or `fail_over_depth` limit is exceeded, if
+/// `lossless` finds a value that would not decode back unchanged, if another option writes
+/// syntax that `spec_version` doesn't define, or if `column_aliases` would repeat a header
+/// name.
+///
+/// # Example
+///
+/// ```
+/// use toon::encode_with_front_matter;
+/// use serde_json::json;
+///
+/// let meta = json!({"source": "crm"});
+/// let result = encode_with_front_matter(&json!({"id": 1}), meta.as_object().unwrap(), None);
+/// assert_eq!(result, "@meta:\n  source: crm\nid: 1");
+/// ```
+pub fn encode_with_front_matter(
+    value: &serde_json::Value,
+    front_matter: &serde_json::Map<String, serde_json::Value>,
+    options: Option<EncodeOptions>,
+) -> String {
+    let opts = options.unwrap_or_default();
+    let meta = normalize_object(front_matter, opts.key_order);
+    check_input(value, &opts)
+        .and_then(|_| encode_document(Some(&meta), &normalize_value(value, opts.key_order), &opts))
+        .unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Encode a stream of row objects as a single `key` table, writing it chunk by chunk
+///
+/// Rows are buffered `table_chunk_rows` at a time (1000 by default), so a large NDJSON export
+/// can be converted from a `serde_json::StreamDeserializer` without loading it. Each chunk gets
+/// a `key[start..end]{...}:` header, and `decode` merges the chunks back into one array.
+///
+/// # Example
+///
+/// ```
+/// use toon::{encode_stream, EncodeOptions};
+///
+/// let ndjson = "{\"id\": 1}\n{\"id\": 2}\n{\"id\": 3}";
+/// let rows = serde_json::Deserializer::from_str(ndjson).into_iter::<serde_json::Value>();
+/// let options = EncodeOptions { table_chunk_rows: Some(2), ..Default::default() };
+///
+/// let mut out = Vec::new();
+/// assert_eq!(encode_stream("rows", rows, &mut out, Some(options)).unwrap(), 3);
+/// assert_eq!(String::from_utf8(out).unwrap(), "rows[0..2]{id}:\n  1\n  2\nrows[2..3]{id}:\n  3");
+/// ```
+#[cfg(feature = "std")]
+pub fn encode_stream<I, E, W>(key: &str, rows: I, writer: W, options: Option<EncodeOptions>) -> std::io::Result<usize>
+where
+    I: IntoIterator<Item = Result<serde_json::Value, E>>,
+    E: Into<std::io::Error>,
+    W: std::io::Write,
+{
+    let opts = options.unwrap_or_default();
+    encode_table_stream(key, rows, writer, &opts)
+}
+
+/// Encode NDJSON (JSON Lines) read from `reader`, one line at a time
+///
+/// With a `key`, every line is a row of one chunked table, as in `encode_stream`. Without
+/// one, each line becomes its own document in a `---`-separated stream, which suits logs
+/// whose records don't share fields. Blank lines are skipped, and a malformed line or an
+/// encoding error fails with `InvalidData`. Returns the number of lines encoded.
+///
+/// # Example
+///
+/// ```
+/// use toon::encode_ndjson;
+///
+/// let log = "{\"level\": \"info\", \"msg\": \"up\"}\n\n{\"level\": \"warn\", \"msg\": \"slow\"}\n";
+///
+/// let mut table = Vec::new();
+/// assert_eq!(encode_ndjson(log.as_bytes(), &mut table, Some("logs"), None).unwrap(), 2);
+/// assert_eq!(String::from_utf8(table).unwrap(), "logs[0..2]{level,msg}:\n  info,up\n  warn,slow");
+///
+/// let mut documents = Vec::new();
+/// encode_ndjson(log.as_bytes(), &mut documents, None, None).unwrap();
+/// assert_eq!(String::from_utf8(documents).unwrap(), "level: info\nmsg: up\n---\nlevel: warn\nmsg: slow");
+/// ```
+#[cfg(feature = "std")]
+pub fn encode_ndjson<R, W>(reader: R, mut writer: W, key: Option<&str>, options: Option<EncodeOptions>) -> std::io::Result<usize>
+where
+    R: std::io::BufRead,
+    W: std::io::Write,
+{
+    let opts = options.unwrap_or_default();
+    if let Some(key) = key {
+        return encode_table_stream(key, read_ndjson(reader), writer, &opts);
+    }
+
+    let line_ending = opts.line_ending.as_str();
+    let document_opts = EncodeOptions { trailing_newline: false, ..opts.clone() };
+    let mut written = 0;
+    for value in read_ndjson(reader) {
+        let value = value?;
+        let document = check_input(&value, &opts)
+            .and_then(|_| encode_value(&normalize_value(&value, opts.key_order), &document_opts))
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        if written > 0 {
+            write!(writer, "{}---{}", line_ending, line_ending)?;
+        }
+        writer.write_all(document.as_bytes())?;
+        written += 1;
+    }
+    if opts.trailing_newline && written > 0 {
+        writer.write_all(line_ending.as_bytes())?;
+    }
+    Ok(written)
+}
+
+/// Encode the rows of a SQLite query as one `key` table, writing it chunk by chunk
+///
+/// Column names come from the statement. Rows are buffered `table_chunk_rows` at a time as in
+/// `encode_stream`; blobs become base64 strings. Returns the number of rows written. Requires
+/// the `rusqlite` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "rusqlite")] {
+/// use toon::encode_sqlite_rows;
+///
+/// let db = rusqlite::Connection::open_in_memory().unwrap();
+/// db.execute_batch("CREATE TABLE t (id INTEGER, name TEXT); INSERT INTO t VALUES (1, 'a'), (2, NULL);").unwrap();
+/// let mut statement = db.prepare("SELECT id, name FROM t").unwrap();
+///
+/// let mut out = Vec::new();
+/// encode_sqlite_rows("t", statement.query([]).unwrap(), &mut out, None).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "t[0..2]{id,name}:\n  1,a\n  2,null");
+/// # }
+/// ```
+#[cfg(feature = "rusqlite")]
+pub fn encode_sqlite_rows<W: std::io::Write>(key: &str, rows: rusqlite::Rows<'_>, writer: W, options: Option<EncodeOptions>) -> std::io::Result<usize> {
+    let opts = options.unwrap_or_default();
+    sql::encode_sqlite(key, rows, writer, &opts)
+}
+
+/// Encode a `sqlx` row stream from the `Any` driver as one `key` table, writing it chunk by chunk
+///
+/// Each value is read by the type its row reports and blobs become base64 strings. Rows are
+/// buffered `table_chunk_rows` at a time as in `encode_stream`. Returns the number of rows
+/// written. Requires the `sqlx` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "sqlx")]
+/// async fn export(pool: &sqlx::AnyPool) -> std::io::Result<usize> {
+///     let rows = sqlx::query("SELECT id, name FROM users").fetch(pool);
+///     toon::encode_sqlx_rows("users", rows, std::io::stdout(), None).await
+/// }
+/// ```
+#[cfg(feature = "sqlx")]
+pub async fn encode_sqlx_rows<S, W>(key: &str, rows: S, writer: W, options: Option<EncodeOptions>) -> std::io::Result<usize>
+where
+    S: futures_core::Stream<Item = Result<sqlx::any::AnyRow, sqlx::Error>>,
+    W: std::io::Write,
+{
+    let opts = options.unwrap_or_default();
+    sql::encode_sqlx(key, rows, writer, &opts).await
+}
+
+/// Encode a serde_json::Value, returning the output with statistics about it
+///
+/// The report counts bytes and lines, lists each table with its row count, tallies fields
+/// removed by `skip_nulls`/`skip_empty` and records the minified JSON size to compare against.
+///
+/// # Example
+///
+/// ```
+/// use toon::{encode_with_report, TableReport};
+/// use serde_json::json;
+///
+/// let data = json!({"items": [{"sku": "A1"}, {"sku": "B2"}]});
+/// let (output, report) = encode_with_report(&data, None).unwrap();
+/// assert_eq!(output, "items[2]{sku}:\n  A1\n  B2");
+/// assert_eq!(report.tables, [TableReport { key: Some("items".to_string()), rows: 2 }]);
+/// assert!(report.savings() > 0.0);
+/// ```
+pub fn encode_with_report(value: &serde_json::Value, options: Option<EncodeOptions>) -> Result<(String, EncodeReport), EncodeError> {
+    let opts = options.unwrap_or_default();
+    check_input(value, &opts)?;
+    let normalized = normalize_value(value, opts.key_order);
+    let (output, mut report) = encode_document_with_report(None, &normalized, &opts, String::new(), &mut writer::Scratch::default())?;
+    report.json_bytes = value.to_string().len();
+    Ok((output, report))
+}
+
+/// Validate a value against a JSON Schema, then encode it
+///
+/// Returns `EncodeError::SchemaViolations` listing every mismatch, with JSON Pointer paths into
+/// the value and the schema, instead of formatting data the schema rejects. Requires the
+/// `schema` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "schema")] {
+/// use toon::{encode_validated, EncodeError};
+/// use serde_json::json;
+///
+/// let schema = json!({"type": "object", "properties": {"id": {"type": "integer"}}});
+/// assert_eq!(encode_validated(&json!({"id": 1}), &schema, None).unwrap(), "id: 1");
+///
+/// let Err(EncodeError::SchemaViolations { violations }) = encode_validated(&json!({"id": "x"}), &schema, None) else {
+///     panic!("expected violations");
+/// };
+/// assert_eq!(violations[0].instance_path, "/id");
+/// # }
+/// ```
+#[cfg(feature = "schema")]
+pub fn encode_validated(value: &serde_json::Value, schema: &serde_json::Value, options: Option<EncodeOptions>) -> Result<String, EncodeError> {
+    schema::validate(value, schema)?;
+    try_encode(value, options)
+}
+
+/// Encode a serde_json::Value, returning the output with its token count for `model`
+///
+/// Requires the `tokens` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "tokens")] {
+/// use toon::{count_tokens, encode_with_token_count, Model};
+/// use serde_json::json;
+///
+/// let data = json!({"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]});
+/// let (output, tokens) = encode_with_token_count(&data, None, Model::Gpt4o).unwrap();
+/// assert_eq!(tokens, count_tokens(&output, Model::Gpt4o));
+/// assert!(tokens < count_tokens(&data.to_string(), Model::Gpt4o));
+/// # }
+/// ```
+#[cfg(feature = "tokens")]
+pub fn encode_with_token_count(value: &serde_json::Value, options: Option<EncodeOptions>, model: Model) -> Result<(String, usize), EncodeError> {
+    let output = try_encode(value, options)?;
+    let tokens = count_tokens(&output, model);
+    Ok((output, tokens))
+}
+
+/// Byte sizes of a value encoded as TOON, minified JSON, pretty-printed JSON and, with the
+/// `yaml` feature, YAML
+///
+/// # Example
+///
+/// ```
+/// use toon::compare;
+/// use serde_json::json;
+///
+/// let data = json!({"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]});
+/// let comparison = compare(&data, None).unwrap();
+/// assert!(comparison.toon.bytes < comparison.json.bytes);
+/// assert!(comparison.json.bytes < comparison.pretty_json.bytes);
+/// assert!(comparison.savings() > 0.0);
+/// ```
+pub fn compare(value: &serde_json::Value, options: Option<EncodeOptions>) -> Result<Comparison, EncodeError> {
+    measure_formats(value, options, |_| None)
+}
+
+/// Byte sizes and token counts for `model` of a value as TOON, minified JSON, pretty-printed JSON
+/// and, with the `yaml` feature, YAML
+///
+/// Requires the `tokens` feature.
+#[cfg(feature = "tokens")]
+pub fn compare_tokens(value: &serde_json::Value, options: Option<EncodeOptions>, model: Model) -> Result<Comparison, EncodeError> {
+    measure_formats(value, options, |text| Some(count_tokens(text, model)))
+}
+
+/// Size every rendering of the value, counting tokens with `tokens`
+fn measure_formats(
+    value: &serde_json::Value,
+    options: Option<EncodeOptions>,
+    tokens: impl Fn(&str) -> Option<usize>,
+) -> Result<Comparison, EncodeError> {
+    let size = |text: &str| FormatSize { bytes: text.len(), tokens: tokens(text) };
+    let toon = try_encode(value, options)?;
+    let pretty = serde_json::to_string_pretty(value).expect("JSON values always serialize");
+    #[cfg(feature = "yaml")]
+    let yaml = Some(size(&serde_yaml::to_string(value).expect("JSON values always serialize")));
+    #[cfg(not(feature = "yaml"))]
+    let yaml = None;
+    Ok(Comparison { toon: size(&toon), json: size(&value.to_string()), pretty_json: size(&pretty), yaml })
+}
+
+/// Encode a serde_json::Value, reducing it step by step until it fits in `max_tokens`
+///
+/// Nulls are dropped first, then strings are cut, table rows sampled and numbers rounded, each
+/// step tighter than the last, until `counter` puts the output within budget. The result lists the
+/// reductions that were needed; `EncodeError::OverTokenBudget` is returned if even the last step
+/// doesn't fit.
+///
+/// # Example
+///
+/// ```
+/// use toon::{encode_within_budget, Reduction};
+/// use serde_json::json;
+///
+/// let data = json!({"note": "x".repeat(400), "extra": null});
+/// let words = |text: &str| text.len() / 4;
+/// let fitted = encode_within_budget(&data, None, 40, &words).unwrap();
+/// assert_eq!(fitted.reductions, [Reduction::DropNulls, Reduction::TruncateStrings(64)]);
+/// assert!(fitted.tokens <= 40);
+/// ```
+pub fn encode_within_budget<C: TokenCounter + ?Sized>(
+    value: &serde_json::Value,
+    options: Option<EncodeOptions>,
+    max_tokens: usize,
+    counter: &C,
+) -> Result<Budgeted, EncodeError> {
+    fit_budget(|opts| try_encode(value, Some(opts.clone())), options.unwrap_or_default(), max_tokens, counter)
+}
+
+/// Options with the delimiter and key folding that cost `counter` the fewest tokens for this value
+///
+/// Comma, tab and pipe delimiters, each with key folding off and `KeyFolding::Safe`, are tried on
+/// a sample holding the first 50 elements of every array; the rest of `options` is kept. The
+/// options as given win ties. Folded output needs `DecodeOptions::expand_paths` to decode back.
+///
+/// # Example
+///
+/// ```
+/// use toon::{encode, optimize_for_tokens, Delimiter};
+/// use serde_json::json;
+///
+/// let data = json!({"rows": [{"a": "x", "b": "y"}, {"a": "z", "b": "w"}]});
+/// // A counter that charges for every comma
+/// let commas = |text: &str| text.len() + 10 * text.matches(',').count();
+/// let options = optimize_for_tokens(&data, None, &commas).unwrap();
+/// assert_ne!(options.delimiter, Delimiter::Comma);
+/// ```
+pub fn optimize_for_tokens<C: TokenCounter + ?Sized>(
+    value: &serde_json::Value,
+    options: Option<EncodeOptions>,
+    counter: &C,
+) -> Result<EncodeOptions, EncodeError> {
+    optimize_layout(value, |sample, opts| try_encode(sample, Some(opts.clone())), options.unwrap_or_default(), counter)
+}
+
+/// Read CSV into a JSON array of objects, ready to encode as a TOON table
+///
+/// With `has_headers` the first record names the fields. Unquoted fields that are JSON numbers
+/// or `true`/`false` keep those types when `infer_types` is set; quoted fields always stay
+/// strings. Records with a different field count from the header fail with `InvalidData`.
+///
+/// # Example
+///
+/// ```
+/// use toon::{encode, from_csv, CsvOptions};
+///
+/// let csv = "sku,qty,note\nA1,2,\"in stock, ships today\"\nB2,1,\n";
+/// let value = from_csv(csv.as_bytes(), CsvOptions::default()).unwrap();
+/// assert_eq!(encode(&value, None), "[2]{note,qty,sku}:\n  \"in stock, ships today\",2,A1\n  null,1,B2");
+/// ```
+#[cfg(feature = "std")]
+pub fn from_csv<R: std::io::Read>(reader: R, options: CsvOptions) -> std::io::Result<serde_json::Value> {
+    read_csv(reader, &options)
+}
+
+/// Parse JSON5 (comments, trailing commas, unquoted keys, single quotes, hex numbers) into JSON
+/// for encoding
+///
+/// `Infinity` and `NaN` become null. Requires the `json5` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "json5")] {
+/// use toon::{encode, from_json5_str};
+///
+/// let value = from_json5_str("{ // settings\n  name: 'toon', retries: 0x3, }").unwrap();
+/// assert_eq!(encode(&value, None), "name: toon\nretries: 3");
+/// # }
+/// ```
+#[cfg(feature = "json5")]
+pub fn from_json5_str(input: &str) -> Result<serde_json::Value, ::json5::Error> {
+    ::json5::from_str(input)
+}
+
+/// Convert a YAML value to JSON for encoding
+///
+/// Keys that aren't strings become their JSON text (`1`, `true`, `null`, `[1,2]`); a tagged
+/// value `!Tag value` becomes `{"!Tag": value}`; `.inf`, `-.inf` and `.nan` become null. Requires
+/// the `yaml` feature.
+#[cfg(feature = "yaml")]
+pub fn from_yaml(value: &serde_yaml::Value) -> serde_json::Value {
+    yaml::yaml_to_json(value)
+}
+
+/// Parse a YAML document into JSON for encoding, resolving `<<` merge keys
+///
+/// Uses the same mapping as `from_yaml`. Requires the `yaml` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "yaml")] {
+/// use toon::{encode, from_yaml_str};
+///
+/// let value = from_yaml_str("server:\n  port: 8080\n  hosts: [a, b]\n").unwrap();
+/// assert_eq!(encode(&value, None), "server:\n  hosts[2]: a,b\n  port: 8080");
+/// # }
+/// ```
+#[cfg(feature = "yaml")]
+pub fn from_yaml_str(input: &str) -> Result<serde_json::Value, serde_yaml::Error> {
+    yaml::parse_yaml(input)
+}
+
+/// Encode a YAML value to TOON, converting it as `from_yaml` does
+///
+/// Requires the `yaml` feature.
+#[cfg(feature = "yaml")]
+pub fn encode_yaml(value: &serde_yaml::Value, options: Option<EncodeOptions>) -> String {
+    encode(&from_yaml(value), options)
+}
+
+/// Convert a TOML value to JSON for encoding
+///
+/// Tables become objects, so arrays of tables become TOON tables; datetimes become their RFC 3339
+/// text and `inf`/`nan` become null. Requires the `toml` feature.
+#[cfg(feature = "toml")]
+pub fn from_toml(value: &::toml::Value) -> serde_json::Value {
+    toml::toml_to_json(value)
+}
+
+/// Parse a TOML document into JSON for encoding, mapping values as `from_toml` does
+///
+/// Requires the `toml` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "toml")] {
+/// use toon::{encode, from_toml_str};
+///
+/// let value = from_toml_str("[[bin]]\nname = \"cli\"\n\n[[bin]]\nname = \"server\"\n").unwrap();
+/// assert_eq!(encode(&value, None), "bin[2]{name}:\n  cli\n  server");
+/// # }
+/// ```
+#[cfg(feature = "toml")]
+pub fn from_toml_str(input: &str) -> Result<serde_json::Value, ::toml::de::Error> {
+    toml::parse_toml(input)
+}
+
+/// Encode a TOML value to TOON, converting it as `from_toml` does
+///
+/// Requires the `toml` feature.
+#[cfg(feature = "toml")]
+pub fn encode_toml(value: &::toml::Value, options: Option<EncodeOptions>) -> String {
+    encode(&from_toml(value), options)
+}
+
+/// Decode one MessagePack value into JSON for encoding, without a JSON text step
+///
+/// Binary data becomes a base64 string and an extension value becomes `{"!ext:<type>": base64}`;
+/// map keys that aren't strings become their JSON text and NaN or infinite floats become null.
+/// Bytes left over after the value are an error. Requires the `msgpack` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "msgpack")] {
+/// use toon::{encode, from_msgpack};
+///
+/// // {"id": 7, "blob": <bytes 01 02 03>}
+/// let bytes = [0x82, 0xa2, b'i', b'd', 0x07, 0xa4, b'b', b'l', b'o', b'b', 0xc4, 0x03, 1, 2, 3];
+/// assert_eq!(encode(&from_msgpack(&bytes).unwrap(), None), "blob: AQID\nid: 7");
+/// # }
+/// ```
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack(bytes: &[u8]) -> Result<serde_json::Value, rmpv::decode::Error> {
+    msgpack::parse_msgpack(bytes)
+}
+
+/// Encode MessagePack bytes to TOON, converting them as `from_msgpack` does
+///
+/// Requires the `msgpack` feature.
+#[cfg(feature = "msgpack")]
+pub fn encode_msgpack(bytes: &[u8], options: Option<EncodeOptions>) -> Result<String, rmpv::decode::Error> {
+    from_msgpack(bytes).map(|value| encode(&value, options))
+}
+
+/// Decode one CBOR data item into JSON for encoding, without a JSON text step
+///
+/// Byte strings become base64 strings. Date/time (0), epoch time (1), URI (32) and
+/// self-describe (55799) tags give just their content, bignums that fit an `i128` become
+/// integers and any other tag `n` becomes `{"!tag:n": content}`. Integers past the 64-bit
+/// range become decimal text, map keys that aren't text become their JSON text and NaN or
+/// infinite floats become null. Bytes left over after the item are an error. Requires the
+/// `cbor` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "cbor")] {
+/// use toon::{encode, from_cbor};
+///
+/// // {"t": 21.5, "raw": h'0102'}
+/// let bytes = [0xa2, 0x61, b't', 0xf9, 0x4d, 0x60, 0x63, b'r', b'a', b'w', 0x42, 1, 2];
+/// assert_eq!(encode(&from_cbor(&bytes).unwrap(), None), "raw: AQI=\nt: 21.5");
+/// # }
+/// ```
+#[cfg(feature = "cbor")]
+pub fn from_cbor(bytes: &[u8]) -> Result<serde_json::Value, ciborium::de::Error<std::io::Error>> {
+    cbor::parse_cbor(bytes)
+}
+
+/// Encode CBOR bytes to TOON, converting them as `from_cbor` does
+///
+/// Requires the `cbor` feature.
+#[cfg(feature = "cbor")]
+pub fn encode_cbor(bytes: &[u8], options: Option<EncodeOptions>) -> Result<String, ciborium::de::Error<std::io::Error>> {
+    from_cbor(bytes).map(|value| encode(&value, options))
+}
+
+/// Encode the records of an Avro object container file as one root table
+///
+/// The schema comes from the file header. Bytes and fixed values become base64, decimals
+/// numbers, dates, times and timestamps ISO 8601 text and durations `PnMnDTnS`; nested
+/// records, arrays and maps become objects and arrays. With `type_hints` set, each column
+/// is annotated with its schema type, so logical types show up as `amount:decimal` or
+/// `created:timestamp` rather than being inferred; entries already in `column_types` win.
+/// Requires the `avro` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "avro")] {
+/// use apache_avro::{types::Record, Schema, Writer};
+/// use toon::{encode_avro, EncodeOptions};
+///
+/// let schema = Schema::parse_str(r#"{"type": "record", "name": "event", "fields": [
+///     {"name": "id", "type": "long"},
+///     {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}}
+/// ]}"#).unwrap();
+/// let mut writer = Writer::new(&schema, Vec::new()).unwrap();
+/// let mut record = Record::new(&schema).unwrap();
+/// record.put("id", 1i64);
+/// record.put("at", apache_avro::types::Value::TimestampMillis(1_700_000_000_000));
+/// writer.append_value(record).unwrap();
+/// let file = writer.into_inner().unwrap();
+///
+/// let options = EncodeOptions { type_hints: true, ..Default::default() };
+/// assert_eq!(encode_avro(&file[..], Some(options)).unwrap(), "[1]{at:timestamp,id:int}:\n  \"2023-11-14T22:13:20Z\",1");
+/// # }
+/// ```
+#[cfg(feature = "avro")]
+pub fn encode_avro<R: std::io::Read>(reader: R, options: Option<EncodeOptions>) -> Result<String, apache_avro::Error> {
+    avro::read_container(reader).map(|records| encode_avro_records(records, options))
+}
+
+/// Encode Avro datums written back to back with `schema` as one root table
+///
+/// For single-object payloads without a container header; values and hints are handled as
+/// in `encode_avro`. Requires the `avro` feature.
+#[cfg(feature = "avro")]
+pub fn encode_avro_datums<R: std::io::BufRead>(schema: &apache_avro::Schema, reader: R, options: Option<EncodeOptions>) -> Result<String, apache_avro::Error> {
+    avro::read_datums(schema, reader).map(|records| encode_avro_records(records, options))
+}
+
+#[cfg(feature = "avro")]
+fn encode_avro_records(records: avro::AvroRecords, options: Option<EncodeOptions>) -> String {
+    let mut opts = options.unwrap_or_default();
+    if opts.type_hints {
+        for (column, column_type) in records.column_types {
+            opts.column_types.entry(column).or_insert(column_type);
+        }
+    }
+    encode(&serde_json::Value::Array(records.rows), Some(opts))
+}
+
+/// Convert a dynamic protobuf message into JSON for encoding, keyed by field name
+///
+/// Repeated message fields become arrays of objects, so they encode as tables. Fields without
+/// presence are written even when they hold their default, unset optional and message fields
+/// become null and only the set member of a oneof is written. Bytes become base64 strings,
+/// enums their value name and NaN or infinite floats null. Requires the `prost-reflect` feature.
+#[cfg(feature = "prost-reflect")]
+pub fn from_dynamic_message(message: &prost_reflect::DynamicMessage) -> serde_json::Value {
+    protobuf::message_to_json(message)
+}
+
+/// Encode a dynamic protobuf message to TOON, converting it as `from_dynamic_message` does
+///
+/// Requires the `prost-reflect` feature.
+#[cfg(feature = "prost-reflect")]
+pub fn encode_dynamic_message(message: &prost_reflect::DynamicMessage, options: Option<EncodeOptions>) -> String {
+    encode(&from_dynamic_message(message), options)
+}
+
+/// Decode protobuf wire bytes as a message of type `descriptor` and encode it to TOON
+///
+/// Requires the `prost-reflect` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "prost-reflect")] {
+/// use prost_reflect::DescriptorPool;
+///
+/// // A descriptor set written by `protoc --descriptor_set_out`
+/// let pool = DescriptorPool::decode(std::fs::read("orders.bin").unwrap().as_slice()).unwrap();
+/// let descriptor = pool.get_message_by_name("shop.ListOrdersResponse").unwrap();
+/// let payload = std::fs::read("response.bin").unwrap();
+/// println!("{}", toon::encode_protobuf(descriptor, &payload, None).unwrap());
+/// # }
+/// ```
+#[cfg(feature = "prost-reflect")]
+pub fn encode_protobuf(descriptor: prost_reflect::MessageDescriptor, bytes: &[u8], options: Option<EncodeOptions>) -> Result<String, prost_reflect::prost::DecodeError> {
+    prost_reflect::DynamicMessage::decode(descriptor, bytes).map(|message| encode_dynamic_message(&message, options))
+}
+
+/// Encode equally long columns of values as one table, without building an object per row
+///
+/// `columns[i]` holds the values of the column named `names[i]`, and the table is written
+/// under `key`, or as the root array without one. The output is what `try_encode` gives for
+/// the matching row objects, with names kept in their given order wherever rows would keep
+/// theirs under `preserve_order`: primitive columns are written straight into rows, while a cell
+/// holding an array or object, a repeated name or an option that reshapes tables (such as
+/// `sort_rows`, `max_rows`, `column_aliases` or a budget) goes through row objects instead.
+///
+/// # Example
+///
+/// ```
+/// use toon::encode_columns;
+/// use serde_json::json;
+///
+/// let names = ["sku".to_string(), "qty".to_string()];
+/// let columns = vec![vec![json!("A1"), json!("B2")], vec![json!(2), json!(1)]];
+/// assert_eq!(encode_columns(Some("items"), &names, columns, None).unwrap(), "items[2]{qty,sku}:\n  2,A1\n  1,B2");
+/// ```
+pub fn encode_columns(key: Option<&str>, names: &[String], columns: Vec<Vec<serde_json::Value>>, options: Option<EncodeOptions>) -> Result<String, EncodeError> {
+    let opts = options.unwrap_or_default();
+    let rows = columns.first().map_or(0, Vec::len);
+    if !columnar::writes_directly(names, &columns, rows, &opts) {
+        return try_encode(&columnar::rows_value(key, names, columns), Some(opts));
+    }
+    if let Some(err) = validate::find_invalid_option(&opts).or_else(|| find_unsupported(&opts)) {
+        return Err(err);
+    }
+
+    let mut writer = writer::LineWriter::with_buffer(String::new(), &opts.indent, opts.line_ending);
+    columnar::write_columnar_table(&mut writer, 0, key, names, rows, |column, row| columnar::json_cell(&columns[column][row], &opts), &opts);
+    let mut output = writer.into_string();
+    if opts.trailing_newline {
+        output.push_str(opts.line_ending.as_str());
+    }
+    Ok(output)
+}
+
+/// Encode an Arrow record batch as one root table, reading the columns directly
+///
+/// No per-row JSON objects are built. Integers keep all their digits, NaN and infinite floats
+/// become null, decimals are written as numbers and other types (dates, timestamps, binary,
+/// lists, structs) as their Arrow display text. Column order, aliases, the delimiter, length
+/// markers and `run_length` apply as for JSON tables; options that rewrite values, such as
+/// `sort_rows`, `max_rows` or `summarize`, do not. Requires the `arrow` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "arrow")] {
+/// use arrow_array::{Float64Array, Int64Array, RecordBatch, StringArray};
+/// use std::sync::Arc;
+/// use toon::encode_record_batch;
+///
+/// let batch = RecordBatch::try_from_iter([
+///     ("sku", Arc::new(StringArray::from(vec!["A1", "B2"])) as _),
+///     ("qty", Arc::new(Int64Array::from(vec![2, 1])) as _),
+///     ("price", Arc::new(Float64Array::from(vec![Some(9.99), None])) as _),
+/// ]).unwrap();
+/// assert_eq!(encode_record_batch(&batch, None).unwrap(), "[2]{price,qty,sku}:\n  9.99,2,A1\n  null,1,B2");
+/// # }
+/// ```
+#[cfg(feature = "arrow")]
+pub fn encode_record_batch(batch: &arrow_array::RecordBatch, options: Option<EncodeOptions>) -> Result<String, arrow_schema::ArrowError> {
+    let opts = options.unwrap_or_default();
+    if let Some(err) = find_unsupported(&opts) {
+        return Err(arrow_schema::ArrowError::InvalidArgumentError(err.to_string()));
+    }
+    arrow::encode_batch(batch, &opts)
+}
+
+/// Encode a Polars data frame as one root table, reading the columns directly
+///
+/// Integers keep all their digits, NaN and infinite floats become null, dates and times are
+/// written as ISO 8601 text and categoricals as their labels. Column order, aliases, the
+/// delimiter, length markers and `run_length` apply as for JSON tables. Requires the `polars`
+/// feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "polars")] {
+/// use polars::prelude::*;
+/// use toon::encode_data_frame;
+///
+/// let frame = df!("city" => ["Oslo", "Lima"], "temp" => [Some(-3.5), None]).unwrap();
+/// assert_eq!(encode_data_frame(&frame, None).unwrap(), "[2]{city,temp}:\n  Oslo,-3.5\n  Lima,null");
+/// # }
+/// ```
+#[cfg(feature = "polars")]
+pub fn encode_data_frame(frame: &::polars::prelude::DataFrame, options: Option<EncodeOptions>) -> ::polars::prelude::PolarsResult<String> {
+    encode_polars(options, |writer, opts| polars::write_frame(writer, None, frame, opts))
+}
+
+/// Encode a Polars data frame as a `rows` table followed by a `stats` table
+///
+/// The stats table has one row per column with its dtype, null count, min, max and, for
+/// numeric columns, mean. Requires the `polars` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "polars")] {
+/// use polars::prelude::*;
+/// use toon::encode_data_frame_with_stats;
+///
+/// let frame = df!("qty" => [Some(2i64), None, Some(4)]).unwrap();
+/// let toon = encode_data_frame_with_stats(&frame, None).unwrap();
+/// assert_eq!(toon, "rows[3]{qty}:\n  2\n  null\n  4\nstats[1]{column,dtype,max,mean,min,nulls}:\n  qty,i64,4,3,2,1");
+/// # }
+/// ```
+#[cfg(feature = "polars")]
+pub fn encode_data_frame_with_stats(frame: &::polars::prelude::DataFrame, options: Option<EncodeOptions>) -> ::polars::prelude::PolarsResult<String> {
+    encode_polars(options, |writer, opts| {
+        polars::write_frame(writer, Some("rows"), frame, opts)?;
+        polars::write_stats(writer, frame, opts)
+    })
+}
+
+/// Encode a Polars series as an inline array keyed by its name
+///
+/// Values are written as in `encode_data_frame`. Requires the `polars` feature.
+#[cfg(feature = "polars")]
+pub fn encode_series(series: &::polars::prelude::Series, options: Option<EncodeOptions>) -> ::polars::prelude::PolarsResult<String> {
+    encode_polars(options, |writer, opts| polars::write_series(writer, series, opts))
+}
+
+#[cfg(feature = "polars")]
+fn encode_polars(
+    options: Option<EncodeOptions>,
+    write: impl FnOnce(&mut writer::LineWriter, &EncodeOptions) -> ::polars::prelude::PolarsResult<()>,
+) -> ::polars::prelude::PolarsResult<String> {
+    let opts = options.unwrap_or_default();
+    if let Some(err) = find_unsupported(&opts) {
+        return Err(::polars::prelude::PolarsError::InvalidOperation(err.to_string().into()));
+    }
+    let mut writer = writer::LineWriter::with_buffer(String::new(), &opts.indent, opts.line_ending);
+    write(&mut writer, &opts)?;
+    let mut output = writer.into_string();
+    if opts.trailing_newline {
+        output.push_str(opts.line_ending.as_str());
+    }
+    Ok(output)
+}
+
+/// SHA-256 of the value's canonical TOON form
+///
+/// Encodes with `EncodeOptions::canonical()`, so two services hashing equal values get equal
+/// digests regardless of their own encoding settings or input key order.
+///
+/// # Example
+///
+/// ```
+/// use toon::canonical_hash;
+/// use serde_json::json;
+///
+/// let a: serde_json::Value = serde_json::from_str(r#"{"b": 2, "a": 1}"#).unwrap();
+/// assert_eq!(canonical_hash(&a), canonical_hash(&json!({"a": 1, "b": 2})));
+/// ```
+pub fn canonical_hash(value: &serde_json::Value) -> [u8; 32] {
+    Sha256::digest(encode(value, Some(EncodeOptions::canonical()))).into()
+}
+
+/// Reusable encoder that keeps its output buffer and writer state between calls
+///
+/// Encoding many values with the same options through one `Encoder` reuses the grown output
+/// allocation, key cache and table row buffer instead of starting from empty ones every time.
+/// The cache and buffer are cleared before each call, so one value never affects another.
+///
+/// # Example
+///
+/// ```
+/// use toon::Encoder;
+/// use serde_json::json;
+///
+/// let mut encoder = Encoder::new(None);
+/// assert_eq!(encoder.encode(&json!({"id": 1})), "id: 1");
+/// assert_eq!(encoder.encode(&json!({"id": 2})), "id: 2");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Encoder {
+    options: EncodeOptions,
+    buffer: String,
+    scratch: writer::Scratch,
+}
+
+impl Encoder {
+    pub fn new(options: Option<EncodeOptions>) -> Self {
+        Self {
+            options: options.unwrap_or_default(),
+            buffer: String::new(),
+            scratch: writer::Scratch::default(),
+        }
+    }
+
+    /// Options used for every call
+    pub fn options(&self) -> &EncodeOptions {
+        &self.options
+    }
+
+    /// Encode `value`, returning output borrowed from the internal buffer
+    ///
+    /// Panics where the free `encode` function does.
+    pub fn encode(&mut self, value: &serde_json::Value) -> &str {
+        match self.try_encode(value) {
+            Ok(_) => &self.buffer,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Encode `value`, returning an error where `encode` would panic
+    pub fn try_encode(&mut self, value: &serde_json::Value) -> Result<&str, EncodeError> {
+        check_input(value, &self.options)?;
+        let normalized = normalize_value(value, self.options.key_order);
+        let buffer = core::mem::take(&mut self.buffer);
+        self.buffer = encode_document_into(None, &normalized, &self.options, buffer, &mut self.scratch)?;
+        Ok(&self.buffer)
+    }
+}
+
+/// Decode a TOON document to a serde_json::Value
+///
+/// # Arguments
+///
+/// * `input` - The TOON text to decode
+/// * `options` - Optional decoding options. If None, defaults are used.
+///
+/// # Returns
+///
+/// The decoded value, or a `DecodeError` describing the first malformed line
+///
+/// # Example
+///
+/// ```
+/// use toon::decode;
+/// use serde_json::json;
+///
+/// let value = decode("items[2]{qty,sku}:\n  2,A1\n  1,B2", None).unwrap();
+/// assert_eq!(value, json!({"items": [{"qty": 2, "sku": "A1"}, {"qty": 1, "sku": "B2"}]}));
+/// ```
+pub fn decode(input: &str, options: Option<DecodeOptions>) -> Result<serde_json::Value, DecodeError> {
+    let opts = options.unwrap_or_default();
+    decode_value(input, &opts)
+}
+
+/// Decode a stream of `---`-separated documents written by `encode_documents`
+pub fn decode_documents(input: &str, options: Option<DecodeOptions>) -> Result<Vec<serde_json::Value>, DecodeError> {
+    let opts = options.unwrap_or_default();
+    decode_stream(input, &opts)
+}
+
+/// Decode a TOON document, returning its `@meta` front matter alongside the value
+pub fn decode_with_front_matter(input: &str, options: Option<DecodeOptions>) -> Result<Document, DecodeError> {
+    let opts = options.unwrap_or_default();
+    let (front_matter, value) = decode_document(input, &opts)?;
+    Ok(Document { front_matter, value })
+}
+
+/// Decode a TOON document and write each of its tables as CSV, one `CsvTable` per table
+///
+/// A table is any array of objects holding only primitives, wherever it sits in the document.
+/// `csv` sets the delimiter and whether a header record is written. Nulls and missing cells
+/// become empty fields, and strings that `from_csv` would read as another type under the same
+/// options are quoted, so each table reads back unchanged.
+///
+/// # Example
+///
+/// ```
+/// use toon::{decode_csv_tables, CsvOptions};
+///
+/// let toon = "id: 7\nlines[2]{qty,sku}:\n  2,A1\n  1,\"42\"";
+/// let tables = decode_csv_tables(toon, None, CsvOptions::default()).unwrap();
+/// assert_eq!(tables[0].path, "lines");
+/// assert_eq!(tables[0].csv, "qty,sku\r\n2,A1\r\n1,\"42\"\r\n");
+/// ```
+pub fn decode_csv_tables(input: &str, options: Option<DecodeOptions>, csv: CsvOptions) -> Result<Vec<CsvTable>, DecodeError> {
+    decode(input, options).map(|value| to_csv_tables(&value, csv))
+}
+
+/// Write each table of a value as CSV, as `decode_csv_tables` does for a TOON document
+pub fn to_csv_tables(value: &serde_json::Value, csv: CsvOptions) -> Vec<CsvTable> {
+    write_tables(value, &csv)
+}
+
+/// Decode a TOON document and write it as JSON text
+///
+/// `json` chooses between compact and indented output; pretty output with two-space
+/// indentation is the default.
+///
+/// # Example
+///
+/// ```
+/// use toon::{to_json_string, JsonOptions};
+///
+/// let toon = "items[2]{qty,sku}:\n  2,A1\n  1,B2";
+/// assert_eq!(to_json_string(toon, None, JsonOptions { pretty: false, ..Default::default() }).unwrap(), r#"{"items":[{"qty":2,"sku":"A1"},{"qty":1,"sku":"B2"}]}"#);
+/// assert!(to_json_string(toon, None, JsonOptions::default()).unwrap().starts_with("{\n  \"items\": [\n    {\n"));
+/// ```
+#[cfg(feature = "std")]
+pub fn to_json_string(input: &str, options: Option<DecodeOptions>, json: JsonOptions) -> Result<String, DecodeError> {
+    use serde::Serialize;
+
+    let value = decode(input, options)?;
+    let mut output = Vec::new();
+    let written = if json.pretty {
+        let indent = json.indent.unit();
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+        value.serialize(&mut serde_json::Serializer::with_formatter(&mut output, formatter))
+    } else {
+        value.serialize(&mut serde_json::Serializer::new(&mut output))
+    };
+    written.expect("JSON values always serialize");
+    if json.trailing_newline {
+        output.push(b'\n');
+    }
+    Ok(String::from_utf8(output).expect("serde_json writes UTF-8"))
+}
+
+/// Decode a TOON document and write it as a YAML document
+///
+/// Requires the `yaml` feature.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "yaml")] {
+/// let toon = "items[2]{qty,sku}:\n  2,A1\n  1,\"007\"";
+/// assert_eq!(toon::to_yaml_string(toon, None).unwrap(), "items:\n- qty: 2\n  sku: A1\n- qty: 1\n  sku: '007'\n");
+/// # }
+/// ```
+#[cfg(feature = "yaml")]
+pub fn to_yaml_string(input: &str, options: Option<DecodeOptions>) -> Result<String, DecodeError> {
+    let value = decode(input, options)?;
+    Ok(serde_yaml::to_string(&value).expect("JSON values always serialize"))
+}
+
+/// Render a value as GitHub-flavored Markdown for humans to read
+///
+/// Arrays of objects holding only primitives become Markdown tables, with all-number columns
+/// right-aligned. An object's primitive fields become a definition list of `- **key**: value`
+/// items and its nested fields follow under `###` headings, one level deeper per nesting.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::to_markdown;
+///
+/// let value = json!({"id": 7, "lines": [{"qty": 2, "sku": "A1"}, {"qty": 10, "sku": "B|2"}]});
+/// assert_eq!(to_markdown(&value), "- **id**: 7\n\n### lines\n\n| qty | sku |\n| ---: | --- |\n| 2 | A1 |\n| 10 | B\\|2 |");
+/// ```
+pub fn to_markdown(value: &serde_json::Value) -> String {
+    markdown::render(value)
+}
+
+/// Decode a TOON document and render it as Markdown, as `to_markdown` does
+pub fn decode_markdown(input: &str, options: Option<DecodeOptions>) -> Result<String, DecodeError> {
+    decode(input, options).map(|value| to_markdown(&value))
+}
+
+/// Rewrite a TOON document or `---` stream with consistent indentation, delimiters and quoting
+///
+/// Nesting is read from how lines are indented relative to each other, so mixed widths and
+/// tabs are corrected; anything else malformed, such as a wrong declared length, is a
+/// `FormatError::Decode` with the line counted from the start of `input`. Front matter, key
+/// order and table column order are kept, key order only with the `preserve_order` feature.
+///
+/// # Example
+///
+/// ```
+/// use toon::{format, FormatOptions};
+///
+/// let messy = "rows[2|]{qty|sku}:\n    2|A1\n\t1|B2\ntags[2]: \"a\",b";
+/// assert_eq!(format(messy, FormatOptions::default()).unwrap(), "rows[2]{qty,sku}:\n  2,A1\n  1,B2\ntags[2]: a,b\n");
+/// ```
+pub fn format(input: &str, options: FormatOptions) -> Result<String, FormatError> {
+    formatter::format_documents(input, &options)
+}
+
+/// Check a TOON document or `---` stream for problems, ordered by line and column
+///
+/// Each diagnostic names the [`Rule`] that found it. Rules catch declared lengths that don't
+/// match, indentation that isn't whole levels, repeated table header fields and bare values
+/// that should be quoted; whatever else strict decoding rejects is reported as `syntax`. Lines
+/// count from the start of `input`.
+///
+/// # Example
+///
+/// ```
+/// use toon::{lint, LintOptions, Rule, Severity};
+///
+/// let mut options = LintOptions::default();
+/// options.rules.insert(Rule::UnquotedValue, Some(Severity::Error));
+/// let found = lint("tags[3]: a,b\nzip: 02134\n", options);
+/// assert_eq!(found.iter().map(|d| (d.line, d.rule)).collect::<Vec<_>>(), [(1, Rule::LengthMismatch), (2, Rule::UnquotedValue)]);
+/// assert_eq!(found[1].to_string(), "line 2, column 6: error[unquoted-value]: `02134` should be quoted as \"02134\"");
+/// ```
+pub fn lint(input: &str, options: LintOptions) -> Vec<Diagnostic> {
+    lint::lint_documents(input, &options)
+}
+
+/// Compare two values by structure: objects by key, arrays by index
+///
+/// Each [`Change`] carries a JSON Pointer path such as `/users/1/name`, empty for the root.
+/// `1` and `1.0` count as the same number. Changes come in sorted key order for objects,
+/// whatever order the maps keep, and index order for arrays.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::{diff, Change};
+///
+/// let old = json!({"status": "open", "tags": ["a", "b"]});
+/// let new = json!({"status": "closed", "tags": ["a"]});
+/// assert_eq!(diff(&old, &new), [
+///     Change::Changed { path: "/status".into(), old: json!("open"), new: json!("closed") },
+///     Change::Removed { path: "/tags/1".into(), value: json!("b") },
+/// ]);
+/// ```
+pub fn diff(a: &serde_json::Value, b: &serde_json::Value) -> Vec<Change> {
+    diff::changes(a, b)
+}
+
+/// Render changes from [`diff`] one per entry, with values written as TOON
+///
+/// Entries read `+ path: value` for additions, `- path: value` for removals and
+/// `~ path: old -> new` for changed primitives. Objects and arrays follow their entry as an
+/// indented TOON block, a changed one as a block of `from` and `to`, and the root path is
+/// written `/`. No changes render as an empty string.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::{diff, render_diff};
+///
+/// let old = json!({"status": "open"});
+/// let new = json!({"status": "closed", "lines": [{"qty": 2, "sku": "A1"}]});
+/// assert_eq!(render_diff(&diff(&old, &new)), "+ /lines:\n    [1]{qty,sku}:\n      2,A1\n~ /status: open -> closed\n");
+/// ```
+pub fn render_diff(changes: &[Change]) -> String {
+    diff::render(changes)
+}
+
+/// Apply an RFC 7386 merge patch to `base`
+///
+/// Each key of an object `patch` replaces the same key of `base`, merging recursively where
+/// both are objects, and a `null` removes it. A `patch` that isn't an object replaces `base`
+/// entirely, so arrays are replaced rather than merged.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::{decode, merge};
+///
+/// let base = json!({"title": "Draft", "author": {"name": "Ada", "email": "ada@example.com"}, "tags": ["a"]});
+/// // An edit a model suggested, in TOON
+/// let patch = decode("title: Final\nauthor:\n  email: null\ntags[2]: a,b", None).unwrap();
+/// assert_eq!(merge(&base, &patch), json!({"title": "Final", "author": {"name": "Ada"}, "tags": ["a", "b"]}));
+/// ```
+pub fn merge(base: &serde_json::Value, patch: &serde_json::Value) -> serde_json::Value {
+    let mut merged = base.clone();
+    patch::merge_into(&mut merged, patch);
+    merged
+}
+
+/// Apply changes from [`diff`] to `value`, so `diff(&a, &b)` applied to `a` gives `b`
+///
+/// An addition needs its parent to exist and its key to be new; an array index may be one past
+/// the end to append. A change or removal needs its path to exist. When any change doesn't
+/// fit, `value` is left as it was and the error names that change's path.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::{apply_changes, diff, Change};
+///
+/// let old = json!({"items": [1, 2, 3], "status": "open"});
+/// let new = json!({"items": [1], "status": "closed"});
+/// let mut value = old.clone();
+/// apply_changes(&mut value, &diff(&old, &new)).unwrap();
+/// assert_eq!(value, new);
+///
+/// let stale = [Change::Removed { path: "/missing".into(), value: json!(null) }];
+/// assert_eq!(apply_changes(&mut value, &stale).unwrap_err().to_string(), "/missing: isn't present");
+/// ```
+pub fn apply_changes(value: &mut serde_json::Value, changes: &[Change]) -> Result<(), PatchError> {
+    patch::apply(value, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_simple_object() {
+        let data = json!({"id": 123, "name": "Ada"});
+        let result = encode(&data, None);
+        assert!(result.contains("id: 123"));
+        assert!(result.contains("name: Ada"));
+    }
+
+    #[test]
+    fn test_primitive_array() {
+        let data = json!({"tags": ["reading", "gaming"]});
+        let result = encode(&data, None);
+        assert_eq!(result, "tags[2]: reading,gaming");
+    }
+
+    #[test]
+    fn test_empty_object() {
+        let data = json!({});
+        let result = encode(&data, None);
+        assert_eq!(result, "");
+    }
+
+    #[test]
+    fn test_semicolon_delimiter() {
+        let data = json!({"prices": ["1,5", "2,25"], "rows": [{"a": "x;y", "b": 1}]});
+        let options = EncodeOptions {
+            delimiter: Delimiter::Semicolon,
+            ..Default::default()
+        };
+        let result = encode(&data, Some(options));
+        assert_eq!(result, "prices[2;]: 1,5;2,25\nrows[1;]{a;b}:\n  \"x;y\";1");
+        assert_eq!(decode(&result, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_round_trip() {
+        let data = json!({
+            "id": 7,
+            "name": "Ada: Lovelace",
+            "empty": {},
+            "matrix": [[1, 2], [], [[3]]],
+            "mixed": [1, "two", {"three": 3, "tags": ["x"]}, [{"deep": true}]],
+            "users": [
+                {"id": 1, "roles": [{"name": "admin", "level": 2}], "active": false},
+                {"id": 2, "roles": [], "active": null}
+            ],
+            "nested": {"ratio": -0.25, "code": "05", "big": 12345678901_u64}
+        });
+        for delimiter in [Delimiter::Comma, Delimiter::Tab, Delimiter::Pipe, Delimiter::Semicolon] {
+            let options = EncodeOptions {
+                delimiter,
+                length_marker: Some('#'.into()),
+                ..Default::default()
+            };
+            let result = encode(&data, Some(options));
+            assert_eq!(decode(&result, None).unwrap(), data, "{}", result);
+        }
+    }
+
+    #[test]
+    fn test_indent_styles() {
+        let data = json!({"a": {"b": {"c": 1}}, "list": [{"x": 1, "y": [1]}]});
+        for indent in [IndentStyle::Spaces(4), IndentStyle::Tabs, IndentStyle::Custom(" \t".to_string())] {
+            let result = encode(&data, Some(EncodeOptions { indent: indent.clone(), ..Default::default() }));
+            let decode_options = DecodeOptions { indent, ..Default::default() };
+            assert_eq!(decode(&result, Some(decode_options)).unwrap(), data, "{}", result);
+        }
+
+        let tabs = encode(&json!({"a": {"b": 1}}), Some(EncodeOptions { indent: IndentStyle::Tabs, ..Default::default() }));
+        assert_eq!(tabs, "a:\n\tb: 1");
+
+        for (indent, reason) in [
+            (IndentStyle::Spaces(0), "nesting needs at least one space per level"),
+            (IndentStyle::Custom(String::new()), "nesting needs a non-empty unit"),
+            (IndentStyle::Custom("  | ".to_string()), "'|' is not a space or tab"),
+        ] {
+            let options = EncodeOptions { indent, ..Default::default() };
+            let err = EncodeError::InvalidOption { option: "indent".to_string(), reason: reason.to_string() };
+            assert_eq!(try_encode(&data, Some(options)), Err(err));
+        }
+    }
+
+    #[test]
+    fn test_line_endings() {
+        let data = json!({"a": 1, "b": [1, 2]});
+        let options = EncodeOptions {
+            line_ending: LineEnding::CrLf,
+            trailing_newline: true,
+            ..Default::default()
+        };
+        let result = encode(&data, Some(options));
+        assert_eq!(result, "a: 1\r\nb[2]: 1,2\r\n");
+        assert_eq!(decode(&result, None).unwrap(), data);
+
+        let options = EncodeOptions { trailing_newline: true, ..Default::default() };
+        assert_eq!(encode(&json!({}), Some(options)), "");
+    }
+
+    #[test]
+    fn test_align_columns() {
+        let data = json!({"users": [
+            {"id": 1, "name": "Alice", "role": "admin"},
+            {"id": 1000, "name": "Bo", "role": "user"}
+        ]});
+        let options = EncodeOptions { align_columns: true, ..Default::default() };
+        let result = encode(&data, Some(options));
+        assert_eq!(result, "users[2]{id,name,role}:\n  1   ,Alice,admin\n  1000,Bo   ,user");
+        assert_eq!(decode(&result, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_max_line_width() {
+        let data = json!({
+            "nums": (1..=12).collect::<Vec<_>>(),
+            "rows": [{"tags": ["alpha", "beta", "gamma", "delta"], "id": 1}]
+        });
+        let options = EncodeOptions {
+            max_line_width: Some(16),
+            ..Default::default()
+        };
+        let result = encode(&data, Some(options));
+        assert!(result.starts_with("nums[12]: 1,2,3,\n  4,5,6,7,8,9,\n  10,11,12\n"), "{}", result);
+        assert_eq!(decode(&result, None).unwrap(), data);
+
+        let tabs = EncodeOptions {
+            delimiter: Delimiter::Tab,
+            max_line_width: Some(10),
+            ..Default::default()
+        };
+        let result = encode(&json!(["aaa", "bbb", "ccc", "ddd"]), Some(tabs));
+        assert_eq!(decode(&result, None).unwrap(), json!(["aaa", "bbb", "ccc", "ddd"]));
+    }
+
+    #[test]
+    fn test_emit_lengths() {
+        let data = json!({"tags": ["a", "b"], "rows": [{"id": 1}, {"id": 2}], "none": []});
+        let never = EncodeOptions {
+            emit_lengths: EmitLengths::Never,
+            delimiter: Delimiter::Pipe,
+            ..Default::default()
+        };
+        let result = encode(&data, Some(never));
+        assert_eq!(result, "none[|]:\nrows[|]{id}:\n  1\n  2\ntags[|]: a|b");
+        assert_eq!(decode(&result, None).unwrap(), data);
+
+        let tables = EncodeOptions {
+            emit_lengths: EmitLengths::OnlyTables,
+            length_marker: Some('#'.into()),
+            ..Default::default()
+        };
+        let result = encode(&data, Some(tables));
+        assert_eq!(result, "none[]:\nrows[#2]{id}:\n  1\n  2\ntags[]: a,b");
+        assert_eq!(decode(&result, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_string_length_marker() {
+        let data = json!({"rows": [{"id": 1}, {"id": 2}], "tags": ["a"]});
+        let options = EncodeOptions {
+            length_marker: Some(LengthMarker::new("len=", "_rows")),
+            delimiter: Delimiter::Pipe,
+            ..Default::default()
+        };
+        let result = encode(&data, Some(options));
+        assert_eq!(result, "rows[len=2_rows|]{id}:\n  1\n  2\ntags[len=1_rows|]: a");
+        assert_eq!(decode(&result, None).unwrap(), data);
+
+        // Each of these would change the count, break the header or pass for a delimiter marker
+        for (prefix, suffix, c) in [("", "1", "'1'"), ("]", "", "']'"), ("", "|", "'|'"), ("n ", "", "' '"), ("", ":", "':'")] {
+            let options = EncodeOptions { length_marker: Some(LengthMarker::new(prefix, suffix)), ..Default::default() };
+            let err = EncodeError::InvalidOption {
+                option: "length_marker".to_string(),
+                reason: format!("{} would be read as part of the array header", c),
+            };
+            assert_eq!(try_encode(&data, Some(options)), Err(err));
+        }
+    }
+
+    #[test]
+    fn test_key_folding() {
+        let data = json!({
+            "a": {"b": {"c": 1}},
+            "config": {"db": {"host": "x", "port": 5432}, "tags": ["t"]},
+            "literal.key": 1,
+            "odd": {"needs quote": {"x": 1}}
+        });
+        let expand = DecodeOptions { expand_paths: true, ..Default::default() };
+
+        let safe = EncodeOptions { key_folding: KeyFolding::Safe, ..Default::default() };
+        let result = encode(&data, Some(safe));
+        assert_eq!(
+            result,
+            "a.b.c: 1\nconfig:\n  db:\n    host: x\n    port: 5432\n  tags[1]: t\n\"literal.key\": 1\nodd:\n  \"needs quote\":\n    x: 1"
+        );
+        assert_eq!(decode(&result, Some(expand.clone())).unwrap(), data);
+
+        let aggressive = EncodeOptions { key_folding: KeyFolding::Aggressive, ..Default::default() };
+        let result = encode(&data, Some(aggressive));
+        assert!(result.starts_with("a.b.c: 1\nconfig.db.host: x\nconfig.db.port: 5432\nconfig.tags[1]: t\n"), "{}", result);
+        assert_eq!(decode(&result, Some(expand)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_table_flattening() {
+        let data = json!({"people": [
+            {"id": 1, "address": {"city": "SF", "geo": {"lat": 1.5}}},
+            {"id": 2, "address": {"city": "NYC", "geo": {"lat": 2.5}}}
+        ]});
+
+        let one_level = EncodeOptions { table_flatten_depth: 1, ..Default::default() };
+        assert!(encode(&data, Some(one_level)).starts_with("people[2]:\n  - address:"));
+
+        let two_levels = EncodeOptions { table_flatten_depth: 2, ..Default::default() };
+        let result = encode(&data, Some(two_levels));
+        assert_eq!(result, "people[2]{address.city,address.geo.lat,id}:\n  SF,1.5,1\n  NYC,2.5,2");
+        let expand = DecodeOptions { expand_paths: true, ..Default::default() };
+        assert_eq!(decode(&result, Some(expand)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_tabular_thresholds() {
+        let data = json!({"rows": [{"a": 1, "b": 2}, {"a": 3, "b": 4}]});
+
+        let min_rows = EncodeOptions { tabular_min_rows: 3, ..Default::default() };
+        assert_eq!(encode(&data, Some(min_rows)), "rows[2]:\n  - a: 1\n    b: 2\n  - a: 3\n    b: 4");
+
+        let max_columns = EncodeOptions { tabular_max_columns: Some(1), ..Default::default() };
+        assert!(encode(&data, Some(max_columns)).starts_with("rows[2]:\n  - a: 1"));
+
+        let within = EncodeOptions { tabular_min_rows: 2, tabular_max_columns: Some(2), ..Default::default() };
+        assert_eq!(encode(&data, Some(within)), "rows[2]{a,b}:\n  1,2\n  3,4");
+    }
+
+    #[test]
+    fn test_union_headers() {
+        let data = json!({"rows": [
+            {"id": 1, "name": "a", "email": "a@x", "age": 30},
+            {"id": 2, "name": "b", "age": 41},
+            {"id": 3, "name": "c", "email": "c@x", "age": 52}
+        ]});
+
+        let strict_union = EncodeOptions { tabular_union_threshold: Some(0.8), ..Default::default() };
+        assert!(encode(&data, Some(strict_union)).starts_with("rows[3]:\n  - "));
+
+        let union = EncodeOptions { tabular_union_threshold: Some(0.75), ..Default::default() };
+        let result = encode(&data, Some(union));
+        assert_eq!(result, "rows[3]{age,email,id,name}:\n  30,a@x,1,a\n  41,null,2,b\n  52,c@x,3,c");
+
+        let empty = EncodeOptions {
+            tabular_union_threshold: Some(0.75),
+            missing_cell: MissingCell::Empty,
+            ..Default::default()
+        };
+        let result = encode(&data, Some(empty));
+        assert!(result.contains("\n  41,,2,b\n"), "{}", result);
+        assert_eq!(decode(&result, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_column_order() {
+        let data = json!({"rows": [{"quantity": 1, "id": 7, "sku": "A"}]});
+        let with_order = |column_order| encode(&data, Some(EncodeOptions { column_order, ..Default::default() }));
+
+        assert_eq!(with_order(ColumnOrder::Alphabetical), "rows[1]{id,quantity,sku}:\n  7,1,A");
+        assert_eq!(with_order(ColumnOrder::ShortestFirst), "rows[1]{id,sku,quantity}:\n  7,A,1");
+        assert_eq!(
+            with_order(ColumnOrder::Explicit(vec!["sku".to_string(), "quantity".to_string()])),
+            "rows[1]{sku,quantity,id}:\n  A,1,7"
+        );
+    }
+
+    #[test]
+    fn test_row_limit() {
+        let rows: Vec<_> = (1..=6).map(|id| json!({"id": id})).collect();
+        let data = json!({"rows": rows});
+        let options = EncodeOptions { max_rows: Some(RowLimit::new(2, 1)), ..Default::default() };
+        let toon = encode(&data, Some(options));
+
+        assert_eq!(toon, "rows[6]{id}:\n  1\n  2\n  …(+3 rows)\n  6");
+        assert_eq!(decode(&toon, None).unwrap(), json!({"rows": [{"id": 1}, {"id": 2}, {"id": 6}]}));
+
+        let marker = json!({"rows": [{"a": "…(+3 rows)"}, {"a": "x"}]});
+        for options in [EncodeOptions::default(), EncodeOptions { lossless: true, ..Default::default() }] {
+            let toon = encode(&marker, Some(options));
+            assert_eq!(toon, "rows[2]{a}:\n  \"…(+3 rows)\"\n  x");
+            assert_eq!(decode(&toon, None).unwrap(), marker);
+        }
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let data = json!({"user": {"name": "Ada", "address": {"city": "London"}, "tags": ["a"]}});
+        let options = EncodeOptions { max_depth: Some(1), ..Default::default() };
+
+        let toon = encode(&data, Some(options));
+        assert_eq!(toon, "user:\n  address: {…}\n  name: Ada\n  tags: […]");
+        // Placeholders decode as the strings they show rather than failing as inline objects
+        assert_eq!(decode(&toon, None).unwrap(), json!({"user": {"name": "Ada", "address": "{…}", "tags": "[…]"}}));
+        assert_eq!(decode("a: {b: 1}", None).unwrap(), json!({"a": {"b": 1}}));
+    }
+
+    #[test]
+    fn test_skip_nulls() {
+        let data = json!({"id": 1, "note": null, "rows": [{"a": null}, {"a": 2}], "items": [{"x": null, "y": 1}, {"z": 2}]});
+        let options = EncodeOptions { skip_nulls: true, ..Default::default() };
+
+        assert_eq!(
+            encode(&data, Some(options)),
+            "id: 1\nitems[2]:\n  - y: 1\n  - z: 2\nrows[2]{a}:\n  null\n  2"
+        );
+    }
+
+    #[test]
+    fn test_skip_empty() {
+        let data = json!({"id": 1, "tags": [], "meta": {}});
+
+        assert_eq!(encode(&data, None), "id: 1\nmeta:\ntags[0]:");
+        let options = EncodeOptions { skip_empty: true, ..Default::default() };
+        assert_eq!(encode(&data, Some(options)), "id: 1");
+    }
+
+    #[test]
+    fn test_sort_rows() {
+        let data = json!({"rows": [
+            {"team": "b", "score": 3},
+            {"team": "a", "score": 1},
+            {"team": "b", "score": 7},
+        ]});
+        let options = EncodeOptions { sort_rows: Some(SortSpec::new().asc("team").desc("score")), ..Default::default() };
+
+        assert_eq!(encode(&data, Some(options)), "rows[3]{score,team}:\n  1,a\n  7,b\n  3,b");
+    }
+
+    #[test]
+    fn test_group_by() {
+        let data = json!({"sales": [
+            {"region": "west", "id": 1},
+            {"region": "east", "id": 2},
+            {"region": "west", "id": 3},
+        ]});
+        let options = EncodeOptions { group_by: Some("region".to_string()), ..Default::default() };
+        let toon = encode(&data, Some(options));
+
+        assert_eq!(toon, "sales:\n  west[2]{id}:\n    1\n    3\n  east[1]{id}:\n    2");
+        assert_eq!(decode(&toon, None).unwrap(), json!({"sales": {"west": [{"id": 1}, {"id": 3}], "east": [{"id": 2}]}}));
+    }
+
+    #[test]
+    fn test_string_dictionary() {
+        let data = json!({"logs": [
+            {"service": "payments-api", "code": "$1"},
+            {"service": "payments-api", "code": "ok"},
+            {"service": "payments-api", "code": "ok"},
+        ]});
+        let options = EncodeOptions { string_dictionary: Some(3), ..Default::default() };
+        let toon = encode(&data, Some(options));
+
+        assert_eq!(toon, "@dict[1]: payments-api\nlogs[3]{code,service}:\n  \"$1\",$0\n  ok,$0\n  ok,$0");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_inline_objects() {
+        let data = json!({"point": {"x": 1, "y": 2}, "label": {"text": "a, b", "size": 3, "bold": true}});
+        let options = EncodeOptions { inline_objects: Some(2), ..Default::default() };
+        let toon = encode(&data, Some(options));
+
+        assert_eq!(toon, "label:\n  bold: true\n  size: 3\n  text: \"a, b\"\npoint: {x: 1, y: 2}");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+        assert_eq!(decode("p: {a: \"x, y\", b: null}", None).unwrap(), json!({"p": {"a": "x, y", "b": null}}));
+    }
+
+    #[test]
+    fn test_table_chunks() {
+        let rows: Vec<_> = (0..5).map(|id| json!({"id": id})).collect();
+        let data = json!({"items": rows});
+        let options = EncodeOptions { table_chunk_rows: Some(2), ..Default::default() };
+        let toon = encode(&data, Some(options));
+
+        assert_eq!(toon, "items[0..2]{id}:\n  0\n  1\nitems[2..4]{id}:\n  2\n  3\nitems[4..5]{id}:\n  4");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+
+        // Chunks of a table under a folded key find their earlier chunk through the expanded path
+        let data = json!({"o": {"t": [{"id": 0}, {"id": 1}, {"id": 2}]}});
+        let options = EncodeOptions { table_chunk_rows: Some(2), key_folding: KeyFolding::Safe, ..Default::default() };
+        let toon = encode(&data, Some(options));
+        assert_eq!(toon, "o.t[0..2]{id}:\n  0\n  1\no.t[2..3]{id}:\n  2");
+        let expand = DecodeOptions { expand_paths: true, ..Default::default() };
+        assert_eq!(decode(&toon, Some(expand)).unwrap(), data);
+        assert_eq!(decode(&toon, None).unwrap(), json!({"o.t": [{"id": 0}, {"id": 1}, {"id": 2}]}));
+    }
+
+    #[test]
+    fn test_column_aliases() {
+        let data = json!({"items": [{"quantity": 2, "sku": "A1"}]});
+        let column_aliases = [("quantity".to_string(), "q".to_string())].into_iter().collect();
+        let options = EncodeOptions { column_aliases, ..Default::default() };
+        assert_eq!(encode(&data, Some(options.clone())), "items[1]{q,sku}:\n  2,A1");
+
+        let toon = encode(&data, Some(EncodeOptions { alias_legend: true, ..options }));
+        assert_eq!(toon, "@aliases[1]{alias,column}:\n  q,quantity\nitems[1]{q,sku}:\n  2,A1");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+
+        // An alias naming a column the rows already have would write `{b,b}`
+        let data = json!({"orders": [{"id": 1, "lines": [{"a": 1, "b": 2}, {"a": 3, "b": 4}]}]});
+        let column_aliases: HashMap<String, String> = [("a".to_string(), "b".to_string())].into_iter().collect();
+        let options = EncodeOptions { column_aliases, ..Default::default() };
+        let collision = EncodeError::AliasCollision { path: "orders[0].lines".to_string(), alias: "b".to_string() };
+        assert_eq!(try_encode(&data, Some(options.clone())), Err(collision));
+        let rows = [json!({"a": 1, "b": 2})].into_iter().map(Ok::<_, std::io::Error>);
+        let err = encode_stream("lines", rows, Vec::new(), Some(options.clone())).unwrap_err();
+        assert_eq!(err.to_string(), "column alias `b` collides with another column at lines");
+        assert_eq!(try_encode(&json!({"lines": [{"a": 1}]}), Some(options)).unwrap(), "lines[1]{b}:\n  1");
+
+        let column_aliases = [("a", "x"), ("b", "x")].into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        let options = EncodeOptions { column_aliases, ..Default::default() };
+        assert_eq!(
+            try_encode(&json!({"lines": [{"a": 1}]}), Some(options)).unwrap_err().to_string(),
+            "invalid `column_aliases`: `a` and `b` share the alias `x`"
+        );
+    }
+
+    #[test]
+    fn test_run_length() {
+        let data = json!({
+            "readings": [0, 0, 0, 0, 5, 5],
+            "states": [{"on": true}, {"on": true}, {"on": true}, {"on": false}],
+            "labels": ["a x3"],
+        });
+        let toon = encode(&data, Some(EncodeOptions { run_length: true, ..Default::default() }));
+
+        assert_eq!(toon, "labels[1]: \"a x3\"\nreadings[6]: 0 x4,5,5\nstates[4]{on}:\n  true x3\n  false");
+        assert_eq!(decode(&toon, Some(DecodeOptions { run_length: true, ..Default::default() })).unwrap(), data);
+    }
+
+    #[test]
+    fn test_type_hints() {
+        let data = json!({"items": [
+            {"id": 1, "name": "A", "price": 2, "zip": "02139"},
+            {"id": 2, "name": "B", "price": 2.5, "zip": null},
+        ]});
+        let toon = encode(&data, Some(EncodeOptions { type_hints: true, ..Default::default() }));
+
+        assert_eq!(toon, "items[2]{id:int,name:str,price:float,zip:str}:\n  1,A,2,\"02139\"\n  2,B,2.5,null");
+        assert_eq!(decode("t[1]{n:str,x:float}:\n  42,1", None).unwrap(), json!({"t": [{"n": "42", "x": 1.0}]}));
+        assert!(decode("t[1]{n:int}:\n  1.5", None).is_err());
+    }
+
+    #[test]
+    fn test_front_matter() {
+        let meta = json!({"source": "crm", "rows": 2, "units": {"price": "USD"}});
+        let data = json!({"items": [{"id": 1}, {"id": 2}]});
+        let toon = encode_with_front_matter(&data, meta.as_object().unwrap(), None);
+
+        assert_eq!(toon, "@meta:\n  rows: 2\n  source: crm\n  units:\n    price: USD\nitems[2]{id}:\n  1\n  2");
+        let document = decode_with_front_matter(&toon, None).unwrap();
+        assert_eq!(serde_json::Value::Object(document.front_matter), meta);
+        assert_eq!(document.value, data);
+        assert_eq!(decode(&toon, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_documents() {
+        let records = vec![json!({"id": 1, "tags": ["a"]}), json!("---"), json!([1, 2])];
+        let toon = encode_documents(&records, None);
+
+        assert_eq!(toon, "id: 1\ntags[1]: a\n---\n\"---\"\n---\n[2]: 1,2");
+        assert_eq!(decode_documents(&toon, None).unwrap(), records);
+        assert_eq!(decode_documents("a: 1\n---\nb[2]: 1", None).unwrap_err().line, 3);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let rows: Vec<_> = (1..=4).map(|id| json!({"id": id, "kind": if id % 2 == 0 { "even" } else { "odd" }})).collect();
+        let data = json!({"rows": rows, "small": [1, 2]});
+        let options = EncodeOptions { summarize: Some(Summarize::new(3, RowLimit::new(1, 1))), ..Default::default() };
+
+        assert_eq!(
+            encode(&data, Some(options)),
+            "rows:\n  count: 4\n  sample[2]{id,kind}:\n    1,odd\n    4,even\n  stats:\n    id:\n      max: 4\n      mean: 2.5\n      min: 1\n    kind:\n      distinct: 2\nsmall[2]: 1,2"
+        );
+    }
+
+    #[test]
+    fn test_lift_constant_columns() {
+        let data = json!({"items": [
+            {"sku": "A1", "qty": 2, "currency": "USD"},
+            {"sku": "B2", "qty": 1, "currency": "USD"},
+        ]});
+        let toon = encode(&data, Some(EncodeOptions { lift_constant_columns: true, ..Default::default() }));
+
+        assert_eq!(toon, "items[2]{qty,sku}:\n  currency: USD\n  2,A1\n  1,B2");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+
+        // Lifting `x` would leave the second row with only an empty cell, a blank line the decoder skips
+        let data = json!({"a": [{"x": 1, "y": 2}, {"x": 1}]});
+        let options = EncodeOptions {
+            lift_constant_columns: true,
+            tabular_union_threshold: Some(0.5),
+            missing_cell: MissingCell::Empty,
+            ..Default::default()
+        };
+        let toon = encode(&data, Some(options.clone()));
+        assert_eq!(toon, "a[2]{x,y}:\n  1,2\n  1,");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+
+        // Every row still has a `y` of its own, so `x` is lifted
+        let data = json!({"a": [{"x": 1, "y": 2, "z": 3}, {"x": 1, "y": 4}]});
+        let toon = encode(&data, Some(options));
+        assert_eq!(toon, "a[2]{y,z}:\n  x: 1\n  2,3\n  4,");
+        assert_eq!(decode(&toon, None).unwrap(), data);
+    }
+
+    #[test]
+    fn test_numeric_like_strings_are_quoted() {
+        let data = json!({"values": ["42", "-3.14", "1e-6", "05", "1.", "1e", "-", "v1.2"]});
+        assert_eq!(encode(&data, None), "values[8]: \"42\",\"-3.14\",\"1e-6\",\"05\",1.,1e,-,v1.2");
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_insertion_key_order() {
+        let data: serde_json::Value = serde_json::from_str(r#"{"name": "Ada", "id": 1, "rows": [{"z": 1, "a": 2}]}"#).unwrap();
+        let options = EncodeOptions { key_order: KeyOrder::Insertion, column_order: ColumnOrder::FirstObject, ..Default::default() };
+
+        assert_eq!(encode(&data, Some(options)), "name: Ada\nid: 1\nrows[1]{z,a}:\n  1,2");
+        assert_eq!(encode(&data, None), "id: 1\nname: Ada\nrows[1]{a,z}:\n  2,1");
+    }
+
+    #[test]
+    fn test_encoder_reuses_buffer() {
+        let mut encoder = Encoder::new(Some(EncodeOptions { trailing_newline: true, ..Default::default() }));
+        let long = json!({"rows": (0..50).map(|i| json!({"id": i})).collect::<Vec<_>>()});
+
+        let first = encoder.encode(&long).to_string();
+        assert_eq!(first, encode(&long, Some(encoder.options().clone())));
+        assert_eq!(encoder.encode(&json!({"id": 1})), "id: 1\n");
+
+        // The key cache and row buffer are reused, but start empty for every value
+        let values = [long.clone(), json!({"rows": [{"id": "a b"}], "id": [1, 2]}), json!({"a.b": 1}), long];
+        for value in &values {
+            let expected = encode(value, Some(encoder.options().clone()));
+            assert_eq!(encoder.encode(value), expected);
+        }
+        assert!(encoder.try_encode(&json!({"id": 1})).is_ok());
+        let limited = EncodeOptions { max_output_bytes: Some(8), ..Default::default() };
+        let mut encoder = Encoder::new(Some(limited));
+        assert!(encoder.try_encode(&values[0]).is_err());
+        assert_eq!(encoder.encode(&json!({"id": 1})), "id: 1");
+
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&encoder);
+    }
+
+    #[test]
+    fn test_encode_stream_round_trips_chunks() {
+        let rows: Vec<serde_json::Value> = (0..5).map(|i| json!({"id": i, "name": format!("n{}", i)})).collect();
+        let options = EncodeOptions { table_chunk_rows: Some(2), ..Default::default() };
+
+        let mut out = Vec::new();
+        let written = encode_stream("rows", rows.iter().cloned().map(Ok::<_, std::io::Error>), &mut out, Some(options.clone())).unwrap();
+        let toon = String::from_utf8(out).unwrap();
+        assert_eq!(written, 5);
+        assert_eq!(decode(&toon, None).unwrap(), json!({"rows": rows}));
+
+        let mixed = [json!({"id": 1}), json!({"id": 2}), json!({"id": 3, "extra": true})];
+        let err = encode_stream("rows", mixed.into_iter().map(Ok::<_, std::io::Error>), Vec::new(), Some(options)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_encode_ndjson() {
+        let log = "{\"id\": 1}\n{\"id\": 2}\n\n{\"id\": 3}\n";
+        let options = EncodeOptions { table_chunk_rows: Some(2), ..Default::default() };
+
+        let mut out = Vec::new();
+        assert_eq!(encode_ndjson(log.as_bytes(), &mut out, Some("rows"), Some(options)).unwrap(), 3);
+        let toon = String::from_utf8(out).unwrap();
+        assert_eq!(decode(&toon, None).unwrap(), json!({"rows": [{"id": 1}, {"id": 2}, {"id": 3}]}));
+
+        let mut out = Vec::new();
+        assert_eq!(encode_ndjson(log.as_bytes(), &mut out, None, None).unwrap(), 3);
+        let toon = String::from_utf8(out).unwrap();
+        assert_eq!(decode_documents(&toon, None).unwrap(), vec![json!({"id": 1}), json!({"id": 2}), json!({"id": 3})]);
+
+        let err = encode_ndjson("{\"id\": 1}\n{oops}\n".as_bytes(), Vec::new(), None, None).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"));
+    }
+
+    #[cfg(feature = "arena")]
+    #[test]
+    fn test_encode_in() {
+        let data = json!({
+            "users": [{"id": 2, "name": "Bob", "tags": ["b"]}, {"id": 1, "name": "Ada", "tags": []}],
+            "meta": {"count": 2, "nested": {"deep": {"x": 1}}},
+            "scores": [1, 2, 3, 4, 5, 6],
+        });
+        let variants = [
+            EncodeOptions::default(),
+            EncodeOptions { max_depth: Some(2), ..Default::default() },
+            EncodeOptions { summarize: Some(Summarize::new(4, RowLimit::new(1, 1))), ..Default::default() },
+            EncodeOptions { delimiter: Delimiter::Tab, key_folding: KeyFolding::Safe, ..Default::default() },
+        ];
+
+        let mut bump = bumpalo::Bump::new();
+        for options in variants {
+            assert_eq!(try_encode_in(&data, Some(options.clone()), &bump), try_encode(&data, Some(options)));
+            assert!(bump.allocated_bytes() > 0);
+            bump.reset();
+        }
+
+        let options = EncodeOptions { max_nodes: Some(3), ..Default::default() };
+        assert_eq!(try_encode_in(&data, Some(options), &bump), Err(EncodeError::TooManyNodes { limit: 3 }));
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_encode_sqlite_rows() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE t (id INTEGER, score REAL, tag TEXT, raw BLOB);").unwrap();
+        for i in 0..5 {
+            db.execute("INSERT INTO t VALUES (?1, ?2, ?3, ?4)", rusqlite::params![i, i as f64 / 2.0, format!("t{}", i), vec![i as u8; 2]]).unwrap();
+        }
+        let options = EncodeOptions { table_chunk_rows: Some(2), ..Default::default() };
+
+        let mut statement = db.prepare("SELECT * FROM t").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(encode_sqlite_rows("t", statement.query([]).unwrap(), &mut out, Some(options.clone())).unwrap(), 5);
+        let toon = String::from_utf8(out).unwrap();
+        assert!(toon.starts_with("t[0..2]{id,raw,score,tag}:\n  0,AAA=,0,t0\n  1,AQE=,0.5,t1\nt[2..4]"));
+        assert_eq!(decode(&toon, None).unwrap()["t"].as_array().unwrap().len(), 5);
+
+        let mut empty = db.prepare("SELECT * FROM t WHERE id < 0").unwrap();
+        let mut out = Vec::new();
+        assert_eq!(encode_sqlite_rows("t", empty.query([]).unwrap(), &mut out, Some(options)).unwrap(), 0);
+        assert_eq!(String::from_utf8(out).unwrap(), "t[0]:");
+    }
+
+    #[test]
+    fn test_encode_deeply_nested_values() {
+        let depth = 3_000;
+        let mut objects = json!(1);
+        let mut arrays = json!(1);
+        for _ in 0..depth {
+            objects = serde_json::Value::Object([("a".to_string(), objects)].into_iter().collect());
+            arrays = serde_json::Value::Array(vec![arrays, json!(2)]);
+        }
+        // One space per level keeps the quadratic output to a few megabytes
+        let options = EncodeOptions { indent: IndentStyle::Spaces(1), fail_over_depth: None, ..Default::default() };
+
+        let toon = encode(&objects, Some(options.clone()));
+        assert_eq!(toon.lines().count(), depth);
+        assert!(toon.ends_with("a: 1"));
+        assert_eq!(encode(&arrays, Some(options)).lines().count(), 2 * depth - 1);
+
+        // serde_json drops nested values recursively, so the inputs are leaked instead
+        std::mem::forget((objects, arrays));
+    }
+
+    #[test]
+    fn test_decode_deeply_nested_values() {
+        let depth = 3_000;
+        let toon: String = (0..depth).map(|level| format!("{}a:\n", " ".repeat(level))).collect();
+        let options = DecodeOptions { indent: IndentStyle::Spaces(1), fail_over_depth: None, ..Default::default() };
+
+        // A small stack shows the recursion moves to the heap rather than fitting by luck
+        let decoded = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn({
+                let (toon, options) = (toon.clone(), options.clone());
+                move || {
+                    let value = decode(&toon, Some(options)).unwrap();
+                    let levels = core::iter::successors(Some(&value), |value| value.get("a")).count();
+                    // serde_json drops nested values recursively, so the value is leaked instead
+                    std::mem::forget(value);
+                    levels
+                }
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(decoded, depth + 1);
+
+        let limited = DecodeOptions { fail_over_depth: Some(100), ..options };
+        assert_eq!(decode(&toon, Some(limited)), Err(DecodeError::new(102, "nesting deeper than 100 levels")));
+        let default = DecodeOptions { indent: IndentStyle::Spaces(1), ..Default::default() };
+        assert_eq!(decode(&toon, Some(default)), Err(DecodeError::new(1002, "nesting deeper than 1000 levels")));
+    }
+
+    #[test]
+    fn test_encode_budgets() {
+        let data = json!({"rows": [{"id": 1}, {"id": 2}, {"id": 3}]});
+        assert_eq!(encode(&data, None), "rows[3]{id}:\n  1\n  2\n  3");
+
+        let fits = EncodeOptions { max_output_bytes: Some(24), max_nodes: Some(8), ..Default::default() };
+        assert!(try_encode(&data, Some(fits)).is_ok());
+
+        let short = EncodeOptions { max_output_bytes: Some(23), ..Default::default() };
+        assert_eq!(try_encode(&data, Some(short)), Err(EncodeError::OutputTooLarge { limit: 23 }));
+
+        let few_nodes = EncodeOptions { max_nodes: Some(7), ..Default::default() };
+        assert_eq!(try_encode(&data, Some(few_nodes)), Err(EncodeError::TooManyNodes { limit: 7 }));
+    }
+
+    #[test]
+    fn test_encode_to_bytes() {
+        let data = json!({"tags": ["a", "b"]});
+        assert_eq!(encode_to_vec(&data, None), encode(&data, None).into_bytes());
+
+        let mut out = Vec::new();
+        encode_to_writer(&data, &mut out, None).unwrap();
+        assert_eq!(out, b"tags[2]: a,b");
+
+        let budget = EncodeOptions { max_output_bytes: Some(4), ..Default::default() };
+        let err = encode_to_writer(&data, Vec::new(), Some(budget)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_fail_over_depth() {
+        let data = json!({"users": [{"id": 1}, {"id": 2, "address": {"city": "Paris"}}]});
+        let options = |levels| EncodeOptions { fail_over_depth: Some(levels), ..Default::default() };
+
+        assert!(try_encode(&data, Some(options(3))).is_ok());
+        let err = try_encode(&data, Some(options(2))).unwrap_err();
+        assert_eq!(err, EncodeError::DepthExceeded { path: "users[1].address".to_string() });
+        assert_eq!(try_encode(&data, Some(options(0))).unwrap_err(), EncodeError::DepthExceeded { path: "users".to_string() });
+
+        // Deep input fails by default rather than relying on the stack to hold it
+        let deep = (0..1_002).fold(json!(1), |inner, _| json!([inner]));
+        let path = "[0]".repeat(1_001);
+        assert_eq!(try_encode(&deep, None), Err(EncodeError::DepthExceeded { path: path.clone() }));
+        let rows = [json!({"a": deep.clone()})].into_iter().map(Ok::<_, std::io::Error>);
+        let err = encode_stream("rows", rows, Vec::new(), None).unwrap_err();
+        assert_eq!(err.to_string(), format!("rows[0].a{} is nested too deeply", &path[..3 * 998]));
+        assert!(try_encode(&deep, Some(EncodeOptions { fail_over_depth: None, ..Default::default() })).is_ok());
+    }
+
+    #[test]
+    fn test_lossless_mode() {
+        let options = EncodeOptions { lossless: true, ..Default::default() };
+        let exact = json!({"id": 9007199254740992u64, "price": 9.5, "tags": ["a"]});
+        assert_eq!(decode(&try_encode(&exact, Some(options.clone())).unwrap(), None).unwrap(), exact);
+
+        let big = json!({"rows": [{"id": 9007199254740993u64}]});
+        let err = try_encode(&big, Some(options.clone())).unwrap_err();
+        assert!(matches!(err, EncodeError::Lossy { ref path, .. } if path == "rows[0].id"));
+        assert!(matches!(try_encode(&json!({"x": 1.0}), Some(options.clone())), Err(EncodeError::Lossy { .. })));
+
+        let sampled = EncodeOptions { max_rows: Some(RowLimit::new(1, 0)), ..options };
+        assert_eq!(
+            try_encode(&exact, Some(sampled)).unwrap_err().to_string(),
+            "not lossless: `max_rows` discards information"
+        );
+    }
+
+    #[test]
+    fn test_canonical_hash() {
+        let data = json!({"items": [{"sku": "A1", "qty": 2}], "total": 9.5});
+        assert_eq!(encode(&data, Some(EncodeOptions::canonical())), "items[1]{qty,sku}:\n  2,A1\ntotal: 9.5");
+
+        let digest = canonical_hash(&data);
+        assert_ne!(digest, canonical_hash(&json!({"items": [], "total": 9.5})));
+        // SHA-256 of the canonical text above
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex, "c2e643dce07012016c365e9d92c8e04a51cff0fa7bcda552ee39d78481a7735d");
+    }
+
+    #[test]
+    fn test_encode_report() {
+        let data = json!({
+            "note": null,
+            "orders": [{"id": 1, "tags": []}, {"id": 2, "tags": ["x"]}],
+            "users": [{"name": "Ada"}, {"name": "Bob"}, {"name": "Cy"}],
+        });
+        let options = EncodeOptions { skip_nulls: true, skip_empty: true, ..Default::default() };
+        let (output, report) = encode_with_report(&data, Some(options)).unwrap();
+
+        assert_eq!(report.bytes, output.len());
+        assert_eq!(report.lines, output.lines().count());
+        assert_eq!(report.tables, [TableReport { key: Some("users".to_string()), rows: 3 }]);
+        assert_eq!(report.dropped_fields, 2);
+        assert_eq!(report.json_bytes, data.to_string().len());
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_encode_validated() {
+        let schema = json!({
+            "type": "object",
+            "required": ["users"],
+            "properties": {
+                "users": {"type": "array", "items": {"type": "object", "properties": {"id": {"type": "integer"}}}}
+            }
+        });
+
+        let valid = json!({"users": [{"id": 1}, {"id": 2}]});
+        assert_eq!(encode_validated(&valid, &schema, None).unwrap(), encode(&valid, None));
+
+        let invalid = json!({"users": [{"id": 1}, {"id": "two"}, {"id": 3.5}]});
+        let Err(EncodeError::SchemaViolations { violations }) = encode_validated(&invalid, &schema, None) else {
+            panic!("expected violations");
+        };
+        let paths: Vec<&str> = violations.iter().map(|v| v.instance_path.as_str()).collect();
+        assert_eq!(paths, ["/users/1/id", "/users/2/id"]);
+        assert_eq!(violations[0].schema_path, "/properties/users/items/properties/id/type");
+
+        let missing = encode_validated(&json!({}), &schema, None).unwrap_err();
+        assert!(matches!(&missing, EncodeError::SchemaViolations { violations } if violations[0].instance_path.is_empty()));
+        assert!(matches!(encode_validated(&valid, &json!({"type": 5}), None), Err(EncodeError::InvalidSchema { .. })));
+    }
+
+    #[test]
+    fn test_root_string_not_read_as_directive() {
+        let value = json!("@meta x");
+        let encoded = encode(&value, None);
+        assert_eq!(encoded, "\"@meta x\"");
+        assert_eq!(decode(&encoded, None).unwrap(), value);
+        assert_eq!(encode(&json!(["@a"]), None), "[1]: @a");
+    }
+
+    #[test]
+    fn test_spec_version() {
+        let data = json!({"a": {"b": [1, 2]}});
+        let pinned = |version, options: EncodeOptions| try_encode(&data, Some(EncodeOptions { spec_version: Some(version), ..options }));
+
+        assert_eq!(pinned(SpecVersion::V1_0, EncodeOptions::default()).unwrap(), encode(&data, None));
+
+        let folded = EncodeOptions { key_folding: KeyFolding::Safe, ..Default::default() };
+        assert_eq!(
+            pinned(SpecVersion::V1_0, folded.clone()),
+            Err(EncodeError::Unsupported { option: "key_folding".to_string(), version: SpecVersion::V1_0 })
+        );
+        assert_eq!(pinned(SpecVersion::V1_5, folded).unwrap(), "a.b[2]: 1,2");
+
+        let marked = EncodeOptions { length_marker: Some('#'.into()), ..Default::default() };
+        assert_eq!(pinned(SpecVersion::V1_5, marked.clone()).unwrap(), "a:\n  b[#2]: 1,2");
+        assert!(matches!(pinned(SpecVersion::V2_0, marked), Err(EncodeError::Unsupported { .. })));
+
+        let dictionary = EncodeOptions { string_dictionary: Some(2), ..Default::default() };
+        let err = pinned(SpecVersion::V2_0, dictionary).unwrap_err();
+        assert_eq!(err.to_string(), "`string_dictionary` is not part of TOON spec 2.0");
+    }
+
+    #[cfg(feature = "tokens")]
+    #[test]
+    fn test_token_counts() {
+        assert_eq!(count_tokens("", Model::Gpt4o), 0);
+        assert_eq!(count_tokens("hello world", Model::Gpt4o), 2);
+        assert_eq!(count_tokens("hello world", Model::Gpt4), 2);
+        assert_eq!(count_tokens("<|endoftext|>", Model::Gpt4), count_tokens("<|endoftext|>", Model::Gpt35Turbo));
+        assert_eq!(Model::from_name("openai/GPT-4o-mini"), Some(Model::Gpt4o));
+        assert_eq!(Model::from_name("gpt-4-turbo"), Some(Model::Gpt4));
+        assert_eq!(Model::from_name("gpt-3.5-turbo"), Some(Model::Gpt35Turbo));
+        assert_eq!(Model::from_name("claude-3-opus"), None);
+
+        let data = json!({"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Grace"}, {"id": 3, "name": "Alan"}]});
+        let (output, tokens) = encode_with_token_count(&data, None, Model::Gpt4o).unwrap();
+        assert_eq!(output, encode(&data, None));
+        assert_eq!(tokens, count_tokens(&output, Model::Gpt4o));
+        assert!(tokens < count_tokens(&serde_json::to_string(&data).unwrap(), Model::Gpt4o));
+    }
+
+    #[test]
+    fn test_format() {
+        let options = FormatOptions { delimiter: Delimiter::Pipe, length_marker: Some(LengthMarker::prefix("#")), ..Default::default() };
+        let stream = "@meta:\n   source: crm\nids[2]: 1,2\n---\nids[1]: 3\n";
+        assert_eq!(format(stream, options.clone()).unwrap(), "@meta:\n  source: crm\nids[#2|]: 1|2\n---\nids[#1|]: 3\n");
+
+        let err = format("a: 1\n---\nids[3]: 1,2\n", options).unwrap_err();
+        assert_eq!(err, FormatError::Decode(DecodeError::new(3, "array declares 3 items but contains 2")));
+    }
+
+    #[test]
+    fn test_lint() {
+        let input = "users[3]{id,id}:\n  1,2\n  3\nnote: a:b\n---\nuser:\n   id: 1\ntags[2]: x,05\n";
+        let found: Vec<_> = lint(input, LintOptions::default()).into_iter().map(|d| (d.rule, d.severity, d.line, d.column)).collect();
+        assert_eq!(
+            found,
+            [
+                (Rule::DuplicateField, Severity::Error, 1, 1),
+                (Rule::LengthMismatch, Severity::Error, 1, 1),
+                (Rule::LengthMismatch, Severity::Error, 3, 3),
+                (Rule::UnquotedValue, Severity::Warning, 4, 7),
+                (Rule::Indentation, Severity::Error, 7, 1),
+                (Rule::UnquotedValue, Severity::Warning, 8, 12),
+            ]
+        );
+        assert_eq!(Rule::from_id("duplicate-field"), Some(Rule::DuplicateField));
+
+        let mut options = LintOptions { run_length: true, ..Default::default() };
+        options.rules.insert(Rule::UnquotedValue, None);
+        assert_eq!(lint("tags[3]: a x2,05\n", options.clone()), []);
+        let syntax = lint("a: \"open\n", options).remove(0);
+        assert_eq!((syntax.rule, syntax.line, syntax.message.as_str()), (Rule::Syntax, 1, "unterminated string"));
+        assert_eq!(lint(&encode(&json!({"rows": [{"a": "x:y"}, {"a": "05"}]}), None), LintOptions::default()), []);
+        assert_eq!(lint("rows[4]{id}:\n  1\n  …(+2 rows)\n  4\n", LintOptions::default()), []);
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = json!({"a/b": 1, "n": 1, "list": [1, {"x": true}], "gone": null});
+        let new = json!({"a/b": 2, "n": 1.0, "list": [1, [2]], "m~": "x"});
+        let changes = diff(&old, &new);
+        assert_eq!(changes.iter().map(Change::path).collect::<Vec<_>>(), ["/a~1b", "/gone", "/list/1", "/m~0"]);
+        assert_eq!(
+            render_diff(&changes),
+            "~ /a~1b: 1 -> 2\n- /gone: null\n~ /list/1:\n    from:\n      x: true\n    to[1]: 2\n+ /m~0: x\n"
+        );
+        assert_eq!(render_diff(&diff(&json!(1), &json!("1"))), "~ /: 1 -> \"1\"\n");
+        assert_eq!(diff(&old, &old), []);
+    }
+
+    #[test]
+    fn test_merge_and_apply_changes() {
+        let base = json!({"a": {"b": 1, "c": 2}, "d": [1, 2], "e": 3});
+        assert_eq!(merge(&base, &json!({"a": {"b": null, "x": {"y": 1}}, "d": [3], "f": null})), json!({"a": {"c": 2, "x": {"y": 1}}, "d": [3], "e": 3}));
+        assert_eq!(merge(&base, &json!([1])), json!([1]));
+        assert_eq!(merge(&json!("text"), &json!({"a": null, "b": 1})), json!({"b": 1}));
+
+        let new = json!({"a/b": [{"x": 1}, 2, 3], "d": [1], "e": {"f": true}});
+        let mut value = json!({"a/b": [{"x": 0}], "d": [1, 2, 3], "e": 3, "g": null});
+        let changes = diff(&value, &new);
+        apply_changes(&mut value, &changes).unwrap();
+        assert_eq!(value, new);
+
+        let mut root = json!(1);
+        apply_changes(&mut root, &[Change::Changed { path: String::new(), old: json!(1), new: json!([2]) }]).unwrap();
+        assert_eq!(root, json!([2]));
+        let changes = [
+            Change::Added { path: "/1".into(), value: json!(3) },
+            Change::Added { path: "/5".into(), value: json!(4) },
+        ];
+        assert_eq!(apply_changes(&mut root, &changes), Err(PatchError::new("/5", "no index `5` in an array of 2")));
+        assert_eq!(root, json!([2]));
+    }
+
+    #[test]
+    fn test_compare() {
+        let data = json!({"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Grace"}]});
+        let comparison = compare(&data, None).unwrap();
+        assert_eq!(comparison.toon, FormatSize { bytes: encode(&data, None).len(), tokens: None });
+        assert_eq!(comparison.json.bytes, data.to_string().len());
+        assert_eq!(comparison.pretty_json.bytes, serde_json::to_string_pretty(&data).unwrap().len());
+        assert_eq!(comparison.savings(), 1.0 - comparison.toon.bytes as f64 / comparison.json.bytes as f64);
+
+        let budget = EncodeOptions { max_output_bytes: Some(4), ..Default::default() };
+        assert_eq!(compare(&data, Some(budget)), Err(EncodeError::OutputTooLarge { limit: 4 }));
+
+        #[cfg(feature = "tokens")]
+        {
+            let comparison = compare_tokens(&data, None, Model::Gpt4o).unwrap();
+            let toon_tokens = comparison.toon.tokens.unwrap();
+            assert_eq!(toon_tokens, count_tokens(&encode(&data, None), Model::Gpt4o));
+            assert_eq!(comparison.savings(), 1.0 - toon_tokens as f64 / comparison.json.tokens.unwrap() as f64);
+        }
+    }
+
+    #[test]
+    fn test_encode_within_budget() {
+        let shortened = EncodeOptions { max_string_chars: Some(3), float_digits: Some(1), ..Default::default() };
+        assert_eq!(encode(&json!({"name": "Grace", "ratio": 1.2345, "id": 7}), Some(shortened)), "id: 7\nname: Gra…\nratio: 1.2");
+
+        let bytes = |text: &str| text.len();
+        let small = json!({"id": 1});
+        let fitted = encode_within_budget(&small, None, 100, &bytes).unwrap();
+        assert_eq!(fitted, Budgeted { output: "id: 1".to_string(), tokens: 5, reductions: Vec::new() });
+
+        let rows: Vec<serde_json::Value> = (0..100).map(|i| json!({"id": i, "score": i as f64 / 7.0})).collect();
+        let data = json!({"rows": rows});
+        let fitted = encode_within_budget(&data, None, 200, &bytes).unwrap();
+        assert!(fitted.tokens <= 200);
+        assert_eq!(fitted.tokens, fitted.output.len());
+        assert_eq!(
+            fitted.reductions,
+            [Reduction::DropNulls, Reduction::TruncateStrings(64), Reduction::SampleRows(RowLimit::new(8, 2)), Reduction::RoundFloats(2)]
+        );
+
+        assert_eq!(encode_within_budget(&data, None, 10, &bytes), Err(EncodeError::OverTokenBudget { limit: 10, tokens: 56 }));
+    }
+
+    #[test]
+    fn test_optimize_for_tokens() {
+        let data = json!({"config": {"server": {"port": 8080}}, "tags": ["a", "b", "c"]});
+        let bytes = |text: &str| text.len();
+
+        let options = optimize_for_tokens(&data, None, &bytes).unwrap();
+        assert_eq!((options.delimiter, options.key_folding), (Delimiter::Comma, KeyFolding::Safe));
+        assert_eq!(encode(&data, Some(options)), "config.server.port: 8080\ntags[3]: a,b,c");
+
+        let pipes_free = |text: &str| text.len() + 5 * text.matches([',', '\t']).count();
+        let pinned = EncodeOptions { spec_version: Some(SpecVersion::V1_0), indent: IndentStyle::Spaces(4), ..Default::default() };
+        let options = optimize_for_tokens(&data, Some(pinned), &pipes_free).unwrap();
+        assert_eq!((options.delimiter, options.key_folding), (Delimiter::Pipe, KeyFolding::Off));
+        assert_eq!(options.indent, IndentStyle::Spaces(4));
+    }
+
+    #[test]
+    fn test_format_instructions() {
+        let default = format_instructions(&EncodeOptions::default());
+        assert!(default.starts_with("The data below is TOON"));
+        assert!(default.contains("indented 2 spaces"));
+        assert!(default.contains("`key[N]: a,b,c` is an array of primitives separated by commas."));
+        assert!(default.contains("`key[N]{f1,f2}:` starts a table"));
+        assert!(!default.contains("@dict"));
+
+        let options = EncodeOptions {
+            delimiter: Delimiter::Pipe,
+            length_marker: Some('#'.into()),
+            key_folding: KeyFolding::Safe,
+            string_dictionary: Some(2),
+            max_rows: Some(RowLimit::new(5, 0)),
+            ..Default::default()
+        };
+        let text = format_instructions(&options);
+        assert!(text.contains("`key[#N|]: a|b|c` is an array of primitives separated by `|`."));
+        assert!(text.contains("`key[#N|]{f1|f2}:` starts a table"));
+        assert!(text.contains("`a.b: 1` are paths"));
+        assert!(text.contains("`@dict[N]:`"));
+        assert!(text.contains("`…(+K rows)`"));
+
+        let tabs = format_instructions(&EncodeOptions { delimiter: Delimiter::Tab, emit_lengths: EmitLengths::Never, ..Default::default() });
+        assert!(tabs.contains("`key[<TAB>]: a<TAB>b<TAB>c`"));
+        assert!(tabs.contains("Array headers leave out the item count."));
+    }
+
+    #[test]
+    fn test_few_shot_examples() {
+        let examples = few_shot_examples(None, &EncodeOptions::default());
+        assert_eq!(examples.len(), 4);
+        assert_eq!(examples[1].toon, "items[2]{price,qty,sku}:\n  9.99,2,A1\n  14.5,1,B2");
+        assert!(examples[2].toon.contains("delimiter: \"a,b\""));
+        for example in &examples {
+            let json: serde_json::Value = serde_json::from_str(&example.json).unwrap();
+            assert_eq!(decode(&example.toon, None).unwrap(), json);
+        }
+
+        let piped = few_shot_examples(Some(&json!({"ids": [1, 2, 3, 4, 5]})), &EncodeOptions { delimiter: Delimiter::Pipe, ..Default::default() });
+        assert!(piped[2].toon.contains("delimiter: \"a|b\""));
+        assert_eq!(piped[4], Example { description: "your data".to_string(), json: r#"{"ids":[1,2,3]}"#.to_string(), toon: "ids[3|]: 1|2|3".to_string() });
+
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer"},
+                "status": {"enum": ["open", "closed"]},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "owner": {"type": ["null", "object"], "properties": {"name": {"type": "string", "examples": ["Ada"]}}}
+            }
+        });
+        assert_eq!(
+            example_from_schema(&schema),
+            json!({"id": 1, "status": "open", "tags": ["text", "text"], "owner": {"name": "Ada"}})
+        );
+    }
+
+    #[test]
+    fn test_options_for_model() {
+        for name in ["gpt-4o", "GPT-4o-mini", "gpt-3.5-turbo", "o1-preview", "meta-llama/Llama-3.1-8B"] {
+            let options = EncodeOptions::for_model(name);
+            assert_eq!((options.delimiter, options.indent), (Delimiter::Tab, IndentStyle::Spaces(1)), "{}", name);
+        }
+        for name in ["claude-3-5-sonnet", "mistral-large", ""] {
+            let options = EncodeOptions::for_model(name);
+            assert_eq!((options.delimiter, options.indent), (Delimiter::Comma, IndentStyle::Spaces(2)), "{}", name);
+        }
+
+        let data = json!({"rows": [{"a": 1, "b": "x y"}, {"a": 2, "b": "z"}], "meta": {"page": 1}});
+        let encoded = encode(&data, Some(EncodeOptions::for_model("gpt-4o")));
+        assert_eq!(encoded, "meta:\n page: 1\nrows[2\t]{a\tb}:\n 1\tx y\n 2\tz");
+        assert_eq!(decode(&encoded, Some(DecodeOptions { indent: IndentStyle::Spaces(1), ..Default::default() })).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_csv_tables() {
+        let toon = "orders[2]:\n  - id: 1\n    lines[2]{note,qty}:\n      \"a,b\",2\n      null,1\n  - id: 2\nusers[2]{flag,name}:\n  true,\"true\"\n  false,\"\"";
+        let tables = decode_csv_tables(toon, None, CsvOptions::default()).unwrap();
+
+        assert_eq!(tables.iter().map(|table| table.path.as_str()).collect::<Vec<_>>(), ["orders[0].lines", "users"]);
+        assert_eq!(tables[0].csv, "note,qty\r\n\"a,b\",2\r\n,1\r\n");
+        assert_eq!(tables[1].csv, "flag,name\r\ntrue,\"true\"\r\nfalse,\"\"\r\n");
+        assert_eq!(from_csv(tables[1].csv.as_bytes(), CsvOptions::default()).unwrap(), decode(toon, None).unwrap()["users"]);
+
+        let options = CsvOptions { delimiter: ';', has_headers: false, ..Default::default() };
+        assert_eq!(decode_csv_tables("[2]{a,b}:\n  1,x;y\n  2,z", None, options).unwrap()[0].csv, "1;\"x;y\"\r\n2;z\r\n");
+    }
+
+    #[test]
+    fn test_to_json_string() {
+        let toon = "id: 7\ntags[2]: a,b";
+        let options = JsonOptions { indent: IndentStyle::Tabs, trailing_newline: true, ..Default::default() };
+        assert_eq!(to_json_string(toon, None, options).unwrap(), "{\n\t\"id\": 7,\n\t\"tags\": [\n\t\t\"a\",\n\t\t\"b\"\n\t]\n}\n");
+        assert_eq!(to_json_string(toon, None, JsonOptions { pretty: false, ..Default::default() }).unwrap(), r#"{"id":7,"tags":["a","b"]}"#);
+        assert!(to_json_string("a[2]: 1", None, JsonOptions::default()).is_err());
+
+        #[cfg(feature = "yaml")]
+        assert_eq!(to_yaml_string(toon, None).unwrap(), "id: 7\ntags:\n- a\n- b\n");
+    }
+
+    #[test]
+    fn test_decode_markdown() {
+        let toon = "customer:\n  name: Ada\n  tags[2]: vip,\"*new*\"\nid: 7\nnotes[2]:\n  - seen\n  - a: 1\nrows[2]{note,qty}:\n  \"two\\nlines\",2\n  null,1";
+        assert_eq!(
+            decode_markdown(toon, None).unwrap(),
+            "- **id**: 7\n\n### customer\n\n- **name**: Ada\n- **tags**: vip, \\*new\\*\n\n### notes\n\n- seen\n\n#### notes 2\n\n- **a**: 1\n\n\
+             ### rows\n\n| note | qty |\n| --- | ---: |\n| two<br>lines | 2 |\n|  | 1 |"
+        );
+        assert_eq!(to_markdown(&json!(["x", 1])), "- x\n- 1");
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "id,name,score,active,zip\r\n1,Ada,9.5,true,\"007\"\r\n2,\"Hopper, Grace\",-3,false,01234\r\n\r\n3,\"say \"\"hi\"\"\nthere\",,TRUE,\"\"\r\n";
+        let value = from_csv(csv.as_bytes(), CsvOptions::default()).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {"id": 1, "name": "Ada", "score": 9.5, "active": true, "zip": "007"},
+                {"id": 2, "name": "Hopper, Grace", "score": -3, "active": false, "zip": "01234"},
+                {"id": 3, "name": "say \"hi\"\nthere", "score": null, "active": "TRUE", "zip": ""}
+            ])
+        );
+
+        let raw = CsvOptions { delimiter: ';', has_headers: false, infer_types: false, empty_as_null: false };
+        assert_eq!(from_csv("1;;x\n2".as_bytes(), raw).unwrap(), json!([{"col1": "1", "col2": "", "col3": "x"}, {"col1": "2"}]));
+        assert_eq!(from_csv("".as_bytes(), CsvOptions::default()).unwrap(), json!([]));
+
+        let ragged = from_csv("a,b\n1,2\n3\n".as_bytes(), CsvOptions::default()).unwrap_err();
+        assert_eq!(ragged.to_string(), "line 3: expected 2 fields, found 1");
+        let unterminated = from_csv("a\n\"open\n".as_bytes(), CsvOptions::default()).unwrap_err();
+        assert_eq!(unterminated.to_string(), "line 2: unterminated quoted field");
+    }
+
+    #[test]
+    fn test_encode_columns() {
+        let names: Vec<String> = ["sku", "qty", "note", "ok"].iter().map(|name| name.to_string()).collect();
+        let columns = vec![
+            vec![json!("A1"), json!("B2"), json!("B2")],
+            vec![json!(2), json!(1.5), json!(-0.0)],
+            vec![json!("a,b"), json!(null), json!("x3")],
+            vec![json!(true), json!(false), json!(false)],
+        ];
+        let expected = |key: Option<&str>, columns: &[Vec<serde_json::Value>], options: &EncodeOptions| {
+            try_encode(&columnar::rows_value(key, &names, columns.to_vec()), Some(options.clone()))
+        };
+
+        // Written column by column, and the same as encoding the rows for the options it handles
+        let layouts = [
+            EncodeOptions::default(),
+            EncodeOptions { delimiter: Delimiter::Tab, length_marker: Some(LengthMarker::prefix("#")), ..Default::default() },
+            EncodeOptions { column_order: ColumnOrder::Explicit(vec!["qty".to_string(), "sku".to_string()]), ..Default::default() },
+            EncodeOptions { run_length: true, trailing_newline: true, line_ending: LineEnding::CrLf, ..Default::default() },
+            EncodeOptions { emit_lengths: EmitLengths::Never, indent: IndentStyle::Tabs, ..Default::default() },
+        ];
+        for options in layouts {
+            assert!(columnar::writes_directly(&names, &columns, 3, &options));
+            for key in [None, Some("items"), Some("a.b")] {
+                let output = encode_columns(key, &names, columns.clone(), Some(options.clone()));
+                assert_eq!(output, expected(key, &columns, &options), "{:?}", options);
+            }
+        }
+        assert_eq!(
+            encode_columns(Some("items"), &names, columns.clone(), None).unwrap(),
+            "items[3]{note,ok,qty,sku}:\n  \"a,b\",true,2,A1\n  null,false,1.5,B2\n  x3,false,0,B2"
+        );
+        // Names keep their order where row objects would keep theirs with `preserve_order`
+        let insertion = EncodeOptions { key_order: KeyOrder::Insertion, column_order: ColumnOrder::FirstObject, ..Default::default() };
+        let output = encode_columns(None, &names[..2], columns[..2].to_vec(), Some(insertion)).unwrap();
+        assert_eq!(output, "[3]{sku,qty}:\n  A1,2\n  B2,1.5\n  B2,0");
+
+        // Options that reshape rows, nested cells and repeated names go through row objects
+        let sorted = EncodeOptions { sort_rows: Some(SortSpec::new().asc("qty")), max_rows: Some(RowLimit::new(2, 0)), ..Default::default() };
+        assert!(!columnar::writes_directly(&names, &columns, 3, &sorted));
+        assert_eq!(encode_columns(None, &names, columns.clone(), Some(sorted.clone())), expected(None, &columns, &sorted));
+        let mut nested = columns.clone();
+        nested[2][1] = json!({"a": [1]});
+        assert_eq!(encode_columns(None, &names, nested.clone(), None), expected(None, &nested, &EncodeOptions::default()));
+        let repeated = vec!["a".to_string(), "a".to_string()];
+        let output = encode_columns(None, &repeated, vec![vec![json!(1)], vec![json!(2)]], None);
+        assert_eq!(output.unwrap(), "[1]{a}:\n  2");
+        assert_eq!(encode_columns(Some("empty"), &names, vec![Vec::new(); 4], None).unwrap(), "empty[0]:");
+
+        let invalid = EncodeOptions { indent: IndentStyle::Spaces(0), ..Default::default() };
+        let err = encode_columns(None, &names, columns, Some(invalid)).unwrap_err();
+        assert!(matches!(err, EncodeError::InvalidOption { .. }), "{}", err);
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_encode_record_batch() {
+        use arrow_array::{ArrayRef, BooleanArray, Date32Array, Float32Array, Int64Array, NullArray, RecordBatch, StringArray};
+        use std::sync::Arc;
+
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("id", Arc::new(Int64Array::from(vec![9_007_199_254_740_993, -1]))),
+            ("name", Arc::new(StringArray::from(vec![Some("a,b"), None]))),
+            ("ratio", Arc::new(Float32Array::from(vec![0.1, f32::NAN]))),
+            ("ok", Arc::new(BooleanArray::from(vec![true, false]))),
+            ("day", Arc::new(Date32Array::from(vec![0, 1]))),
+            ("gap", Arc::new(NullArray::new(2))),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+
+        let toon = encode_record_batch(&batch, None).unwrap();
+        assert_eq!(toon, "[2]{day,gap,id,name,ok,ratio}:\n  1970-01-01,null,9007199254740993,\"a,b\",true,0.1\n  1970-01-02,null,-1,null,false,null");
+
+        let options = EncodeOptions { delimiter: Delimiter::Tab, key_order: KeyOrder::Insertion, column_order: ColumnOrder::FirstObject, ..Default::default() };
+        let toon = encode_record_batch(&batch, Some(options.clone())).unwrap();
+        assert!(toon.starts_with("[2\t]{id\tname\tratio\tok\tday\tgap}:\n  9007199254740993\ta,b\t0.1"));
+        assert_eq!(decode(&toon, None).unwrap()[1]["name"], json!(null));
+
+        let empty = batch.slice(0, 0);
+        assert_eq!(encode_record_batch(&empty, None).unwrap(), "[0]{day,gap,id,name,ok,ratio}:");
+    }
+
+    #[cfg(feature = "polars")]
+    #[test]
+    fn test_encode_data_frame() {
+        use ::polars::prelude::*;
+
+        let day = Column::new("day".into(), [19_723i32, 19_724]).cast(&DataType::Date).unwrap();
+        let tag = Column::new("tag".into(), ["red", "blue"]).cast(&DataType::from_categories(Categories::global())).unwrap();
+        let id = Column::new("id".into(), [9_007_199_254_740_993i64, -1]);
+        let note = Column::new("note".into(), [Some("a,b"), None]);
+        let frame = DataFrame::new_infer_height(vec![id, note, day, tag]).unwrap();
+
+        let toon = encode_data_frame(&frame, None).unwrap();
+        assert_eq!(toon, "[2]{day,id,note,tag}:\n  2024-01-01,9007199254740993,\"a,b\",red\n  2024-01-02,-1,null,blue");
+
+        let toon = encode_data_frame_with_stats(&frame, Some(EncodeOptions { column_order: ColumnOrder::FirstObject, key_order: KeyOrder::Insertion, ..Default::default() })).unwrap();
+        let stats = toon.split_once("stats[4]{column,dtype,nulls,min,max,mean}:\n").unwrap().1;
+        assert!(stats.starts_with("  id,i64,0,-1,9007199254740993,"));
+        assert!(stats.contains("  note,str,1,\"a,b\",\"a,b\",null\n  day,date,0,2024-01-01,2024-01-02,null"));
+
+        let series = Series::new("temps".into(), [Some(1.5f64), Some(f64::NAN), None]);
+        assert_eq!(encode_series(&series, None).unwrap(), "temps[3]: 1.5,null,null");
+    }
+
+    #[cfg(feature = "avro")]
+    #[test]
+    fn test_encode_avro() {
+        use apache_avro::types::Value as Avro;
+        use apache_avro::writer::datum::GenericDatumWriter;
+        use apache_avro::{Decimal, Schema, Writer};
+
+        let schema = Schema::parse_str(r#"{"type": "record", "name": "order", "fields": [
+            {"name": "amount", "type": {"type": "bytes", "logicalType": "decimal", "precision": 8, "scale": 2}},
+            {"name": "day", "type": {"type": "int", "logicalType": "date"}},
+            {"name": "note", "type": ["null", "string"]}
+        ]}"#).unwrap();
+        let rows = [(Decimal::from(vec![0x30, 0x39]), 19723, Avro::Union(1, Box::new(Avro::String("1,2".into())))), (Decimal::from(vec![0xff]), 0, Avro::Union(0, Box::new(Avro::Null)))];
+        let records: Vec<Avro> = rows.into_iter().map(|(amount, day, note)| {
+            Avro::Record(vec![("amount".into(), Avro::Decimal(amount)), ("day".into(), Avro::Date(day)), ("note".into(), note)])
+        }).collect();
+
+        let mut writer = Writer::new(&schema, Vec::new()).unwrap();
+        for record in &records {
+            writer.append_value(record.clone()).unwrap();
+        }
+        let file = writer.into_inner().unwrap();
+        let options = EncodeOptions { type_hints: true, ..Default::default() };
+        let toon = encode_avro(&file[..], Some(options.clone())).unwrap();
+        assert_eq!(toon, "[2]{amount:decimal,day:date,note:str}:\n  123.45,2024-01-01,\"1,2\"\n  -0.01,1970-01-01,null");
+        assert_eq!(decode(&toon, None).unwrap(), json!([
+            {"amount": 123.45, "day": "2024-01-01", "note": "1,2"},
+            {"amount": -0.01, "day": "1970-01-01", "note": null},
+        ]));
+
+        let datum_writer = GenericDatumWriter::builder(&schema).build().unwrap();
+        let mut datums = Vec::new();
+        for record in &records {
+            datums.extend(datum_writer.write_value_to_vec(record.clone()).unwrap());
+        }
+        assert_eq!(encode_avro_datums(&schema, &datums[..], Some(options)).unwrap(), toon);
+        assert!(encode_avro(&b"Obj"[..], None).is_err());
+    }
+
+    #[cfg(feature = "prost-reflect")]
+    #[test]
+    fn test_encode_protobuf() {
+        use prost_reflect::prost::Message;
+        use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+        use prost_reflect::prost_types::{DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto, FileDescriptorProto};
+        use prost_reflect::{DescriptorPool, DynamicMessage, Value as Proto};
+
+        let field = |name: &str, number: i32, label: Label, kind: Type, type_name: Option<&str>| FieldDescriptorProto {
+            name: Some(name.into()),
+            number: Some(number),
+            label: Some(label as i32),
+            r#type: Some(kind as i32),
+            type_name: type_name.map(str::to_string),
+            ..Default::default()
+        };
+        let message = |name: &str, field: Vec<FieldDescriptorProto>| DescriptorProto { name: Some(name.into()), field, ..Default::default() };
+        let file = FileDescriptorProto {
+            name: Some("shop.proto".into()),
+            package: Some("shop".into()),
+            syntax: Some("proto3".into()),
+            message_type: vec![
+                message("Item", vec![
+                    field("sku", 1, Label::Optional, Type::String, None),
+                    field("qty", 2, Label::Optional, Type::Int64, None),
+                    field("status", 3, Label::Optional, Type::Enum, Some(".shop.Status")),
+                ]),
+                message("Order", vec![
+                    field("id", 1, Label::Optional, Type::String, None),
+                    field("items", 2, Label::Repeated, Type::Message, Some(".shop.Item")),
+                    field("gift", 3, Label::Optional, Type::Message, Some(".shop.Item")),
+                    field("digest", 4, Label::Optional, Type::Bytes, None),
+                ]),
+            ],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Status".into()),
+                value: ["PENDING", "SHIPPED"].iter().enumerate().map(|(number, name)| EnumValueDescriptorProto {
+                    name: Some(name.to_string()),
+                    number: Some(number as i32),
+                    ..Default::default()
+                }).collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let pool = DescriptorPool::from_file_descriptor_set(prost_reflect::prost_types::FileDescriptorSet { file: vec![file] }).unwrap();
+        let (order_type, item_type) = (pool.get_message_by_name("shop.Order").unwrap(), pool.get_message_by_name("shop.Item").unwrap());
+
+        let items = [("A1", 2, 1), ("B2", 0, 0)].map(|(sku, qty, status)| {
+            let mut item = DynamicMessage::new(item_type.clone());
+            item.set_field_by_name("sku", Proto::String(sku.into()));
+            item.set_field_by_name("qty", Proto::I64(qty));
+            item.set_field_by_name("status", Proto::EnumNumber(status));
+            Proto::Message(item)
+        });
+        let mut order = DynamicMessage::new(order_type.clone());
+        order.set_field_by_name("id", Proto::String("o-1".into()));
+        order.set_field_by_name("items", Proto::List(items.to_vec()));
+        order.set_field_by_name("digest", Proto::Bytes(vec![1, 2, 3].into()));
+
+        assert_eq!(
+            from_dynamic_message(&order),
+            json!({"id": "o-1", "items": [{"sku": "A1", "qty": 2, "status": "SHIPPED"}, {"sku": "B2", "qty": 0, "status": "PENDING"}], "gift": null, "digest": "AQID"})
+        );
+        let toon = encode_protobuf(order_type.clone(), &order.encode_to_vec(), None).unwrap();
+        assert_eq!(toon, "digest: AQID\ngift: null\nid: o-1\nitems[2]{qty,sku,status}:\n  2,A1,SHIPPED\n  0,B2,PENDING");
+        assert!(encode_protobuf(order_type, &[0x0a, 0x05], None).is_err());
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_input() {
+        // 55799({"at": 0("2026-01-01T00:00:00Z"), "big": 2(h'010000000000000000'), 7: 37(h'00'), "n": NaN})
+        let mut bytes = vec![0xd9, 0xd9, 0xf7, 0xa4];
+        bytes.extend([0x62, b'a', b't', 0xc0, 0x74]);
+        bytes.extend(b"2026-01-01T00:00:00Z");
+        bytes.extend([0x63, b'b', b'i', b'g', 0xc2, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]);
+        bytes.extend([0x07, 0xd8, 0x25, 0x41, 0x00]);
+        bytes.extend([0x61, b'n', 0xf9, 0x7e, 0x00]);
+
+        let value = from_cbor(&bytes).unwrap();
+        assert_eq!(
+            value,
+            json!({"at": "2026-01-01T00:00:00Z", "big": "18446744073709551616", "7": {"!tag:37": "AA=="}, "n": null})
+        );
+        assert_eq!(encode_cbor(&[0x82, 0x01, 0x20], None).unwrap(), "[2]: 1,-1");
+        assert!(from_cbor(&[0x82, 0x01]).is_err());
+        assert!(from_cbor(&[0x01, 0x02]).is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_input() {
+        // {1: "one", "data": [<ext 5: ff>, 1.5, nil], "raw": <bytes 68 69>}
+        let bytes = [
+            0x83, 0x01, 0xa3, b'o', b'n', b'e',
+            0xa4, b'd', b'a', b't', b'a', 0x93, 0xd4, 0x05, 0xff, 0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0, 0xc0,
+            0xa3, b'r', b'a', b'w', 0xc4, 0x02, b'h', b'i',
+        ];
+        let value = from_msgpack(&bytes).unwrap();
+        assert_eq!(value, json!({"1": "one", "data": [{"!ext:5": "/w=="}, 1.5, null], "raw": "aGk="}));
+        assert_eq!(encode_msgpack(&[0x92, 0x01, 0x02], None).unwrap(), "[2]: 1,2");
+        assert!(from_msgpack(&[0x92, 0x01]).is_err());
+        assert!(from_msgpack(&[0x01, 0x02]).is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_yaml_input() {
+        let yaml = "defaults: &defaults\n  retries: 3\n  timeout: 1.5\nservice:\n  <<: *defaults\n  name: api\n  ports: [80, 443]\n  1: one\n  true: yes\n  ~: nothing\n  ref: !Ref bucket\n  ratio: .nan\n";
+        let value = from_yaml_str(yaml).unwrap();
+        assert_eq!(
+            value["service"],
+            json!({"retries": 3, "timeout": 1.5, "name": "api", "ports": [80, 443], "1": "one", "true": "yes", "null": "nothing", "ref": {"!Ref": "bucket"}, "ratio": null})
+        );
+
+        let parsed: serde_yaml::Value = serde_yaml::from_str("[{id: 1}, {id: 2}]").unwrap();
+        assert_eq!(encode_yaml(&parsed, None), "[2]{id}:\n  1\n  2");
+        assert!(from_yaml_str("a: [").is_err());
+
+        let comparison = compare(&value, None).unwrap();
+        assert_eq!(comparison.yaml.unwrap().bytes, serde_yaml::to_string(&value).unwrap().len());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml_input() {
+        let toml = "[package]\nname = \"toon\"\nedition = 2021\nratio = nan\nreleased = 1979-05-27T07:32:00Z\nbuilt = 07:32:00\n\n[[package.authors]]\nname = \"Ada\"\n\n[[package.authors]]\nname = \"Grace\"\n";
+        let value = from_toml_str(toml).unwrap();
+        assert_eq!(
+            value,
+            json!({"package": {
+                "name": "toon", "edition": 2021, "ratio": null, "released": "1979-05-27T07:32:00Z", "built": "07:32:00",
+                "authors": [{"name": "Ada"}, {"name": "Grace"}]
+            }})
+        );
+        assert!(encode(&value, None).contains("authors[2]{name}:"));
+
+        let parsed: ::toml::Value = ::toml::from_str("ports = [80, 443]").unwrap();
+        assert_eq!(encode_toml(&parsed, None), "ports[2]: 80,443");
+        assert!(from_toml_str("a = ").is_err());
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_json5_input() {
+        let input = "/* users */ {\n  users: [\n    {id: 1, name: 'Ada', score: +.5,},\n    {id: 0x2, name: \"Grace\", score: Infinity},\n  ],\n}";
+        let value = from_json5_str(input).unwrap();
+        assert_eq!(value, json!({"users": [{"id": 1, "name": "Ada", "score": 0.5}, {"id": 2, "name": "Grace", "score": null}]}));
+        assert_eq!(encode(&value, None), "users[2]{id,name,score}:\n  1,Ada,0.5\n  2,Grace,null");
+        assert!(from_json5_str("{a: }").is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_length_mismatch() {
+        let err = decode("tags[3]: a,b", None).unwrap_err();
+        assert_eq!(err.line, 1);
+        let lenient = DecodeOptions {
+            strict: false,
+            ..Default::default()
+        };
+        assert_eq!(decode("tags[3]: a,b", Some(lenient)).unwrap(), json!({"tags": ["a", "b"]}));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi() {
+        use std::ffi::{CStr, CString};
+        use std::ptr;
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            let json = CString::new(r#"{"items":[{"id":1,"name":"Ada"},{"id":2,"name":"Bo"}]}"#).unwrap();
+            let toon = ffi::toon_encode(json.as_ptr(), &mut error);
+            assert!(error.is_null());
+            assert_eq!(CStr::from_ptr(toon).to_str().unwrap(), "items[2]{id,name}:\n  1,Ada\n  2,Bo");
+
+            let decoded = ffi::toon_decode(toon, &mut error);
+            assert_eq!(CStr::from_ptr(decoded).to_str().unwrap(), r#"{"items":[{"id":1,"name":"Ada"},{"id":2,"name":"Bo"}]}"#);
+            ffi::toon_free(toon);
+            ffi::toon_free(decoded);
+
+            let malformed = CString::new("a[2]: 1").unwrap();
+            assert!(ffi::toon_decode(malformed.as_ptr(), &mut error).is_null());
+            assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "line 1: array declares 2 items but contains 1");
+            ffi::toon_free(error);
+
+            assert!(ffi::toon_encode(ptr::null(), ptr::null_mut()).is_null());
+            ffi::toon_free(ptr::null_mut());
+        }
     }
 }
@@ -0,0 +1,343 @@
+use crate::prelude::*;
+use core::fmt;
+use crate::decoder::{decode_document, is_list_item, is_row, parse_entry, parse_number, parse_omitted_rows, split_documents, split_values, Entry};
+use crate::primitives::{escape_string, is_safe_unquoted, split_run_suffix, DOCUMENT_SEPARATOR};
+use crate::types::{DecodeOptions, Delimiter, IndentStyle};
+
+/// A check run by [`lint`](crate::lint), named by its rule ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rule {
+    /// `length-mismatch`: an array's declared length differs from its items, or a row from its header
+    LengthMismatch,
+    /// `indentation`: indentation that isn't a whole number of levels, or that skips a level
+    Indentation,
+    /// `duplicate-field`: a table header names the same field twice
+    DuplicateField,
+    /// `unquoted-value`: a bare value that only reads as a string because it can't be anything
+    /// else, such as `05` or `a:b`
+    UnquotedValue,
+    /// `syntax`: anything else strict decoding rejects
+    Syntax,
+}
+
+impl Rule {
+    /// Every rule
+    pub const ALL: [Rule; 5] = [Rule::LengthMismatch, Rule::Indentation, Rule::DuplicateField, Rule::UnquotedValue, Rule::Syntax];
+
+    /// The rule ID, such as `length-mismatch`
+    pub fn id(&self) -> &'static str {
+        match self {
+            Rule::LengthMismatch => "length-mismatch",
+            Rule::Indentation => "indentation",
+            Rule::DuplicateField => "duplicate-field",
+            Rule::UnquotedValue => "unquoted-value",
+            Rule::Syntax => "syntax",
+        }
+    }
+
+    /// The rule with ID `id`
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+
+    /// Severity when [`LintOptions::rules`] doesn't set one
+    ///
+    /// Only `unquoted-value` is a warning: such documents still decode the same everywhere.
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            Rule::UnquotedValue => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// How serious a [`Diagnostic`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem found by [`lint`](crate::lint), at a 1-based line and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}[{}]: {}", self.line, self.column, self.severity, self.rule, self.message)
+    }
+}
+
+/// Options for [`lint`](crate::lint)
+#[derive(Debug, Clone, Default)]
+pub struct LintOptions {
+    /// Indentation the document is expected to use
+    pub indent: IndentStyle,
+    /// Count `value xN` runs as N items, as `DecodeOptions::run_length` reads them
+    pub run_length: bool,
+    /// Severity per rule in place of its default; `None` turns the rule off
+    pub rules: HashMap<Rule, Option<Severity>>,
+}
+
+impl LintOptions {
+    /// The severity `rule` reports at, or `None` when it is turned off
+    pub fn severity(&self, rule: Rule) -> Option<Severity> {
+        self.rules.get(&rule).copied().unwrap_or(Some(rule.default_severity()))
+    }
+}
+
+/// A non-blank line with its depth counted in whole or partial indentation levels
+struct Line<'a> {
+    number: usize,
+    depth: usize,
+    raw: &'a str,
+    content: &'a str,
+}
+
+impl Line<'_> {
+    /// 1-based column where `text`, a slice of this line, starts
+    fn column(&self, text: &str) -> usize {
+        text.as_ptr() as usize - self.raw.as_ptr() as usize + 1
+    }
+}
+
+/// How earlier lines claimed a line: a table row `width` values wide, or the continuation of a
+/// wrapped inline array
+#[derive(Clone, Copy)]
+enum Claim {
+    Row { delimiter: Delimiter, width: usize },
+    Continuation,
+}
+
+struct Linter<'a> {
+    lines: Vec<Line<'a>>,
+    claims: Vec<Option<Claim>>,
+    found: Vec<(Rule, usize, usize, String)>,
+    run_length: bool,
+}
+
+impl<'a> Linter<'a> {
+    fn report(&mut self, rule: Rule, line: usize, column: usize, message: String) {
+        self.found.push((rule, line, column, message));
+    }
+
+    /// Split `text` into lines, reporting indentation that isn't whole levels or skips one
+    fn scan(&mut self, text: &'a str, first_line: usize, indent: &IndentStyle) {
+        let unit = indent.unit();
+        let expected = match indent {
+            IndentStyle::Spaces(n) => format!("{} spaces", n),
+            IndentStyle::Tabs => "tabs".to_string(),
+            IndentStyle::Custom(s) => format!("{:?}", s),
+        };
+        let mut previous = 0;
+        for (index, raw) in text.split('\n').enumerate() {
+            let number = first_line + index + 1;
+            let raw = raw.strip_suffix('\r').unwrap_or(raw);
+            if raw.trim().is_empty() {
+                continue;
+            }
+            let mut depth = 0;
+            let mut rest = raw;
+            while let Some(after) = rest.strip_prefix(unit.as_str()).filter(|_| !unit.is_empty()) {
+                depth += 1;
+                rest = after;
+            }
+            let content = rest.trim_start_matches([' ', '\t']);
+            if content.len() != rest.len() {
+                self.report(Rule::Indentation, number, 1, format!("indentation must be a multiple of {}", expected));
+                depth += 1;
+            } else if depth > previous + 1 {
+                let message = format!("indented {} levels past the line above; expected at most one", depth - previous);
+                self.report(Rule::Indentation, number, 1, message);
+            }
+            // Fields of a list item sit a level past its hyphen
+            previous = depth + usize::from(is_list_item(content));
+            self.lines.push(Line { number, depth, raw, content: content.trim_end_matches(' ') });
+        }
+        self.claims = vec![None; self.lines.len()];
+    }
+
+    /// Report an unquoted `token` that decodes to a string only because nothing else fits
+    fn check_token(&mut self, index: usize, token: &str, delimiter: Delimiter) {
+        if token.is_empty() || token.starts_with(['"', '[', '{']) || matches!(token, "true" | "false" | "null") {
+            return;
+        }
+        if parse_number(token).is_some() || is_safe_unquoted(token, &delimiter) {
+            return;
+        }
+        let line = &self.lines[index];
+        let (number, column) = (line.number, line.column(token));
+        self.report(Rule::UnquotedValue, number, column, format!("`{}` should be quoted as \"{}\"", token, escape_string(token)));
+    }
+
+    /// Check each of `tokens`, returning how many items they stand for
+    fn check_tokens(&mut self, index: usize, tokens: &[&str], delimiter: Delimiter) -> usize {
+        let mut count = 0;
+        for token in tokens {
+            let (token, n) = self.run(token);
+            self.check_token(index, token, delimiter);
+            count += n;
+        }
+        count
+    }
+
+    /// `token` without its ` xN` run suffix, and N
+    fn run<'t>(&self, token: &'t str) -> (&'t str, usize) {
+        match split_run_suffix(token) {
+            Some(run) if self.run_length => run,
+            _ => (token, 1),
+        }
+    }
+
+    /// Count the items below the array header on line `index`, claiming table rows as it goes
+    fn count_children(&mut self, index: usize, delimiter: Delimiter, width: Option<usize>) -> usize {
+        let depth = self.lines[index].depth;
+        let Some(child) = self.lines.get(index + 1).map(|line| line.depth).filter(|&child| child > depth) else { return 0 };
+        let mut count = 0;
+        for next in index + 1..self.lines.len() {
+            let line = &self.lines[next];
+            if line.depth <= depth {
+                break;
+            }
+            if line.depth != child {
+                continue;
+            }
+            if let Some(omitted) = parse_omitted_rows(line.content) {
+                count += omitted;
+                continue;
+            }
+            match width {
+                Some(width) if is_row(line.content, delimiter) => {
+                    self.claims[next] = Some(Claim::Row { delimiter, width });
+                    count += self.run(line.content).1;
+                }
+                // Shared `name: value` columns may come before a table's rows
+                Some(_) if count == 0 => {}
+                None if is_list_item(line.content) => count += 1,
+                _ => break,
+            }
+        }
+        count
+    }
+
+    /// Check the values of an inline array, following its continuation lines
+    fn inline_count(&mut self, index: usize, rest: &'a str, delimiter: Delimiter) -> usize {
+        let mut tokens = split_values(rest, delimiter);
+        let mut count = self.check_tokens(index, &tokens, delimiter);
+        // Wrapped values continue one level in
+        let depth = self.lines[index].depth + 1;
+        let mut next = index + 1;
+        while tokens.len() > 1 && tokens.last() == Some(&"") {
+            let Some(line) = self.lines.get(next) else { break };
+            if line.depth != depth || is_list_item(line.content) || !is_row(line.content, delimiter) {
+                break;
+            }
+            self.claims[next] = Some(Claim::Continuation);
+            // The empty token after the previous line's trailing delimiter counted as one
+            tokens = split_values(line.content, delimiter);
+            count += self.check_tokens(next, &tokens, delimiter) - 1;
+            next += 1;
+        }
+        count
+    }
+
+    fn check_line(&mut self, index: usize) {
+        let line = &self.lines[index];
+        let (number, raw, whole) = (line.number, line.raw, line.content);
+        match self.claims[index] {
+            Some(Claim::Row { delimiter, width }) => {
+                let cells = split_values(self.run(whole).0, delimiter);
+                if cells.len() != width {
+                    let message = format!("row has {} values but the header declares {} fields", cells.len(), width);
+                    self.report(Rule::LengthMismatch, number, raw.len() - raw.trim_start().len() + 1, message);
+                }
+                self.check_tokens(index, &cells, delimiter);
+                return;
+            }
+            Some(Claim::Continuation) => return,
+            None => {}
+        }
+        if whole == DOCUMENT_SEPARATOR || whole == "-" || parse_omitted_rows(whole).is_some() {
+            return;
+        }
+        let content = whole.strip_prefix("- ").unwrap_or(whole);
+        match parse_entry(content, number) {
+            // Tabs always force quotes, so a tab stands in for having no delimiter
+            Ok(Some(Entry::Field { rest, .. })) => self.check_token(index, rest, Delimiter::Tab),
+            Ok(Some(Entry::Array { key, header, rest })) => {
+                let column = self.lines[index].column(content);
+                let fields = header.fields.as_deref().unwrap_or_default();
+                for (i, field) in fields.iter().enumerate() {
+                    if fields[..i].iter().any(|earlier| earlier.name == field.name) {
+                        self.report(Rule::DuplicateField, number, column, format!("field `{}` appears more than once in the header", field.name));
+                    }
+                }
+                let count = match rest.is_empty() {
+                    true => self.count_children(index, header.delimiter, header.fields.as_ref().map(Vec::len)),
+                    false => self.inline_count(index, rest, header.delimiter),
+                };
+                if let (Some(length), None) = (header.length, header.chunk_start) {
+                    if length != count {
+                        let name = key.map_or_else(|| "array".to_string(), |key| format!("`{}`", key.name));
+                        self.report(Rule::LengthMismatch, number, column, format!("{} declares {} items but has {}", name, length, count));
+                    }
+                }
+            }
+            // A bare list item or root value
+            Ok(None) => self.check_token(index, content, Delimiter::Tab),
+            Err(_) => {}
+        }
+    }
+}
+
+/// Diagnostics for every `---`-separated document of `input`, ordered by position
+pub fn lint_documents(input: &str, options: &LintOptions) -> Vec<Diagnostic> {
+    let decode = DecodeOptions { indent: options.indent.clone(), strict: true, run_length: options.run_length, ..Default::default() };
+    let mut found = Vec::new();
+    for (first_line, text) in split_documents(input) {
+        let mut linter = Linter { lines: Vec::new(), claims: Vec::new(), found: Vec::new(), run_length: options.run_length };
+        linter.scan(text, first_line, &options.indent);
+        for index in 0..linter.lines.len() {
+            linter.check_line(index);
+        }
+        // Strict decoding catches what the rules above don't, once per line at most
+        if let Err(err) = decode_document(text, &decode) {
+            let number = err.line + first_line;
+            if !linter.found.iter().any(|(_, line, _, _)| *line == number) {
+                let raw = text.split('\n').nth(err.line.wrapping_sub(1)).unwrap_or_default();
+                linter.report(Rule::Syntax, number, raw.len() - raw.trim_start().len() + 1, err.message);
+            }
+        }
+        found.append(&mut linter.found);
+    }
+    let mut diagnostics: Vec<Diagnostic> = found
+        .into_iter()
+        .filter_map(|(rule, line, column, message)| {
+            let severity = options.severity(rule)?;
+            Some(Diagnostic { rule, severity, line, column, message })
+        })
+        .collect();
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
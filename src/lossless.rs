@@ -0,0 +1,78 @@
+use crate::prelude::*;
+use crate::error::EncodeError;
+use crate::types::{EncodeOptions, MissingCell};
+use serde_json::{Number, Value};
+
+/// Largest integer magnitude an f64 holds exactly (2^53)
+const MAX_SAFE_INTEGER: u64 = 1 << 53;
+
+/// First reason encoding `value` with `options` would not decode back to the same value
+pub fn find_loss(value: &Value, options: &EncodeOptions) -> Option<EncodeError> {
+    if let Some(option) = lossy_option(options) {
+        return Some(EncodeError::Lossy { path: String::new(), reason: format!("`{}` discards information", option) });
+    }
+
+    let mut stack = vec![(value, String::new())];
+    while let Some((value, path)) = stack.pop() {
+        match value {
+            Value::Number(n) => {
+                if let Some(reason) = lossy_number(n) {
+                    return Some(EncodeError::Lossy { path, reason });
+                }
+            }
+            Value::Array(arr) => {
+                stack.extend(arr.iter().enumerate().rev().map(|(i, v)| (v, format!("{}[{}]", path, i))));
+            }
+            Value::Object(obj) => {
+                stack.extend(obj.iter().rev().map(|(k, v)| {
+                    let child = if path.is_empty() { k.clone() } else { format!("{}.{}", path, k) };
+                    (v, child)
+                }));
+            }
+            Value::Null | Value::Bool(_) | Value::String(_) => {}
+        }
+    }
+    None
+}
+
+/// Name of an enabled option that drops or merges data
+fn lossy_option(options: &EncodeOptions) -> Option<&'static str> {
+    if options.max_depth.is_some() {
+        Some("max_depth")
+    } else if options.max_rows.is_some() {
+        Some("max_rows")
+    } else if options.summarize.is_some() {
+        Some("summarize")
+    } else if options.max_string_chars.is_some() {
+        Some("max_string_chars")
+    } else if options.float_digits.is_some() {
+        Some("float_digits")
+    } else if options.skip_nulls {
+        Some("skip_nulls")
+    } else if options.skip_empty {
+        Some("skip_empty")
+    } else if !options.column_aliases.is_empty() && !options.alias_legend {
+        Some("column_aliases without alias_legend")
+    } else if options.tabular_union_threshold.is_some() && options.missing_cell == MissingCell::Null {
+        Some("tabular_union_threshold with MissingCell::Null")
+    } else {
+        None
+    }
+}
+
+/// Why a number would change on the way through the f64 encoder, if it would
+fn lossy_number(n: &Number) -> Option<String> {
+    if let Some(i) = n.as_i64() {
+        return (i.unsigned_abs() > MAX_SAFE_INTEGER).then(|| format!("integer {} is beyond 2^53 and would lose precision", i));
+    }
+    if let Some(u) = n.as_u64() {
+        return (u > MAX_SAFE_INTEGER).then(|| format!("integer {} is beyond 2^53 and would lose precision", u));
+    }
+    match n.as_f64() {
+        Some(f) if !f.is_finite() => Some(format!("{} is not finite and would be written as null", f)),
+        Some(f) if f == 0.0 && f.is_sign_negative() => Some("-0 would be written as 0".to_string()),
+        Some(f) if f.fract() == 0.0 => Some(format!("float {:?} would decode as an integer", f)),
+        Some(_) => None,
+        None => Some(format!("{} has no f64 representation and would be written as null", n)),
+    }
+}
@@ -0,0 +1,120 @@
+use crate::prelude::*;
+use crate::csv::{is_table, table_columns};
+use serde_json::Value;
+
+/// Heading level of top-level sections, small enough for issue comments
+const FIRST_LEVEL: usize = 3;
+
+/// Render a value as GitHub-flavored Markdown
+///
+/// Tables become Markdown tables, with all-number columns right-aligned. The primitive fields
+/// of an object become a definition list of `- **key**: value` items, and its other fields
+/// follow under their own headings. Other arrays list primitives as bullets and give each
+/// container item a numbered heading.
+pub fn render(value: &Value) -> String {
+    let mut blocks = Vec::new();
+    // The root has no heading, so its fields start at the first level
+    section(value, None, FIRST_LEVEL - 1, &mut blocks);
+    blocks.join("\n\n")
+}
+
+fn section(value: &Value, title: Option<&str>, level: usize, blocks: &mut Vec<String>) {
+    if let Some(title) = title {
+        blocks.push(heading(title, level));
+    }
+    match value {
+        Value::Object(map) => {
+            let terms: Vec<String> = map.iter().filter_map(|(key, value)| {
+                inline_text(value).map(|text| format!("- **{}**: {}", escape(key), text))
+            }).collect();
+            if !terms.is_empty() {
+                blocks.push(terms.join("\n"));
+            }
+            for (key, value) in map.iter().filter(|(_, value)| inline_text(value).is_none()) {
+                section(value, Some(key), level + 1, blocks);
+            }
+        }
+        Value::Array(items) if is_table(items) => blocks.push(table(items)),
+        Value::Array(items) if items.iter().all(is_primitive) => {
+            if !items.is_empty() {
+                blocks.push(items.iter().map(|item| format!("- {}", primitive_text(item))).collect::<Vec<_>>().join("\n"));
+            }
+        }
+        Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                let item_title = format!("{} {}", title.unwrap_or("Item"), index + 1);
+                match item {
+                    Value::Object(_) | Value::Array(_) => section(item, Some(&item_title), level + 1, blocks),
+                    _ => blocks.push(format!("- {}", primitive_text(item))),
+                }
+            }
+        }
+        primitive => blocks.push(primitive_text(primitive)),
+    }
+}
+
+/// Headings past `######` fall back to bold text
+fn heading(title: &str, level: usize) -> String {
+    match level {
+        1..=6 => format!("{} {}", "#".repeat(level), escape(title)),
+        _ => format!("**{}**", escape(title)),
+    }
+}
+
+fn table(rows: &[Value]) -> String {
+    let columns = table_columns(rows);
+    let cell = |row: &Value, column: &str| match row.get(column) {
+        None | Some(Value::Null) => String::new(),
+        Some(value) => primitive_text(value).replace('|', "\\|"),
+    };
+    let numeric = |column: &str| {
+        let mut cells = rows.iter().filter_map(|row| row.get(column)).filter(|value| !value.is_null()).peekable();
+        cells.peek().is_some() && cells.all(Value::is_number)
+    };
+
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    lines.push(format!("| {} |", columns.iter().map(|name| escape(name).replace('|', "\\|")).collect::<Vec<_>>().join(" | ")));
+    lines.push(format!("|{}|", columns.iter().map(|name| if numeric(name) { " ---: " } else { " --- " }).collect::<Vec<_>>().join("|")));
+    for row in rows {
+        lines.push(format!("| {} |", columns.iter().map(|name| cell(row, name)).collect::<Vec<_>>().join(" | ")));
+    }
+    lines.join("\n")
+}
+
+fn is_primitive(value: &Value) -> bool {
+    !value.is_array() && !value.is_object()
+}
+
+/// Text for a definition list item: a primitive, or primitives joined with commas
+fn inline_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Array(items) if items.iter().all(is_primitive) => Some(items.iter().map(primitive_text).collect::<Vec<_>>().join(", ")),
+        Value::Object(_) | Value::Array(_) => None,
+        primitive => Some(primitive_text(primitive)),
+    }
+}
+
+/// A primitive as it reads in prose: strings without quotes, line breaks as `<br>`
+fn primitive_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => escape(s),
+        other => other.to_string(),
+    }
+}
+
+/// Escape characters that would start inline markup or break a block
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' => {}
+            '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
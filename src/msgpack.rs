@@ -0,0 +1,54 @@
+use crate::binary::base64;
+use crate::stack::nested;
+use rmpv::decode::{read_value, Error};
+use rmpv::Value as MsgPack;
+use serde_json::{Map, Value};
+use std::io;
+
+/// Convert a MessagePack value to JSON
+///
+/// Binary data becomes a base64 string, an extension value becomes `{"!ext:<type>": base64}`,
+/// map keys that aren't strings become their JSON text, strings that aren't valid UTF-8 are
+/// read lossily and NaN or infinite floats become null.
+pub fn msgpack_to_json(value: &MsgPack) -> Value {
+    match value {
+        MsgPack::Nil => Value::Null,
+        MsgPack::Boolean(b) => Value::Bool(*b),
+        MsgPack::Integer(n) => match n.as_i64() {
+            Some(i) => Value::from(i),
+            None => n.as_u64().map_or(Value::Null, Value::from),
+        },
+        MsgPack::F32(f) => float(*f as f64),
+        MsgPack::F64(f) => float(*f),
+        MsgPack::String(s) => Value::String(s.as_str().map_or_else(|| String::from_utf8_lossy(s.as_bytes()).into_owned(), str::to_string)),
+        MsgPack::Binary(bytes) => Value::String(base64(bytes)),
+        MsgPack::Array(items) => Value::Array(items.iter().map(|v| nested(|| msgpack_to_json(v))).collect()),
+        MsgPack::Map(entries) => Value::Object(entries.iter().map(|(k, v)| (key_text(k), nested(|| msgpack_to_json(v)))).collect()),
+        MsgPack::Ext(tag, bytes) => {
+            let mut object = Map::new();
+            object.insert(format!("!ext:{}", tag), Value::String(base64(bytes)));
+            Value::Object(object)
+        }
+    }
+}
+
+fn float(f: f64) -> Value {
+    serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn key_text(key: &MsgPack) -> String {
+    match msgpack_to_json(key) {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+/// Decode exactly one MessagePack value from `bytes` and convert it to JSON
+pub fn parse_msgpack(mut bytes: &[u8]) -> Result<Value, Error> {
+    let value = read_value(&mut bytes)?;
+    if !bytes.is_empty() {
+        let message = format!("{} trailing bytes after the MessagePack value", bytes.len());
+        return Err(Error::InvalidDataRead(io::Error::new(io::ErrorKind::InvalidData, message)));
+    }
+    Ok(msgpack_to_json(&value))
+}
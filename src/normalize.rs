@@ -1,9 +1,15 @@
-use crate::types::{JsonPrimitive, JsonValue};
-use serde_json::Value;
-use std::collections::HashMap;
+use crate::prelude::*;
+use crate::stack::nested;
+use crate::types::{JsonObject, JsonPrimitive, JsonValue, KeyOrder};
+#[cfg(feature = "arena")]
+use crate::types::Nodes;
+#[cfg(feature = "arena")]
+use bumpalo::Bump;
+use serde_json::{Map, Value};
+use alloc::borrow::Cow;
 
-/// Convert serde_json::Value to JsonValue
-pub fn normalize_value(value: &Value) -> JsonValue {
+/// Convert serde_json::Value to JsonValue, putting object keys in `key_order` once up front
+pub fn normalize_value(value: &Value, key_order: KeyOrder) -> JsonValue<'_> {
     match value {
         Value::Null => JsonValue::Primitive(JsonPrimitive::Null),
         Value::Bool(b) => JsonValue::Primitive(JsonPrimitive::Boolean(*b)),
@@ -25,19 +31,139 @@ pub fn normalize_value(value: &Value) -> JsonValue {
                 JsonValue::Primitive(JsonPrimitive::Null)
             }
         }
-        Value::String(s) => JsonValue::Primitive(JsonPrimitive::String(s.clone())),
+        Value::String(s) => JsonValue::Primitive(JsonPrimitive::String(Cow::Borrowed(s))),
         Value::Array(arr) => {
-            let normalized: Vec<JsonValue> = arr.iter().map(normalize_value).collect();
-            JsonValue::Array(normalized)
+            JsonValue::Array(arr.iter().map(|v| nested(|| normalize_value(v, key_order))).collect())
         }
-        Value::Object(obj) => {
-            let mut map = HashMap::new();
-            for (k, v) in obj.iter() {
-                map.insert(k.clone(), normalize_value(v));
+        Value::Object(obj) => JsonValue::Object(normalize_object(obj, key_order)),
+    }
+}
+
+/// Convert the fields of a serde_json object
+pub fn normalize_object(obj: &Map<String, Value>, key_order: KeyOrder) -> JsonObject<'_> {
+    let mut fields: Vec<_> = obj.iter().map(|(k, v)| (Cow::Borrowed(k.as_str()), nested(|| normalize_value(v, key_order)))).collect();
+    if key_order == KeyOrder::Alphabetical {
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    fields.into()
+}
+
+/// Convert serde_json::Value to JsonValue like `normalize_value`, allocating every container in `bump`
+///
+/// The tree holds nothing else that needs freeing, so dropping it walks nothing and the arena
+/// releases it in one go.
+#[cfg(feature = "arena")]
+pub fn normalize_value_in<'a>(value: &'a Value, key_order: KeyOrder, bump: &'a Bump) -> JsonValue<'a> {
+    match value {
+        Value::Array(arr) => {
+            JsonValue::Array(Nodes::Arena(bump.alloc_slice_fill_iter(arr.iter().map(|v| nested(|| normalize_value_in(v, key_order, bump))))))
+        }
+        Value::Object(obj) => JsonValue::Object(normalize_object_in(obj, key_order, bump)),
+        _ => normalize_value(value, key_order),
+    }
+}
+
+/// Convert the fields of a serde_json object into `bump`
+#[cfg(feature = "arena")]
+pub fn normalize_object_in<'a>(obj: &'a Map<String, Value>, key_order: KeyOrder, bump: &'a Bump) -> JsonObject<'a> {
+    let fields =
+        bump.alloc_slice_fill_iter(obj.iter().map(|(k, v)| (Cow::Borrowed(k.as_str()), nested(|| normalize_value_in(v, key_order, bump)))));
+    if key_order == KeyOrder::Alphabetical {
+        fields.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+    Nodes::Arena(fields)
+}
+
+/// Whether `value` holds more than `limit` values, counting containers and primitives alike
+pub fn exceeds_node_count(value: &Value, limit: usize) -> bool {
+    let mut count = 0;
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        count += 1;
+        if count > limit {
+            return true;
+        }
+        match value {
+            Value::Array(arr) => stack.extend(arr),
+            Value::Object(obj) => stack.extend(obj.values()),
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Look up a field of an object by key
+pub fn get_field<'v, 'a>(obj: &'v JsonObject<'a>, key: &str) -> Option<&'v JsonValue<'a>> {
+    obj.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Replace non-empty containers nested more than `levels` below the root with placeholders
+pub fn truncate_value<'a>(value: &JsonValue<'a>, levels: usize) -> JsonValue<'a> {
+    match value {
+        JsonValue::Array(arr) if levels == 0 && !arr.is_empty() => JsonValue::Primitive(JsonPrimitive::Raw("[…]".to_string())),
+        JsonValue::Object(obj) if levels == 0 && !obj.is_empty() => JsonValue::Primitive(JsonPrimitive::Raw("{…}".to_string())),
+        JsonValue::Array(arr) => JsonValue::Array(arr.iter().map(|v| nested(|| truncate_value(v, levels - 1))).collect()),
+        JsonValue::Object(obj) => {
+            JsonValue::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| truncate_value(v, levels - 1)))).collect())
+        }
+        JsonValue::Primitive(_) => value.clone(),
+    }
+}
+
+/// Cut strings longer than `max_chars` characters and round numbers to `digits` decimal places
+pub fn shorten_primitives<'a>(value: &JsonValue<'a>, max_chars: Option<usize>, digits: Option<usize>) -> JsonValue<'a> {
+    match value {
+        JsonValue::Array(arr) => JsonValue::Array(arr.iter().map(|v| nested(|| shorten_primitives(v, max_chars, digits))).collect()),
+        JsonValue::Object(obj) => {
+            JsonValue::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| shorten_primitives(v, max_chars, digits)))).collect())
+        }
+        JsonValue::Primitive(JsonPrimitive::String(s)) => match max_chars.and_then(|max| s.char_indices().nth(max)) {
+            Some((cut, _)) => JsonValue::Primitive(JsonPrimitive::String(Cow::Owned(format!("{}…", &s[..cut])))),
+            None => value.clone(),
+        },
+        JsonValue::Primitive(JsonPrimitive::Number(n)) => match digits {
+            Some(digits) => {
+                let scale = 10f64.powi(digits.min(i32::MAX as usize) as i32);
+                let rounded = (n * scale).round() / scale;
+                // Rounding very large numbers overflows the scaled value; keep those as they are
+                JsonValue::Primitive(JsonPrimitive::Number(if rounded.is_finite() { rounded } else { *n }))
+            }
+            None => value.clone(),
+        },
+        JsonValue::Primitive(_) => value.clone(),
+    }
+}
+
+/// Path of the first non-empty container nested more than `levels` below the root, if any
+///
+/// Paths are written as `users[3].address`. The input is checked before it is normalized, since
+/// normalizing recurses as deep as the input goes.
+pub fn find_over_depth(value: &Value, levels: usize) -> Option<String> {
+    let mut stack = vec![(value, 0, String::new())];
+    while let Some((value, depth, path)) = stack.pop() {
+        let is_container = match value {
+            Value::Array(arr) => !arr.is_empty(),
+            Value::Object(obj) => !obj.is_empty(),
+            _ => false,
+        };
+        if is_container && depth > levels {
+            return Some(path);
+        }
+        // Children are pushed in reverse so the first offending path in document order is found
+        match value {
+            Value::Array(arr) => {
+                stack.extend(arr.iter().enumerate().rev().map(|(i, v)| (v, depth + 1, format!("{}[{}]", path, i))));
+            }
+            Value::Object(obj) => {
+                stack.extend(obj.iter().rev().map(|(k, v)| {
+                    let child = if path.is_empty() { k.clone() } else { format!("{}.{}", path, k) };
+                    (v, depth + 1, child)
+                }));
             }
-            JsonValue::Object(map)
+            _ => {}
         }
     }
+    None
 }
 
 /// Check if value is a primitive
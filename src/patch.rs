@@ -0,0 +1,116 @@
+use crate::prelude::*;
+use crate::diff::Change;
+use crate::error::PatchError;
+use serde_json::{Map, Value};
+
+/// Apply an RFC 7386 merge patch to `target` in place
+pub fn merge_into(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(fields) = target else { unreachable!("replaced by an object above") };
+    for (key, value) in patch {
+        match value {
+            // `retain` keeps the order of the other keys, which `remove` may not
+            Value::Null => fields.retain(|existing, _| existing != key),
+            value => merge_into(fields.entry(key.clone()).or_insert(Value::Null), value),
+        }
+    }
+}
+
+/// The steps of a JSON Pointer, with `~1` and `~0` unescaped
+fn steps(path: &str) -> Result<Vec<String>, PatchError> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rest = path.strip_prefix('/').ok_or_else(|| PatchError::new(path, "paths start with `/`"))?;
+    Ok(rest.split('/').map(|step| step.replace("~1", "/").replace("~0", "~")).collect())
+}
+
+/// Index `step` of an array `len` long, allowing `len` itself when `append` is set
+fn index(path: &str, step: &str, len: usize, append: bool) -> Result<usize, PatchError> {
+    let limit = if append { len + 1 } else { len };
+    match step.parse::<usize>() {
+        Ok(i) if i < limit => Ok(i),
+        _ => Err(PatchError::new(path, format!("no index `{}` in an array of {}", step, len))),
+    }
+}
+
+/// The container holding the last step of `path`, and that step
+fn parent<'v>(value: &'v mut Value, path: &str) -> Result<(&'v mut Value, String), PatchError> {
+    let mut steps = steps(path)?;
+    let last = steps.pop().ok_or_else(|| PatchError::new(path, "the root has no parent"))?;
+    let mut current = value;
+    for step in &steps {
+        current = match current {
+            Value::Object(fields) => fields.get_mut(step).ok_or_else(|| PatchError::new(path, format!("no key `{}`", step)))?,
+            Value::Array(items) => {
+                let i = index(path, step, items.len(), false)?;
+                &mut items[i]
+            }
+            _ => return Err(PatchError::new(path, format!("`{}` is inside a primitive", step))),
+        };
+    }
+    Ok((current, last))
+}
+
+fn apply_one(value: &mut Value, change: &Change) -> Result<(), PatchError> {
+    let path = change.path();
+    if path.is_empty() {
+        let (Change::Added { value: new, .. } | Change::Changed { new, .. }) = change else {
+            return Err(PatchError::new(path, "the root can't be removed"));
+        };
+        *value = new.clone();
+        return Ok(());
+    }
+    let (container, step) = parent(value, path)?;
+    match (container, change) {
+        (Value::Object(fields), Change::Added { value, .. }) => {
+            if fields.contains_key(&step) {
+                return Err(PatchError::new(path, "is already present"));
+            }
+            fields.insert(step, value.clone());
+        }
+        (Value::Object(fields), Change::Changed { new, .. }) => {
+            *fields.get_mut(&step).ok_or_else(|| PatchError::new(path, "isn't present"))? = new.clone();
+        }
+        (Value::Object(fields), Change::Removed { .. }) => {
+            if !fields.contains_key(&step) {
+                return Err(PatchError::new(path, "isn't present"));
+            }
+            fields.retain(|existing, _| *existing != step);
+        }
+        (Value::Array(items), Change::Added { value, .. }) => {
+            let i = index(path, &step, items.len(), true)?;
+            items.insert(i, value.clone());
+        }
+        (Value::Array(items), Change::Changed { new, .. }) => {
+            let i = index(path, &step, items.len(), false)?;
+            items[i] = new.clone();
+        }
+        (Value::Array(items), Change::Removed { .. }) => {
+            let i = index(path, &step, items.len(), false)?;
+            items.remove(i);
+        }
+        _ => return Err(PatchError::new(path, "the parent is a primitive")),
+    }
+    Ok(())
+}
+
+/// Apply `changes` to `value`, leaving it untouched when any doesn't fit
+///
+/// Removals run last and in reverse, so with the ascending indexes `diff` lists, removing
+/// `/a/1` doesn't shift `/a/2` before its turn.
+pub fn apply(value: &mut Value, changes: &[Change]) -> Result<(), PatchError> {
+    let mut patched = value.clone();
+    let (removals, others): (Vec<&Change>, Vec<&Change>) = changes.iter().partition(|change| matches!(change, Change::Removed { .. }));
+    for change in others.into_iter().chain(removals.into_iter().rev()) {
+        apply_one(&mut patched, change)?;
+    }
+    *value = patched;
+    Ok(())
+}
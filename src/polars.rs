@@ -0,0 +1,94 @@
+use crate::columnar::{float_cell, write_columnar_table};
+use crate::primitives::{encode_field_key, encode_run_primitive, format_header};
+use crate::types::{EncodeOptions, JsonPrimitive};
+use crate::writer::LineWriter;
+use ::polars::prelude::*;
+use std::borrow::Cow;
+
+/// Fields of the per-column statistics table, in series order
+const STATS_FIELDS: [&str; 6] = ["column", "dtype", "nulls", "min", "max", "mean"];
+
+/// One column's values, held in the representation its cells are written from
+enum Cells {
+    /// Exact text written as is: integers and booleans
+    Raw(StringChunked),
+    Float(Float64Chunked),
+    /// Text that is quoted when needed: strings, categoricals, temporal values and the rest
+    Text(StringChunked),
+}
+
+impl Cells {
+    fn new(series: &Series) -> PolarsResult<Self> {
+        let dtype = series.dtype();
+        Ok(if dtype.is_float() {
+            Cells::Float(series.cast(&DataType::Float64)?.f64()?.clone())
+        } else if dtype.is_integer() || dtype.is_bool() || dtype.is_null() {
+            Cells::Raw(series.cast(&DataType::String)?.str()?.clone())
+        } else {
+            // Dates and times cast to ISO 8601 text, categoricals and enums to their labels
+            Cells::Text(series.cast(&DataType::String)?.str()?.clone())
+        })
+    }
+
+    fn get(&self, row: usize, options: &EncodeOptions) -> String {
+        let text = |value: Option<&str>| value.map(|s| encode_run_primitive(&JsonPrimitive::String(Cow::Borrowed(s)), options));
+        match self {
+            Cells::Raw(values) => values.get(row).map(str::to_string),
+            Cells::Float(values) => values.get(row).map(float_cell),
+            Cells::Text(values) => text(values.get(row)),
+        }
+        .unwrap_or_else(|| "null".to_string())
+    }
+}
+
+/// Write a data frame as a table, reading each column directly
+pub fn write_frame(writer: &mut LineWriter, key: Option<&str>, frame: &DataFrame, options: &EncodeOptions) -> PolarsResult<()> {
+    let names: Vec<String> = frame.columns().iter().map(|column| column.name().to_string()).collect();
+    let cells = frame.columns().iter().map(|column| Cells::new(column.as_materialized_series())).collect::<PolarsResult<Vec<_>>>()?;
+    write_columnar_table(writer, 0, key, &names, frame.height(), |column, row| cells[column].get(row, options), options);
+    Ok(())
+}
+
+/// Write a `stats` table with each column's dtype, null count, min, max and numeric mean
+///
+/// A min or max the column's dtype cannot order, such as for lists, is written as null.
+pub fn write_stats(writer: &mut LineWriter, frame: &DataFrame, options: &EncodeOptions) -> PolarsResult<()> {
+    let mut rows = Vec::with_capacity(frame.width());
+    for column in frame.columns() {
+        let series = column.as_materialized_series();
+        let extreme = |scalar: PolarsResult<Scalar>| -> PolarsResult<String> {
+            match scalar {
+                Ok(scalar) => Ok(Cells::new(&scalar.into_series(series.name().clone()))?.get(0, options)),
+                Err(_) => Ok("null".to_string()),
+            }
+        };
+        let mean = series.dtype().is_primitive_numeric().then(|| series.mean()).flatten().map_or_else(|| "null".to_string(), float_cell);
+        let text = |s: &str| encode_run_primitive(&JsonPrimitive::String(Cow::Borrowed(s)), options);
+        rows.push([
+            text(series.name()),
+            text(&series.dtype().to_string()),
+            series.null_count().to_string(),
+            extreme(series.min_reduce())?,
+            extreme(series.max_reduce())?,
+            mean,
+        ]);
+    }
+
+    let names: Vec<String> = STATS_FIELDS.iter().map(|name| name.to_string()).collect();
+    write_columnar_table(writer, 0, Some("stats"), &names, rows.len(), |column, row| rows[row][column].clone(), options);
+    Ok(())
+}
+
+/// Write a series as an inline `name[N]: ...` array, or a bare one if it has no name
+pub fn write_series(writer: &mut LineWriter, series: &Series, options: &EncodeOptions) -> PolarsResult<()> {
+    let cells = Cells::new(series)?;
+    let key = (!series.name().is_empty()).then(|| encode_field_key(series.name(), options));
+    let mut line = format_header(series.len(), key.as_deref(), None, options);
+    if !series.is_empty() {
+        let values: Vec<String> = (0..series.len()).map(|row| cells.get(row, options)).collect();
+        line.push(' ');
+        line.push_str(&values.join(options.delimiter.as_str()));
+    }
+    writer.push(0, line);
+    Ok(())
+}
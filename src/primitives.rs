@@ -1,5 +1,10 @@
-use crate::types::{Delimiter, JsonPrimitive};
-use regex::Regex;
+use crate::prelude::*;
+use crate::decoder::parse_omitted_rows;
+use crate::types::{Delimiter, EmitLengths, EncodeOptions, JsonPrimitive, KeyFolding};
+use core::ops::Range;
+
+/// Line separating the documents of a multi-document stream
+pub const DOCUMENT_SEPARATOR: &str = "---";
 
 /// Encode a primitive value
 pub fn encode_primitive(value: &JsonPrimitive, delimiter: &Delimiter) -> String {
@@ -8,11 +13,20 @@ pub fn encode_primitive(value: &JsonPrimitive, delimiter: &Delimiter) -> String
         JsonPrimitive::Boolean(b) => b.to_string(),
         JsonPrimitive::Number(n) => format_number(*n),
         JsonPrimitive::String(s) => encode_string_literal(s, delimiter),
+        JsonPrimitive::Raw(text) => text.clone(),
+    }
+}
+
+/// Encode a document-level primitive, quoting strings that would read as an `@` directive
+pub fn encode_root_primitive(value: &JsonPrimitive, delimiter: &Delimiter) -> String {
+    match value {
+        JsonPrimitive::String(s) if s.starts_with('@') => format!("\"{}\"", escape_string(s)),
+        _ => encode_primitive(value, delimiter),
     }
 }
 
 /// Format number without scientific notation
-fn format_number(n: f64) -> String {
+pub fn format_number(n: f64) -> String {
     if n.fract() == 0.0 && n.abs() < 1e15 {
         format!("{:.0}", n)
     } else {
@@ -31,6 +45,25 @@ pub fn encode_string_literal(value: &str, delimiter: &Delimiter) -> String {
     }
 }
 
+/// Split a trailing ` xN` run count (N ≥ 1) off a token
+pub fn split_run_suffix(token: &str) -> Option<(&str, usize)> {
+    let (value, count) = token.rsplit_once(" x")?;
+    if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    count.parse().ok().filter(|count| *count > 0).map(|count| (value, count))
+}
+
+/// Encode a primitive for a row cell or inline array, quoting strings that would read as a run
+pub fn encode_run_primitive(value: &JsonPrimitive, options: &EncodeOptions) -> String {
+    match value {
+        JsonPrimitive::String(s) if options.run_length && split_run_suffix(s).is_some() => {
+            format!("\"{}\"", escape_string(s))
+        }
+        _ => encode_primitive(value, &options.delimiter),
+    }
+}
+
 /// Escape special characters in strings
 pub fn escape_string(value: &str) -> String {
     value
@@ -41,8 +74,20 @@ pub fn escape_string(value: &str) -> String {
         .replace('\t', "\\t")
 }
 
+/// Bytes that force quoting: colons, quotes, backslashes, brackets, braces and control characters
+static STRUCTURAL_BYTES: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut i = 0;
+    let bytes = b":\"\\[]{}\n\r\t";
+    while i < bytes.len() {
+        table[bytes[i] as usize] = true;
+        i += 1;
+    }
+    table
+};
+
 /// Check if string can be safely unquoted
-fn is_safe_unquoted(value: &str, delimiter: &Delimiter) -> bool {
+pub fn is_safe_unquoted(value: &str, delimiter: &Delimiter) -> bool {
     if value.is_empty() {
         return false;
     }
@@ -59,33 +104,24 @@ fn is_safe_unquoted(value: &str, delimiter: &Delimiter) -> bool {
         return false;
     }
 
-    // Check for colon (always structural)
-    if value.contains(':') {
-        return false;
-    }
-
-    // Check for quotes and backslash
-    if value.contains('"') || value.contains('\\') {
+    // Structural characters and the active delimiter are all ASCII, so one byte scan covers both
+    let delimiter = delimiter.as_str().as_bytes()[0];
+    if value.bytes().any(|b| STRUCTURAL_BYTES[b as usize] || b == delimiter) {
         return false;
     }
 
-    // Check for brackets and braces
-    if value.contains('[') || value.contains(']') || value.contains('{') || value.contains('}') {
+    // Check for list marker at start
+    if value.starts_with("- ") {
         return false;
     }
 
-    // Check for control characters
-    if value.contains('\n') || value.contains('\r') || value.contains('\t') {
+    // A bare `---` would read as a document separator
+    if value == DOCUMENT_SEPARATOR {
         return false;
     }
 
-    // Check for the active delimiter
-    if value.contains(delimiter.as_str()) {
-        return false;
-    }
-
-    // Check for list marker at start
-    if value.starts_with("- ") {
+    // A bare `…(+K rows)` would read as rows left out of a table
+    if parse_omitted_rows(value).is_some() {
         return false;
     }
 
@@ -95,8 +131,28 @@ fn is_safe_unquoted(value: &str, delimiter: &Delimiter) -> bool {
 /// Check if string looks like a number
 fn is_numeric_like(value: &str) -> bool {
     // Match numbers like: 42, -3.14, 1e-6, 05, etc.
-    let re = Regex::new(r"^-?\d+(?:\.\d+)?(?:e[+-]?\d+)?$|^0\d+$").unwrap();
-    re.is_match(value)
+    let rest = value.strip_prefix('-').unwrap_or(value);
+    let Some(rest) = strip_digits(rest) else { return false };
+    let rest = match rest.strip_prefix('.') {
+        Some(fraction) => match strip_digits(fraction) {
+            Some(rest) => rest,
+            None => return false,
+        },
+        None => rest,
+    };
+    match rest.strip_prefix('e') {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            strip_digits(exponent).is_some_and(str::is_empty)
+        }
+        None => rest.is_empty(),
+    }
+}
+
+/// Strip a non-empty run of leading ASCII digits
+fn strip_digits(value: &str) -> Option<&str> {
+    let rest = value.trim_start_matches(|c: char| c.is_ascii_digit());
+    (rest.len() < value.len()).then_some(rest)
 }
 
 /// Check if string has leading or trailing whitespace
@@ -113,52 +169,83 @@ pub fn encode_key(key: &str) -> String {
     }
 }
 
-/// Check if key can be unquoted
-fn is_valid_unquoted_key(key: &str) -> bool {
-    let re = Regex::new(r"^[A-Za-z_][\w.]*$").unwrap();
-    re.is_match(key)
+/// Encode a key, quoting literal dots whenever dotted keys are read as paths
+pub fn encode_field_key(key: &str, options: &EncodeOptions) -> String {
+    let dots_are_paths = options.key_folding != KeyFolding::Off || options.table_flatten_depth > 0;
+    if dots_are_paths && key.contains('.') {
+        format!("\"{}\"", escape_string(key))
+    } else {
+        encode_key(key)
+    }
 }
 
-/// Join encoded values with delimiter
-pub fn join_encoded_values(values: &[&JsonPrimitive], delimiter: &Delimiter) -> String {
-    values
-        .iter()
-        .map(|v| encode_primitive(v, delimiter))
-        .collect::<Vec<_>>()
-        .join(delimiter.as_str())
+/// Check if key can be joined into a dotted path without quoting
+pub fn is_foldable_segment(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+/// Check if key can be unquoted
+fn is_valid_unquoted_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_' || c == '.'),
+        _ => false,
+    }
 }
 
 /// Format array header with optional key, fields, and delimiter marker
+///
+/// The key and field names must already be encoded.
 pub fn format_header(
     length: usize,
     key: Option<&str>,
     fields: Option<&[String]>,
-    delimiter: &Delimiter,
-    length_marker: Option<char>,
+    options: &EncodeOptions,
 ) -> String {
+    let emit_length = match options.emit_lengths {
+        EmitLengths::Always => true,
+        EmitLengths::Never => false,
+        EmitLengths::OnlyTables => fields.is_some(),
+    };
+
+    let length_text = match &options.length_marker {
+        _ if !emit_length => String::new(),
+        Some(marker) => format!("{}{}{}", marker.prefix, length, marker.suffix),
+        None => length.to_string(),
+    };
+
+    write_header(&length_text, key, fields, options)
+}
+
+/// Format the header of one chunk of a split table, as `key[start..end]{fields}:`
+pub fn format_chunk_header(rows: Range<usize>, key: Option<&str>, fields: &[String], options: &EncodeOptions) -> String {
+    write_header(&format!("{}..{}", rows.start, rows.end), key, Some(fields), options)
+}
+
+fn write_header(length_text: &str, key: Option<&str>, fields: Option<&[String]>, options: &EncodeOptions) -> String {
     let mut header = String::new();
 
     if let Some(k) = key {
-        header.push_str(&encode_key(k));
+        header.push_str(k);
     }
 
     header.push('[');
-    if let Some(marker) = length_marker {
-        header.push(marker);
-    }
-    header.push_str(&length.to_string());
+    header.push_str(length_text);
     
     // Only include delimiter if it's not the default (comma)
-    if !delimiter.is_default() {
-        header.push_str(delimiter.as_str());
+    if !options.delimiter.is_default() {
+        header.push_str(options.delimiter.as_str());
     }
     
     header.push(']');
 
     if let Some(field_list) = fields {
         header.push('{');
-        let encoded_fields: Vec<String> = field_list.iter().map(|f| encode_key(f)).collect();
-        header.push_str(&encoded_fields.join(delimiter.as_str()));
+        header.push_str(&field_list.join(options.delimiter.as_str()));
         header.push('}');
     }
 
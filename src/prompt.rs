@@ -0,0 +1,223 @@
+use crate::prelude::*;
+use crate::stack::nested;
+use crate::types::{Delimiter, EmitLengths, EncodeOptions, IndentStyle, KeyFolding, MissingCell};
+use serde_json::{json, Value};
+
+/// Short description of the TOON dialect `options` produce, for the start of a prompt
+pub fn format_instructions(options: &EncodeOptions) -> String {
+    let delimiter = delimiter_name(&options.delimiter);
+    let count = count_example(options);
+    let (table_count, array_count) = match options.emit_lengths {
+        EmitLengths::Always => (count.as_str(), count.as_str()),
+        EmitLengths::Never => ("", ""),
+        EmitLengths::OnlyTables => (count.as_str(), ""),
+    };
+    // Tabs are spelled out so the instructions survive copying between editors
+    let shown_sep = if options.delimiter == Delimiter::Tab { "<TAB>" } else { options.delimiter.as_str() };
+    let header_sep = if options.delimiter.is_default() { "" } else { shown_sep };
+
+    let mut lines = vec![
+        "The data below is TOON (Token-Oriented Object Notation), a compact encoding of JSON:".to_string(),
+        format!("- `key: value` lines are object fields; a nested object is indented {} under its `key:` line.", indent_name(&options.indent)),
+        format!(
+            "- `key[{count}{hd}]: a{s}b{s}c` is an array of primitives separated by {name}.",
+            count = array_count,
+            hd = header_sep,
+            s = shown_sep,
+            name = delimiter
+        ),
+        format!(
+            "- `key[{count}{hd}]{{f1{s}f2}}:` starts a table: each following line is one object, giving the listed fields in order, separated by {name}.",
+            count = table_count,
+            hd = header_sep,
+            s = shown_sep,
+            name = delimiter
+        ),
+        "- `- ` starts an item of an array whose elements are not uniform objects.".to_string(),
+        "- Strings are unquoted unless they would be ambiguous; quoted strings use JSON escapes. Unquoted `true`, `false`, `null` and numbers are literals.".to_string(),
+    ];
+
+    match options.emit_lengths {
+        EmitLengths::Always => lines.push(format!("- `{}` in a header is the number of items or rows.", count)),
+        EmitLengths::OnlyTables => lines.push(format!("- `{}` in a table header is the number of rows; other arrays omit it.", count)),
+        EmitLengths::Never => lines.push("- Array headers leave out the item count.".to_string()),
+    }
+    if options.key_folding != KeyFolding::Off || options.table_flatten_depth > 0 {
+        lines.push("- Unquoted dotted keys such as `a.b: 1` are paths through nested objects.".to_string());
+    }
+    if options.string_dictionary.is_some() {
+        lines.push("- `@dict[N]:` lists repeated strings; `$0`, `$1`, … in values stand for its entries in order.".to_string());
+    }
+    if options.alias_legend && !options.column_aliases.is_empty() {
+        lines.push("- `@aliases` maps the short column names used in table headers back to the full field names.".to_string());
+    }
+    if options.inline_objects.is_some() {
+        lines.push("- `{x: 1, y: 2}` is a small object written on one line.".to_string());
+    }
+    if options.table_chunk_rows.is_some() {
+        lines.push("- `key[a..b]{…}:` holds rows a to b-1 of a long table split into consecutive blocks.".to_string());
+    }
+    if options.lift_constant_columns {
+        lines.push("- `field: value` lines directly under a table header give a value every row shares.".to_string());
+    }
+    if options.run_length {
+        lines.push("- `value xN` stands for N consecutive copies of a row or item.".to_string());
+    }
+    if options.type_hints {
+        lines.push("- Table fields carry their types, as `{id:int,name:str}`.".to_string());
+    }
+    if options.tabular_union_threshold.is_some() {
+        lines.push(match options.missing_cell {
+            MissingCell::Null => "- A `null` table cell may also mean the row has no such field.".to_string(),
+            MissingCell::Empty => "- An empty table cell means the row has no such field.".to_string(),
+        });
+    }
+    if options.summarize.is_some() {
+        lines.push("- Long arrays are summarized as a `count`, a `sample` of items and per-field `stats`.".to_string());
+    }
+    if options.max_rows.is_some() {
+        lines.push("- `…(+K rows)` stands for K table rows left out.".to_string());
+    }
+    if options.max_depth.is_some() {
+        lines.push("- `{…}` and `[…]` stand for nested content left out.".to_string());
+    }
+    if options.max_string_chars.is_some() {
+        lines.push("- A string ending in `…` was cut short.".to_string());
+    }
+    if let Some(digits) = options.float_digits {
+        lines.push(format!("- Numbers are rounded to {} decimal places.", digits));
+    }
+    match (options.skip_nulls, options.skip_empty) {
+        (true, true) => lines.push("- Fields that are null or empty are left out.".to_string()),
+        (true, false) => lines.push("- Fields that are null are left out.".to_string()),
+        (false, true) => lines.push("- Fields holding empty arrays or objects are left out.".to_string()),
+        (false, false) => {}
+    }
+
+    lines.join("\n")
+}
+
+/// How the item count appears inside a header's brackets
+fn count_example(options: &EncodeOptions) -> String {
+    match &options.length_marker {
+        Some(marker) => format!("{}N{}", marker.prefix, marker.suffix),
+        None => "N".to_string(),
+    }
+}
+
+fn delimiter_name(delimiter: &Delimiter) -> &'static str {
+    match delimiter {
+        Delimiter::Comma => "commas",
+        Delimiter::Tab => "tabs",
+        Delimiter::Pipe => "`|`",
+        Delimiter::Semicolon => "`;`",
+    }
+}
+
+fn indent_name(indent: &IndentStyle) -> String {
+    match indent {
+        IndentStyle::Spaces(1) => "1 space".to_string(),
+        IndentStyle::Spaces(n) => format!("{} spaces", n),
+        IndentStyle::Tabs => "one tab".to_string(),
+        IndentStyle::Custom(unit) => format!("{:?}", unit),
+    }
+}
+
+/// JSON input with its TOON encoding, for few-shot prompts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Example {
+    /// What the example shows
+    pub description: String,
+    /// Minified JSON input
+    pub json: String,
+    /// The input encoded with the caller's options
+    pub toon: String,
+}
+
+/// Items of each array kept when a caller's sample becomes an example
+const EXAMPLE_ITEMS: usize = 3;
+
+/// Example pairs encoded with `options`: a nested object, a table, quoting edge cases and a mixed
+/// list, then `sample` cut to a few items per array
+///
+/// Examples the options cannot encode, such as ones over a byte budget, are left out.
+pub fn few_shot_examples(sample: Option<&Value>, options: &EncodeOptions) -> Vec<Example> {
+    let delimiter_text = format!("a{}b", options.delimiter.as_str());
+    let mut inputs = vec![
+        ("nested objects", json!({"user": {"id": 1, "name": "Ada Lovelace", "address": {"city": "London"}}})),
+        (
+            "a table of uniform objects",
+            json!({"items": [{"sku": "A1", "qty": 2, "price": 9.99}, {"sku": "B2", "qty": 1, "price": 14.5}]}),
+        ),
+        (
+            "strings that need quotes",
+            json!({"delimiter": delimiter_text, "number": "007", "keyword": "true", "empty": "", "colon": "a: b", "padded": " x "}),
+        ),
+        ("a list of mixed items", json!({"events": [{"type": "click", "x": 3}, {"type": "scroll"}, "done", [1, 2]]})),
+    ];
+    if let Some(sample) = sample {
+        inputs.push(("your data", shorten_arrays(sample)));
+    }
+
+    inputs
+        .into_iter()
+        .filter_map(|(description, value)| {
+            let toon = crate::try_encode(&value, Some(options.clone())).ok()?;
+            Some(Example { description: description.to_string(), json: value.to_string(), toon })
+        })
+        .collect()
+}
+
+/// Smallest plausible value matching `schema`, for `few_shot_examples` when no real data is at hand
+///
+/// Uses `examples`, `default`, `const` and `enum` when present, otherwise a placeholder for the
+/// declared `type`; objects get every listed property and arrays two items.
+pub fn example_from_schema(schema: &Value) -> Value {
+    let Value::Object(schema) = schema else { return Value::Null };
+    if let Some(example) = schema.get("examples").and_then(|examples| examples.get(0)) {
+        return example.clone();
+    }
+    for keyword in ["default", "const"] {
+        if let Some(value) = schema.get(keyword) {
+            return value.clone();
+        }
+    }
+    if let Some(first) = schema.get("enum").and_then(|choices| choices.get(0)) {
+        return first.clone();
+    }
+    if let Some(first) = ["oneOf", "anyOf", "allOf"].iter().find_map(|keyword| schema.get(*keyword).and_then(|s| s.get(0))) {
+        return nested(|| example_from_schema(first));
+    }
+
+    let declared = match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).find(|t| *t != "null"),
+        Some(Value::String(t)) => Some(t.as_str()),
+        _ if schema.contains_key("properties") => Some("object"),
+        _ if schema.contains_key("items") => Some("array"),
+        _ => None,
+    };
+    match declared {
+        Some("object") => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let fields = properties.into_iter().flatten().map(|(k, v)| (k.clone(), nested(|| example_from_schema(v))));
+            Value::Object(fields.collect())
+        }
+        Some("array") => {
+            let item = schema.get("items").map_or(Value::Null, |items| nested(|| example_from_schema(items)));
+            Value::Array(vec![item.clone(), item])
+        }
+        Some("string") => Value::String("text".to_string()),
+        Some("integer") => json!(1),
+        Some("number") => json!(1.5),
+        Some("boolean") => Value::Bool(true),
+        _ => Value::Null,
+    }
+}
+
+fn shorten_arrays(value: &Value) -> Value {
+    match value {
+        Value::Array(arr) => Value::Array(arr.iter().take(EXAMPLE_ITEMS).map(|v| nested(|| shorten_arrays(v))).collect()),
+        Value::Object(obj) => Value::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| shorten_arrays(v)))).collect()),
+        _ => value.clone(),
+    }
+}
@@ -0,0 +1,77 @@
+use crate::binary::base64;
+use crate::stack::nested;
+use prost_reflect::{DynamicMessage, Kind, MapKey, ReflectMessage, Value as Proto};
+use serde_json::{Map, Value};
+
+/// Convert a dynamic message to a JSON object keyed by field name
+///
+/// Fields without presence are written even when they hold their default, so repeated
+/// messages share one set of columns; unset optional and message fields become null and
+/// only the set member of a oneof is written. Unknown fields are dropped.
+pub fn message_to_json(message: &DynamicMessage) -> Value {
+    let mut object = Map::new();
+    for field in message.descriptor().fields() {
+        let value = if field.supports_presence() && !message.has_field(&field) {
+            if field.containing_oneof().is_some() {
+                continue;
+            }
+            Value::Null
+        } else {
+            nested(|| value_to_json(&message.get_field(&field), &field.kind()))
+        };
+        object.insert(field.name().to_string(), value);
+    }
+    Value::Object(object)
+}
+
+/// Convert a field value, given the kind of the field or of its list items
+///
+/// Bytes become base64 strings, enums their value name (or number when the descriptor has
+/// none), map keys their text and NaN or infinite floats null.
+fn value_to_json(value: &Proto, kind: &Kind) -> Value {
+    match value {
+        Proto::Bool(b) => Value::Bool(*b),
+        Proto::I32(i) => Value::from(*i),
+        Proto::I64(i) => Value::from(*i),
+        Proto::U32(u) => Value::from(*u),
+        Proto::U64(u) => Value::from(*u),
+        // The shortest text of an f32 keeps 0.1 from widening to 0.10000000149
+        Proto::F32(f) => float(f.to_string().parse().unwrap_or(f64::NAN)),
+        Proto::F64(f) => float(*f),
+        Proto::String(s) => Value::String(s.clone()),
+        Proto::Bytes(bytes) => Value::String(base64(bytes)),
+        Proto::EnumNumber(number) => match kind {
+            Kind::Enum(descriptor) => match descriptor.get_value(*number) {
+                Some(enum_value) => Value::String(enum_value.name().to_string()),
+                None => Value::from(*number),
+            },
+            _ => Value::from(*number),
+        },
+        Proto::Message(message) => message_to_json(message),
+        Proto::List(items) => Value::Array(items.iter().map(|item| nested(|| value_to_json(item, kind))).collect()),
+        Proto::Map(entries) => {
+            let value_kind = match kind {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                other => other.clone(),
+            };
+            let mut entries: Vec<_> = entries.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(entries.into_iter().map(|(k, v)| (key_text(k), nested(|| value_to_json(v, &value_kind)))).collect())
+        }
+    }
+}
+
+fn float(f: f64) -> Value {
+    serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+fn key_text(key: &MapKey) -> String {
+    match key {
+        MapKey::Bool(b) => b.to_string(),
+        MapKey::I32(i) => i.to_string(),
+        MapKey::I64(i) => i.to_string(),
+        MapKey::U32(u) => u.to_string(),
+        MapKey::U64(u) => u.to_string(),
+        MapKey::String(s) => s.clone(),
+    }
+}
@@ -0,0 +1,20 @@
+use crate::error::{EncodeError, SchemaViolation};
+use serde_json::Value;
+
+/// Every violation of `schema` by `value`, or an error if the schema itself is invalid
+pub fn validate(value: &Value, schema: &Value) -> Result<(), EncodeError> {
+    let validator = jsonschema::validator_for(schema).map_err(|err| EncodeError::InvalidSchema { reason: err.to_string() })?;
+    let violations: Vec<SchemaViolation> = validator
+        .iter_errors(value)
+        .map(|err| SchemaViolation {
+            instance_path: err.instance_path().to_string(),
+            schema_path: err.schema_path().to_string(),
+            message: err.to_string(),
+        })
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(EncodeError::SchemaViolations { violations })
+    }
+}
@@ -0,0 +1,64 @@
+use crate::prelude::*;
+use crate::error::EncodeError;
+use crate::types::{Delimiter, EmitLengths, EncodeOptions, IndentStyle, KeyFolding, LineEnding, MissingCell, SpecVersion};
+
+/// Error for the first option whose syntax `options.spec_version` doesn't define
+pub fn find_unsupported(options: &EncodeOptions) -> Option<EncodeError> {
+    let version = options.spec_version?;
+    unsupported_option(options, version).map(|option| EncodeError::Unsupported { option: option.to_string(), version })
+}
+
+fn unsupported_option(options: &EncodeOptions, version: SpecVersion) -> Option<&'static str> {
+    let folds_keys = options.key_folding != KeyFolding::Off || options.table_flatten_depth > 0;
+    if folds_keys && version < SpecVersion::V1_5 {
+        return Some("key_folding");
+    }
+    if let Some(marker) = &options.length_marker {
+        if version >= SpecVersion::V2_0 {
+            return Some("length_marker");
+        }
+        if marker.prefix != "#" || !marker.suffix.is_empty() {
+            return Some("length_marker other than `#`");
+        }
+    }
+    extension_option(options)
+}
+
+/// Name of an enabled option that writes syntax of this crate's own
+fn extension_option(options: &EncodeOptions) -> Option<&'static str> {
+    if options.delimiter == Delimiter::Semicolon {
+        Some("Delimiter::Semicolon")
+    } else if !matches!(options.indent, IndentStyle::Spaces(_)) {
+        Some("indent other than spaces")
+    } else if options.line_ending != LineEnding::Lf {
+        Some("LineEnding::CrLf")
+    } else if options.trailing_newline {
+        Some("trailing_newline")
+    } else if options.align_columns {
+        Some("align_columns")
+    } else if options.max_line_width.is_some() {
+        Some("max_line_width")
+    } else if options.emit_lengths != EmitLengths::Always {
+        Some("emit_lengths")
+    } else if options.tabular_union_threshold.is_some() && options.missing_cell == MissingCell::Empty {
+        Some("MissingCell::Empty")
+    } else if options.string_dictionary.is_some() {
+        Some("string_dictionary")
+    } else if options.inline_objects.is_some() {
+        Some("inline_objects")
+    } else if options.table_chunk_rows.is_some() {
+        Some("table_chunk_rows")
+    } else if options.alias_legend && !options.column_aliases.is_empty() {
+        Some("alias_legend")
+    } else if options.run_length {
+        Some("run_length")
+    } else if options.type_hints {
+        Some("type_hints")
+    } else if options.summarize.is_some() {
+        Some("summarize")
+    } else if options.lift_constant_columns {
+        Some("lift_constant_columns")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,75 @@
+use crate::binary::base64;
+use crate::stream::TableStream;
+use crate::types::EncodeOptions;
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+
+fn float(f: f64) -> Value {
+    serde_json::Number::from_f64(f).map_or(Value::Null, Value::Number)
+}
+
+/// Write SQLite result rows as one chunked table, a chunk at a time
+///
+/// Blobs become base64 strings and text that isn't valid UTF-8 is read lossily.
+#[cfg(feature = "rusqlite")]
+pub fn encode_sqlite<W: Write>(key: &str, mut rows: rusqlite::Rows<'_>, out: W, options: &EncodeOptions) -> io::Result<usize> {
+    use rusqlite::types::ValueRef;
+
+    let names: Vec<String> = match rows.as_ref() {
+        Some(statement) => statement.column_names().into_iter().map(str::to_string).collect(),
+        None => Vec::new(),
+    };
+
+    let mut table = TableStream::new(key, out, options);
+    while let Some(row) = rows.next().map_err(io::Error::other)? {
+        let mut object = Map::new();
+        for (index, name) in names.iter().enumerate() {
+            let value = match row.get_ref(index).map_err(io::Error::other)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(i) => Value::from(i),
+                ValueRef::Real(f) => float(f),
+                ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+                ValueRef::Blob(bytes) => Value::String(base64(bytes)),
+            };
+            object.insert(name.clone(), value);
+        }
+        table.push(Value::Object(object))?;
+    }
+    table.finish()
+}
+
+/// Write rows from the `sqlx` `Any` driver as one chunked table, a chunk at a time
+///
+/// Values are read by the column type each row reports; blobs become base64 strings.
+#[cfg(feature = "sqlx")]
+pub async fn encode_sqlx<S, W>(key: &str, rows: S, out: W, options: &EncodeOptions) -> io::Result<usize>
+where
+    S: futures_core::Stream<Item = Result<sqlx::any::AnyRow, sqlx::Error>>,
+    W: Write,
+{
+    use sqlx::any::AnyTypeInfoKind as Kind;
+    use sqlx::{Column, Row, ValueRef};
+
+    let mut rows = std::pin::pin!(rows);
+    let mut table = TableStream::new(key, out, options);
+    while let Some(row) = std::future::poll_fn(|cx| rows.as_mut().poll_next(cx)).await {
+        let row = row.map_err(io::Error::other)?;
+        let mut object = Map::new();
+        for column in row.columns() {
+            let index = column.ordinal();
+            let raw = row.try_get_raw(index).map_err(io::Error::other)?;
+            let kind = if raw.is_null() { Kind::Null } else { raw.type_info().kind() };
+            let value = match kind {
+                Kind::Null => Ok(Value::Null),
+                Kind::Bool => row.try_get::<bool, _>(index).map(Value::Bool),
+                Kind::SmallInt | Kind::Integer | Kind::BigInt => row.try_get::<i64, _>(index).map(Value::from),
+                Kind::Real | Kind::Double => row.try_get::<f64, _>(index).map(float),
+                Kind::Text => row.try_get::<String, _>(index).map(Value::String),
+                Kind::Blob => row.try_get::<Vec<u8>, _>(index).map(|bytes| Value::String(base64(&bytes))),
+            };
+            object.insert(column.name().to_string(), value.map_err(io::Error::other)?);
+        }
+        table.push(Value::Object(object))?;
+    }
+    table.finish()
+}
@@ -0,0 +1,38 @@
+/// Stack space that must remain before a nested call moves onto a new segment
+#[cfg(feature = "std")]
+const RED_ZONE: usize = 128 * 1024;
+
+/// Size of each heap-allocated stack segment
+#[cfg(feature = "std")]
+const SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+/// Nesting that encoding and decoding accept unless `fail_over_depth` is changed
+///
+/// With `std` the walks spill onto the heap, so the limit guards the recursive drop and
+/// serialization of `serde_json` values; without it the limit is all that guards the stack.
+#[cfg(feature = "std")]
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+/// Nesting that encoding and decoding accept unless `fail_over_depth` is changed
+///
+/// Without `std` the walks recurse on the caller's stack, so the limit stays close to the 128
+/// levels `serde_json` parses.
+#[cfg(not(feature = "std"))]
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Run one nesting level of a recursive walk, spilling onto the heap when the stack runs low
+///
+/// Wrapping the recursive calls keeps input depth bounded by memory rather than thread stack size.
+#[cfg(feature = "std")]
+pub fn nested<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(RED_ZONE, SEGMENT_SIZE, f)
+}
+
+/// Run one nesting level of a recursive walk directly on the stack
+///
+/// Without `std` there is no stack probing, so deep input is bounded by the stack the caller
+/// provides; `fail_over_depth`, [`DEFAULT_MAX_DEPTH`] unless changed, rejects it ahead of time.
+#[cfg(not(feature = "std"))]
+pub fn nested<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
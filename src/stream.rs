@@ -0,0 +1,166 @@
+use crate::prelude::*;
+use crate::encoders::{colliding_alias, encode_table_chunk, Column};
+use crate::error::EncodeError;
+use crate::validate::find_invalid_option;
+use crate::normalize::{find_over_depth, normalize_value};
+use crate::primitives::{encode_field_key, format_header};
+use crate::types::{EncodeOptions, JsonValue};
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Rows buffered per chunk when `table_chunk_rows` is unset
+const DEFAULT_CHUNK_ROWS: usize = 1000;
+
+/// Write rows as one `key[start..end]{...}:` chunked table, holding a single chunk at a time
+///
+/// Sorting and head/tail sampling need every row up front, so `sort_rows` and `max_rows`
+/// are ignored. Returns the number of rows written.
+pub fn encode_table_stream<I, E, W>(key: &str, rows: I, out: W, options: &EncodeOptions) -> io::Result<usize>
+where
+    I: IntoIterator<Item = Result<Value, E>>,
+    E: Into<io::Error>,
+    W: Write,
+{
+    let mut table = TableStream::new(key, out, options);
+    for row in rows {
+        table.push(row.map_err(Into::into)?)?;
+    }
+    table.finish()
+}
+
+/// A chunked table that is written as its rows arrive, for sources that push rather than iterate
+///
+/// Rows are written `table_chunk_rows` at a time, or sooner with `flush`, each chunk under its
+/// own `key[start..end]{...}:` header so `decode` merges them back into one array.
+///
+/// # Example
+///
+/// ```
+/// use serde_json::json;
+/// use toon::TableStream;
+///
+/// let mut out = Vec::new();
+/// let mut table = TableStream::new("logs", &mut out, &Default::default());
+/// table.push(json!({"level": "info"})).unwrap();
+/// table.flush().unwrap();
+/// table.push(json!({"level": "warn"})).unwrap();
+/// assert_eq!(table.finish().unwrap(), 2);
+/// assert_eq!(String::from_utf8(out).unwrap(), "logs[0..1]{level}:\n  info\nlogs[1..2]{level}:\n  warn");
+/// ```
+pub struct TableStream<'k, W: Write> {
+    key: &'k str,
+    out: W,
+    options: EncodeOptions,
+    chunk_rows: usize,
+    chunk: Vec<Value>,
+    columns: Option<Vec<Column>>,
+    buffer: String,
+    written: usize,
+}
+
+impl<'k, W: Write> TableStream<'k, W> {
+    pub fn new(key: &'k str, out: W, options: &EncodeOptions) -> Self {
+        let options = EncodeOptions { sort_rows: None, max_rows: None, ..options.clone() };
+        let chunk_rows = options.table_chunk_rows.filter(|&n| n > 0).unwrap_or(DEFAULT_CHUNK_ROWS);
+        Self {
+            key,
+            out,
+            options,
+            chunk_rows,
+            chunk: Vec::with_capacity(chunk_rows),
+            columns: None,
+            buffer: String::new(),
+            written: 0,
+        }
+    }
+
+    /// Add a row, writing the chunk once it is full
+    pub fn push(&mut self, row: Value) -> io::Result<()> {
+        self.chunk.push(row);
+        if self.chunk.len() == self.chunk_rows {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Write the rows pushed since the last chunk as a chunk of their own and flush the writer
+    ///
+    /// Fails with `InvalidData` when the rows don't fit the columns of the first chunk, and with
+    /// `InvalidInput` when an option is invalid or a column alias collides with another column.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.out.flush()
+    }
+
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
+        }
+
+        // Rows sit two levels down, inside the array under `key`; normalizing them recurses
+        let deep_row = self.chunk.iter().enumerate().find_map(|(i, row)| {
+            let path = find_over_depth(row, self.options.fail_over_depth?.saturating_sub(2))?;
+            let separator = if path.is_empty() || path.starts_with('[') { "" } else { "." };
+            Some(EncodeError::DepthExceeded { path: format!("{}[{}]{}{}", self.key, self.written + i, separator, path) })
+        });
+        if let Some(err) = deep_row {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, err.to_string()));
+        }
+        let normalized: Vec<JsonValue> = self.chunk.iter().map(|row| normalize_value(row, self.options.key_order)).collect();
+        let (start, end) = (self.written, self.written + self.chunk.len());
+        // The first chunk fixes the header, so only it needs checking
+        if self.columns.is_none() {
+            let collision = || colliding_alias(&normalized, &self.options).map(|alias| EncodeError::AliasCollision { path: self.key.to_string(), alias });
+            if let Some(err) = find_invalid_option(&self.options).or_else(collision) {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, err.to_string()));
+            }
+        }
+        let buffer = core::mem::take(&mut self.buffer);
+        self.buffer = encode_table_chunk(self.key, start, &normalized, &mut self.columns, buffer, &self.options).ok_or_else(|| {
+            let message = format!("rows {}..{} do not fit one table header", start, end);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        // `JsonValue` has a `Drop` impl, so the borrow of the chunk lasts until it is dropped
+        drop(normalized);
+
+        if self.written > 0 {
+            self.out.write_all(self.options.line_ending.as_str().as_bytes())?;
+        }
+        self.out.write_all(self.buffer.as_bytes())?;
+        self.written = end;
+        self.chunk.clear();
+        Ok(())
+    }
+
+    /// Write the last chunk, or an empty table if no rows came, and return the row count
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.write_chunk()?;
+        let line_ending = self.options.line_ending.as_str().as_bytes();
+        if self.written == 0 {
+            let header = format_header(0, Some(&encode_field_key(self.key, &self.options)), None, &self.options);
+            self.out.write_all(header.as_bytes())?;
+        }
+        if self.options.trailing_newline {
+            self.out.write_all(line_ending)?;
+        }
+        Ok(self.written)
+    }
+}
+
+/// Parse each non-blank line of `reader` as one JSON value, reading a line at a time
+///
+/// Malformed lines fail with `InvalidData` and their 1-based line number.
+pub fn read_ndjson<R: BufRead>(reader: R) -> impl Iterator<Item = io::Result<Value>> {
+    reader.lines().enumerate().filter_map(|(index, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => return Some(Err(err)),
+        };
+        if line.trim().is_empty() {
+            return None;
+        }
+        Some(serde_json::from_str(&line).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", index + 1, err))
+        }))
+    })
+}
@@ -0,0 +1,98 @@
+use crate::prelude::*;
+use crate::stack::nested;
+use crate::types::{JsonObject, JsonPrimitive, JsonValue, Summarize};
+use alloc::borrow::Cow;
+
+/// Replace arrays longer than the threshold with a sample and per-column aggregates
+///
+/// A summarized array becomes an object with `count`, a `sample` of its first and last items,
+/// and `stats`: `min`/`max`/`mean` for numeric columns and `distinct` counts for the rest.
+pub fn summarize_value<'a>(value: &JsonValue<'a>, summarize: &Summarize) -> JsonValue<'a> {
+    match value {
+        JsonValue::Array(arr) if arr.len() > summarize.threshold => summarize_array(arr, summarize),
+        JsonValue::Array(arr) => JsonValue::Array(arr.iter().map(|v| nested(|| summarize_value(v, summarize))).collect()),
+        JsonValue::Object(obj) => {
+            JsonValue::Object(obj.iter().map(|(k, v)| (k.clone(), nested(|| summarize_value(v, summarize)))).collect())
+        }
+        JsonValue::Primitive(_) => value.clone(),
+    }
+}
+
+fn summarize_array<'a>(arr: &[JsonValue<'a>], summarize: &Summarize) -> JsonValue<'a> {
+    let head = summarize.sample.head.min(arr.len());
+    let tail = summarize.sample.tail.min(arr.len() - head);
+    let sample = arr[..head].iter().chain(&arr[arr.len() - tail..]).cloned().collect();
+
+    let mut summary = vec![
+        ("count".into(), number(arr.len() as f64)),
+        ("sample".into(), JsonValue::Array(sample)),
+    ];
+
+    let stats = if arr.iter().all(|v| matches!(v, JsonValue::Primitive(_))) {
+        let values: Vec<&JsonPrimitive> = arr.iter().filter_map(as_primitive).collect();
+        column_stats(&values)
+    } else {
+        // Columns keep the order in which rows first mention them
+        let mut columns: Vec<(&Cow<'a, str>, Vec<&JsonPrimitive>)> = Vec::new();
+        for item in arr {
+            if let JsonValue::Object(obj) = item {
+                for (key, value) in obj {
+                    let Some(p) = as_primitive(value) else { continue };
+                    match columns.iter_mut().find(|(name, _)| *name == key) {
+                        Some((_, values)) => values.push(p),
+                        None => columns.push((key, vec![p])),
+                    }
+                }
+            }
+        }
+        let fields: JsonObject = columns.into_iter().map(|(key, values)| (key.clone(), column_stats(&values))).collect();
+        JsonValue::Object(fields)
+    };
+    if !matches!(&stats, JsonValue::Object(fields) if fields.is_empty()) {
+        summary.push(("stats".into(), stats));
+    }
+
+    JsonValue::Object(summary.into())
+}
+
+/// Aggregates for one column: numeric range and mean, or the number of distinct values
+fn column_stats(values: &[&JsonPrimitive]) -> JsonValue<'static> {
+    let mut stats = Vec::new();
+
+    let present: Vec<&JsonPrimitive> = values.iter().copied().filter(|p| !matches!(p, JsonPrimitive::Null)).collect();
+    let numbers: Vec<f64> = present.iter().filter_map(|p| match p {
+        JsonPrimitive::Number(n) => Some(*n),
+        _ => None,
+    }).collect();
+
+    if !numbers.is_empty() && numbers.len() == present.len() {
+        let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        // Four decimals are plenty for a summary and keep the mean short
+        let mean = (numbers.iter().sum::<f64>() / numbers.len() as f64 * 1e4).round() / 1e4;
+        stats.push(("max".into(), number(max)));
+        stats.push(("mean".into(), number(mean)));
+        stats.push(("min".into(), number(min)));
+    } else if !present.is_empty() {
+        let distinct: HashSet<String> = present.iter().map(|p| format!("{:?}", p)).collect();
+        stats.push(("distinct".into(), number(distinct.len() as f64)));
+    }
+
+    let nulls = values.len() - present.len();
+    if nulls > 0 {
+        stats.push(("nulls".into(), number(nulls as f64)));
+    }
+
+    JsonValue::Object(stats.into())
+}
+
+fn as_primitive<'v, 'a>(value: &'v JsonValue<'a>) -> Option<&'v JsonPrimitive<'a>> {
+    match value {
+        JsonValue::Primitive(p) => Some(p),
+        _ => None,
+    }
+}
+
+fn number(n: f64) -> JsonValue<'static> {
+    JsonValue::Primitive(JsonPrimitive::Number(n))
+}
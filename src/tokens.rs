@@ -0,0 +1,52 @@
+use crate::budget::TokenCounter;
+use tiktoken_rs::{cl100k_base_singleton, o200k_base_singleton, CoreBPE};
+
+/// Model family whose tokenizer counts tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Model {
+    /// GPT-4o and GPT-4o mini (`o200k_base`)
+    Gpt4o,
+    /// GPT-4 and GPT-4 Turbo (`cl100k_base`)
+    Gpt4,
+    /// GPT-3.5 Turbo (`cl100k_base`)
+    Gpt35Turbo,
+}
+
+impl Model {
+    /// The model whose tokenizer a model name such as `gpt-4o-mini` or `openai/gpt-4` uses
+    ///
+    /// Names are matched by prefix, ignoring case and any `provider/` part. Models whose
+    /// tokenizer isn't published, such as Claude's, give `None`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let name = name.rsplit('/').next().unwrap_or(&name);
+        let o200k = ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4", "chatgpt-4o"];
+        if o200k.iter().any(|prefix| name.starts_with(prefix)) {
+            Some(Model::Gpt4o)
+        } else if name.starts_with("gpt-4") {
+            Some(Model::Gpt4)
+        } else if name.starts_with("gpt-3.5") {
+            Some(Model::Gpt35Turbo)
+        } else {
+            None
+        }
+    }
+
+    fn bpe(self) -> &'static CoreBPE {
+        match self {
+            Model::Gpt4o => o200k_base_singleton(),
+            Model::Gpt4 | Model::Gpt35Turbo => cl100k_base_singleton(),
+        }
+    }
+}
+
+/// Tokens in `text` for `model`, reading special-token text as ordinary text
+pub fn count_tokens(text: &str, model: Model) -> usize {
+    model.bpe().count_ordinary(text)
+}
+
+impl TokenCounter for Model {
+    fn count_tokens(&self, text: &str) -> usize {
+        count_tokens(text, *self)
+    }
+}
@@ -0,0 +1,29 @@
+use crate::stack::nested;
+use ::toml::{Table, Value as Toml};
+use serde_json::Value;
+
+/// Convert a TOML value to JSON
+///
+/// Datetimes become their RFC 3339 text (`1979-05-27T07:32:00Z`, `07:32:00`), and `inf` and
+/// `nan` become null as JSON has no such numbers.
+pub fn toml_to_json(value: &Toml) -> Value {
+    match value {
+        Toml::String(s) => Value::String(s.clone()),
+        Toml::Integer(i) => Value::from(*i),
+        Toml::Float(f) => serde_json::Number::from_f64(*f).map_or(Value::Null, Value::Number),
+        Toml::Boolean(b) => Value::Bool(*b),
+        Toml::Datetime(datetime) => Value::String(datetime.to_string()),
+        Toml::Array(items) => Value::Array(items.iter().map(|v| nested(|| toml_to_json(v))).collect()),
+        Toml::Table(table) => table_to_json(table),
+    }
+}
+
+fn table_to_json(table: &Table) -> Value {
+    Value::Object(table.iter().map(|(k, v)| (k.clone(), nested(|| toml_to_json(v)))).collect())
+}
+
+/// Parse a TOML document and convert it to JSON
+pub fn parse_toml(input: &str) -> Result<Value, ::toml::de::Error> {
+    let table: Table = ::toml::from_str(input)?;
+    Ok(table_to_json(&table))
+}
@@ -1,28 +1,118 @@
-use std::collections::HashMap;
+use crate::prelude::*;
+use alloc::borrow::Cow;
+use crate::stack::DEFAULT_MAX_DEPTH;
+use core::fmt;
 
 /// JSON primitive types
+///
+/// Strings borrow from the input `serde_json::Value`, so normalizing does not copy them.
 #[derive(Debug, Clone, PartialEq)]
-pub enum JsonPrimitive {
-    String(String),
+pub enum JsonPrimitive<'a> {
+    String(Cow<'a, str>),
     Number(f64),
     Boolean(bool),
     Null,
+    /// Written verbatim, such as the placeholder for a container cut off by `max_depth`
+    Raw(String),
 }
 
+/// The children of a container, owned or held in an arena
+#[derive(Debug)]
+pub enum Nodes<'a, T> {
+    Owned(Vec<T>),
+    /// Allocated in a `bumpalo::Bump` by `encode_in`; the arena frees them all at once
+    Arena(&'a [T]),
+}
+
+impl<T> core::ops::Deref for Nodes<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Nodes::Owned(nodes) => nodes,
+            Nodes::Arena(nodes) => nodes,
+        }
+    }
+}
+
+impl<T: Clone> Clone for Nodes<'_, T> {
+    /// Arena nodes can't change, so their clone shares them
+    fn clone(&self) -> Self {
+        match self {
+            Nodes::Owned(nodes) => Nodes::Owned(nodes.clone()),
+            Nodes::Arena(nodes) => Nodes::Arena(nodes),
+        }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Nodes<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T> From<Vec<T>> for Nodes<'_, T> {
+    fn from(nodes: Vec<T>) -> Self {
+        Nodes::Owned(nodes)
+    }
+}
+
+impl<T> FromIterator<T> for Nodes<'_, T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Nodes::Owned(iter.into_iter().collect())
+    }
+}
+
+impl<'n, T> IntoIterator for &'n Nodes<'_, T> {
+    type Item = &'n T;
+    type IntoIter = core::slice::Iter<'n, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Object fields in the order they are written
+pub type JsonObject<'a> = Nodes<'a, (Cow<'a, str>, JsonValue<'a>)>;
+
 /// JSON value types
 #[derive(Debug, Clone, PartialEq)]
-pub enum JsonValue {
-    Primitive(JsonPrimitive),
-    Object(HashMap<String, JsonValue>),
-    Array(Vec<JsonValue>),
+pub enum JsonValue<'a> {
+    Primitive(JsonPrimitive<'a>),
+    Object(JsonObject<'a>),
+    Array(Nodes<'a, JsonValue<'a>>),
+}
+
+impl Drop for JsonValue<'_> {
+    /// Tear nested containers down from a heap stack, so dropping deep trees cannot overflow
+    ///
+    /// Arena nodes hold nothing that needs dropping, so they are left to their arena.
+    fn drop(&mut self) {
+        fn take_children<'a>(value: &mut JsonValue<'a>, stack: &mut Vec<JsonValue<'a>>) {
+            let is_container = |v: &JsonValue| matches!(v, JsonValue::Array(a) if !a.is_empty()) || matches!(v, JsonValue::Object(o) if !o.is_empty());
+            match value {
+                JsonValue::Array(Nodes::Owned(arr)) => stack.extend(arr.drain(..).filter(is_container)),
+                JsonValue::Object(Nodes::Owned(obj)) => stack.extend(obj.drain(..).map(|(_, v)| v).filter(is_container)),
+                JsonValue::Array(Nodes::Arena(_)) | JsonValue::Object(Nodes::Arena(_)) | JsonValue::Primitive(_) => {}
+            }
+        }
+
+        let mut stack = Vec::new();
+        take_children(self, &mut stack);
+        while let Some(mut value) = stack.pop() {
+            take_children(&mut value, &mut stack);
+        }
+    }
 }
 
 /// Delimiter types for array values and tabular rows
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Delimiter {
+    #[default]
     Comma,
     Tab,
     Pipe,
+    Semicolon,
 }
 
 impl Delimiter {
@@ -31,6 +121,17 @@ impl Delimiter {
             Delimiter::Comma => ",",
             Delimiter::Tab => "\t",
             Delimiter::Pipe => "|",
+            Delimiter::Semicolon => ";",
+        }
+    }
+
+    /// Delimiter declared by a marker character inside an array header
+    pub fn from_marker(marker: char) -> Option<Self> {
+        match marker {
+            '\t' => Some(Delimiter::Tab),
+            '|' => Some(Delimiter::Pipe),
+            ';' => Some(Delimiter::Semicolon),
+            _ => None,
         }
     }
 
@@ -39,31 +140,678 @@ impl Delimiter {
     }
 }
 
-impl Default for Delimiter {
+/// Indentation emitted for each nesting level
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndentStyle {
+    /// A number of spaces per level
+    Spaces(usize),
+    /// One tab character per level
+    Tabs,
+    /// An arbitrary string repeated once per level
+    Custom(String),
+}
+
+impl IndentStyle {
+    /// The string written for a single indentation level
+    pub fn unit(&self) -> String {
+        match self {
+            IndentStyle::Spaces(n) => " ".repeat(*n),
+            IndentStyle::Tabs => "\t".to_string(),
+            IndentStyle::Custom(s) => s.clone(),
+        }
+    }
+}
+
+impl Default for IndentStyle {
     fn default() -> Self {
-        Delimiter::Comma
+        IndentStyle::Spaces(2)
+    }
+}
+
+impl From<usize> for IndentStyle {
+    fn from(spaces: usize) -> Self {
+        IndentStyle::Spaces(spaces)
+    }
+}
+
+/// Line terminator placed between output lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// Text placed around array lengths, e.g. `[#3]` or `[len=3]`
+///
+/// The prefix and suffix must not contain digits, whitespace, brackets, braces, colons or
+/// delimiter characters, or decoders cannot find the count again; encoding rejects them with
+/// `EncodeError::InvalidOption`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LengthMarker {
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl LengthMarker {
+    pub fn new(prefix: impl Into<String>, suffix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            suffix: suffix.into(),
+        }
+    }
+
+    /// A marker written only before the count
+    pub fn prefix(prefix: impl Into<String>) -> Self {
+        Self::new(prefix, "")
+    }
+}
+
+impl From<char> for LengthMarker {
+    fn from(marker: char) -> Self {
+        Self::prefix(marker.to_string())
+    }
+}
+
+impl From<&str> for LengthMarker {
+    fn from(prefix: &str) -> Self {
+        Self::prefix(prefix)
+    }
+}
+
+/// Which array headers carry an explicit `[N]` length
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmitLengths {
+    #[default]
+    Always,
+    Never,
+    /// Only tabular headers, where the count helps readers check row totals
+    OnlyTables,
+}
+
+/// Folding of nested object keys into dotted paths such as `a.b.c: 1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyFolding {
+    #[default]
+    Off,
+    /// Fold chains of single-key objects
+    Safe,
+    /// Flatten every nested object into dotted keys
+    Aggressive,
+}
+
+/// How a union-header table writes a cell for a field the row doesn't have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingCell {
+    /// Write `null`, which decodes as a null field
+    #[default]
+    Null,
+    /// Leave the cell empty, which decodes as an absent field
+    Empty,
+}
+
+/// Order in which object keys are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    #[default]
+    Alphabetical,
+    /// The order of the input map; serde_json keeps insertion order with the
+    /// `preserve_order` feature and sorts keys without it
+    Insertion,
+}
+
+/// Order of columns in table headers
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ColumnOrder {
+    #[default]
+    Alphabetical,
+    /// The order of the first row's keys, which follows `key_order`
+    FirstObject,
+    /// Listed columns first, in the given order, then the rest alphabetically; flattened
+    /// columns are named by their dotted path
+    Explicit(Vec<String>),
+    /// Shortest header names first, ties broken alphabetically
+    ShortestFirst,
+}
+
+/// Type of a table column, written as `name:type` in headers when `type_hints` is set
+///
+/// Beyond the JSON types, `decimal` marks exact numbers and `date`, `time`, `timestamp`,
+/// `duration` and `uuid` mark strings holding ISO 8601 or UUID text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Decimal,
+    Date,
+    Time,
+    Timestamp,
+    Duration,
+    Uuid,
+}
+
+impl ColumnType {
+    const ALL: [ColumnType; 10] = [
+        ColumnType::Int,
+        ColumnType::Float,
+        ColumnType::Bool,
+        ColumnType::Str,
+        ColumnType::Decimal,
+        ColumnType::Date,
+        ColumnType::Time,
+        ColumnType::Timestamp,
+        ColumnType::Duration,
+        ColumnType::Uuid,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Str => "str",
+            ColumnType::Decimal => "decimal",
+            ColumnType::Date => "date",
+            ColumnType::Time => "time",
+            ColumnType::Timestamp => "timestamp",
+            ColumnType::Duration => "duration",
+            ColumnType::Uuid => "uuid",
+        }
+    }
+
+    /// The type written as `name` in a header
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// Whether cells of this type are strings
+    pub fn is_text(self) -> bool {
+        !matches!(self, ColumnType::Int | ColumnType::Float | ColumnType::Bool | ColumnType::Decimal)
+    }
+}
+
+/// Rows kept when a table is too long: the first `head` and the last `tail`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowLimit {
+    pub head: usize,
+    pub tail: usize,
+}
+
+impl RowLimit {
+    pub fn new(head: usize, tail: usize) -> Self {
+        Self { head, tail }
+    }
+}
+
+/// Direction of a row sort key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// One column of a row sort; flattened columns are named by their dotted path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub direction: SortDirection,
+}
+
+/// Columns tabular rows are sorted by, most significant first
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SortSpec {
+    pub keys: Vec<SortKey>,
+}
+
+impl SortSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an ascending sort key
+    pub fn asc(mut self, column: impl Into<String>) -> Self {
+        self.keys.push(SortKey { column: column.into(), direction: SortDirection::Ascending });
+        self
+    }
+
+    /// Add a descending sort key
+    pub fn desc(mut self, column: impl Into<String>) -> Self {
+        self.keys.push(SortKey { column: column.into(), direction: SortDirection::Descending });
+        self
+    }
+}
+
+/// When to summarize arrays, and which items to keep as a sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Summarize {
+    /// Arrays with more items than this are summarized
+    pub threshold: usize,
+    /// Items kept from the start and end of a summarized array
+    pub sample: RowLimit,
+}
+
+impl Summarize {
+    pub fn new(threshold: usize, sample: RowLimit) -> Self {
+        Self { threshold, sample }
+    }
+}
+
+/// Revision of the TOON specification that output can be held to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpecVersion {
+    /// Headers, tables and list items, with an optional `#` before array lengths
+    V1_0,
+    /// Adds key folding into dotted paths
+    V1_5,
+    /// Drops the `#` length marker
+    V2_0,
+}
+
+impl fmt::Display for SpecVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpecVersion::V1_0 => "1.0",
+            SpecVersion::V1_5 => "1.5",
+            SpecVersion::V2_0 => "2.0",
+        })
     }
 }
 
 /// Encoding options
 #[derive(Debug, Clone)]
 pub struct EncodeOptions {
-    /// Number of spaces per indentation level
-    pub indent: usize,
+    /// Indentation per nesting level
+    pub indent: IndentStyle,
     /// Delimiter to use for arrays and tabular rows
     pub delimiter: Delimiter,
-    /// Optional marker to prefix array lengths
-    pub length_marker: Option<char>,
+    /// Optional marker around array lengths
+    pub length_marker: Option<LengthMarker>,
+    /// Line terminator between lines
+    pub line_ending: LineEnding,
+    /// Terminate the last line as well, as POSIX text files expect
+    pub trailing_newline: bool,
+    /// Pad tabular cells so columns line up, trading tokens for readability
+    pub align_columns: bool,
+    /// Wrap inline arrays onto continuation lines once a line would exceed this width
+    pub max_line_width: Option<usize>,
+    /// Which array headers include their length
+    pub emit_lengths: EmitLengths,
+    /// Fold nested object keys into dotted paths; literal dotted keys are then quoted
+    pub key_folding: KeyFolding,
+    /// Levels of nested objects flattened into dotted columns such as `address.city`,
+    /// so rows with nested objects stay tabular (0 disables flattening)
+    pub table_flatten_depth: usize,
+    /// Fewest rows an array of objects needs before it is written as a table
+    pub tabular_min_rows: usize,
+    /// Most columns a table may have; wider arrays are written as list items
+    pub tabular_max_columns: Option<usize>,
+    /// Tabulate rows with differing keys under the union of their keys, provided the keys
+    /// shared by every row make up at least this fraction (0.0 to 1.0) of the union
+    pub tabular_union_threshold: Option<f64>,
+    /// Cell written for fields missing from a row of a union-header table
+    pub missing_cell: MissingCell,
+    /// Order of columns in table headers
+    pub column_order: ColumnOrder,
+    /// Rows kept per table; the rest are replaced by a `…(+K rows)` marker row
+    pub max_rows: Option<RowLimit>,
+    /// Nesting levels kept below the root; deeper containers become `{…}` or `[…]`
+    pub max_depth: Option<usize>,
+    /// Leave out null-valued fields of objects not written as table rows
+    pub skip_nulls: bool,
+    /// Leave out fields holding empty arrays or objects
+    pub skip_empty: bool,
+    /// Sort tabular rows by these columns before writing them
+    pub sort_rows: Option<SortSpec>,
+    /// Write arrays of objects as an object of arrays keyed by this column's values
+    pub group_by: Option<String>,
+    /// Move strings repeated at least this many times into a leading `@dict` line of `$N` codes
+    pub string_dictionary: Option<usize>,
+    /// Write objects of at most this many primitive fields inline as `{x: 1, y: 2}`
+    pub inline_objects: Option<usize>,
+    /// Split tables longer than this into consecutive `key[start..end]{...}:` blocks
+    pub table_chunk_rows: Option<usize>,
+    /// Short names written in table headers in place of these columns (dotted paths when flattened)
+    pub column_aliases: HashMap<String, String>,
+    /// Write an `@aliases` legend so readers and the decoder can map aliases back
+    pub alias_legend: bool,
+    /// Collapse runs of identical table rows and inline values into `value xN`
+    pub run_length: bool,
+    /// Annotate table header fields with inferred types, as `{id:int,name:str}`
+    pub type_hints: bool,
+    /// Hints written for these columns (dotted paths when flattened) in place of the inferred ones
+    pub column_types: HashMap<String, ColumnType>,
+    /// Replace long arrays with a sample plus count and per-column aggregates
+    pub summarize: Option<Summarize>,
+    /// Write table columns that hold one value in every row once, above the rows
+    pub lift_constant_columns: bool,
+    /// Order of object keys
+    pub key_order: KeyOrder,
+    /// Fail with `EncodeError::OutputTooLarge` once the output would exceed this many bytes
+    pub max_output_bytes: Option<usize>,
+    /// Fail with `EncodeError::TooManyNodes` before encoding inputs with more values than this
+    pub max_nodes: Option<usize>,
+    /// Fail with `EncodeError::DepthExceeded` when a container is nested more than this many
+    /// levels; 1000 by default, 128 without `std`, and `None` for no limit
+    pub fail_over_depth: Option<usize>,
+    /// Fail with `EncodeError::Lossy` instead of writing anything that would not decode back exactly
+    pub lossless: bool,
+    /// Fail with `EncodeError::Unsupported` for options whose syntax this spec revision doesn't
+    /// define, including this crate's extensions; `None` allows everything
+    pub spec_version: Option<SpecVersion>,
+    /// Cut strings longer than this many characters, marking the cut with `…`
+    pub max_string_chars: Option<usize>,
+    /// Round numbers to this many decimal places
+    pub float_digits: Option<usize>,
 }
 
 impl Default for EncodeOptions {
     fn default() -> Self {
         Self {
-            indent: 2,
+            indent: IndentStyle::default(),
+            delimiter: Delimiter::Comma,
+            length_marker: None,
+            line_ending: LineEnding::Lf,
+            trailing_newline: false,
+            align_columns: false,
+            max_line_width: None,
+            emit_lengths: EmitLengths::Always,
+            key_folding: KeyFolding::Off,
+            table_flatten_depth: 0,
+            tabular_min_rows: 1,
+            tabular_max_columns: None,
+            tabular_union_threshold: None,
+            missing_cell: MissingCell::Null,
+            column_order: ColumnOrder::Alphabetical,
+            max_rows: None,
+            max_depth: None,
+            skip_nulls: false,
+            skip_empty: false,
+            sort_rows: None,
+            group_by: None,
+            string_dictionary: None,
+            inline_objects: None,
+            table_chunk_rows: None,
+            column_aliases: HashMap::new(),
+            alias_legend: false,
+            run_length: false,
+            type_hints: false,
+            column_types: HashMap::new(),
+            summarize: None,
+            lift_constant_columns: false,
+            key_order: KeyOrder::Alphabetical,
+            max_output_bytes: None,
+            max_nodes: None,
+            fail_over_depth: Some(DEFAULT_MAX_DEPTH),
+            lossless: false,
+            spec_version: None,
+            max_string_chars: None,
+            float_digits: None,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// Layout tuned to spend the fewest tokens with the named model's tokenizer
+    ///
+    /// OpenAI models (`gpt-4o`, `gpt-4.1`, `o1`, `gpt-4`, `gpt-3.5-turbo`, …) and Llama 3, whose
+    /// tokenizer extends GPT-4's, read a tab delimiter and one-space indentation in fewer tokens
+    /// than commas and two spaces. Other names, including Claude models whose tokenizer isn't
+    /// published, get the default options.
+    pub fn for_model(name: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        let name = name.rsplit('/').next().unwrap_or(&name);
+        let tiktoken_family = ["gpt-", "o1", "o3", "o4", "chatgpt", "llama-3", "llama3"].iter().any(|prefix| name.starts_with(prefix));
+        if tiktoken_family {
+            Self { delimiter: Delimiter::Tab, indent: IndentStyle::Spaces(1), ..Self::default() }
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Deterministic layout for hashing and comparing payloads
+    ///
+    /// Every setting is pinned here rather than taken from `Default`, so canonical output stays
+    /// byte-for-byte stable even if the defaults change.
+    pub fn canonical() -> Self {
+        Self {
+            indent: IndentStyle::Spaces(2),
             delimiter: Delimiter::Comma,
             length_marker: None,
+            line_ending: LineEnding::Lf,
+            trailing_newline: false,
+            align_columns: false,
+            max_line_width: None,
+            emit_lengths: EmitLengths::Always,
+            key_folding: KeyFolding::Off,
+            table_flatten_depth: 0,
+            tabular_min_rows: 1,
+            tabular_max_columns: None,
+            tabular_union_threshold: None,
+            missing_cell: MissingCell::Null,
+            column_order: ColumnOrder::Alphabetical,
+            max_rows: None,
+            max_depth: None,
+            skip_nulls: false,
+            skip_empty: false,
+            sort_rows: None,
+            group_by: None,
+            string_dictionary: None,
+            inline_objects: None,
+            table_chunk_rows: None,
+            column_aliases: HashMap::new(),
+            alias_legend: false,
+            run_length: false,
+            type_hints: false,
+            column_types: HashMap::new(),
+            summarize: None,
+            lift_constant_columns: false,
+            key_order: KeyOrder::Alphabetical,
+            max_output_bytes: None,
+            max_nodes: None,
+            fail_over_depth: Some(DEFAULT_MAX_DEPTH),
+            lossless: false,
+            spec_version: None,
+            max_string_chars: None,
+            float_digits: None,
+        }
+    }
+}
+
+/// Statistics gathered while encoding, returned by `encode_with_report`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EncodeReport {
+    /// Size of the output in bytes
+    pub bytes: usize,
+    /// Number of output lines
+    pub lines: usize,
+    /// Tables written, in output order
+    pub tables: Vec<TableReport>,
+    /// Object fields left out by `skip_nulls` and `skip_empty`
+    pub dropped_fields: usize,
+    /// Size of the same value as minified JSON
+    pub json_bytes: usize,
+}
+
+impl EncodeReport {
+    /// Fraction of the minified JSON size saved, negative when TOON came out larger
+    pub fn savings(&self) -> f64 {
+        if self.json_bytes == 0 {
+            return 0.0;
         }
+        1.0 - self.bytes as f64 / self.json_bytes as f64
     }
 }
 
+/// One table in an `EncodeReport`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableReport {
+    /// Encoded key of the table, `None` for a root array
+    pub key: Option<String>,
+    /// Rows in the table, including any left out by `max_rows`
+    pub rows: usize,
+}
+
+/// Size of one rendering of a value in a `Comparison`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FormatSize {
+    pub bytes: usize,
+    /// Token count, present when compared with `compare_tokens`
+    pub tokens: Option<usize>,
+}
+
+/// Sizes of a value as TOON, minified JSON, pretty-printed JSON and YAML, returned by `compare`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Comparison {
+    pub toon: FormatSize,
+    pub json: FormatSize,
+    pub pretty_json: FormatSize,
+    /// Present with the `yaml` feature
+    pub yaml: Option<FormatSize>,
+}
+
+impl Comparison {
+    /// Fraction of the minified JSON saved by TOON, counted in tokens when available and bytes
+    /// otherwise; negative when TOON came out larger
+    pub fn savings(&self) -> f64 {
+        let (toon, json) = match (self.toon.tokens, self.json.tokens) {
+            (Some(toon), Some(json)) => (toon, json),
+            _ => (self.toon.bytes, self.json.bytes),
+        };
+        if json == 0 {
+            return 0.0;
+        }
+        1.0 - toon as f64 / json as f64
+    }
+}
+
+/// Options for reading CSV with `from_csv`
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Field separator
+    pub delimiter: char,
+    /// Take field names from the first record; otherwise fields are named `col1`, `col2`, …
+    pub has_headers: bool,
+    /// Read fields that look like JSON numbers or `true`/`false` as those types
+    pub infer_types: bool,
+    /// Read empty fields as null instead of empty strings
+    pub empty_as_null: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            has_headers: true,
+            infer_types: true,
+            empty_as_null: true,
+        }
+    }
+}
+
+/// Layout of the JSON written by `to_json_string`
+#[derive(Debug, Clone)]
+pub struct JsonOptions {
+    /// Write one value per line, indented by nesting; otherwise write everything on one line
+    pub pretty: bool,
+    /// Indentation per nesting level when `pretty` is set
+    pub indent: IndentStyle,
+    /// End the output with a newline
+    pub trailing_newline: bool,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        Self {
+            pretty: true,
+            indent: IndentStyle::Spaces(2),
+            trailing_newline: false,
+        }
+    }
+}
+
+/// Layout of the TOON written by `format`
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Indentation per nesting level
+    pub indent: IndentStyle,
+    /// Delimiter to use for arrays and tabular rows
+    pub delimiter: Delimiter,
+    /// Optional marker around array lengths
+    pub length_marker: Option<LengthMarker>,
+    /// Line terminator between lines
+    pub line_ending: LineEnding,
+    /// Terminate the last line as well, as POSIX text files expect
+    pub trailing_newline: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            indent: IndentStyle::default(),
+            delimiter: Delimiter::Comma,
+            length_marker: None,
+            line_ending: LineEnding::Lf,
+            trailing_newline: true,
+        }
+    }
+}
+
+/// One table found by `decode_csv_tables`, written as CSV
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvTable {
+    /// Where the table sits in the document, such as `orders[0].lines`; empty for a root table
+    pub path: String,
+    /// The header record and one record per row, each ending in CRLF
+    pub csv: String,
+}
+
+/// Decoding options
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    /// Indentation per nesting level
+    pub indent: IndentStyle,
+    /// Reject malformed indentation and array lengths that don't match their header
+    pub strict: bool,
+    /// Expand unquoted dotted keys such as `a.b.c` into nested objects
+    pub expand_paths: bool,
+    /// Expand `value xN` runs written with `EncodeOptions::run_length`
+    pub run_length: bool,
+    /// Fail on a line indented more than this many levels, before anything is decoded; 1000 by
+    /// default, 128 without `std`, and `None` for no limit
+    pub fail_over_depth: Option<usize>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            indent: IndentStyle::default(),
+            strict: true,
+            expand_paths: false,
+            run_length: false,
+            fail_over_depth: Some(DEFAULT_MAX_DEPTH),
+        }
+    }
+}
+
+/// A decoded document together with its front matter
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    /// Fields of the `@meta:` block; empty when the document has none
+    pub front_matter: serde_json::Map<String, serde_json::Value>,
+    /// The document body
+    pub value: serde_json::Value,
+}
+
 pub type Depth = usize;
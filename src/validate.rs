@@ -0,0 +1,37 @@
+use crate::prelude::*;
+use crate::error::EncodeError;
+use crate::types::{EncodeOptions, IndentStyle};
+
+/// First option whose value no output could be decoded from, checked before anything is encoded
+pub fn find_invalid_option(options: &EncodeOptions) -> Option<EncodeError> {
+    invalid_option(options).map(|(option, reason)| EncodeError::InvalidOption { option: option.to_string(), reason })
+}
+
+fn invalid_option(options: &EncodeOptions) -> Option<(&'static str, String)> {
+    match &options.indent {
+        IndentStyle::Spaces(0) => return Some(("indent", "nesting needs at least one space per level".to_string())),
+        IndentStyle::Custom(unit) if unit.is_empty() => return Some(("indent", "nesting needs a non-empty unit".to_string())),
+        IndentStyle::Custom(unit) => {
+            if let Some(c) = unit.chars().find(|&c| c != ' ' && c != '\t') {
+                return Some(("indent", format!("{:?} is not a space or tab", c)));
+            }
+        }
+        _ => {}
+    }
+    let mut aliases: Vec<(&String, &String)> = options.column_aliases.iter().map(|(column, alias)| (alias, column)).collect();
+    aliases.sort();
+    if let Some(pair) = aliases.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Some(("column_aliases", format!("`{}` and `{}` share the alias `{}`", pair[0].1, pair[1].1, pair[0].0)));
+    }
+    if let Some(marker) = &options.length_marker {
+        if let Some(c) = marker.prefix.chars().chain(marker.suffix.chars()).find(|&c| !is_marker_char(c)) {
+            return Some(("length_marker", format!("{:?} would be read as part of the array header", c)));
+        }
+    }
+    None
+}
+
+/// Whether `c` can sit beside an array length without changing how the header reads
+fn is_marker_char(c: char) -> bool {
+    !(c.is_ascii_digit() || c.is_whitespace() || matches!(c, '[' | ']' | '{' | '}' | ':' | ',' | '\t' | '|' | ';'))
+}
@@ -1,25 +1,155 @@
-use crate::types::Depth;
+use crate::prelude::*;
+use crate::primitives::encode_field_key;
+use crate::types::{Depth, EncodeOptions, EncodeReport, IndentStyle, LineEnding, TableReport};
+use alloc::sync::Arc;
+
+/// Distinct keys remembered per encode, so maps keyed by unique ids don't grow the cache without bound
+const KEY_CACHE_CAPACITY: usize = 4096;
+
+/// Allocations a writer fills while encoding one document, handed back for the next
+///
+/// `Encoder` keeps one across calls; each writer clears it on the way in, so nothing from an
+/// earlier document is seen, but the key cache and row buffer keep their capacity. Keys are
+/// `Arc`s so an `Encoder` holding this stays `Send`.
+#[derive(Debug, Clone, Default)]
+pub struct Scratch {
+    encoded_keys: HashMap<String, Arc<str>>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Scratch {
+    fn clear(&mut self) {
+        self.encoded_keys.clear();
+        self.rows.clear();
+    }
+}
 
 /// Line writer for building indented output
+///
+/// Lines are appended straight into one output buffer, so the document is never held twice.
 pub struct LineWriter {
-    lines: Vec<String>,
+    output: String,
+    has_lines: bool,
+    limit: Option<usize>,
+    exceeded: bool,
     indentation_string: String,
+    line_ending: LineEnding,
+    scratch: Scratch,
+    report: EncodeReport,
 }
 
 impl LineWriter {
-    pub fn new(indent_size: usize) -> Self {
+    /// Writer that appends into `buffer` after clearing it, keeping its capacity
+    pub fn with_buffer(mut buffer: String, indent: &IndentStyle, line_ending: LineEnding) -> Self {
+        buffer.clear();
         Self {
-            lines: Vec::new(),
-            indentation_string: " ".repeat(indent_size),
+            output: buffer,
+            has_lines: false,
+            limit: None,
+            exceeded: false,
+            indentation_string: indent.unit(),
+            line_ending,
+            scratch: Scratch::default(),
+            report: EncodeReport::default(),
         }
     }
 
+    /// Reuse the key cache and row buffer of `scratch` after clearing them
+    pub fn with_scratch(mut self, mut scratch: Scratch) -> Self {
+        scratch.clear();
+        self.scratch = scratch;
+        self
+    }
+
+    /// Stop accepting lines once the output would pass `limit` bytes
+    pub fn with_limit(mut self, limit: Option<usize>) -> Self {
+        self.limit = limit;
+        self
+    }
+
     pub fn push(&mut self, depth: Depth, content: String) {
-        let indent = self.indentation_string.repeat(depth);
-        self.lines.push(format!("{}{}", indent, content));
+        if self.exceeded {
+            return;
+        }
+        if let Some(limit) = self.limit {
+            let separator = if self.has_lines { self.line_ending.as_str().len() } else { 0 };
+            let line_len = separator + self.indentation_string.len() * depth + content.len();
+            if self.output.len() + line_len > limit {
+                self.exceeded = true;
+                return;
+            }
+        }
+
+        if self.has_lines {
+            self.output.push_str(self.line_ending.as_str());
+        }
+        self.has_lines = true;
+        self.report.lines += 1;
+
+        self.output.reserve(self.indentation_string.len() * depth + content.len());
+        if !self.indentation_string.is_empty() {
+            for _ in 0..depth {
+                self.output.push_str(&self.indentation_string);
+            }
+        }
+        self.output.push_str(&content);
+    }
+
+    /// Reserve room for at least `additional` more bytes of output
+    pub fn reserve(&mut self, additional: usize) {
+        self.output.reserve(additional);
+    }
+
+    /// `encode_field_key` for `key`, validated and allocated once per distinct key
+    pub fn encoded_key(&mut self, key: &str, options: &EncodeOptions) -> Arc<str> {
+        if let Some(encoded) = self.scratch.encoded_keys.get(key) {
+            return Arc::clone(encoded);
+        }
+        let encoded: Arc<str> = encode_field_key(key, options).into();
+        if self.scratch.encoded_keys.len() < KEY_CACHE_CAPACITY {
+            self.scratch.encoded_keys.insert(key.to_string(), Arc::clone(&encoded));
+        }
+        encoded
+    }
+
+    /// An empty buffer for a table's encoded rows, to be handed back with `restore_rows`
+    pub fn take_rows(&mut self) -> Vec<Vec<String>> {
+        core::mem::take(&mut self.scratch.rows)
+    }
+
+    /// Keep `rows`'s allocation for the next table
+    pub fn restore_rows(&mut self, mut rows: Vec<Vec<String>>) {
+        rows.clear();
+        self.scratch.rows = rows;
+    }
+
+    /// Display width of the indentation at `depth`
+    pub fn indent_width(&self, depth: Depth) -> usize {
+        self.indentation_string.chars().count() * depth
+    }
+
+    /// Note a table about to be written under `key`
+    pub fn record_table(&mut self, key: Option<&str>, rows: usize) {
+        self.report.tables.push(TableReport { key: key.map(str::to_string), rows });
+    }
+
+    /// Note object fields skipped by the null and empty filters
+    pub fn record_dropped_fields(&mut self, count: usize) {
+        self.report.dropped_fields += count;
+    }
+
+    /// Whether a line was dropped for passing the limit
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    pub fn into_string(self) -> String {
+        self.output
     }
 
-    pub fn to_string(self) -> String {
-        self.lines.join("\n")
+    /// The output together with the tables, dropped fields and line count seen while writing,
+    /// and the scratch allocations for the next document
+    pub fn into_parts(self) -> (String, EncodeReport, Scratch) {
+        (self.output, self.report, self.scratch)
     }
 }
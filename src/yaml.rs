@@ -0,0 +1,46 @@
+use crate::stack::nested;
+use serde_json::{Map, Value};
+use serde_yaml::Value as Yaml;
+
+/// Convert a YAML value to JSON
+///
+/// Mapping keys that aren't strings become their JSON text (`1`, `true`, `null`, `[1,2]`), a
+/// tagged value `!Tag value` becomes the object `{"!Tag": value}`, and `.inf`, `-.inf` and
+/// `.nan` become null as JSON has no such numbers.
+pub fn yaml_to_json(value: &Yaml) -> Value {
+    match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(*b),
+        Yaml::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64().and_then(serde_json::Number::from_f64).map_or(Value::Null, Value::Number)
+            }
+        }
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Sequence(items) => Value::Array(items.iter().map(|v| nested(|| yaml_to_json(v))).collect()),
+        Yaml::Mapping(mapping) => Value::Object(mapping.iter().map(|(k, v)| (key_text(k), nested(|| yaml_to_json(v)))).collect()),
+        Yaml::Tagged(tagged) => {
+            let mut object = Map::new();
+            object.insert(tagged.tag.to_string(), nested(|| yaml_to_json(&tagged.value)));
+            Value::Object(object)
+        }
+    }
+}
+
+fn key_text(key: &Yaml) -> String {
+    match key {
+        Yaml::String(s) => s.clone(),
+        _ => yaml_to_json(key).to_string(),
+    }
+}
+
+/// Parse one YAML document, resolve `<<` merge keys and convert it to JSON
+pub fn parse_yaml(input: &str) -> Result<Value, serde_yaml::Error> {
+    let mut value: Yaml = serde_yaml::from_str(input)?;
+    value.apply_merge()?;
+    Ok(yaml_to_json(&value))
+}
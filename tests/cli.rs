@@ -0,0 +1,331 @@
+//! End-to-end runs of the `toon` binary; build with `--features bin`
+
+#![cfg(feature = "bin")]
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn toon(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("toon binary runs");
+    // Commands that fail before reading close standard input early
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+/// Wait up to five seconds for `path` to hold `expected`
+fn wait_for(path: &Path, expected: &str) -> bool {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if fs::read_to_string(path).is_ok_and(|text| text == expected) {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    false
+}
+
+#[test]
+fn encode_and_decode_through_stdin() {
+    let json = r#"{"items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]}"#;
+    let encoded = toon(&["encode", "--delimiter", "pipe", "--length-marker", "#"], json);
+    assert!(encoded.status.success());
+    assert_eq!(String::from_utf8(encoded.stdout.clone()).unwrap(), "items[#2|]{qty|sku}:\n  2|A1\n  1|B2\n");
+
+    let decoded = toon(&["decode", "--compact"], &String::from_utf8(encoded.stdout).unwrap());
+    assert_eq!(String::from_utf8(decoded.stdout).unwrap(), "{\"items\":[{\"qty\":2,\"sku\":\"A1\"},{\"qty\":1,\"sku\":\"B2\"}]}\n");
+
+    let csv = toon(&["decode", "--to", "csv"], "items[2]{qty,sku}:\n  2,A1\n  1,B2");
+    assert_eq!(String::from_utf8(csv.stdout).unwrap(), "qty,sku\r\n2,A1\r\n1,B2\r\n");
+
+    let malformed = toon(&["decode"], "a[3]: 1,2");
+    assert!(!malformed.status.success());
+    assert_eq!(String::from_utf8(malformed.stderr).unwrap(), "toon: line 1: array declares 3 items but contains 2\n");
+}
+
+#[test]
+fn stats_compare_input_and_output() {
+    let json = "{\n  \"tags\": [\"a\", \"b\"]\n}\n";
+    let output = toon(&["encode", "--stats"], json);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "tags[2]: a,b\n");
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "<stdin>: 25 -> 13 bytes (-48.0%)\n");
+}
+
+#[test]
+fn watch_re_encodes_changed_files() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("data.json");
+    fs::write(&input, r#"{"id": 1}"#).unwrap();
+
+    let out = dir.join("out");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(["encode", "--watch", input.to_str().unwrap(), "-o", out.to_str().unwrap()])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let target = out.join("data.toon");
+    let first = wait_for(&target, "id: 1\n");
+    // Modification times can be coarse, so make sure the rewrite lands on a later tick
+    thread::sleep(Duration::from_millis(1100));
+    fs::write(&input, r#"{"id": 2}"#).unwrap();
+    let second = wait_for(&target, "id: 2\n");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(first && second);
+}
+
+#[test]
+fn batch_mirrors_the_input_tree() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("batch");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("data/nested")).unwrap();
+    fs::write(dir.join("data/top.json"), r#"{"id": 1}"#).unwrap();
+    fs::write(dir.join("data/nested/rows.json"), r#"[{"id": 1}, {"id": 2}]"#).unwrap();
+    fs::write(dir.join("data/notes.txt"), "skipped").unwrap();
+
+    let out = dir.join("out");
+    let output = toon(&["encode", dir.join("data").to_str().unwrap(), "-o", out.to_str().unwrap()], "");
+    assert!(output.status.success());
+    assert_eq!(fs::read_to_string(out.join("top.toon")).unwrap(), "id: 1\n");
+    assert_eq!(fs::read_to_string(out.join("nested/rows.toon")).unwrap(), "[2]{id}:\n  1\n  2\n");
+    assert!(!out.join("notes.toon").exists());
+
+    let pattern = dir.join("data/**/rows.json");
+    let globbed = dir.join("globbed");
+    assert!(toon(&["encode", pattern.to_str().unwrap(), "-o", globbed.to_str().unwrap()], "").status.success());
+    assert!(globbed.join("nested/rows.toon").exists());
+
+    fs::create_dir_all(dir.join("other")).unwrap();
+    fs::write(dir.join("other/top.json"), r#"{"id": 2}"#).unwrap();
+    let clashing = dir.join("clashing");
+    let (first, second) = (dir.join("data/top.json"), dir.join("other/top.json"));
+    let clash = toon(&["encode", first.to_str().unwrap(), second.to_str().unwrap(), "-o", clashing.to_str().unwrap()], "");
+    assert!(!clash.status.success());
+    let message = format!("{} and {} would both write {}", first.display(), second.display(), clashing.join("top.toon").display());
+    assert!(String::from_utf8(clash.stderr).unwrap().contains(&message));
+    assert!(!clashing.exists());
+}
+
+#[test]
+fn get_and_query_select_from_toon() {
+    let document = "orders[2]:\n  - id: 1\n    status: open\n    lines[2]{qty,sku}:\n      2,A1\n      5,C3\n  - id: 2\n    status: closed\n    lines[1]{qty,sku}:\n      3,D4";
+    let get = toon(&["get", "orders[0].lines[-1].sku"], document);
+    assert_eq!(String::from_utf8(get.stdout).unwrap(), "C3\n");
+
+    let filtered = toon(&["query", "orders[?status=open].lines[?qty>2]{sku}", "--json"], document);
+    assert_eq!(String::from_utf8(filtered.stdout).unwrap(), "[\n  {\n    \"sku\": \"C3\"\n  }\n]\n");
+
+    let ids = toon(&["query", "orders[*].id"], document);
+    assert_eq!(String::from_utf8(ids.stdout).unwrap(), "[2]: 1,2\n");
+
+    let missing = toon(&["get", "orders[5]"], document);
+    assert!(!missing.status.success());
+    assert_eq!(String::from_utf8(missing.stderr).unwrap(), "toon: nothing matches `orders[5]`\n");
+}
+
+#[test]
+fn diff_reports_changes_by_path() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("diff");
+    fs::create_dir_all(&dir).unwrap();
+    let old = dir.join("old.toon");
+    let new = dir.join("new.json");
+    fs::write(&old, "status: open\nitems[2]{qty,sku}:\n  2,A1\n  1,B2").unwrap();
+    fs::write(&new, r#"{"status": "closed", "items": [{"sku": "A1", "qty": 2.0}], "note": "x"}"#).unwrap();
+
+    let output = toon(&["diff", old.to_str().unwrap(), new.to_str().unwrap()], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "- /items/1:\n    qty: 1\n    sku: B2\n+ /note: x\n~ /status: open -> closed\n");
+    assert_eq!(toon(&["diff", old.to_str().unwrap(), old.to_str().unwrap()], "").status.code(), Some(0));
+}
+
+#[test]
+fn fmt_rewrites_and_checks() {
+    let messy = "name: demo\nrows[2|]{sku|qty}:\n    A1|2\n\tB2|1\ntags[2]: \"a\",b\n";
+    let formatted = toon(&["fmt"], messy);
+    assert_eq!(String::from_utf8(formatted.stdout.clone()).unwrap(), "name: demo\nrows[2]{sku,qty}:\n  A1,2\n  B2,1\ntags[2]: a,b\n");
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fmt");
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("prompt.toon");
+    fs::write(&file, messy).unwrap();
+    let path = file.to_str().unwrap();
+    let check = toon(&["fmt", "--check", path], "");
+    assert_eq!(check.status.code(), Some(1));
+    assert_eq!(String::from_utf8(check.stdout).unwrap(), format!("{}\n", path));
+    assert!(toon(&["fmt", path], "").status.success());
+    assert_eq!(fs::read(&file).unwrap(), formatted.stdout);
+    assert_eq!(toon(&["fmt", "--check", path], "").status.code(), Some(0));
+}
+
+#[test]
+fn check_reports_diagnostics_with_exit_codes() {
+    assert_eq!(toon(&["check"], "items[2]: a,b\n").status.code(), Some(0));
+
+    let broken = "a: 1\nitems[3]: x,y\n---\nb:\n   c: 1\nzip: 02134\n";
+    let human = toon(&["check"], broken);
+    assert_eq!(human.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(human.stdout).unwrap(),
+        "<stdin>:2:1: error[length-mismatch]: `items` declares 3 items but has 2\n\
+         <stdin>:5:1: error[indentation]: indentation must be a multiple of 2 spaces\n\
+         <stdin>:6:6: warning[unquoted-value]: `02134` should be quoted as \"02134\"\n"
+    );
+
+    let json = toon(&["check", "--format", "json"], broken);
+    let diagnostics: serde_json::Value = serde_json::from_slice(&json.stdout).unwrap();
+    assert_eq!(diagnostics[1]["line"], 5);
+    assert_eq!(diagnostics[1]["column"], 1);
+    assert_eq!(diagnostics[1]["severity"], "error");
+    assert_eq!(diagnostics[1]["rule"], "indentation");
+
+    let relaxed = toon(&["check", "--warn", "length-mismatch", "--allow", "indentation"], broken);
+    assert_eq!(relaxed.status.code(), Some(0));
+    assert_eq!(String::from_utf8(relaxed.stdout).unwrap().lines().count(), 2);
+    assert_eq!(toon(&["check", "--deny", "unquoted-value"], "zip: 02134\n").status.code(), Some(1));
+
+    assert_eq!(toon(&["check", "missing.toon"], "").status.code(), Some(2));
+}
+
+#[test]
+fn config_file_sets_defaults_under_flags() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config");
+    fs::create_dir_all(dir.join("nested")).unwrap();
+    fs::write(dir.join(".toonrc"), "delimiter = \"pipe\"\nindent = 4\nskip_nulls = true\n").unwrap();
+    let run = |args: &[&str]| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+            .args(args)
+            .current_dir(dir.join("nested"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let _ = child.stdin.take().unwrap().write_all(br#"{"a":{"b":null,"c":[1,2]}}"#);
+        child.wait_with_output().unwrap()
+    };
+
+    assert_eq!(String::from_utf8(run(&["encode"]).stdout).unwrap(), "a:\n    c[2|]: 1|2\n");
+    assert_eq!(String::from_utf8(run(&["encode", "-d", "comma", "-i", "2"]).stdout).unwrap(), "a:\n  c[2]: 1,2\n");
+
+    fs::write(dir.join("strict.toml"), "max_depth = -1\n").unwrap();
+    let invalid = run(&["encode", "--config", "../strict.toml"]);
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(String::from_utf8(invalid.stderr).unwrap().contains("`max_depth` must be a non-negative integer"));
+}
+
+#[test]
+fn color_highlights_only_when_asked_or_on_a_terminal() {
+    let input = r#"{"rows":[{"id":1,"t":"x"}],"ok":true}"#;
+    let colored = toon(&["--color", "always", "encode", "-d", "pipe"], input);
+    assert_eq!(
+        String::from_utf8(colored.stdout).unwrap(),
+        "\x1b[1;34mok\x1b[0m: \x1b[35mtrue\x1b[0m\n\x1b[1;34mrows\x1b[0m\x1b[36m[1|]{id|t}\x1b[0m:\n  \x1b[33m1\x1b[0m|\x1b[32mx\x1b[0m\n"
+    );
+    let piped = toon(&["encode"], input);
+    assert_eq!(String::from_utf8(piped.stdout).unwrap(), "ok: true\nrows[1]{id,t}:\n  1,x\n");
+}
+
+#[test]
+fn convert_between_formats() {
+    let csv = "sku,qty\nA1,2\nB2,1\n";
+    assert_eq!(String::from_utf8(toon(&["convert", "--from", "csv"], csv).stdout).unwrap(), "[2]{qty,sku}:\n  2,A1\n  1,B2\n");
+    assert_eq!(
+        String::from_utf8(toon(&["convert", "--from", "csv", "--to", "md"], csv).stdout).unwrap(),
+        "| sku | qty |\n| --- | ---: |\n| A1 | 2 |\n| B2 | 1 |\n"
+    );
+    let yaml = toon(&["convert", "--from", "yaml", "--to", "json", "--compact"], "a: 1\nb: [x, y]\n");
+    assert_eq!(String::from_utf8(yaml.stdout).unwrap(), "{\"a\":1,\"b\":[\"x\",\"y\"]}\n");
+    let jsonl = toon(&["convert", "--from", "jsonl", "--to", "csv"], "{\"a\":1}\n\n{\"a\":2}\n");
+    assert_eq!(String::from_utf8(jsonl.stdout).unwrap(), "a\r\n1\r\n2\r\n");
+    assert_eq!(String::from_utf8(toon(&["convert", "--from", "toml", "--to", "toon"], "[s]\nk = 1\n").stdout).unwrap(), "s:\n  k: 1\n");
+
+    let unknown = toon(&["convert"], "{}");
+    assert!(!unknown.status.success());
+    assert!(String::from_utf8(unknown.stderr).unwrap().contains("pass --from"));
+}
+
+/// Send one HTTP request to `address` and return the status code and body
+fn http(address: &str, method: &str, target: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", method, target, address, body.len(), body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head[9..12].parse().unwrap(), body.to_string())
+}
+
+#[test]
+fn serve_encodes_and_decodes_over_http() {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(["serve", "--port", "0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut banner = String::new();
+    BufReader::new(server.stderr.take().unwrap()).read_line(&mut banner).unwrap();
+    let address = banner.trim().strip_prefix("listening on http://").unwrap().to_string();
+
+    assert_eq!(http(&address, "POST", "/encode?delimiter=%7C", r#"{"a":[1,2]}"#), (200, "a[2|]: 1|2".to_string()));
+    assert_eq!(http(&address, "POST", "/decode?compact", "a[2]: 1,2"), (200, r#"{"a":[1,2]}"#.to_string()));
+    assert_eq!(http(&address, "POST", "/decode", "a[3]: 1").0, 400);
+    assert_eq!(http(&address, "GET", "/encode", "").0, 405);
+    assert_eq!(http(&address, "POST", "/other", "").0, 404);
+
+    // Deep enough to overflow a worker's stack if it were decoded
+    let deep: String = (0..5000).map(|depth| format!("{}a:\n", " ".repeat(depth))).collect();
+    let (status, body) = http(&address, "POST", "/decode?indent=1", &deep);
+    assert_eq!((status, body.as_str()), (400, "line 130: nesting deeper than 128 levels"));
+    assert_eq!(http(&address, "POST", "/decode?compact", "a: 1").0, 200);
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn follow_appends_rows_as_the_file_grows() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("follow");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("app.jsonl");
+    fs::write(&input, "{\"level\":\"info\",\"n\":1}\n").unwrap();
+
+    let out = dir.join("out.toon");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_toon"))
+        .args(["encode", "--follow", input.to_str().unwrap(), "--key", "logs"])
+        .stdout(fs::File::create(&out).unwrap())
+        .spawn()
+        .unwrap();
+    let first = wait_for(&out, "logs[0..1]{level,n}:\n  info,1");
+    // A line only counts once it's ended, however the writes are split
+    fs::OpenOptions::new().append(true).open(&input).unwrap().write_all(b"{\"level\":\"warn\",").unwrap();
+    thread::sleep(Duration::from_millis(300));
+    fs::OpenOptions::new().append(true).open(&input).unwrap().write_all(b"\"n\":2}\n").unwrap();
+    let second = wait_for(&out, "logs[0..1]{level,n}:\n  info,1\nlogs[1..2]{level,n}:\n  warn,2");
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert!(first && second);
+}
+
+#[cfg(feature = "tokens")]
+#[test]
+fn tokens_compare_json_and_toon_per_model() {
+    let json = r#"{"users": [{"id": 1, "name": "Ada"}, {"id": 2, "name": "Grace"}, {"id": 3, "name": "Alan"}]}"#;
+    let output = toon(&["tokens", "--model", "gpt-4o", "--model", "claude-3"], json);
+    let report = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("gpt-4o: 80 -> 43 bytes (-46.2%), "), "{}", lines[0]);
+    assert!(lines[1].starts_with("claude-3 (estimated with cl100k_base): "), "{}", lines[1]);
+    assert!(lines.iter().all(|line| line.ends_with("%)") && line.contains(" tokens (-")));
+}
@@ -0,0 +1,164 @@
+//! Conformance runner for the TOON specification fixtures
+//!
+//! Point `TOON_SPEC_FIXTURES` at the `tests/fixtures` directory of a checkout of the upstream
+//! specification to run its cases; without it the local cases in `tests/fixtures` are used.
+//! Fixture options are mapped onto `EncodeOptions`/`DecodeOptions`, and every case that fails,
+//! panics or uses an option this crate cannot express is reported as a divergence. Run with
+//! `--features preserve_order` so keys keep the order the fixtures expect.
+
+use serde_json::Value;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use toon::{ColumnOrder, DecodeOptions, Delimiter, EncodeOptions, IndentStyle, KeyFolding, KeyOrder, LengthMarker};
+
+/// One case that did not behave as its fixture expects
+struct Divergence {
+    file: String,
+    name: String,
+    detail: String,
+}
+
+fn fixtures_dir() -> PathBuf {
+    match std::env::var_os("TOON_SPEC_FIXTURES") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures"),
+    }
+}
+
+fn fixture_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn encode_options(options: Option<&Value>) -> Result<EncodeOptions, String> {
+    let mut mapped = EncodeOptions {
+        key_order: KeyOrder::Insertion,
+        column_order: ColumnOrder::FirstObject,
+        ..Default::default()
+    };
+    let Some(options) = options.and_then(Value::as_object) else { return Ok(mapped) };
+    for (name, value) in options {
+        match (name.as_str(), value) {
+            ("delimiter", Value::String(d)) => {
+                mapped.delimiter = match d.as_str() {
+                    "," => Delimiter::Comma,
+                    "\t" => Delimiter::Tab,
+                    "|" => Delimiter::Pipe,
+                    ";" => Delimiter::Semicolon,
+                    other => return Err(format!("unsupported delimiter {:?}", other)),
+                }
+            }
+            ("indent", Value::Number(n)) => mapped.indent = IndentStyle::Spaces(n.as_u64().ok_or("bad indent")? as usize),
+            ("lengthMarker", Value::String(marker)) => mapped.length_marker = Some(LengthMarker::prefix(marker.as_str())),
+            ("lengthMarker", Value::Bool(false)) => mapped.length_marker = None,
+            ("keyFolding", Value::String(folding)) => {
+                mapped.key_folding = match folding.as_str() {
+                    "off" => KeyFolding::Off,
+                    "safe" => KeyFolding::Safe,
+                    other => return Err(format!("unsupported keyFolding {:?}", other)),
+                }
+            }
+            _ => return Err(format!("unsupported encode option {}={}", name, value)),
+        }
+    }
+    Ok(mapped)
+}
+
+fn decode_options(options: Option<&Value>) -> Result<DecodeOptions, String> {
+    let mut mapped = DecodeOptions::default();
+    let Some(options) = options.and_then(Value::as_object) else { return Ok(mapped) };
+    for (name, value) in options {
+        match (name.as_str(), value) {
+            ("indent", Value::Number(n)) => mapped.indent = IndentStyle::Spaces(n.as_u64().ok_or("bad indent")? as usize),
+            ("strict", Value::Bool(strict)) => mapped.strict = *strict,
+            ("expandPaths", Value::String(mode)) => {
+                mapped.expand_paths = match mode.as_str() {
+                    "off" => false,
+                    "safe" => true,
+                    other => return Err(format!("unsupported expandPaths {:?}", other)),
+                }
+            }
+            _ => return Err(format!("unsupported decode option {}={}", name, value)),
+        }
+    }
+    Ok(mapped)
+}
+
+/// Check one case, returning why it diverges
+fn run_case(category: &str, case: &Value) -> Result<(), String> {
+    let should_error = case.get("shouldError").and_then(Value::as_bool).unwrap_or(false);
+    let input = case.get("input").ok_or("case has no input")?;
+    let expected = case.get("expected");
+    let options = case.get("options");
+
+    match category {
+        "encode" => {
+            let options = encode_options(options)?;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| toon::try_encode(input, Some(options))))
+                .map_err(|_| "encoder panicked".to_string())?;
+            match (result, should_error) {
+                (Err(_), true) => Ok(()),
+                (Ok(output), true) => Err(format!("expected an error, got {:?}", output)),
+                (Err(err), false) => Err(format!("unexpected error: {}", err)),
+                (Ok(output), false) => match expected.and_then(Value::as_str) {
+                    Some(expected) if expected == output => Ok(()),
+                    Some(expected) => Err(format!("expected {:?}, got {:?}", expected, output)),
+                    None => Err("case has no expected string".to_string()),
+                },
+            }
+        }
+        "decode" => {
+            let options = decode_options(options)?;
+            let input = input.as_str().ok_or("decode input is not a string")?;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| toon::decode(input, Some(options))))
+                .map_err(|_| "decoder panicked".to_string())?;
+            match (result, should_error) {
+                (Err(_), true) => Ok(()),
+                (Ok(value), true) => Err(format!("expected an error, got {}", value)),
+                (Err(err), false) => Err(format!("unexpected error: {}", err)),
+                (Ok(value), false) => match expected {
+                    Some(expected) if *expected == value => Ok(()),
+                    Some(expected) => Err(format!("expected {}, got {}", expected, value)),
+                    None => Err("case has no expected value".to_string()),
+                },
+            }
+        }
+        other => Err(format!("unknown category {:?}", other)),
+    }
+}
+
+#[test]
+fn spec_fixtures() {
+    let dir = fixtures_dir();
+    let mut total = 0;
+    let mut divergences = Vec::new();
+
+    for category in ["encode", "decode"] {
+        for path in fixture_files(&dir.join(category)) {
+            let file = path.file_name().unwrap().to_string_lossy().into_owned();
+            let fixture: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+            let category = fixture.get("category").and_then(Value::as_str).unwrap_or(category);
+            for case in fixture.get("tests").and_then(Value::as_array).into_iter().flatten() {
+                total += 1;
+                if let Err(detail) = run_case(category, case) {
+                    let name = case.get("name").and_then(Value::as_str).unwrap_or("<unnamed>").to_string();
+                    divergences.push(Divergence { file: file.clone(), name, detail });
+                }
+            }
+        }
+    }
+
+    for divergence in &divergences {
+        eprintln!("{} / {}: {}", divergence.file, divergence.name, divergence.detail);
+    }
+    eprintln!("{} of {} fixture cases pass ({})", total - divergences.len(), total, dir.display());
+    assert!(total > 0, "no fixture cases found in {}", dir.display());
+    assert!(divergences.is_empty(), "{} fixture cases diverge from the specification", divergences.len());
+}
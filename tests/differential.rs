@@ -0,0 +1,117 @@
+//! Differential tests against the TypeScript reference implementation
+//!
+//! Set `TOON_REFERENCE` to a command that reads JSON on stdin and prints the reference TOON
+//! encoding, such as `node tests/reference/encode.mjs` after installing `@toon-format/toon`.
+//! Each document in `tests/corpus` is encoded by both implementations. Outputs that differ are
+//! classified as key ordering, quoting, number formatting or layout differences and printed;
+//! the test fails only when the two outputs decode to different values. Run with
+//! `--features preserve_order` so keys keep the input order the reference uses.
+
+use serde_json::Value;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use toon::{ColumnOrder, EncodeOptions, KeyOrder};
+
+/// How two encodings of the same document differ
+#[derive(Debug, PartialEq, Eq)]
+enum Difference {
+    /// Same lines, in another order
+    KeyOrdering,
+    /// Same text once quotes are removed
+    Quoting,
+    /// Tokens differ only in how equal numbers are written
+    NumberFormatting,
+    /// Decode to the same value but are laid out differently
+    Layout,
+    /// Decode to different values
+    Semantic,
+}
+
+fn reference_encode(command: &str, input: &str) -> Result<String, String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or("TOON_REFERENCE is empty")?;
+    let mut child = Command::new(program)
+        .args(parts)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run {}: {}", command, err))?;
+    child.stdin.take().unwrap().write_all(input.as_bytes()).map_err(|err| err.to_string())?;
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).into_owned());
+    }
+    String::from_utf8(output.stdout).map_err(|err| err.to_string())
+}
+
+fn classify(ours: &str, reference: &str) -> Difference {
+    let same_value = match (toon::decode(ours, None), toon::decode(reference, None)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    };
+    if !same_value {
+        return Difference::Semantic;
+    }
+
+    let mut our_lines: Vec<&str> = ours.lines().collect();
+    let mut reference_lines: Vec<&str> = reference.lines().collect();
+    our_lines.sort_unstable();
+    reference_lines.sort_unstable();
+    if our_lines == reference_lines {
+        return Difference::KeyOrdering;
+    }
+    if ours.replace('"', "") == reference.replace('"', "") {
+        return Difference::Quoting;
+    }
+    if same_numbers(ours, reference) {
+        return Difference::NumberFormatting;
+    }
+    Difference::Layout
+}
+
+/// Whether the outputs split into the same tokens, apart from numbers written differently
+fn same_numbers(ours: &str, reference: &str) -> bool {
+    let split = |s: &str| s.split([',', ' ', '\n']).map(str::to_string).collect::<Vec<_>>();
+    let (ours, reference) = (split(ours), split(reference));
+    ours.len() == reference.len()
+        && ours.iter().zip(&reference).all(|(a, b)| {
+            a == b || matches!((a.parse::<f64>(), b.parse::<f64>()), (Ok(x), Ok(y)) if x == y)
+        })
+}
+
+#[test]
+fn reference_implementation() {
+    let Ok(command) = std::env::var("TOON_REFERENCE") else {
+        eprintln!("TOON_REFERENCE is not set; skipping differential tests");
+        return;
+    };
+    let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths: Vec<_> = fs::read_dir(&corpus).unwrap().map(|entry| entry.unwrap().path()).collect();
+    paths.sort();
+
+    let options = EncodeOptions {
+        key_order: KeyOrder::Insertion,
+        column_order: ColumnOrder::FirstObject,
+        ..Default::default()
+    };
+    let mut semantic = Vec::new();
+    for path in paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == "json")) {
+        let input = fs::read_to_string(path).unwrap();
+        let value: Value = serde_json::from_str(&input).unwrap();
+        let ours = toon::encode(&value, Some(options.clone()));
+        let reference = reference_encode(&command, &input).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        if ours == reference {
+            continue;
+        }
+        let difference = classify(&ours, &reference);
+        eprintln!("{}: {:?}\n--- ours\n{}\n--- reference\n{}\n", path.display(), difference, ours, reference);
+        if difference == Difference::Semantic {
+            semantic.push(path.display().to_string());
+        }
+    }
+    assert!(semantic.is_empty(), "outputs decode differently for {:?}", semantic);
+}
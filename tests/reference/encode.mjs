@@ -0,0 +1,7 @@
+// Encodes the JSON document on stdin with the reference TypeScript implementation.
+// Install it next to this script with `npm install --prefix tests/reference @toon-format/toon`.
+import { encode } from '@toon-format/toon'
+
+const chunks = []
+for await (const chunk of process.stdin) chunks.push(chunk)
+process.stdout.write(encode(JSON.parse(Buffer.concat(chunks).toString('utf8'))))
@@ -0,0 +1,35 @@
+// Run after `wasm-pack build --target nodejs`
+const { encode, decode } = require("./pkg/toon.js");
+
+// Simple example
+const data = {
+    user: {
+        id: 123,
+        name: "Ada",
+        tags: ["reading", "gaming"],
+        active: true,
+        joined: new Date("2024-01-15T00:00:00Z"),
+    },
+};
+
+console.log("=== Simple encoding ===");
+console.log(encode(data));
+
+// Array of objects
+const data2 = {
+    items: [
+        { sku: "A1", qty: 2, price: 9.99 },
+        { sku: "B2", qty: 1, price: 14.5 },
+    ],
+};
+
+console.log("\n=== Tabular format ===");
+console.log(encode(data2));
+
+// Options
+console.log("\n=== Pipe delimiter, sorted by price ===");
+console.log(encode(data2, { delimiter: "|", sortRows: ["-price"] }));
+
+// Round trip
+console.log("\n=== Decoded ===");
+console.log(JSON.stringify(decode(encode(data2))));
@@ -0,0 +1,216 @@
+extern crate toon as toon_lib;
+
+mod options;
+
+use js_sys::{Array, Object, Reflect};
+use options::{JsDecodeOptions, JsEncodeOptions};
+use serde_json::{Map, Number, Value};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Nesting depth past which conversion stops, so cyclic objects raise instead of overflowing
+const MAX_DEPTH: usize = 512;
+
+#[wasm_bindgen(typescript_custom_section)]
+const OPTIONS_TYPES: &'static str = r#"
+/** Encoding options, named after the Rust `EncodeOptions` fields in camelCase */
+export interface EncodeOptions {
+    indent?: number | string;
+    delimiter?: "," | "\t" | "|" | ";";
+    lengthMarker?: string | null;
+    lineEnding?: "\n" | "\r\n";
+    trailingNewline?: boolean;
+    alignColumns?: boolean;
+    maxLineWidth?: number | null;
+    emitLengths?: "always" | "never" | "tables";
+    keyFolding?: "off" | "safe" | "aggressive";
+    tableFlattenDepth?: number;
+    tabularMinRows?: number;
+    tabularMaxColumns?: number | null;
+    tabularUnionThreshold?: number | null;
+    missingCell?: "null" | "empty";
+    columnOrder?: "alphabetical" | "first_object" | "shortest_first" | string[];
+    maxRows?: number | [number, number] | null;
+    maxDepth?: number | null;
+    skipNulls?: boolean;
+    skipEmpty?: boolean;
+    sortRows?: string[] | null;
+    groupBy?: string | null;
+    stringDictionary?: number | null;
+    inlineObjects?: number | null;
+    tableChunkRows?: number | null;
+    columnAliases?: Record<string, string>;
+    aliasLegend?: boolean;
+    runLength?: boolean;
+    typeHints?: boolean;
+    columnTypes?: Record<string, string>;
+    summarize?: [number, number | [number, number]] | null;
+    liftConstantColumns?: boolean;
+    keyOrder?: "alphabetical" | "insertion";
+    maxOutputBytes?: number | null;
+    maxNodes?: number | null;
+    failOverDepth?: number | null;
+    lossless?: boolean;
+    specVersion?: "1.0" | "1.5" | "2.0" | null;
+    maxStringChars?: number | null;
+    floatDigits?: number | null;
+}
+
+/** Decoding options, named after the Rust `DecodeOptions` fields in camelCase */
+export interface DecodeOptions {
+    indent?: number | string;
+    strict?: boolean;
+    expandPaths?: boolean;
+    runLength?: boolean;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "EncodeOptions")]
+    pub type EncodeOptionsArg;
+
+    #[wasm_bindgen(typescript_type = "DecodeOptions")]
+    pub type DecodeOptionsArg;
+}
+
+fn error(message: impl AsRef<str>) -> JsValue {
+    js_sys::Error::new(message.as_ref()).into()
+}
+
+fn type_error(message: impl AsRef<str>) -> JsValue {
+    js_sys::TypeError::new(message.as_ref()).into()
+}
+
+/// Convert a JavaScript value to JSON the way `JSON.stringify` would see it
+///
+/// Objects with a `toJSON` method (such as `Date`) are converted through it, `Map`s become
+/// objects, other iterables become arrays and `BigInt`s become numbers, or strings when
+/// they don't fit in 64 bits. `NaN` and infinities become null.
+fn to_json(value: &JsValue, depth: usize) -> Result<Value, JsValue> {
+    if depth > MAX_DEPTH {
+        return Err(error(format!("value is nested more than {} levels deep", MAX_DEPTH)));
+    }
+    if value.is_null() || value.is_undefined() {
+        return Ok(Value::Null);
+    }
+    if let Some(b) = value.as_bool() {
+        return Ok(Value::Bool(b));
+    }
+    if let Some(n) = value.as_f64() {
+        if js_sys::Number::is_safe_integer(value) {
+            return Ok(Value::from(n as i64));
+        }
+        return Ok(Number::from_f64(n).map_or(Value::Null, Value::Number));
+    }
+    if let Some(s) = value.as_string() {
+        return Ok(Value::String(s));
+    }
+    if value.is_bigint() {
+        if let Ok(i) = i64::try_from(value.clone()) {
+            return Ok(Value::from(i));
+        }
+        if let Ok(u) = u64::try_from(value.clone()) {
+            return Ok(Value::from(u));
+        }
+        let text = value.unchecked_ref::<js_sys::BigInt>().to_string(10).map_err(JsValue::from)?;
+        return Ok(Value::String(text.into()));
+    }
+    if value.is_function() || value.is_symbol() {
+        return Err(type_error(format!("cannot encode a {}", value.js_typeof().as_string().unwrap_or_default())));
+    }
+
+    if let Some(array) = value.dyn_ref::<Array>() {
+        return array.iter().map(|item| to_json(&item, depth + 1)).collect::<Result<_, _>>().map(Value::Array);
+    }
+    let to_json_method = Reflect::get(value, &JsValue::from_str("toJSON"))?;
+    if let Some(method) = to_json_method.dyn_ref::<js_sys::Function>() {
+        return to_json(&method.call0(value)?, depth + 1);
+    }
+    if let Some(map) = value.dyn_ref::<js_sys::Map>() {
+        let mut object = Map::new();
+        for entry in map.entries() {
+            let entry: Array = entry?.unchecked_into();
+            object.insert(key_text(&entry.get(0), depth)?, to_json(&entry.get(1), depth + 1)?);
+        }
+        return Ok(Value::Object(object));
+    }
+    if let Some(items) = js_sys::try_iter(value)? {
+        let mut array = Vec::new();
+        for item in items {
+            array.push(to_json(&item?, depth + 1)?);
+        }
+        return Ok(Value::Array(array));
+    }
+
+    let mut object = Map::new();
+    for entry in Object::entries(value.unchecked_ref::<Object>()) {
+        let entry: Array = entry.unchecked_into();
+        let item = entry.get(1);
+        // `JSON.stringify` leaves out properties it can't represent
+        if item.is_undefined() || item.is_function() || item.is_symbol() {
+            continue;
+        }
+        object.insert(entry.get(0).as_string().unwrap_or_default(), to_json(&item, depth + 1)?);
+    }
+    Ok(Value::Object(object))
+}
+
+/// A `Map` key as an object key: strings as they are, anything else as its JSON text
+fn key_text(key: &JsValue, depth: usize) -> Result<String, JsValue> {
+    match key.as_string() {
+        Some(key) => Ok(key),
+        None => Ok(to_json(key, depth + 1)?.to_string()),
+    }
+}
+
+/// Read an options object, or the defaults when it's left out
+fn read_options<T: Default + serde::de::DeserializeOwned>(options: Option<&JsValue>) -> Result<T, JsValue> {
+    match options {
+        Some(options) if !options.is_null() && !options.is_undefined() => {
+            serde_json::from_value(to_json(options, 0)?).map_err(|e| type_error(format!("invalid options: {}", e)))
+        }
+        _ => Ok(T::default()),
+    }
+}
+
+/// Encode a JavaScript value to TOON
+///
+/// Throws `TypeError` for functions, symbols and invalid options, and `Error` when a budget
+/// is exceeded or a value is lossy under `lossless`.
+#[wasm_bindgen]
+pub fn encode(data: JsValue, options: Option<EncodeOptionsArg>) -> Result<String, JsValue> {
+    let json_value = to_json(&data, 0)?;
+    let options: JsEncodeOptions = read_options(options.as_deref())?;
+    let options = options.to_rust().map_err(type_error)?;
+    toon_lib::try_encode(&json_value, Some(options)).map_err(|e| error(e.to_string()))
+}
+
+/// Convert a decoded JSON value to plain JavaScript objects and arrays
+fn to_js(value: &Value) -> Result<JsValue, JsValue> {
+    Ok(match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => JsValue::from_f64(n.as_f64().unwrap_or(f64::NAN)),
+        Value::String(s) => JsValue::from_str(s),
+        Value::Array(items) => items.iter().map(to_js).collect::<Result<Array, _>>()?.into(),
+        Value::Object(map) => {
+            // Built from entries so a `__proto__` key stays an ordinary property
+            let entries = Array::new();
+            for (key, item) in map {
+                entries.push(&Array::of2(&JsValue::from_str(key), &to_js(item)?));
+            }
+            Object::from_entries(&entries)?.into()
+        }
+    })
+}
+
+/// Decode TOON text to plain JavaScript values
+///
+/// Throws `Error` with the line number when the text is malformed.
+#[wasm_bindgen]
+pub fn decode(text: &str, options: Option<DecodeOptionsArg>) -> Result<JsValue, JsValue> {
+    let options: JsDecodeOptions = read_options(options.as_deref())?;
+    let value = toon_lib::decode(text, Some(options.to_rust())).map_err(|e| error(e.to_string()))?;
+    to_js(&value)
+}